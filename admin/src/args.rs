@@ -2,6 +2,7 @@ use std::{fs, io, path::PathBuf};
 
 use clap::{Error, Parser, Subcommand};
 use shuttle_common::{constants::API_URL_PRODUCTION, models::user::UserId};
+use shuttle_resource_recorder::encryption::EncryptionKey;
 
 #[derive(Parser, Debug)]
 pub struct Args {
@@ -33,6 +34,27 @@ pub enum Command {
         new_user_id: UserId,
     },
 
+    /// Set the resources of a project container, instead of the ones of its owner's account tier
+    ProjectLimits {
+        project_name: String,
+
+        /// Hard memory limit, in GiB
+        #[arg(long, required_unless_present = "clear")]
+        memory: Option<u64>,
+
+        /// Soft memory limit that is enforced when the host is low on memory, in GiB
+        #[arg(long, required_unless_present = "clear")]
+        memory_reservation: Option<u64>,
+
+        /// Number of CPUs
+        #[arg(long, required_unless_present = "clear")]
+        cpus: Option<u64>,
+
+        /// Go back to the limits of the account tier the next time the project is created
+        #[arg(long, conflicts_with_all = ["memory", "memory_reservation", "cpus"])]
+        clear: bool,
+    },
+
     /// Viewing and managing stats
     #[command(subcommand)]
     Stats(StatsCommand),
@@ -46,6 +68,10 @@ pub enum Command {
     UnsetBetaAccess {
         user_id: String,
     },
+
+    /// Manage the keys that the resource recorder encrypts sensitive resources with
+    #[command(subcommand)]
+    RecorderKey(RecorderKeyCommand),
 }
 
 #[derive(Subcommand, Debug)]
@@ -71,6 +97,11 @@ pub enum AcmeCommand {
         #[arg(long)]
         project: String,
 
+        /// Also cover all the subdomains of the FQDN, and route them to the project.
+        /// Needs the gateway to be able to complete DNS-01 challenges.
+        #[arg(long)]
+        wildcard: bool,
+
         /// Path to acme credentials file
         /// This should have been created with `acme create-account`
         #[arg(long, value_parser = load_credentials)]
@@ -94,8 +125,8 @@ pub enum AcmeCommand {
     },
 
     /// Renew the certificate for the shuttle gateway.
-    /// Note: this step should be completed manually in terms
-    /// of DNS-01 challenge completion.
+    /// Note: unless the gateway is set up with a DNS-01 provider, this step
+    /// should be completed manually in terms of DNS-01 challenge completion.
     RenewGateway {
         /// Path to acme credentials file
         /// This should have been created with `acme create-account`
@@ -104,6 +135,34 @@ pub enum AcmeCommand {
     },
 }
 
+#[derive(Subcommand, Debug)]
+pub enum RecorderKeyCommand {
+    /// Generate a new key for the resource recorder
+    Generate,
+
+    /// Encrypt all resources with the current key, so that the previous keys can be removed from the recorder.
+    /// Run this next to the state of the recorder, after restarting it with the new key as the current one.
+    Rotate {
+        /// Where the resource recorder stores its state
+        #[arg(long, default_value = "./")]
+        state: PathBuf,
+
+        /// The current key of the recorder
+        #[arg(long, env = "SHUTTLE_RECORDER_ENCRYPTION_KEY", hide_env_values = true)]
+        encryption_key: EncryptionKey,
+
+        /// The keys to rotate away from
+        #[arg(
+            long,
+            env = "SHUTTLE_RECORDER_PREVIOUS_ENCRYPTION_KEYS",
+            value_delimiter = ',',
+            hide_env_values = true,
+            required = true
+        )]
+        previous_encryption_keys: Vec<EncryptionKey>,
+    },
+}
+
 #[derive(Subcommand, Debug)]
 pub enum StatsCommand {
     /// View load stats
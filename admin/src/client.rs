@@ -1,6 +1,6 @@
 use anyhow::Result;
 use shuttle_api_client::ShuttleApiClient;
-use shuttle_common::models::{admin::ProjectResponse, stats};
+use shuttle_common::models::{admin::ProjectResponse, project::ContainerLimits, stats};
 
 pub struct Client {
     pub inner: ShuttleApiClient,
@@ -46,9 +46,10 @@ impl Client {
         &self,
         fqdn: &str,
         project_name: &str,
+        wildcard: bool,
         credentials: &serde_json::Value,
     ) -> Result<String> {
-        let path = format!("/admin/acme/request/{project_name}/{fqdn}");
+        let path = format!("/admin/acme/request/{project_name}/{fqdn}?wildcard={wildcard}");
         self.inner.post_json(&path, Some(credentials)).await
     }
 
@@ -85,6 +86,22 @@ impl Client {
         Ok(())
     }
 
+    pub async fn set_project_limits(
+        &self,
+        project_name: &str,
+        container_limits: Option<ContainerLimits>,
+    ) -> Result<()> {
+        self.inner
+            .put(
+                format!("/admin/projects/{project_name}/limits"),
+                Some(container_limits),
+            )
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
     pub async fn get_load(&self) -> Result<stats::LoadResponse> {
         self.inner.get_json("/admin/stats/load").await
     }
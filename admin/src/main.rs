@@ -1,9 +1,14 @@
 use shuttle_admin::{
-    args::{AcmeCommand, Args, Command, StatsCommand},
+    args::{AcmeCommand, Args, Command, RecorderKeyCommand, StatsCommand},
     client::Client,
     config::get_api_key,
 };
 use shuttle_backends::project_name::ProjectName;
+use shuttle_common::models::project::ContainerLimits;
+use shuttle_resource_recorder::{
+    encryption::{EncryptionKey, Keyring},
+    Sqlite,
+};
 use tracing::trace;
 
 #[tokio::main]
@@ -14,6 +19,12 @@ async fn main() {
 
     trace!(?args, "starting with args");
 
+    // Works on the state of the recorder directly, so no API key is needed
+    if let Command::RecorderKey(command) = args.command {
+        recorder_key(command).await;
+        return;
+    }
+
     let api_key = get_api_key();
     let client = Client::new(args.api_url.clone(), api_key);
 
@@ -38,10 +49,11 @@ async fn main() {
         Command::Acme(AcmeCommand::Request {
             fqdn,
             project,
+            wildcard,
             credentials,
         }) => {
             let s = client
-                .acme_request_certificate(&fqdn, &project, &credentials)
+                .acme_request_certificate(&fqdn, &project, wildcard, &credentials)
                 .await
                 .expect("to get a certificate challenge response");
             println!("{s}");
@@ -103,6 +115,32 @@ async fn main() {
                 .unwrap();
             println!("Changed project owner: {project_name} -> {new_user_id}")
         }
+        Command::ProjectLimits {
+            project_name,
+            memory,
+            memory_reservation,
+            cpus,
+            clear,
+        } => {
+            let container_limits = (!clear).then(|| {
+                ContainerLimits::new(
+                    memory.expect("clap to require the memory"),
+                    memory_reservation.expect("clap to require the memory reservation"),
+                    cpus.expect("clap to require the cpus"),
+                )
+            });
+            client
+                .set_project_limits(&project_name, container_limits)
+                .await
+                .expect("to set the project limits");
+
+            match container_limits {
+                Some(container_limits) => {
+                    println!("Set the limits of {project_name} to {container_limits}")
+                }
+                None => println!("Cleared the limits of {project_name}, the ones of its account tier apply the next time it is created"),
+            }
+        }
         Command::SetBetaAccess { user_id } => {
             client.set_beta_access(&user_id, true).await.unwrap();
             println!("Set user {user_id} beta access");
@@ -111,5 +149,36 @@ async fn main() {
             client.set_beta_access(&user_id, false).await.unwrap();
             println!("Unset user {user_id} beta access");
         }
+        Command::RecorderKey(_) => unreachable!("recorder key commands are handled first"),
     };
 }
+
+async fn recorder_key(command: RecorderKeyCommand) {
+    match command {
+        RecorderKeyCommand::Generate => {
+            let key = EncryptionKey::generate().expect("to generate a key");
+            println!("{}", key.to_base64());
+        }
+        RecorderKeyCommand::Rotate {
+            state,
+            encryption_key,
+            previous_encryption_keys,
+        } => {
+            let key_id = encryption_key.id().to_owned();
+            let db_path = state.join("resource-recorder.sqlite");
+            assert!(
+                db_path.exists(),
+                "no resource recorder state at {}",
+                db_path.display()
+            );
+            let sqlite = Sqlite::new(
+                db_path.display().to_string().as_str(),
+                Keyring::new(encryption_key, previous_encryption_keys),
+            )
+            .await;
+
+            let count = sqlite.rotate_keys().await.expect("resources to be rotated");
+            println!("Rotated {count} resources to key {key_id}");
+        }
+    }
+}
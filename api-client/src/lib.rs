@@ -11,12 +11,14 @@ use serde_json::Value;
 use shuttle_common::certificate::{
     AddCertificateRequest, CertificateResponse, DeleteCertificateRequest,
 };
-use shuttle_common::log::{LogsRange, LogsResponseBeta};
+use shuttle_common::log::{LogsFilter, LogsRange, LogsResponseBeta};
 use shuttle_common::models::deployment::{
     DeploymentRequest, DeploymentRequestBeta, UploadArchiveResponseBeta,
 };
+use shuttle_common::models::secret::{SecretsUpdateRequest, SecretsUpdateResponse};
 use shuttle_common::models::{deployment, project, service, team, user};
 use shuttle_common::resource::{ProvisionResourceRequest, ShuttleResourceOutput};
+use shuttle_common::secrets::SecretStore;
 use shuttle_common::{resource, LogItem, VersionInfo};
 use tokio::net::TcpStream;
 use tokio_tungstenite::tungstenite::client::IntoClientRequest;
@@ -196,12 +198,14 @@ impl ShuttleApiClient {
         &self,
         project: &str,
         resource_type: &resource::Type,
+        name: Option<&str>,
     ) -> Result<()> {
         let r#type = resource_type.to_string();
         let r#type = utf8_percent_encode(&r#type, percent_encoding::NON_ALPHANUMERIC).to_owned();
+        let query = Self::resource_name_query(name);
 
         self.delete_json(format!(
-            "/projects/{project}/services/{project}/resources/{}",
+            "/projects/{project}/services/{project}/resources/{}{query}",
             r#type
         ))
         .await
@@ -210,11 +214,13 @@ impl ShuttleApiClient {
         &self,
         project: &str,
         resource_type: &resource::Type,
+        name: Option<&str>,
     ) -> Result<()> {
         let r#type = resource_type.to_string();
         let r#type = utf8_percent_encode(&r#type, percent_encoding::NON_ALPHANUMERIC).to_owned();
+        let query = Self::resource_name_query(name);
 
-        self.delete_json(format!("/projects/{project}/resources/{}", r#type))
+        self.delete_json(format!("/projects/{project}/resources/{}{query}", r#type))
             .await
     }
     pub async fn provision_resource_beta(
@@ -225,6 +231,21 @@ impl ShuttleApiClient {
         self.post_json(format!("/projects/{project}/resources"), Some(req))
             .await
     }
+    pub async fn get_secrets(&self, project: &str) -> Result<SecretStore> {
+        self.get_json(format!("/projects/{project}/services/{project}/secrets"))
+            .await
+    }
+    pub async fn update_secrets(
+        &self,
+        project: &str,
+        update: &SecretsUpdateRequest,
+    ) -> Result<SecretsUpdateResponse> {
+        self.put_json(
+            format!("/projects/{project}/services/{project}/secrets"),
+            Some(update),
+        )
+        .await
+    }
     pub async fn get_secrets_beta(&self, project: &str) -> Result<resource::Response> {
         self.get_json(format!("/projects/{project}/resources/secrets"))
             .await
@@ -268,8 +289,12 @@ impl ShuttleApiClient {
             .context("failed to make create project request")
     }
 
-    pub async fn clean_project(&self, project: &str) -> Result<String> {
-        let path = format!("/projects/{project}/clean");
+    pub async fn clean_project(&self, project: &str, evict_cache: bool) -> Result<String> {
+        let path = if evict_cache {
+            format!("/projects/{project}/clean?evict_cache=true")
+        } else {
+            format!("/projects/{project}/clean")
+        };
 
         self.post_json(path, Option::<String>::None)
             .await
@@ -315,6 +340,15 @@ impl ShuttleApiClient {
         self.delete_json(format!("/projects/{project}")).await
     }
 
+    pub async fn enable_project_tcp(&self, project: &str) -> Result<u16> {
+        self.post_json(format!("/projects/{project}/tcp"), None::<()>)
+            .await
+    }
+
+    pub async fn disable_project_tcp(&self, project: &str) -> Result<Option<u16>> {
+        self.delete_json(format!("/projects/{project}/tcp")).await
+    }
+
     pub async fn get_teams_list(&self) -> Result<Vec<team::Response>> {
         self.get_json("/teams".to_string()).await
     }
@@ -334,9 +368,10 @@ impl ShuttleApiClient {
         project: &str,
         deployment_id: &str,
         range: LogsRange,
+        filter: &LogsFilter,
     ) -> Result<Vec<LogItem>> {
         let mut path = format!("/projects/{project}/deployments/{deployment_id}/logs");
-        Self::add_range_query(range, &mut path);
+        Self::add_logs_query(range, filter, &mut path);
 
         self.get_json(path)
             .await
@@ -362,25 +397,60 @@ impl ShuttleApiClient {
         project: &str,
         deployment_id: &str,
         range: LogsRange,
+        filter: &LogsFilter,
     ) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>> {
         let mut path = format!("/projects/{project}/ws/deployments/{deployment_id}/logs");
-        Self::add_range_query(range, &mut path);
+        Self::add_logs_query(range, filter, &mut path);
 
         self.ws_get(path).await
     }
 
-    fn add_range_query(range: LogsRange, path: &mut String) {
+    /// The query selecting a named instance of a resource type, if there is a name
+    fn resource_name_query(name: Option<&str>) -> String {
+        name.map(|name| {
+            let query = url::form_urlencoded::Serializer::new(String::new())
+                .append_pair("name", name)
+                .finish();
+            format!("?{query}")
+        })
+        .unwrap_or_default()
+    }
+
+    fn add_logs_query(range: LogsRange, filter: &LogsFilter, path: &mut String) {
+        let mut query = url::form_urlencoded::Serializer::new(String::new());
         match range {
             LogsRange::Head(n) => {
-                path.push_str("?head=");
-                path.push_str(&n.to_string())
+                query.append_pair("head", &n.to_string());
             }
             LogsRange::Tail(n) => {
-                path.push_str("?tail=");
-                path.push_str(&n.to_string())
+                query.append_pair("tail", &n.to_string());
             }
             _ => {}
         };
+        if let Some(since) = filter.since {
+            query.append_pair("since", &since.to_rfc3339());
+        }
+        if let Some(until) = filter.until {
+            query.append_pair("until", &until.to_rfc3339());
+        }
+        if let Some(grep) = &filter.grep {
+            query.append_pair("grep", grep);
+        }
+        if filter.regex {
+            query.append_pair("regex", "true");
+        }
+        if let Some(source) = &filter.source {
+            query.append_pair("source", source);
+        }
+        if let Some(level) = &filter.level {
+            query.append_pair("level", level);
+        }
+
+        let query = query.finish();
+        if !query.is_empty() {
+            path.push('?');
+            path.push_str(&query);
+        }
     }
 
     pub async fn get_deployments(
@@ -440,10 +510,38 @@ impl ShuttleApiClient {
         self.get_json(path).await
     }
 
+    pub async fn rollback_deployment(
+        &self,
+        project: &str,
+        deployment_id: &Uuid,
+    ) -> Result<deployment::Response> {
+        let path = format!("/projects/{project}/deployments/{deployment_id}/rollback");
+
+        self.post_json(path, None::<()>).await
+    }
+
     pub async fn reset_api_key(&self) -> Result<Response> {
         self.put("/users/reset-api-key", Option::<()>::None).await
     }
 
+    pub async fn get_api_keys(&self) -> Result<Vec<user::ApiKeyResponse>> {
+        self.get_json("/users/keys").await
+    }
+
+    pub async fn create_api_key(
+        &self,
+        request: &user::ApiKeyRequest,
+    ) -> Result<user::ApiKeyResponse> {
+        self.post_json("/users/keys", Some(request)).await
+    }
+
+    pub async fn revoke_api_key(&self, name: &str) -> Result<Response> {
+        let name = utf8_percent_encode(name, percent_encoding::NON_ALPHANUMERIC);
+
+        self.delete(format!("/users/keys/{name}"), Option::<()>::None)
+            .await
+    }
+
     pub async fn ws_get(&self, path: String) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>> {
         let ws_url = self.api_url.clone().replace("http", "ws");
         let url = format!("{ws_url}{path}");
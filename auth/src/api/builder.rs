@@ -20,8 +20,8 @@ use crate::{
 };
 
 use super::handlers::{
-    convert_key, delete_subscription, get_public_key, get_user, get_user_by_name,
-    post_subscription, post_user, put_user_reset_key,
+    convert_key, delete_api_key, delete_subscription, get_api_keys, get_public_key, get_user,
+    get_user_by_name, post_api_key, post_subscription, post_user, put_user_reset_key,
 };
 
 pub type UserManagerState = Arc<Box<dyn UserManagement>>;
@@ -80,6 +80,8 @@ where
             .route("/users/:account_name/:account_tier", post(post_user))
             .route("/users/:user_id", get(get_user))
             .route("/users/reset-api-key", put(put_user_reset_key))
+            .route("/users/keys", get(get_api_keys).post(post_api_key))
+            .route("/users/keys/:name", delete(delete_api_key))
             .route("/users/:user_id/subscribe", post(post_subscription))
             .route(
                 "/users/:user_id/subscribe/:subscription_id",
@@ -1,15 +1,19 @@
+use std::str::FromStr;
+
 use crate::{
     error::Error,
-    user::{Admin, Key},
+    user::{Admin, Key, User},
 };
 use axum::{
     extract::{Path, State},
     Json,
 };
+use chrono::Utc;
 use http::StatusCode;
 use shuttle_common::{
-    claims::{AccountTier, Claim},
-    models::user::{self, SubscriptionRequest, UserId},
+    claims::{AccountTier, Claim, Scope},
+    models::user::{self, ApiKeyRequest, SubscriptionRequest, UserId},
+    ApiKey,
 };
 use tracing::{field, instrument, Span};
 
@@ -59,6 +63,79 @@ pub(crate) async fn put_user_reset_key(
     user_manager.reset_key(user_id).await
 }
 
+pub(crate) async fn get_api_keys(
+    State(user_manager): State<UserManagerState>,
+    user: User,
+) -> Result<Json<Vec<user::ApiKeyResponse>>, Error> {
+    let keys = user_manager.get_api_keys(&user.id).await?;
+
+    Ok(Json(keys.into_iter().map(Into::into).collect()))
+}
+
+pub(crate) async fn post_api_key(
+    State(user_manager): State<UserManagerState>,
+    user: User,
+    Json(request): Json<ApiKeyRequest>,
+) -> Result<Json<user::ApiKeyResponse>, Error> {
+    if request.name.trim().is_empty() {
+        return Err(Error::InvalidApiKey(
+            "the name can not be empty".to_string(),
+        ));
+    }
+    if request.scopes.is_empty() {
+        return Err(Error::InvalidApiKey(
+            "at least one scope is required".to_string(),
+        ));
+    }
+    if request
+        .expires_at
+        .is_some_and(|expires_at| expires_at <= Utc::now())
+    {
+        return Err(Error::InvalidApiKey(
+            "the expiry has to be in the future".to_string(),
+        ));
+    }
+
+    let scopes = request
+        .scopes
+        .iter()
+        .map(|scope| {
+            Scope::from_str(scope)
+                .map_err(|_| Error::InvalidApiKey(format!("unknown scope '{scope}'")))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // A key can not be created with rights that its user does not have
+    let user_scopes = Vec::<Scope>::from(user.account_tier);
+    if let Some(scope) = scopes.iter().find(|scope| !user_scopes.contains(scope)) {
+        return Err(Error::InvalidApiKey(format!(
+            "the scope '{scope}' is not available to this account"
+        )));
+    }
+
+    let named_key = user_manager
+        .create_api_key(&user.id, request.name, scopes, request.expires_at)
+        .await?;
+    let key = named_key.key.expose().as_ref().to_owned();
+
+    let mut response: user::ApiKeyResponse = named_key.into();
+    response.key = Some(key);
+
+    Ok(Json(response))
+}
+
+/// Revoke a named key of the user.
+///
+/// Note that the gateway keeps using a token it cached for the key for up to 5 minutes (its `CACHE_MINUTES`), which
+/// the CLI tells users about.
+pub(crate) async fn delete_api_key(
+    State(user_manager): State<UserManagerState>,
+    user: User,
+    Path(name): Path<String>,
+) -> Result<(), Error> {
+    user_manager.revoke_api_key(&user.id, &name).await
+}
+
 pub(crate) async fn post_subscription(
     _: Admin,
     State(user_manager): State<UserManagerState>,
@@ -85,23 +162,36 @@ pub(crate) async fn delete_subscription(
 }
 
 /// Convert a valid API-key bearer token to a JWT.
+///
+/// Named keys get the scopes of the user's tier that the key was created with.
 pub(crate) async fn convert_key(
     _: Admin,
     State(user_manager): State<UserManagerState>,
     State(key_manager): State<KeyManagerState>,
     key: Key,
 ) -> Result<Json<shuttle_backends::auth::ConvertResponse>, StatusCode> {
-    let user = user_manager
-        .get_user_by_key(key.into())
-        .await
-        .map_err(|_| StatusCode::UNAUTHORIZED)?;
-
-    let claim = Claim::new(
-        user.id.clone(),
-        user.account_tier.into(),
-        user.account_tier,
-        user,
-    );
+    let key: ApiKey = key.into();
+
+    let (user, scopes) = match user_manager.get_user_by_key(key.clone()).await {
+        Ok(user) => {
+            let scopes = user.account_tier.into();
+            (user, scopes)
+        }
+        Err(_) => {
+            let (user, named_key) = user_manager
+                .get_user_by_named_key(key)
+                .await
+                .map_err(|_| StatusCode::UNAUTHORIZED)?;
+            // A key can never have more rights than its user currently has
+            let scopes = Vec::<Scope>::from(user.account_tier)
+                .into_iter()
+                .filter(|scope| named_key.scopes.contains(scope))
+                .collect();
+            (user, scopes)
+        }
+    };
+
+    let claim = Claim::new(user.id.clone(), scopes, user.account_tier, user);
 
     let token = claim.into_token(key_manager.private_key())?;
 
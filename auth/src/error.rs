@@ -12,6 +12,12 @@ use stripe::StripeError;
 pub enum Error {
     #[error("User could not be found")]
     UserNotFound,
+    #[error("API key could not be found")]
+    ApiKeyNotFound,
+    #[error("An API key with this name already exists")]
+    ApiKeyExists,
+    #[error("Invalid API key request: {0}")]
+    InvalidApiKey(String),
     #[error("API key is missing.")]
     KeyMissing,
     #[error("Unauthorized.")]
@@ -48,9 +54,11 @@ impl IntoResponse for Error {
         let code = match self {
             Error::Forbidden => StatusCode::FORBIDDEN,
             Error::Unauthorized | Error::KeyMissing => StatusCode::UNAUTHORIZED,
-            Error::Database(sqlx::Error::RowNotFound) | Error::UserNotFound => {
-                StatusCode::NOT_FOUND
-            }
+            Error::Database(sqlx::Error::RowNotFound)
+            | Error::UserNotFound
+            | Error::ApiKeyNotFound => StatusCode::NOT_FOUND,
+            Error::ApiKeyExists => StatusCode::CONFLICT,
+            Error::InvalidApiKey(_) => StatusCode::BAD_REQUEST,
             _ => {
                 // We only want to emit error events for internal errors, not e.g. 404s.
                 tracing::error!(
@@ -10,9 +10,13 @@ use axum::{
 use chrono::{DateTime, Utc};
 use shuttle_backends::{client::PermissionsDal, headers::XShuttleAdminSecret};
 use shuttle_common::{
-    claims::AccountTier, limits::Limits, models, models::user::UserId, ApiKey, Secret,
+    claims::{AccountTier, Scope},
+    limits::Limits,
+    models,
+    models::user::UserId,
+    ApiKey, Secret,
 };
-use sqlx::{postgres::PgRow, query, FromRow, PgPool, Row};
+use sqlx::{postgres::PgRow, query, types::Json, FromRow, PgPool, Row};
 use stripe::{SubscriptionId, SubscriptionStatus};
 use tracing::{debug, error, trace, Span};
 
@@ -28,6 +32,18 @@ pub trait UserManagement: Send + Sync {
     async fn get_user(&self, user_id: UserId) -> Result<User, Error>;
     async fn get_user_by_key(&self, key: ApiKey) -> Result<User, Error>;
     async fn reset_key(&self, user_id: UserId) -> Result<(), Error>;
+    /// Create a named key for a user that can only use the given scopes
+    async fn create_api_key(
+        &self,
+        user_id: &UserId,
+        name: String,
+        scopes: Vec<Scope>,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<NamedKey, Error>;
+    async fn get_api_keys(&self, user_id: &UserId) -> Result<Vec<NamedKey>, Error>;
+    async fn revoke_api_key(&self, user_id: &UserId, name: &str) -> Result<(), Error>;
+    /// Get the user of a named key that has not expired, along with the key
+    async fn get_user_by_named_key(&self, key: ApiKey) -> Result<(User, NamedKey), Error>;
     async fn insert_subscription(
         &self,
         user_id: &UserId,
@@ -193,6 +209,79 @@ where
         }
     }
 
+    async fn create_api_key(
+        &self,
+        user_id: &UserId,
+        name: String,
+        scopes: Vec<Scope>,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<NamedKey, Error> {
+        let key = ApiKey::generate();
+
+        let named_key: NamedKey = sqlx::query_as(
+            r#"INSERT INTO api_keys (key, user_id, name, scopes, expires_at)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING *
+        "#,
+        )
+        .bind(&key)
+        .bind(user_id)
+        .bind(name)
+        .bind(Json(scopes))
+        .bind(expires_at)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|err| match err {
+            sqlx::Error::Database(ref db_err) if db_err.is_unique_violation() => {
+                Error::ApiKeyExists
+            }
+            sqlx::Error::Database(ref db_err) if db_err.is_foreign_key_violation() => {
+                Error::UserNotFound
+            }
+            err => Error::Database(err),
+        })?;
+
+        Ok(named_key)
+    }
+
+    async fn get_api_keys(&self, user_id: &UserId) -> Result<Vec<NamedKey>, Error> {
+        let keys = sqlx::query_as("SELECT * FROM api_keys WHERE user_id = $1 ORDER BY created_at")
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(keys)
+    }
+
+    async fn revoke_api_key(&self, user_id: &UserId, name: &str) -> Result<(), Error> {
+        let rows_affected = query("DELETE FROM api_keys WHERE user_id = $1 AND name = $2")
+            .bind(user_id)
+            .bind(name)
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+
+        if rows_affected > 0 {
+            Ok(())
+        } else {
+            Err(Error::ApiKeyNotFound)
+        }
+    }
+
+    async fn get_user_by_named_key(&self, key: ApiKey) -> Result<(User, NamedKey), Error> {
+        let named_key: NamedKey = sqlx::query_as(
+            "SELECT * FROM api_keys WHERE key = $1 AND (expires_at IS NULL OR expires_at > NOW())",
+        )
+        .bind(&key)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or(Error::ApiKeyNotFound)?;
+
+        let user = self.get_user(named_key.user_id.clone()).await?;
+
+        Ok((user, named_key))
+    }
+
     async fn insert_subscription(
         &self,
         user_id: &UserId,
@@ -282,6 +371,17 @@ pub struct User {
     pub has_access_to_beta: bool,
 }
 
+/// A named API key of a user, which is limited to a subset of the scopes of the user
+#[derive(Clone, Debug)]
+pub struct NamedKey {
+    pub key: Secret<ApiKey>,
+    pub user_id: UserId,
+    pub name: String,
+    pub scopes: Vec<Scope>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
 #[derive(Clone, Debug)]
 pub struct Subscription {
     pub id: stripe::SubscriptionId,
@@ -399,6 +499,21 @@ impl FromRow<'_, PgRow> for User {
     }
 }
 
+impl FromRow<'_, PgRow> for NamedKey {
+    fn from_row(row: &PgRow) -> Result<Self, sqlx::Error> {
+        let Json(scopes) = row.try_get("scopes")?;
+
+        Ok(NamedKey {
+            key: Secret::new(row.try_get("key")?),
+            user_id: row.try_get("user_id")?,
+            name: row.try_get("name")?,
+            scopes,
+            expires_at: row.try_get("expires_at")?,
+            created_at: row.try_get("created_at")?,
+        })
+    }
+}
+
 impl FromRow<'_, PgRow> for Subscription {
     fn from_row(row: &PgRow) -> Result<Self, sqlx::Error> {
         Ok(Subscription {
@@ -487,6 +602,18 @@ impl From<Subscription> for models::user::Subscription {
     }
 }
 
+impl From<NamedKey> for models::user::ApiKeyResponse {
+    fn from(named_key: NamedKey) -> Self {
+        Self {
+            name: named_key.name,
+            key: None,
+            scopes: named_key.scopes.iter().map(ToString::to_string).collect(),
+            expires_at: named_key.expires_at,
+            created_at: named_key.created_at,
+        }
+    }
+}
+
 /// A wrapper around [ApiKey] so we can implement [FromRequestParts] for it.
 pub struct Key(ApiKey);
 
@@ -2,8 +2,11 @@ mod needs_docker {
     use http::header::AUTHORIZATION;
     use http::{Request, StatusCode};
     use hyper::Body;
-    use serde_json::Value;
-    use shuttle_common::claims::AccountTier;
+    use serde_json::{json, Value};
+    use shuttle_common::{
+        claims::{AccountTier, Scope},
+        models::user,
+    };
 
     use crate::helpers::{app, ADMIN_KEY};
 
@@ -82,4 +85,101 @@ mod needs_docker {
         assert_eq!(claim.tier, AccountTier::Basic);
         assert_eq!(claim.limits.project_limit(), 3);
     }
+
+    #[tokio::test]
+    async fn convert_named_api_key_to_jwt() {
+        let app = app().await;
+
+        let response = app.post_user("test-user-basic", "basic").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let user: Value = serde_json::from_slice(&body).unwrap();
+        let user_key = user["key"].as_str().unwrap();
+
+        // Unknown scopes and expiries in the past are rejected.
+        let response = app
+            .post_api_key(user_key, json!({"name": "ci", "scopes": ["not_a_scope"]}))
+            .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let response = app
+            .post_api_key(
+                user_key,
+                json!({"name": "ci", "scopes": ["logs"], "expires_at": "2020-01-01T00:00:00Z"}),
+            )
+            .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        // Admin scopes can not be requested by a basic user.
+        let response = app
+            .post_api_key(
+                user_key,
+                json!({"name": "ci", "scopes": ["deployment_push", "logs", "admin"]}),
+            )
+            .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = app
+            .post_api_key(
+                user_key,
+                json!({"name": "ci", "scopes": ["deployment_push", "logs"]}),
+            )
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let named_key: user::ApiKeyResponse = serde_json::from_slice(&body).unwrap();
+        let ci_key = named_key.key.unwrap();
+
+        let response = app
+            .post_api_key(user_key, json!({"name": "ci", "scopes": ["logs"]}))
+            .await;
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+
+        // A named key can not be used to manage keys.
+        let response = app
+            .post_api_key(&ci_key, json!({"name": "other", "scopes": ["logs"]}))
+            .await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let claim = app.get_claim(&ci_key).await;
+        assert_eq!(claim.sub, user["id"].as_str().unwrap());
+        assert_eq!(claim.tier, AccountTier::Basic);
+        assert_eq!(claim.scopes, vec![Scope::DeploymentPush, Scope::Logs]);
+
+        // List the keys, without exposing them.
+        let request = Request::builder()
+            .uri("/users/keys")
+            .header(AUTHORIZATION, format!("Bearer {user_key}"))
+            .body(Body::empty())
+            .unwrap();
+        let response = app.send_request(request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let keys: Vec<user::ApiKeyResponse> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(keys.len(), 1);
+        assert_eq!(keys[0].name, "ci");
+        assert_eq!(keys[0].scopes, vec!["deployment_push", "logs"]);
+        assert!(keys[0].key.is_none());
+
+        // Revoked keys can not be converted anymore.
+        let request = Request::builder()
+            .uri("/users/keys/ci")
+            .method("DELETE")
+            .header(AUTHORIZATION, format!("Bearer {user_key}"))
+            .body(Body::empty())
+            .unwrap();
+        let response = app.send_request(request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = app.get_jwt_from_api_key(&ci_key, Some(ADMIN_KEY)).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let request = Request::builder()
+            .uri("/users/keys/ci")
+            .method("DELETE")
+            .header(AUTHORIZATION, format!("Bearer {user_key}"))
+            .body(Body::empty())
+            .unwrap();
+        let response = app.send_request(request).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
 }
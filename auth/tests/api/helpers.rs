@@ -173,6 +173,18 @@ impl TestApp {
         self.send_request(request).await
     }
 
+    pub async fn post_api_key(&self, user_api_key: &str, body: Value) -> Response {
+        let request = Request::builder()
+            .uri("/users/keys")
+            .method("POST")
+            .header(AUTHORIZATION, format!("Bearer {user_api_key}"))
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(serde_json::to_vec(&body).unwrap()))
+            .unwrap();
+
+        self.send_request(request).await
+    }
+
     pub async fn claim_from_response(&self, res: Response) -> Claim {
         let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
         let convert: Value = serde_json::from_slice(&body).unwrap();
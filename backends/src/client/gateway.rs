@@ -114,6 +114,8 @@ mod tests {
                     idle_minutes: Some(30),
                     owner: shuttle_common::models::project::Owner::User("user-1".to_string()),
                     is_admin: true,
+                    container_limits: None,
+                    tcp_port: None,
                 },
                 Response {
                     id: "00000000000000000000000002".to_string(),
@@ -122,6 +124,8 @@ mod tests {
                     idle_minutes: Some(30),
                     owner: shuttle_common::models::project::Owner::User("user-1".to_string()),
                     is_admin: true,
+                    container_limits: None,
+                    tcp_port: None,
                 }
             ]
         )
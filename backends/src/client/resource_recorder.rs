@@ -128,11 +128,13 @@ mod tests {
                         r#type: "database::shared::postgres".to_string(),
                         config: serde_json::to_vec(&json!({"public": true})).unwrap(),
                         data: serde_json::to_vec(&json!({"username": "test"})).unwrap(),
+                        name: String::new(),
                     },
                     record_request::Resource {
                         r#type: "database::aws_rds::mariadb".to_string(),
                         config: serde_json::to_vec(&json!({})).unwrap(),
                         data: serde_json::to_vec(&json!({"username": "maria"})).unwrap(),
+                        name: String::new(),
                     },
                 ],
             }))
@@ -153,6 +155,7 @@ mod tests {
                     )),
                     config: json!({"public": true}),
                     data: json!({"username": "test"}),
+                    name: None,
                 },
                 resource::Response {
                     r#type: resource::Type::Database(database::Type::AwsRds(
@@ -160,6 +163,7 @@ mod tests {
                     )),
                     config: json!({}),
                     data: json!({"username": "maria"}),
+                    name: None,
                 }
             ]
         );
@@ -178,6 +182,7 @@ mod tests {
                 )),
                 config: json!({}),
                 data: json!({"username": "maria"}),
+                name: None,
             }]
         );
     }
@@ -41,6 +41,7 @@ impl ResourceRecorder for MockedResourceRecorder {
                 is_active: true,
                 created_at: None,
                 last_updated: None,
+                name: r.name,
             })
             .collect();
 
@@ -119,6 +120,7 @@ impl ResourceRecorder for MockedResourceRecorder {
             project_id,
             service_id,
             r#type,
+            name,
         } = request.into_inner();
         let resource = self
             .resources
@@ -126,7 +128,10 @@ impl ResourceRecorder for MockedResourceRecorder {
             .unwrap()
             .iter()
             .find(|r| {
-                r.project_id == project_id && r.service_id == service_id && r.r#type == r#type
+                r.project_id == project_id
+                    && r.service_id == service_id
+                    && r.r#type == r#type
+                    && r.name == name
             })
             .cloned();
 
@@ -147,10 +152,14 @@ impl ResourceRecorder for MockedResourceRecorder {
             project_id,
             service_id,
             r#type,
+            name,
         } = request.into_inner();
 
         self.resources.lock().unwrap().retain(|r| {
-            !(r.project_id == project_id && r.service_id == service_id && r.r#type == r#type)
+            !(r.project_id == project_id
+                && r.service_id == service_id
+                && r.r#type == r#type
+                && r.name == name)
         });
 
         Ok(Response::new(ResultResponse {
@@ -7,12 +7,15 @@ use std::{
 
 use anyhow::{bail, Context};
 use cargo_metadata::MetadataCommand;
+use chrono::{DateTime, Duration, Utc};
 use clap::{
     builder::{OsStringValueParser, PossibleValue, TypedValueParser},
-    Parser, ValueEnum,
+    CommandFactory, Parser, ValueEnum,
 };
 use clap_complete::Shell;
-use shuttle_common::constants::{DEFAULT_IDLE_MINUTES, EXAMPLES_REPO};
+use shuttle_common::constants::{DEFAULT_IDLE_MINUTES, DEFAULT_WAKE_TIMEOUT_SECS, EXAMPLES_REPO};
+use shuttle_common::log::LogsFilter;
+use shuttle_common::models::project::WakeConfig;
 use shuttle_common::resource;
 
 #[derive(Parser)]
@@ -41,11 +44,26 @@ pub struct ShuttleArgs {
     /// Target Shuttle's development environment
     #[arg(long, env = "SHUTTLE_BETA", hide = true)]
     pub beta: bool,
+    /// Output format for command results
+    #[arg(global = true, long, value_enum, default_value_t = OutputMode::Table)]
+    pub output: OutputMode,
 
     #[command(subcommand)]
     pub cmd: Command,
 }
 
+/// How command results are printed
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputMode {
+    /// Human readable tables and log lines
+    #[default]
+    Table,
+    /// JSON, with one object per line for logs
+    Json,
+    /// YAML
+    Yaml,
+}
+
 // Common args for subcommands that deal with projects.
 #[derive(Parser, Clone, Debug)]
 pub struct ProjectArgs {
@@ -120,14 +138,20 @@ pub enum Command {
     /// Manage resources
     #[command(subcommand, visible_alias = "res")]
     Resource(ResourceCommand),
+    /// Manage the secrets of a service without redeploying it
+    ///
+    /// Secrets set with these commands are kept across deployments, but values in Secrets.toml take precedence
+    /// over them when a deployment includes it.
+    #[command(subcommand)]
+    Secrets(SecretsCommand),
     /// BETA: Manage SSL certificates for custom domains
     #[command(subcommand, visible_alias = "cert", hide = true)]
     Certificate(CertificateCommand),
     /// Remove cargo build artifacts in the Shuttle environment
-    Clean,
-    /// BETA: Show info about your Shuttle account
-    #[command(visible_alias = "acc", hide = true)]
-    Account,
+    Clean(CleanArgs),
+    /// Show info about your Shuttle account (BETA) and manage its API keys
+    #[command(visible_alias = "acc")]
+    Account(AccountArgs),
     /// Login to the Shuttle platform
     Login(LoginArgs),
     /// Log out of the Shuttle platform
@@ -145,14 +169,58 @@ pub enum GenerateCommand {
     Shell {
         /// The shell to generate shell completion for
         shell: Shell,
-        /// Output to a file (stdout by default)
+        /// Output to a file (stdout by default). `--output` is also accepted, as the older name of this option
         #[arg(short, long)]
-        output: Option<PathBuf>,
+        output_file: Option<PathBuf>,
     },
     /// Generate man page to the standard output
     Manpage,
 }
 
+#[derive(Parser)]
+pub struct AccountArgs {
+    #[command(subcommand)]
+    pub cmd: Option<AccountCommand>,
+}
+
+#[derive(Parser)]
+pub enum AccountCommand {
+    /// Manage named API keys with limited scopes, such as for CI
+    #[command(subcommand)]
+    Keys(KeysCommand),
+}
+
+#[derive(Parser)]
+pub enum KeysCommand {
+    /// Create a named API key
+    Create {
+        /// Name of the key
+        name: String,
+        /// Scopes the key can use, such as 'deployment_push' or 'logs'. They have to be available to your account.
+        /// Can be given several times or as a comma-separated list.
+        #[arg(long = "scope", required = true, value_delimiter = ',')]
+        scopes: Vec<String>,
+        /// Number of days until the key expires. Never expires by default
+        #[arg(long)]
+        expires_in_days: Option<u32>,
+    },
+    /// List your named API keys
+    #[command(visible_alias = "ls")]
+    List {
+        #[command(flatten)]
+        table: TableArgs,
+    },
+    /// Revoke a named API key. It can keep working for up to 5 minutes, until the platform stops using its cached
+    /// token
+    #[command(visible_alias = "rm")]
+    Revoke {
+        /// Name of the key
+        name: String,
+        #[command(flatten)]
+        confirmation: ConfirmationArgs,
+    },
+}
+
 #[derive(Parser)]
 pub struct TableArgs {
     #[arg(long, default_value_t = false)]
@@ -181,6 +249,11 @@ pub enum DeploymentCommand {
         /// ID of deployment to get status for
         id: Option<String>,
     },
+    /// Run a previous deployment again, without building it
+    Rollback {
+        /// ID of deployment to roll back to. Defaults to the one that ran before the current deployment
+        id: Option<String>,
+    },
     /// BETA: Stop running deployment(s)
     #[command(hide = true)]
     Stop,
@@ -205,11 +278,56 @@ pub enum ResourceCommand {
         /// Use the string in the 'Type' column as displayed in the `resource list` command.
         /// For example, 'database::shared::postgres'.
         resource_type: resource::Type,
+        /// Name of the resource instance to delete, if the resource was given a name.
+        /// Use the string in the 'Name' column as displayed in the `resource list` command.
+        #[arg(long)]
+        name: Option<String>,
         #[command(flatten)]
         confirmation: ConfirmationArgs,
     },
 }
 
+#[derive(Parser)]
+pub enum SecretsCommand {
+    /// List the secrets of the service
+    #[command(visible_alias = "ls")]
+    List {
+        #[command(flatten)]
+        table: TableArgs,
+
+        #[arg(long, default_value_t = false)]
+        /// Show the secret values instead of redacting them
+        show_secrets: bool,
+    },
+    /// Add or change secrets
+    Set {
+        /// Secrets to set, given as KEY=VALUE
+        #[arg(required = true, value_parser = parse_secret)]
+        secrets: Vec<(String, String)>,
+        #[arg(long, default_value_t = false)]
+        /// Restart the running deployment so that it uses the new secrets
+        restart: bool,
+    },
+    /// Remove secrets
+    #[command(visible_alias = "rm")]
+    Unset {
+        /// Keys of the secrets to remove
+        #[arg(required = true)]
+        keys: Vec<String>,
+        #[arg(long, default_value_t = false)]
+        /// Restart the running deployment so that it uses the new secrets
+        restart: bool,
+    },
+    /// Add or change the secrets in a .env file
+    Import {
+        /// Path to the .env file [default: .env in the working directory]
+        path: Option<PathBuf>,
+        #[arg(long, default_value_t = false)]
+        /// Restart the running deployment so that it uses the new secrets
+        restart: bool,
+    },
+}
+
 #[derive(Parser)]
 pub enum CertificateCommand {
     /// Add an SSL certificate for a custom domain
@@ -263,6 +381,18 @@ pub enum ProjectCommand {
     /// Delete a project and all linked data
     #[command(visible_alias = "rm")]
     Delete(ConfirmationArgs),
+    /// Manage the port that forwards raw TCP connections to this project, for services that do not speak HTTP.
+    /// UDP is not supported.
+    #[command(subcommand)]
+    Tcp(TcpCommand),
+}
+
+#[derive(Parser)]
+pub enum TcpCommand {
+    /// Give this project a TCP port, or show the one it has
+    Enable,
+    /// Give up the TCP port of this project
+    Disable,
 }
 
 #[derive(Parser, Debug)]
@@ -278,6 +408,23 @@ pub struct ProjectStartArgs {
     /// How long to wait before putting the project in an idle state due to inactivity.
     /// 0 means the project will never idle
     pub idle_minutes: u64,
+    #[arg(long)]
+    /// How many seconds requests to an idle project wait for it to wake up, at most 300 [default: 60]
+    pub wake_timeout: Option<u64>,
+    #[arg(long)]
+    /// Answer requests that time out waiting for the project to wake up with a "waking up" page
+    /// that asks to retry later, instead of an error
+    pub waking_response: bool,
+}
+
+impl ProjectStartArgs {
+    /// How the project should wake up, if any of the options for it were given
+    pub fn wake_config(&self) -> Option<WakeConfig> {
+        (self.wake_timeout.is_some() || self.waking_response).then(|| WakeConfig {
+            timeout: self.wake_timeout.unwrap_or(DEFAULT_WAKE_TIMEOUT_SECS),
+            waking_response: self.waking_response,
+        })
+    }
 }
 
 #[derive(Parser, Clone, Debug, Default)]
@@ -294,6 +441,13 @@ pub struct LogoutArgs {
     pub reset_api_key: bool,
 }
 
+#[derive(Parser, Debug, Default)]
+pub struct CleanArgs {
+    /// Also remove the cached dependencies of the project from the build cache
+    #[arg(long)]
+    pub evict_cache: bool,
+}
+
 #[derive(Parser, Default)]
 pub struct DeployArgs {
     /// BETA: Deploy this Docker image instead of building one
@@ -315,6 +469,9 @@ pub struct DeployArgs {
     /// Output the deployment archive to a file instead of sending a deployment request
     #[arg(long)]
     pub output_archive: Option<PathBuf>,
+    /// Run the pre-deploy tests locally like the deployer would, without deploying
+    #[arg(long, conflicts_with_all = ["no_test", "output_archive"])]
+    pub dry_run: bool,
 
     #[command(flatten)]
     pub secret_args: SecretsArgs,
@@ -334,6 +491,9 @@ pub struct RunArgs {
     /// Don't display timestamps and log origin tags
     #[arg(long)]
     pub raw: bool,
+    /// Rebuild and restart the service when files in the project change
+    #[arg(long, short = 'w')]
+    pub watch: bool,
 
     #[command(flatten)]
     pub secret_args: SecretsArgs,
@@ -344,6 +504,12 @@ pub struct SecretsArgs {
     /// Use this secrets file instead
     #[arg(long, value_parser = OsStringValueParser::new().try_map(parse_path))]
     pub secrets: Option<PathBuf>,
+    /// Apply the secrets in Secrets.<ENV>.toml on top of Secrets.toml (`run` uses `dev` by default)
+    #[arg(long, conflicts_with = "secrets")]
+    pub env: Option<String>,
+    /// Replace `${NAME}` in secret values with the environment variable NAME (use `$${` for a literal `${`)
+    #[arg(long)]
+    pub interpolate: bool,
 }
 
 #[derive(Parser, Clone, Debug, Default)]
@@ -479,6 +645,136 @@ pub struct LogsArgs {
     /// Get logs from all deployments instead of one deployment
     #[arg(long)]
     pub all_deployments: bool,
+    /// Only show logs since a time, either as RFC 3339 or as a duration ago, such as 30m, 1h or 2d
+    #[arg(long, value_parser = parse_time)]
+    pub since: Option<DateTime<Utc>>,
+    /// Only show logs until a time, either as RFC 3339 or as a duration ago, such as 30m, 1h or 2d
+    #[arg(long, value_parser = parse_time)]
+    pub until: Option<DateTime<Utc>>,
+    /// Only show log lines containing this text
+    #[arg(long)]
+    pub grep: Option<String>,
+    /// Match --grep as a regular expression
+    #[arg(long, requires = "grep")]
+    pub regex: bool,
+    /// Only show logs from this source, such as deployer or runtime
+    #[arg(long)]
+    pub source: Option<String>,
+    /// Only show logs at this level or above
+    #[arg(long, value_parser = ["trace", "debug", "info", "warn", "error"], ignore_case = true)]
+    pub level: Option<String>,
+}
+
+impl LogsArgs {
+    pub fn filter(&self) -> LogsFilter {
+        LogsFilter {
+            since: self.since,
+            until: self.until,
+            grep: self.grep.clone(),
+            regex: self.regex,
+            source: self.source.clone(),
+            level: self.level.clone(),
+        }
+    }
+}
+
+/// Helper function to parse a point in time, either as RFC 3339 or as a duration before now
+fn parse_time(time: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(time) {
+        return Ok(time.into());
+    }
+
+    let invalid =
+        || format!("'{time}' is neither an RFC 3339 time nor a duration like 30m, 1h or 2d");
+    let unit_at = time
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(invalid)?;
+    let (amount, unit) = time.split_at(unit_at);
+    let amount: i64 = amount.parse().map_err(|_| invalid())?;
+    let ago = match unit {
+        "s" => Duration::try_seconds(amount),
+        "m" => Duration::try_minutes(amount),
+        "h" => Duration::try_hours(amount),
+        "d" => Duration::try_days(amount),
+        "w" => Duration::try_weeks(amount),
+        _ => None,
+    }
+    .ok_or_else(invalid)?;
+
+    Ok(Utc::now() - ago)
+}
+
+/// Helper function to parse a secret given as KEY=VALUE
+fn parse_secret(secret: &str) -> Result<(String, String), String> {
+    match secret.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_owned(), value.to_owned())),
+        // Leave the argument out of the error, since it could contain the secret value
+        _ => Err("expected a secret in the KEY=VALUE format".to_owned()),
+    }
+}
+
+/// `generate shell` took its file as `--output` before that became the global output mode, which clap does not allow
+/// as an alias. Rename it to `--output-file` before parsing, so that existing scripts keep working.
+pub fn rename_legacy_args(args: impl IntoIterator<Item = OsString>) -> Vec<OsString> {
+    let mut args: Vec<OsString> = args.into_iter().collect();
+
+    let command = ShuttleArgs::command();
+    // Whether `option` is one of the options before the subcommand that take a value, like `--name <NAME>`
+    let takes_value = |option: &str| {
+        let long = option.strip_prefix("--");
+        let short = option
+            .strip_prefix('-')
+            .filter(|short| long.is_none() && short.chars().count() == 1)
+            .and_then(|short| short.chars().next());
+
+        command
+            .get_arguments()
+            .filter(|arg| arg.get_action().takes_values())
+            .any(|arg| match long {
+                Some(long) => {
+                    arg.get_long() == Some(long)
+                        || arg
+                            .get_all_aliases()
+                            .is_some_and(|aliases| aliases.contains(&long))
+                }
+                None => short.is_some() && arg.get_short() == short,
+            })
+    };
+
+    // Find the subcommands, skipping the options and their values, which could be `generate` too
+    let mut subcommands = Vec::new();
+    let mut index = 1;
+    while index < args.len() && subcommands.len() < 2 {
+        let arg = args[index].to_string_lossy();
+        if arg == "--" {
+            break;
+        } else if arg.starts_with('-') {
+            if !arg.contains('=') && takes_value(&arg) {
+                index += 1;
+            }
+        } else if !(index == 1 && arg == "shuttle") {
+            // Cargo passes in the name of the subcommand it runs first
+            subcommands.push(index);
+        }
+        index += 1;
+    }
+
+    let [generate, shell] = subcommands[..] else {
+        return args;
+    };
+    if args[generate] != "generate" || args[shell] != "shell" {
+        return args;
+    }
+
+    for arg in &mut args[shell + 1..] {
+        if arg == "--output" {
+            *arg = "--output-file".into();
+        } else if let Some(file) = arg.to_str().and_then(|arg| arg.strip_prefix("--output=")) {
+            *arg = format!("--output-file={file}").into();
+        }
+    }
+
+    args
 }
 
 /// Helper function to parse and return the absolute path
@@ -515,6 +811,100 @@ mod tests {
         ShuttleArgs::command().debug_assert();
     }
 
+    #[test]
+    fn test_generate_shell_legacy_output() {
+        let parse = |args: &[&str]| {
+            let args = rename_legacy_args(args.iter().map(OsString::from));
+            ShuttleArgs::try_parse_from(args).unwrap()
+        };
+
+        for args in [
+            &[
+                "cargo-shuttle",
+                "generate",
+                "shell",
+                "bash",
+                "--output",
+                "out",
+            ][..],
+            &["cargo-shuttle", "generate", "shell", "bash", "--output=out"],
+            &[
+                "cargo-shuttle",
+                "generate",
+                "shell",
+                "bash",
+                "--output-file",
+                "out",
+            ],
+            &["cargo-shuttle", "generate", "shell", "bash", "-o", "out"],
+        ] {
+            let ShuttleArgs {
+                cmd: Command::Generate(GenerateCommand::Shell { output_file, .. }),
+                output,
+                ..
+            } = parse(args)
+            else {
+                panic!("expected a generate shell command for {args:?}");
+            };
+            assert_eq!(output_file, Some(PathBuf::from("out")), "{args:?}");
+            assert_eq!(output, OutputMode::Table, "{args:?}");
+        }
+
+        // Option values are not mistaken for the subcommand
+        let ShuttleArgs {
+            project_args,
+            cmd: Command::Generate(GenerateCommand::Shell { output_file, .. }),
+            ..
+        } = parse(&[
+            "cargo-shuttle",
+            "shuttle",
+            "--wd",
+            ".",
+            "--name",
+            "generate",
+            "generate",
+            "shell",
+            "bash",
+            "--output",
+            "out",
+        ])
+        else {
+            panic!("expected a generate shell command");
+        };
+        assert_eq!(project_args.name.as_deref(), Some("generate"));
+        assert_eq!(output_file, Some(PathBuf::from("out")));
+
+        // The output mode of other commands is not touched
+        let args = parse(&["cargo-shuttle", "--output", "json", "project", "list"]);
+        assert_eq!(args.output, OutputMode::Json);
+        let args = parse(&[
+            "cargo-shuttle",
+            "--name",
+            "generate",
+            "project",
+            "list",
+            "--output",
+            "json",
+        ]);
+        assert_eq!(args.output, OutputMode::Json);
+    }
+
+    #[test]
+    fn test_parse_time() {
+        assert_eq!(
+            parse_time("2024-05-01T12:00:00Z").unwrap(),
+            DateTime::parse_from_rfc3339("2024-05-01T12:00:00+00:00").unwrap()
+        );
+
+        let hour_ago = parse_time("1h").unwrap();
+        let elapsed = Utc::now() - hour_ago;
+        assert!(elapsed >= Duration::hours(1) && elapsed < Duration::minutes(61));
+
+        assert!(parse_time("1y").is_err());
+        assert!(parse_time("h").is_err());
+        assert!(parse_time("yesterday").is_err());
+    }
+
     #[test]
     fn test_init_args_framework() {
         // pre-defined template (only hello world)
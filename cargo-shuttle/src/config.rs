@@ -6,6 +6,7 @@ use anyhow::{anyhow, Context, Result};
 use serde::{Deserialize, Serialize};
 use shuttle_common::constants::API_URL_BETA;
 use shuttle_common::{constants::API_URL_DEFAULT, ApiKey};
+use shuttle_service::builder::{BuildConfig, HealthCheckConfig, RestartConfig, TestConfig};
 use tracing::trace;
 
 use crate::args::ProjectArgs;
@@ -149,6 +150,16 @@ impl GlobalConfig {
 pub struct ProjectConfig {
     pub name: Option<String>,
     pub assets: Option<Vec<String>>,
+    pub build: Option<BuildConfig>,
+    pub deploy: Option<DeployConfig>,
+}
+
+/// The `[deploy]` section of the project config
+#[derive(Deserialize, Serialize, Default)]
+pub struct DeployConfig {
+    pub test: Option<TestConfig>,
+    pub health_check: Option<HealthCheckConfig>,
+    pub restart: Option<RestartConfig>,
 }
 
 /// A handler for configuration files. The type parameter `M` is the [`ConfigManager`] which handles
@@ -400,6 +411,31 @@ impl RequestContext {
             .assets
             .as_ref()
     }
+
+    /// # Panics
+    /// Panics if the project configuration has not been loaded.
+    pub fn build_config(&self) -> Option<&BuildConfig> {
+        self.project
+            .as_ref()
+            .unwrap()
+            .as_ref()
+            .unwrap()
+            .build
+            .as_ref()
+    }
+
+    /// # Panics
+    /// Panics if the project configuration has not been loaded.
+    pub fn deploy_test_config(&self) -> Option<&TestConfig> {
+        self.project
+            .as_ref()
+            .unwrap()
+            .as_ref()
+            .unwrap()
+            .deploy
+            .as_ref()
+            .and_then(|deploy| deploy.test.as_ref())
+    }
 }
 
 #[cfg(test)]
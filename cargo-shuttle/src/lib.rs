@@ -2,12 +2,14 @@ mod args;
 pub mod config;
 mod init;
 mod provisioner_server;
+mod secrets;
 mod suggestions;
+mod watch;
 
 use std::collections::{BTreeMap, HashMap};
 use std::ffi::OsString;
 use std::fmt::Write as FmtWrite;
-use std::fs::{read_to_string, File};
+use std::fs::File;
 use std::io::{stdout, Read, Write};
 use std::net::{Ipv4Addr, SocketAddr};
 use std::path::{Path, PathBuf};
@@ -32,8 +34,10 @@ use ignore::WalkBuilder;
 use indicatif::ProgressBar;
 use indoc::{formatdoc, printdoc};
 use reqwest::header::HeaderMap;
+use serde::Serialize;
 use shuttle_api_client::ShuttleApiClient;
 use shuttle_common::models::resource::get_certificates_table_beta;
+use shuttle_common::models::user::{get_api_keys_table, ApiKeyRequest};
 use shuttle_common::{
     constants::{
         headers::X_CARGO_SHUTTLE_VERSION, API_URL_DEFAULT, DEFAULT_IDLE_MINUTES, EXAMPLES_REPO,
@@ -42,7 +46,7 @@ use shuttle_common::{
         TEMPLATES_SCHEMA_VERSION,
     },
     deployment::{EcsState, DEPLOYER_END_MESSAGES_BAD, DEPLOYER_END_MESSAGES_GOOD},
-    log::LogsRange,
+    log::{LogsFilter, LogsRange},
     models::{
         deployment::{
             deployments_table_beta, get_deployments_table, BuildArgsBeta, BuildArgsRustBeta,
@@ -51,8 +55,9 @@ use shuttle_common::{
             CREATE_SERVICE_BODY_LIMIT, GIT_STRINGS_MAX_LENGTH,
         },
         error::ApiError,
-        project,
-        resource::get_resource_tables,
+        project::{self, WakeConfig},
+        resource::{get_resource_tables, redact_resource_secrets},
+        secret::{get_secrets_table, secrets_map, SecretsUpdateRequest},
     },
     resource::{self, ResourceInput, ShuttleResourceOutput},
     semvers_are_compatible,
@@ -64,7 +69,9 @@ use shuttle_proto::{
     runtime::{self, LoadRequest, StartRequest, StopRequest},
 };
 use shuttle_service::{
-    builder::{async_cargo_metadata, build_workspace, find_shuttle_packages, BuiltService},
+    builder::{
+        async_cargo_metadata, build_workspace, find_shuttle_packages, BuiltService, TestWaitError,
+    },
     runner, Environment,
 };
 use strum::{EnumMessage, VariantArray};
@@ -78,20 +85,28 @@ use uuid::Uuid;
 use zip::write::FileOptions;
 
 use crate::args::{
-    CertificateCommand, ConfirmationArgs, DeployArgs, DeploymentCommand, GenerateCommand, InitArgs,
-    LoginArgs, LogoutArgs, LogsArgs, ProjectCommand, ProjectStartArgs, ResourceCommand, TableArgs,
-    TemplateLocation,
+    rename_legacy_args, AccountArgs, AccountCommand, CertificateCommand, CleanArgs,
+    ConfirmationArgs, DeployArgs, DeploymentCommand, GenerateCommand, InitArgs, KeysCommand,
+    LoginArgs, LogoutArgs, LogsArgs, ProjectCommand, ResourceCommand, SecretsArgs, SecretsCommand,
+    TableArgs, TcpCommand, TemplateLocation,
 };
-pub use crate::args::{Command, ProjectArgs, RunArgs, ShuttleArgs};
+pub use crate::args::{Command, OutputMode, ProjectArgs, RunArgs, ShuttleArgs};
 use crate::config::RequestContext;
 use crate::provisioner_server::beta::{ProvApiState, ProvisionerServerBeta};
 use crate::provisioner_server::LocalProvisioner;
+use crate::watch::Watcher;
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
+/// How long to wait for a local runtime to shut down gracefully before killing it
+const RUNTIME_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Outputs of the databases and containers that were provisioned for a local run, keyed by their resource
+/// request. Reused when `--watch` restarts the services, so that the containers are not checked again.
+type ProvisionedResources = HashMap<Vec<u8>, (Vec<u8>, Option<resource::Response>)>;
 
 // Returns the args and whether the PATH arg of the init command was explicitly given
 pub fn parse_args() -> (ShuttleArgs, bool) {
-    let matches = ShuttleArgs::command().get_matches();
+    let matches = ShuttleArgs::command().get_matches_from(rename_legacy_args(std::env::args_os()));
     let args =
         ShuttleArgs::from_arg_matches(&matches).expect("args to already be parsed successfully");
     let provided_path_to_init = matches
@@ -110,6 +125,8 @@ pub struct Shuttle {
     version_warnings: Vec<String>,
     /// alter behaviour to interact with the new platform
     beta: bool,
+    /// format for printing command results
+    output: OutputMode,
 }
 
 impl Shuttle {
@@ -121,6 +138,7 @@ impl Shuttle {
             version_info: None,
             version_warnings: vec![],
             beta: false,
+            output: OutputMode::Table,
         })
     }
 
@@ -130,6 +148,12 @@ impl Shuttle {
         provided_path_to_init: bool,
     ) -> Result<CommandOutcome> {
         self.beta = args.beta;
+        self.output = args.output;
+        if self.output == OutputMode::Yaml
+            && matches!(args.cmd, Command::Logs(..) | Command::Run(..))
+        {
+            bail!("YAML output is not supported for logs, use `--output json` instead");
+        }
         if self.beta {
             if matches!(args.cmd, Command::Project(ProjectCommand::Restart { .. })) {
                 eprintln!("This command is discontinued on the beta platform. Deploy to start a new deployment.");
@@ -146,14 +170,23 @@ impl Shuttle {
                 eprintln!("This command is discontinued on the beta platform. Use `deployment stop` instead.");
                 return Ok(CommandOutcome::Ok);
             }
-            if matches!(args.cmd, Command::Clean) {
+            if matches!(
+                args.cmd,
+                Command::Clean(..)
+                    | Command::Secrets(..)
+                    | Command::Project(ProjectCommand::Tcp(..))
+                    | Command::Account(AccountArgs {
+                        cmd: Some(AccountCommand::Keys(..))
+                    })
+            ) {
                 eprintln!("This command is not yet implemented on the beta platform.");
                 return Ok(CommandOutcome::Ok);
             }
             eprintln!("INFO: Using beta platform API");
         } else if matches!(
             args.cmd,
-            Command::Deployment(DeploymentCommand::Stop) | Command::Account
+            Command::Deployment(DeploymentCommand::Stop)
+                | Command::Account(AccountArgs { cmd: None })
         ) {
             eprintln!("This command is not supported on the legacy platform. Set --beta or SHUTTLE_BETA=true.");
             return Ok(CommandOutcome::Ok);
@@ -174,6 +207,7 @@ impl Shuttle {
             Command::Deploy(..)
                 | Command::Deployment(..)
                 | Command::Resource(..)
+                | Command::Secrets(..)
                 | Command::Certificate(..)
                 | Command::Project(
                     // ProjectCommand::List does not need to know which project we are in
@@ -182,9 +216,10 @@ impl Shuttle {
                         | ProjectCommand::Restart { .. }
                         | ProjectCommand::Status { .. }
                         | ProjectCommand::Delete { .. }
+                        | ProjectCommand::Tcp(..)
                 )
                 | Command::Stop
-                | Command::Clean
+                | Command::Clean(..)
                 | Command::Status
                 | Command::Logs { .. }
                 | Command::Run(..)
@@ -199,14 +234,15 @@ impl Shuttle {
                 | Command::Deploy(..)
                 | Command::Status
                 | Command::Logs { .. }
-                | Command::Account
+                | Command::Account(..)
                 | Command::Login(..)
                 | Command::Logout(..)
                 | Command::Deployment(..)
                 | Command::Resource(..)
+                | Command::Secrets(..)
                 | Command::Certificate(..)
                 | Command::Stop
-                | Command::Clean
+                | Command::Clean(..)
                 | Command::Project(..)
         ) {
             let client = ShuttleApiClient::new(
@@ -238,15 +274,33 @@ impl Shuttle {
             }
             Command::Generate(cmd) => match cmd {
                 GenerateCommand::Manpage => self.generate_manpage(),
-                GenerateCommand::Shell { shell, output } => self.complete(shell, output),
+                GenerateCommand::Shell { shell, output_file } => self.complete(shell, output_file),
+            },
+            Command::Account(AccountArgs { cmd: None }) => self.account().await,
+            Command::Account(AccountArgs {
+                cmd: Some(AccountCommand::Keys(cmd)),
+            }) => match cmd {
+                KeysCommand::Create {
+                    name,
+                    scopes,
+                    expires_in_days,
+                } => self.create_api_key(name, scopes, expires_in_days).await,
+                KeysCommand::List { table } => self.list_api_keys(table).await,
+                KeysCommand::Revoke { name, confirmation } => {
+                    self.revoke_api_key(name, confirmation.yes).await
+                }
             },
-            Command::Account => self.account().await,
             Command::Login(login_args) => self.login(login_args, args.offline).await,
             Command::Logout(logout_args) => self.logout(logout_args).await,
             Command::Feedback => self.feedback(),
             Command::Run(run_args) => {
-                if self.beta {
+                if self.beta && run_args.watch {
+                    eprintln!("Watching for changes is not yet supported on beta.");
+                    Ok(CommandOutcome::Ok)
+                } else if self.beta {
                     self.local_run_beta(run_args).await
+                } else if run_args.watch {
+                    self.local_run_watch(run_args).await
                 } else {
                     self.local_run(run_args).await
                 }
@@ -266,9 +320,10 @@ impl Shuttle {
                 }
                 DeploymentCommand::Status { id } => self.deployment_get(id).await,
                 DeploymentCommand::Stop => self.stop_beta().await,
+                DeploymentCommand::Rollback { id } => self.deployment_rollback(id).await,
             },
             Command::Stop => self.stop().await,
-            Command::Clean => self.clean().await,
+            Command::Clean(clean_args) => self.clean(clean_args).await,
             Command::Resource(cmd) => match cmd {
                 ResourceCommand::List {
                     table,
@@ -276,8 +331,37 @@ impl Shuttle {
                 } => self.resources_list(table, show_secrets).await,
                 ResourceCommand::Delete {
                     resource_type,
+                    name,
                     confirmation: ConfirmationArgs { yes },
-                } => self.resource_delete(&resource_type, yes).await,
+                } => {
+                    self.resource_delete(&resource_type, name.as_deref(), yes)
+                        .await
+                }
+            },
+            Command::Secrets(cmd) => match cmd {
+                SecretsCommand::List {
+                    table,
+                    show_secrets,
+                } => self.secrets_list(table, show_secrets).await,
+                SecretsCommand::Set { secrets, restart } => {
+                    self.secrets_update(SecretsUpdateRequest {
+                        set: secrets.into_iter().collect(),
+                        restart,
+                        ..Default::default()
+                    })
+                    .await
+                }
+                SecretsCommand::Unset { keys, restart } => {
+                    self.secrets_update(SecretsUpdateRequest {
+                        unset: keys,
+                        restart,
+                        ..Default::default()
+                    })
+                    .await
+                }
+                SecretsCommand::Import { path, restart } => {
+                    self.secrets_import(path, restart).await
+                }
             },
             Command::Certificate(cmd) => match cmd {
                 CertificateCommand::Add { domain } => self.add_certificate(domain).await,
@@ -288,15 +372,17 @@ impl Shuttle {
                 } => self.delete_certificate(domain, yes).await,
             },
             Command::Project(cmd) => match cmd {
-                ProjectCommand::Start(ProjectStartArgs { idle_minutes }) => {
+                ProjectCommand::Start(args) => {
                     if self.beta {
                         self.project_start_beta().await
                     } else {
-                        self.project_start(idle_minutes).await
+                        self.project_start(args.idle_minutes, args.wake_config())
+                            .await
                     }
                 }
-                ProjectCommand::Restart(ProjectStartArgs { idle_minutes }) => {
-                    self.project_restart(idle_minutes).await
+                ProjectCommand::Restart(args) => {
+                    self.project_restart(args.idle_minutes, args.wake_config())
+                        .await
                 }
                 ProjectCommand::Status { follow } => {
                     if self.beta {
@@ -308,11 +394,16 @@ impl Shuttle {
                 ProjectCommand::List { table, .. } => self.projects_list(table).await,
                 ProjectCommand::Stop => self.project_stop().await,
                 ProjectCommand::Delete(ConfirmationArgs { yes }) => self.project_delete(yes).await,
+                ProjectCommand::Tcp(cmd) => match cmd {
+                    TcpCommand::Enable => self.project_tcp_enable().await,
+                    TcpCommand::Disable => self.project_tcp_disable().await,
+                },
             },
         };
 
+        // Printed to stderr to keep JSON and YAML output parseable
         for w in self.version_warnings {
-            println!("{w}");
+            eprintln!("{w}");
         }
 
         res
@@ -657,7 +748,7 @@ impl Shuttle {
             project_args.working_directory.clone_from(&path);
 
             self.load_project(&project_args)?;
-            self.project_start(DEFAULT_IDLE_MINUTES).await?;
+            self.project_start(DEFAULT_IDLE_MINUTES, None).await?;
         }
 
         if std::env::current_dir().is_ok_and(|d| d != path) {
@@ -754,7 +845,11 @@ impl Shuttle {
 
     async fn account(&self) -> Result<CommandOutcome> {
         let client = self.client.as_ref().unwrap();
-        let user = client.get_current_user_beta().await?;
+        let mut user = client.get_current_user_beta().await?;
+        if self.output != OutputMode::Table {
+            user.key = "********".to_string();
+            return print_structured(&user, self.output);
+        }
         println!("{}", "Account info:".bold());
         println!("  User Id: {}", user.id);
         println!("  Username: {}", user.name);
@@ -770,6 +865,86 @@ impl Shuttle {
         Ok(CommandOutcome::Ok)
     }
 
+    async fn create_api_key(
+        &self,
+        name: String,
+        scopes: Vec<String>,
+        expires_in_days: Option<u32>,
+    ) -> Result<CommandOutcome> {
+        let client = self.client.as_ref().unwrap();
+        let request = ApiKeyRequest {
+            name,
+            scopes,
+            expires_at: expires_in_days
+                .map(|days| chrono::Utc::now() + chrono::Duration::days(days.into())),
+        };
+        let key = client.create_api_key(&request).await?;
+        if self.output != OutputMode::Table {
+            return print_structured(&key, self.output);
+        }
+
+        println!("Created API key '{}'", key.name.bold());
+        println!("  Key: {}", key.key.as_deref().unwrap_or_default().bold());
+        println!("  Scopes: {}", key.scopes.join(", "));
+        if let Some(expires_at) = key.expires_at {
+            println!("  Expires: {}", expires_at.format("%Y-%m-%dT%H:%M:%SZ"));
+        }
+        println!("This key will not be shown again.");
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    async fn list_api_keys(&self, table_args: TableArgs) -> Result<CommandOutcome> {
+        let client = self.client.as_ref().unwrap();
+        let keys = client.get_api_keys().await?;
+        if self.output != OutputMode::Table {
+            return print_structured(&keys, self.output);
+        }
+
+        if keys.is_empty() {
+            println!("No API keys found.");
+        } else {
+            println!("{}", get_api_keys_table(&keys, table_args.raw));
+        }
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    async fn revoke_api_key(&self, name: String, no_confirm: bool) -> Result<CommandOutcome> {
+        let client = self.client.as_ref().unwrap();
+
+        if !no_confirm {
+            println!(
+                "{}",
+                formatdoc!(
+                    "
+                WARNING:
+                    Revoke the API key '{}'? Anything using it will lose access within 5 minutes.",
+                    name
+                )
+                .bold()
+                .red()
+            );
+            if !Confirm::with_theme(&ColorfulTheme::default())
+                .with_prompt("Are you sure?")
+                .default(false)
+                .interact()
+                .unwrap()
+            {
+                return Ok(CommandOutcome::Ok);
+            }
+        }
+
+        let res = client.revoke_api_key(&name).await?;
+        if !res.status().is_success() {
+            bail!("Revoking API key '{name}' failed: {}", res.status());
+        }
+
+        println!("Revoked API key '{name}'. It can keep working for up to 5 minutes.");
+
+        Ok(CommandOutcome::Ok)
+    }
+
     /// Log in with the given API key or after prompting the user for one.
     async fn login(&mut self, login_args: LoginArgs, offline: bool) -> Result<CommandOutcome> {
         let api_key_str = match login_args.api_key {
@@ -953,16 +1128,19 @@ impl Shuttle {
     async fn status(&self) -> Result<CommandOutcome> {
         let client = self.client.as_ref().unwrap();
         let summary = client.get_service(self.ctx.project_name()).await?;
+        if self.output != OutputMode::Table {
+            return print_structured(&summary, self.output);
+        }
 
         println!("{summary}");
 
         Ok(CommandOutcome::Ok)
     }
 
-    async fn clean(&self) -> Result<CommandOutcome> {
+    async fn clean(&self, args: CleanArgs) -> Result<CommandOutcome> {
         let client = self.client.as_ref().unwrap();
         let message = client
-            .clean_project(self.ctx.project_name())
+            .clean_project(self.ctx.project_name(), args.evict_cache)
             .await
             .map_err(|err| {
                 suggestions::project::project_request_failure(
@@ -982,6 +1160,14 @@ impl Shuttle {
             eprintln!("Streamed logs are not yet supported on beta.");
             return Ok(CommandOutcome::Ok);
         }
+        if !args.filter().is_empty() {
+            eprintln!("Filtering logs is not yet supported on beta.");
+            return Ok(CommandOutcome::Ok);
+        }
+        if self.output != OutputMode::Table {
+            eprintln!("JSON and YAML log output is not yet supported on beta.");
+            return Ok(CommandOutcome::Ok);
+        }
         // TODO: implement logs range
         let client = self.client.as_ref().unwrap();
         let proj_name = self.ctx.project_name();
@@ -1036,6 +1222,7 @@ impl Shuttle {
             (_, _, true) => LogsRange::All,
             _ => LogsRange::Tail(1000),
         };
+        let filter = args.filter();
         let client = self.client.as_ref().unwrap();
         let id = if let Some(id) = args.id {
             id
@@ -1071,7 +1258,7 @@ impl Shuttle {
 
         if args.follow {
             let mut stream = client
-                .get_logs_ws(self.ctx.project_name(), &id, range)
+                .get_logs_ws(self.ctx.project_name(), &id, range, &filter)
                 .await
                 .map_err(|err| {
                     suggestions::logs::get_logs_failure(err, "Connecting to the logs stream failed")
@@ -1080,13 +1267,7 @@ impl Shuttle {
             while let Some(Ok(msg)) = stream.next().await {
                 if let tokio_tungstenite::tungstenite::Message::Text(line) = msg {
                     match serde_json::from_str::<shuttle_common::LogItem>(&line) {
-                        Ok(log) => {
-                            if args.raw {
-                                println!("{}", log.get_raw_line())
-                            } else {
-                                println!("{log}")
-                            }
-                        }
+                        Ok(log) => print_log_item(&log, args.raw, self.output),
                         Err(err) => {
                             debug!(error = %err, "failed to parse message into log item");
 
@@ -1103,18 +1284,14 @@ impl Shuttle {
             }
         } else {
             let logs = client
-                .get_logs(self.ctx.project_name(), &id, range)
+                .get_logs(self.ctx.project_name(), &id, range, &filter)
                 .await
                 .map_err(|err| {
                     suggestions::logs::get_logs_failure(err, "Fetching the deployment failed")
                 })?;
 
             for log in logs.into_iter() {
-                if args.raw {
-                    println!("{}", log.get_raw_line())
-                } else {
-                    println!("{log}")
-                }
+                print_log_item(&log, args.raw, self.output);
             }
         }
 
@@ -1147,6 +1324,9 @@ impl Shuttle {
             } else {
                 false
             };
+            if self.output != OutputMode::Table {
+                return print_structured(&deployments, self.output);
+            }
             let table = deployments_table_beta(&deployments, table_args.raw);
 
             println!(
@@ -1170,6 +1350,9 @@ impl Shuttle {
             } else {
                 false
             };
+            if self.output != OutputMode::Table {
+                return print_structured(&deployments, self.output);
+            }
             let table =
                 get_deployments_table(&deployments, proj_name, page, table_args.raw, page_hint);
             println!("{table}");
@@ -1227,6 +1410,57 @@ impl Shuttle {
         Ok(CommandOutcome::Ok)
     }
 
+    async fn deployment_rollback(&self, deployment_id: Option<String>) -> Result<CommandOutcome> {
+        if self.beta {
+            eprintln!("Rollbacks are not yet supported on beta.");
+            return Ok(CommandOutcome::Ok);
+        }
+        let client = self.client.as_ref().unwrap();
+        let proj_name = self.ctx.project_name();
+
+        let deployment_id = match deployment_id {
+            Some(id) => Uuid::from_str(&id)
+                .map_err(|err| anyhow!("Provided deployment id is not a valid UUID: {err}"))?,
+            None => {
+                let deployments = client
+                    .get_deployments(proj_name, 0, 10)
+                    .await
+                    .map_err(suggestions::deployment::get_deployments_list_failure)?;
+                // Deployments are sorted by last update, so the first stopped one is the one
+                // that was replaced by the current deployment
+                deployments
+                    .iter()
+                    .find(|d| {
+                        matches!(
+                            d.state,
+                            shuttle_common::deployment::State::Stopped
+                                | shuttle_common::deployment::State::Completed
+                        )
+                    })
+                    .context(
+                        "Could not find a previous deployment to roll back to. Try passing a deployment ID manually",
+                    )?
+                    .id
+            }
+        };
+
+        let deployment = client
+            .rollback_deployment(proj_name, &deployment_id)
+            .await
+            .map_err(suggestions::deployment::rollback_deployment_failure)?;
+        if self.output != OutputMode::Table {
+            return print_structured(&deployment, self.output);
+        }
+
+        println!("Rolling back to deployment {}", deployment.id);
+        println!(
+            "Run `cargo shuttle logs {} --follow` to follow its startup.",
+            deployment.id
+        );
+
+        Ok(CommandOutcome::Ok)
+    }
+
     async fn resources_list(
         &self,
         table_args: TableArgs,
@@ -1242,6 +1476,14 @@ impl Shuttle {
         }
         .map_err(suggestions::resources::get_service_resources_failure)?;
 
+        if self.output != OutputMode::Table {
+            let mut resources = resources;
+            if !show_secrets {
+                resources.iter_mut().for_each(redact_resource_secrets);
+            }
+            return print_structured(&resources, self.output);
+        }
+
         // TODO: Beta table formats
         let table = get_resource_tables(
             &resources,
@@ -1259,9 +1501,14 @@ impl Shuttle {
     async fn resource_delete(
         &self,
         resource_type: &resource::Type,
+        name: Option<&str>,
         no_confirm: bool,
     ) -> Result<CommandOutcome> {
         let client = self.client.as_ref().unwrap();
+        let resource_display = match name {
+            Some(name) => format!("{resource_type} ({name})"),
+            None => resource_type.to_string(),
+        };
 
         if !no_confirm {
             println!(
@@ -1271,7 +1518,7 @@ impl Shuttle {
                 WARNING:
                     Are you sure you want to delete this project's {}?
                     This action is permanent.",
-                    resource_type
+                    resource_display
                 )
                 .bold()
                 .red()
@@ -1288,15 +1535,15 @@ impl Shuttle {
 
         if self.beta {
             client
-                .delete_service_resource_beta(self.ctx.project_name(), resource_type)
+                .delete_service_resource_beta(self.ctx.project_name(), resource_type, name)
                 .await?;
         } else {
             client
-                .delete_service_resource(self.ctx.project_name(), resource_type)
+                .delete_service_resource(self.ctx.project_name(), resource_type, name)
                 .await?;
         }
 
-        println!("Deleted resource {resource_type}");
+        println!("Deleted resource {resource_display}");
         println!(
             "{}",
             formatdoc! {"
@@ -1310,11 +1557,91 @@ impl Shuttle {
         Ok(CommandOutcome::Ok)
     }
 
+    async fn secrets_list(
+        &self,
+        table_args: TableArgs,
+        show_secrets: bool,
+    ) -> Result<CommandOutcome> {
+        let client = self.client.as_ref().unwrap();
+        let secrets = client.get_secrets(self.ctx.project_name()).await?;
+
+        if self.output != OutputMode::Table {
+            return print_structured(&secrets_map(&secrets, show_secrets), self.output);
+        }
+
+        let table = get_secrets_table(
+            &secrets,
+            self.ctx.project_name(),
+            table_args.raw,
+            show_secrets,
+        );
+        println!("{table}");
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    async fn secrets_import(&self, path: Option<PathBuf>, restart: bool) -> Result<CommandOutcome> {
+        let path = path.unwrap_or_else(|| self.ctx.working_directory().join(".env"));
+        let contents = std::fs::read_to_string(&path)
+            .context(format!("reading secrets from {}", path.display()))?;
+        let set = secrets::parse_dotenv(&contents)
+            .context(format!("parsing secrets from {}", path.display()))?;
+        if set.is_empty() {
+            println!("No secrets found in {}", path.display());
+            return Ok(CommandOutcome::Ok);
+        }
+
+        self.secrets_update(SecretsUpdateRequest {
+            set,
+            restart,
+            ..Default::default()
+        })
+        .await
+    }
+
+    async fn secrets_update(&self, request: SecretsUpdateRequest) -> Result<CommandOutcome> {
+        let client = self.client.as_ref().unwrap();
+        let response = client
+            .update_secrets(self.ctx.project_name(), &request)
+            .await?;
+
+        if self.output != OutputMode::Table {
+            return print_structured(
+                &serde_json::json!({
+                    "secrets": secrets_map(&response.secrets, false),
+                    "restarted": response.restarted,
+                }),
+                self.output,
+            );
+        }
+
+        if !request.set.is_empty() {
+            println!("Set {} secret(s)", request.set.len());
+        }
+        if !request.unset.is_empty() {
+            println!("Removed {} secret(s)", request.unset.len());
+        }
+        if response.restarted {
+            println!("Restarted the running deployment with the new secrets");
+        } else if request.restart {
+            println!("There is no running deployment, so the next one will use the new secrets");
+        } else {
+            println!(
+                "The new secrets are used from the next deployment on. Use `--restart` to apply them to the running one."
+            );
+        }
+
+        Ok(CommandOutcome::Ok)
+    }
+
     async fn list_certificates(&self, table_args: TableArgs) -> Result<CommandOutcome> {
         let client = self.client.as_ref().unwrap();
         let certs = client
             .list_certificates_beta(self.ctx.project_name())
             .await?;
+        if self.output != OutputMode::Table {
+            return print_structured(&certs, self.output);
+        }
 
         let table = get_certificates_table_beta(certs.as_ref(), table_args.raw);
         println!("{}", table);
@@ -1366,34 +1693,35 @@ impl Shuttle {
     }
 
     fn get_secrets(run_args: &RunArgs, service: &BuiltService) -> Result<HashMap<String, String>> {
-        let secrets_file = run_args.secret_args.secrets.clone().or_else(|| {
-            let crate_dir = service.crate_directory();
-            // Prioritise crate-local prod secrets over workspace dev secrets (in the rare case that both exist)
-            [
-                crate_dir.join("Secrets.dev.toml"),
-                crate_dir.join("Secrets.toml"),
-                service.workspace_path.join("Secrets.dev.toml"),
-                service.workspace_path.join("Secrets.toml"),
-            ]
-            .into_iter()
-            .find(|f| f.exists() && f.is_file())
-        });
-        let secrets = if let Some(secrets_file) = secrets_file {
-            trace!("Loading secrets from {}", secrets_file.display());
-            if let Ok(secrets_str) = read_to_string(secrets_file) {
-                let secrets = toml::from_str::<HashMap<String, String>>(&secrets_str)?;
-                trace!(keys = ?secrets.keys(), "available secrets");
-                secrets
-            } else {
-                trace!("No secrets were loaded");
-                Default::default()
-            }
+        let secrets = if let Some(ref secrets_file) = run_args.secret_args.secrets {
+            Some(secrets::load_file(
+                secrets_file,
+                run_args.secret_args.interpolate,
+            )?)
         } else {
+            let env = run_args
+                .secret_args
+                .env
+                .as_deref()
+                .unwrap_or(secrets::DEFAULT_LOCAL_ENV);
+            // Prioritise crate-local secrets over workspace secrets (in the rare case that both exist)
+            let mut secrets = None;
+            for dir in [service.crate_directory(), service.workspace_path.as_path()] {
+                secrets = secrets::load_layered(dir, Some(env), run_args.secret_args.interpolate)?;
+                if secrets.is_some() {
+                    break;
+                }
+            }
+            secrets
+        };
+
+        let Some(secrets) = secrets else {
             trace!("No secrets file was found");
-            Default::default()
+            return Ok(Default::default());
         };
+        trace!(keys = ?secrets.keys(), "available secrets");
 
-        Ok(secrets)
+        Ok(secrets.into_iter().collect())
     }
 
     async fn check_and_warn_runtime_version(path: &Path) -> Result<()> {
@@ -1434,6 +1762,8 @@ impl Shuttle {
         run_args: &RunArgs,
         service: &BuiltService,
         idx: u16,
+        output: OutputMode,
+        provisioned: &mut ProvisionedResources,
     ) -> Result<Option<(Child, runtime::Client)>> {
         let secrets = Shuttle::get_secrets(run_args, service)?;
 
@@ -1466,11 +1796,7 @@ impl Shuttle {
                     line,
                 );
 
-                if raw {
-                    println!("{}", log_item.get_raw_line())
-                } else {
-                    println!("{log_item}")
-                }
+                print_log_item(&log_item, raw, output);
             }
         });
 
@@ -1512,7 +1838,8 @@ impl Shuttle {
 
         let resources = response.resources;
         let (resources, mocked_responses) =
-            Shuttle::local_provision_phase(service_name.as_str(), resources, secrets).await?;
+            Shuttle::local_provision_phase(service_name.as_str(), resources, secrets, provisioned)
+                .await?;
 
         println!(
             "{}",
@@ -1569,6 +1896,7 @@ impl Shuttle {
         project_name: &str,
         mut resources: Vec<Vec<u8>>,
         secrets: HashMap<String, String>,
+        provisioned: &mut ProvisionedResources,
     ) -> Result<(Vec<Vec<u8>>, Vec<resource::Response>)> {
         // for displaying the tables
         let mut mocked_responses: Vec<resource::Response> = Vec::new();
@@ -1607,6 +1935,18 @@ impl Shuttle {
                     }
                 }).collect::<anyhow::Result<Vec<_>>>()?.into_iter()
         {
+            let request = bytes.clone();
+            let is_database = matches!(shuttle_resource.r#type, resource::Type::Database(_));
+            let reusable = is_database || shuttle_resource.r#type == resource::Type::Container;
+            if reusable {
+                if let Some((output, response)) = provisioned.get(&request) {
+                    trace!(r#type = %shuttle_resource.r#type, "reusing provisioned resource");
+                    *bytes = output.clone();
+                    mocked_responses.extend(response.clone());
+                    continue;
+                }
+            }
+
             match shuttle_resource.r#type {
                 resource::Type::Database(db_type) => {
                     let config: DbInput = serde_json::from_value(shuttle_resource.config)
@@ -1618,6 +1958,7 @@ impl Shuttle {
                                 project_name: project_name.to_string(),
                                 db_type: Some(db_type.into()),
                                 db_name: config.db_name,
+                                resource_name: shuttle_resource.name.clone(),
                             }))
                             .await
                             .context("Failed to start database container. Make sure that a Docker engine is running.")?
@@ -1629,6 +1970,7 @@ impl Shuttle {
                         r#type: shuttle_resource.r#type,
                         config: serde_json::Value::Null,
                         data: serde_json::to_value(&res).unwrap(),
+                        name: shuttle_resource.name,
                     });
                     *bytes = serde_json::to_vec(&ShuttleResourceOutput {
                         output: res,
@@ -1643,6 +1985,7 @@ impl Shuttle {
                         r#type: shuttle_resource.r#type,
                         config: serde_json::Value::Null,
                         data: serde_json::to_value(secrets.clone()).unwrap(),
+                        name: None,
                     });
                     *bytes = serde_json::to_vec(&ShuttleResourceOutput {
                         output: secrets.clone(),
@@ -1657,6 +2000,7 @@ impl Shuttle {
                         r#type: shuttle_resource.r#type,
                         config: serde_json::Value::Null,
                         data: serde_json::Value::Null,
+                        name: None,
                     });
                 }
                 resource::Type::Container => {
@@ -1671,6 +2015,12 @@ impl Shuttle {
                     .unwrap();
                 }
             }
+
+            if reusable {
+                // Only databases are shown in the resource table
+                let response = is_database.then(|| mocked_responses.last().cloned()).flatten();
+                provisioned.insert(request, (bytes.clone(), response));
+            }
         }
 
         Ok((resources, mocked_responses))
@@ -1695,6 +2045,44 @@ impl Shuttle {
         Ok(())
     }
 
+    /// Ask a beta runtime to shut down gracefully, and kill it if it does not exit in time
+    async fn stop_runtime_beta(runtime: &mut Child) -> Result<()> {
+        // The runtime is in its own process group, so forward the signal to it
+        #[cfg(target_family = "unix")]
+        if let Some(pid) = runtime.id() {
+            let _ = nix::sys::signal::kill(
+                nix::unistd::Pid::from_raw(pid as i32),
+                nix::sys::signal::Signal::SIGTERM,
+            );
+        }
+
+        if tokio::time::timeout(RUNTIME_SHUTDOWN_TIMEOUT, runtime.wait())
+            .await
+            .is_err()
+        {
+            trace!("killing the runtime by force since it did not shut down in time");
+            runtime.kill().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Stop every beta runtime, and only then report the ones that failed to stop
+    async fn stop_runtimes_beta(runtimes: &mut [Child], services: &[BuiltService]) -> Result<()> {
+        let mut errors = Vec::new();
+        for (runtime, service) in runtimes.iter_mut().zip(services) {
+            if let Err(err) = Shuttle::stop_runtime_beta(runtime).await {
+                errors.push(format!("{}: {err}", service.package_name));
+            }
+        }
+
+        if !errors.is_empty() {
+            bail!("Failed to stop the runtime of {}", errors.join(", "));
+        }
+
+        Ok(())
+    }
+
     async fn add_runtime_info(
         runtime: Option<(Child, runtime::Client)>,
         existing_runtimes: &mut Vec<(Child, runtime::Client)>,
@@ -1746,7 +2134,8 @@ impl Shuttle {
             working_directory.display()
         );
 
-        build_workspace(working_directory, run_args.release, tx, false).await
+        let config = self.ctx.build_config().cloned().unwrap_or_default();
+        build_workspace(working_directory, run_args.release, &config, tx, false).await
     }
 
     fn find_available_port(run_args: &mut RunArgs, services_len: usize) {
@@ -1774,11 +2163,13 @@ impl Shuttle {
             exit(0);
         }
     }
-    fn find_available_port_beta(run_args: &mut RunArgs) {
+    fn find_available_port_beta(run_args: &mut RunArgs, services_len: usize) {
         let original_port = run_args.port;
-        for port in (run_args.port..=u16::MAX).step_by(10) {
-            if !portpicker::is_free_tcp(port) {
-                continue;
+        'outer: for port in (run_args.port..=u16::MAX).step_by(services_len.max(10)) {
+            for inner_port in port..(port + services_len as u16) {
+                if !portpicker::is_free_tcp(inner_port) {
+                    continue 'outer;
+                }
             }
             run_args.port = port;
             break;
@@ -1805,6 +2196,7 @@ impl Shuttle {
 
         // Start all the services.
         let mut runtimes: Vec<(Child, runtime::Client)> = Vec::new();
+        let mut provisioned = ProvisionedResources::new();
 
         Shuttle::find_available_port(&mut run_args, services.len());
 
@@ -1813,7 +2205,7 @@ impl Shuttle {
             // We must cover the case of starting multiple workspace services and receiving a signal in parallel.
             // This must stop all the existing runtimes and creating new ones.
             signal_received = tokio::select! {
-                res = Shuttle::spin_local_runtime(&run_args, service, i as u16) => {
+                res = Shuttle::spin_local_runtime(&run_args, service, i as u16, self.output, &mut provisioned) => {
                     match res {
                         Ok(runtime) => {
                             Shuttle::add_runtime_info(runtime, &mut runtimes).await?;
@@ -1909,66 +2301,152 @@ impl Shuttle {
     async fn local_run_beta(&self, mut run_args: RunArgs) -> Result<CommandOutcome> {
         let project_name = self.ctx.project_name().to_owned();
         let services = self.pre_local_run(&run_args).await?;
-        let service = services
-            .first()
-            .expect("at least one shuttle service")
-            .to_owned();
+        Shuttle::find_available_port_beta(&mut run_args, services.len());
+
+        let ip = if run_args.external {
+            Ipv4Addr::UNSPECIFIED
+        } else {
+            Ipv4Addr::LOCALHOST
+        };
+
+        // Start all the services, and stop the ones already started if one of them fails to start
+        let mut runtimes: Vec<Child> = Vec::new();
+        for (i, service) in services.iter().enumerate() {
+            // Only tell the log lines apart when there are several services
+            let log_source = if services.len() > 1 {
+                service.package_name.clone()
+            } else {
+                "app".to_owned()
+            };
+            let runtime = self
+                .spawn_runtime_beta(
+                    &run_args,
+                    service,
+                    &project_name,
+                    SocketAddr::new(ip.into(), run_args.port + i as u16),
+                    log_source,
+                )
+                .await;
+
+            match runtime {
+                Ok(runtime) => runtimes.push(runtime),
+                Err(err) => {
+                    if let Err(stop_err) =
+                        Shuttle::stop_runtimes_beta(&mut runtimes, &services).await
+                    {
+                        eprintln!("{stop_err}");
+                    }
+                    return Err(err);
+                }
+            }
+        }
+
+        // Wait for any runtime to exit or a signal to arrive, and then stop all of them
+        let exit_result = tokio::select! {
+            (exit_result, idx, _) = futures::future::select_all(
+                runtimes.iter_mut().map(|runtime| Box::pin(runtime.wait()))
+            ) => {
+                Some((idx, exit_result))
+            }
+            _ = Shuttle::handle_signals() => {
+                eprintln!("Stopping the runtimes...");
+                None
+            }
+        };
+        let stop_result = Shuttle::stop_runtimes_beta(&mut runtimes, &services).await;
+        if exit_result.is_some() {
+            if let Err(ref stop_err) = stop_result {
+                eprintln!("{stop_err}");
+            }
+        }
 
+        match exit_result {
+            Some((idx, Ok(exit_status))) => {
+                bail!(
+                    "Runtime process of {} exited with code {}",
+                    services[idx].package_name,
+                    exit_status.code().unwrap_or_default()
+                );
+            }
+            Some((idx, Err(e))) => {
+                bail!(
+                    "Failed to wait for runtime process of {} to exit: {e}",
+                    services[idx].package_name
+                );
+            }
+            None => stop_result?,
+        }
+
+        println!("Run `cargo shuttle deploy` to deploy your Shuttle service.");
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    /// Start the local provisioner server and the runtime process of a service on the beta platform
+    async fn spawn_runtime_beta(
+        &self,
+        run_args: &RunArgs,
+        service: &BuiltService,
+        project_name: &str,
+        addr: SocketAddr,
+        log_source: String,
+    ) -> Result<Child> {
         trace!(path = ?service.executable_path, "runtime executable");
 
-        let secrets = Shuttle::get_secrets(&run_args, &service)?;
-        Shuttle::find_available_port_beta(&mut run_args);
+        let secrets = Shuttle::get_secrets(run_args, service)?;
         Shuttle::check_and_warn_runtime_version(&service.executable_path).await?;
 
         let runtime_executable = service.executable_path.clone();
         let api_port = portpicker::pick_unused_port()
             .expect("failed to find available port for local provisioner server");
         let api_addr = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), api_port);
-        let ip = if run_args.external {
-            Ipv4Addr::UNSPECIFIED
-        } else {
-            Ipv4Addr::LOCALHOST
-        };
+        let healthz_port =
+            portpicker::pick_unused_port().expect("failed to find available port for health check");
 
         let state = Arc::new(ProvApiState {
-            project_name: project_name.clone(),
+            project_name: project_name.to_owned(),
             secrets,
         });
         ProvisionerServerBeta::start(state, &api_addr);
 
         println!(
-            "\n    {} {} on http://{}:{}\n",
+            "\n    {} {} on http://{}\n",
             "Starting".bold().green(),
             service.package_name,
-            ip,
-            run_args.port,
+            addr,
         );
 
         info!(
             path = %runtime_executable.display(),
             "Spawning runtime process",
         );
-        let mut runtime = tokio::process::Command::new(
+        let mut runtime_command = tokio::process::Command::new(
             dunce::canonicalize(runtime_executable).context("canonicalize path of executable")?,
-        )
-        .current_dir(&service.workspace_path)
-        .args(["--run"])
-        .envs([
-            ("SHUTTLE_BETA", "true"),
-            ("SHUTTLE_PROJECT_ID", "proj_LOCAL"),
-            ("SHUTTLE_PROJECT_NAME", project_name.as_str()),
-            ("SHUTTLE_ENV", Environment::Local.to_string().as_str()),
-            ("SHUTTLE_RUNTIME_IP", ip.to_string().as_str()),
-            ("SHUTTLE_RUNTIME_PORT", run_args.port.to_string().as_str()),
-            (
-                "SHUTTLE_API",
-                format!("http://127.0.0.1:{}", api_port).as_str(),
-            ),
-        ])
-        .stdout(std::process::Stdio::piped())
-        .kill_on_drop(true)
-        .spawn()
-        .context("spawning runtime process")?;
+        );
+        runtime_command
+            .current_dir(&service.workspace_path)
+            .args(["--run"])
+            .envs([
+                ("SHUTTLE_BETA", "true"),
+                ("SHUTTLE_PROJECT_ID", "proj_LOCAL"),
+                ("SHUTTLE_PROJECT_NAME", project_name),
+                ("SHUTTLE_ENV", Environment::Local.to_string().as_str()),
+                ("SHUTTLE_RUNTIME_IP", addr.ip().to_string().as_str()),
+                ("SHUTTLE_RUNTIME_PORT", addr.port().to_string().as_str()),
+                ("SHUTTLE_HEALTHZ_PORT", healthz_port.to_string().as_str()),
+                (
+                    "SHUTTLE_API",
+                    format!("http://127.0.0.1:{}", api_port).as_str(),
+                ),
+            ])
+            .stdout(std::process::Stdio::piped())
+            .kill_on_drop(true);
+        // Keep terminal signals (Ctrl-C) away from the runtime so that it is stopped via `stop_runtime_beta`
+        #[cfg(target_family = "unix")]
+        runtime_command.process_group(0);
+        let mut runtime = runtime_command
+            .spawn()
+            .context("spawning runtime process")?;
 
         let child_stdout = runtime
             .stdout
@@ -1976,95 +2454,48 @@ impl Shuttle {
             .context("child process did not have a handle to stdout")?;
         let mut reader = BufReader::new(child_stdout).lines();
         let raw = run_args.raw;
+        let output = self.output;
         tokio::spawn(async move {
             while let Some(line) = reader.next_line().await.unwrap() {
                 if raw {
                     println!("{}", line);
+                } else if output != OutputMode::Table {
+                    // Use the same schema as the alpha logs, so that tools reading them work for both
+                    let log_item = LogItem::new(
+                        Uuid::default(),
+                        shuttle_common::log::Backend::Runtime(log_source.clone()),
+                        line,
+                    );
+                    println!("{}", log_item.to_json_line());
                 } else {
-                    let log_item = LogItemBeta::new(Utc::now(), "app".to_owned(), line);
+                    let log_item = LogItemBeta::new(Utc::now(), log_source.clone(), line);
                     println!("{log_item}");
                 }
             }
         });
 
-        #[cfg(target_family = "unix")]
-        let exit_result = {
-            let mut sigterm_notif =
-                tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
-                    .expect("Can not get the SIGTERM signal receptor");
-            let mut sigint_notif =
-                tokio::signal::unix::signal(tokio::signal::unix::SignalKind::interrupt())
-                    .expect("Can not get the SIGINT signal receptor");
-            tokio::select! {
-                exit_result = runtime.wait() => {
-                    Some(exit_result)
-                }
-                _ = sigterm_notif.recv() => {
-                    eprintln!("cargo-shuttle received SIGTERM. Killing the runtime...");
-                    None
-                },
-                _ = sigint_notif.recv() => {
-                    eprintln!("cargo-shuttle received SIGINT. Killing the runtime...");
-                    None
-                }
-            }
-        };
-        #[cfg(target_family = "windows")]
-        let exit_result = {
-            let mut ctrl_break_notif = tokio::signal::windows::ctrl_break()
-                .expect("Can not get the CtrlBreak signal receptor");
-            let mut ctrl_c_notif =
-                tokio::signal::windows::ctrl_c().expect("Can not get the CtrlC signal receptor");
-            let mut ctrl_close_notif = tokio::signal::windows::ctrl_close()
-                .expect("Can not get the CtrlClose signal receptor");
-            let mut ctrl_logoff_notif = tokio::signal::windows::ctrl_logoff()
-                .expect("Can not get the CtrlLogoff signal receptor");
-            let mut ctrl_shutdown_notif = tokio::signal::windows::ctrl_shutdown()
-                .expect("Can not get the CtrlShutdown signal receptor");
-            tokio::select! {
-                exit_result = runtime.wait() => {
-                    Some(exit_result)
-                }
-                _ = ctrl_break_notif.recv() => {
-                    eprintln!("cargo-shuttle received ctrl-break.");
-                    None
-                },
-                _ = ctrl_c_notif.recv() => {
-                    eprintln!("cargo-shuttle received ctrl-c.");
-                    None
-                },
-                _ = ctrl_close_notif.recv() => {
-                    eprintln!("cargo-shuttle received ctrl-close.");
-                    None
-                },
-                _ = ctrl_logoff_notif.recv() => {
-                    eprintln!("cargo-shuttle received ctrl-logoff.");
-                    None
-                },
-                _ = ctrl_shutdown_notif.recv() => {
-                    eprintln!("cargo-shuttle received ctrl-shutdown.");
-                    None
-                }
-            }
-        };
-        match exit_result {
-            Some(Ok(exit_status)) => {
-                bail!(
-                    "Runtime process exited with code {}",
-                    exit_status.code().unwrap_or_default()
-                );
-            }
-            Some(Err(e)) => {
-                bail!("Failed to wait for runtime process to exit: {e}");
-            }
-            None => {
-                runtime.kill().await?;
-            }
-        }
+        Ok(runtime)
+    }
 
-        println!("Run `cargo shuttle deploy` to deploy your Shuttle service.");
+    #[cfg(target_family = "unix")]
+    async fn handle_signals() -> bool {
+        let mut sigterm_notif =
+            tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+                .expect("Can not get the SIGTERM signal receptor");
+        let mut sigint_notif =
+            tokio::signal::unix::signal(tokio::signal::unix::SignalKind::interrupt())
+                .expect("Can not get the SIGINT signal receptor");
 
-        Ok(CommandOutcome::Ok)
+        tokio::select! {
+            _ = sigterm_notif.recv() => {
+                println!("cargo-shuttle received SIGTERM.");
+                true
+            },
+            _ = sigint_notif.recv() => {
+                println!("cargo-shuttle received SIGINT.");
+                true
+            }
+        }
     }
 
     #[cfg(target_family = "windows")]
@@ -2113,13 +2544,14 @@ impl Shuttle {
 
         // Start all the services.
         let mut runtimes: Vec<(Child, runtime::Client)> = Vec::new();
+        let mut provisioned = ProvisionedResources::new();
 
         Shuttle::find_available_port(&mut run_args, services.len());
 
         let mut signal_received = false;
         for (i, service) in services.iter().enumerate() {
             signal_received = tokio::select! {
-                res = Shuttle::spin_local_runtime(&run_args, service, i as u16) => {
+                res = Shuttle::spin_local_runtime(&run_args, service, i as u16, self.output, &mut provisioned) => {
                     Shuttle::add_runtime_info(res.unwrap(), &mut runtimes).await?;
                     false
                 },
@@ -2192,7 +2624,144 @@ impl Shuttle {
         Ok(CommandOutcome::Ok)
     }
 
+    /// Run the services locally, and rebuild and restart them whenever a project file changes
+    async fn local_run_watch(&self, mut run_args: RunArgs) -> Result<CommandOutcome> {
+        let mut watcher = Watcher::new(self.ctx.working_directory(), self.ctx.assets())?;
+        // Databases and containers stay up between restarts, and are reused by the new runtimes
+        let mut provisioned = ProvisionedResources::new();
+        let mut services = self.pre_local_run(&run_args).await?;
+
+        Shuttle::find_available_port(&mut run_args, services.len());
+
+        loop {
+            let mut runtimes: Vec<(Child, runtime::Client)> = Vec::new();
+            let mut signal_received = false;
+            for (i, service) in services.iter().enumerate() {
+                signal_received = tokio::select! {
+                    res = Shuttle::spin_local_runtime(&run_args, service, i as u16, self.output, &mut provisioned) => {
+                        match res {
+                            Ok(Some(runtime)) => runtimes.push(runtime),
+                            Ok(None) => println!("Service failed to load, waiting for changes..."),
+                            Err(e) => println!("Error while starting service: {e:?}"),
+                        }
+                        false
+                    },
+                    _ = Shuttle::handle_signals() => true,
+                };
+
+                if signal_received {
+                    break;
+                }
+            }
+
+            if !signal_received {
+                signal_received = tokio::select! {
+                    res = watcher.changed() => {
+                        res?;
+                        false
+                    },
+                    _ = Shuttle::handle_signals() => true,
+                };
+            }
+
+            for (mut rt, mut rt_client) in runtimes {
+                Shuttle::stop_runtime(&mut rt, &mut rt_client)
+                    .await
+                    .unwrap_or_else(|err| {
+                        trace!(status = ?err, "stopping the runtime errored out");
+                    });
+                // Make sure the old runtime is gone before the new one binds to its port
+                let _ = rt.kill().await;
+            }
+
+            if signal_received {
+                println!("Stopped all the runtimes.");
+                return Ok(CommandOutcome::Ok);
+            }
+
+            // Keep rebuilding on changes until the build succeeds
+            loop {
+                println!(
+                    "\n    {} changes, restarting...\n",
+                    "Detected".bold().yellow()
+                );
+                let res = tokio::select! {
+                    res = self.pre_local_run(&run_args) => Some(res),
+                    _ = Shuttle::handle_signals() => None,
+                };
+                match res {
+                    Some(Ok(built)) => {
+                        services = built;
+                        break;
+                    }
+                    Some(Err(err)) => {
+                        println!("Build failed, waiting for changes: {err:?}");
+                    }
+                    None => return Ok(CommandOutcome::Ok),
+                }
+
+                let changed = tokio::select! {
+                    res = watcher.changed() => {
+                        res?;
+                        true
+                    },
+                    _ = Shuttle::handle_signals() => false,
+                };
+                if !changed {
+                    return Ok(CommandOutcome::Ok);
+                }
+            }
+        }
+    }
+
+    /// Run the pre-deploy tests with the `[deploy.test]` config in Shuttle.toml, the same way the deployer does
+    async fn deploy_dry_run(&self) -> Result<CommandOutcome> {
+        let config = self.ctx.deploy_test_config().cloned().unwrap_or_default();
+        config.validate()?;
+        let build_config = self.ctx.build_config().cloned().unwrap_or_default();
+        let test_args = config.cargo_test_args(&build_config, true);
+        eprintln!(
+            "Running pre-deploy tests: cargo test {}",
+            test_args.join(" ")
+        );
+
+        let mut test_command = tokio::process::Command::new("cargo");
+        test_command
+            .arg("test")
+            .args(test_args)
+            .current_dir(self.ctx.working_directory())
+            .kill_on_drop(true);
+        if let Some(rustflags) = build_config.rustflags_env() {
+            test_command.env("RUSTFLAGS", rustflags);
+        }
+        let mut tests = test_command.spawn().context("spawning cargo test")?;
+
+        let status = match config.wait_for_tests(&mut tests).await {
+            Ok(status) => status,
+            Err(TestWaitError::Timeout(limit)) => bail!(
+                "The tests did not finish within {} seconds, so the deployer would reject this deployment",
+                limit.as_secs()
+            ),
+            Err(TestWaitError::Io(err)) => return Err(err).context("waiting for cargo test"),
+        };
+
+        if !status.success() {
+            bail!("The tests failed, so the deployer would reject this deployment");
+        }
+        println!("The tests passed. Nothing was deployed.");
+
+        Ok(CommandOutcome::Ok)
+    }
+
     async fn deploy(&mut self, args: DeployArgs) -> Result<CommandOutcome> {
+        if args.dry_run {
+            if self.beta {
+                eprintln!("Dry runs are not yet supported on beta.");
+                return Ok(CommandOutcome::Ok);
+            }
+            return self.deploy_dry_run().await;
+        }
+
         let client = self.client.as_ref().unwrap();
         let working_directory = self.ctx.working_directory();
         let manifest_path = working_directory.join("Cargo.toml");
@@ -2201,32 +2770,26 @@ impl Shuttle {
         let secrets = if self.beta {
             // Look for a secrets file, first in the command args, and if it isn't there look
             // in the root of the crate or workspace.
-            let secrets_file = args.secret_args.secrets.clone().or_else(|| {
-                let secrets_file = manifest_path.parent().unwrap().join("Secrets.toml");
-
-                if secrets_file.exists() && secrets_file.is_file() {
-                    Some(secrets_file)
-                } else {
-                    None
-                }
-            });
-
-            if let Some(secrets_file) = secrets_file {
-                trace!("Loading secrets from {}", secrets_file.display());
-                if let Ok(secrets_str) = read_to_string(&secrets_file) {
-                    let secrets = toml::from_str::<HashMap<String, String>>(&secrets_str)?;
-
-                    trace!(keys = ?secrets.keys(), "available secrets");
+            let secrets = if let Some(ref secrets_file) = args.secret_args.secrets {
+                Some(secrets::load_file(
+                    secrets_file,
+                    args.secret_args.interpolate,
+                )?)
+            } else {
+                secrets::load_layered(
+                    manifest_path.parent().unwrap(),
+                    args.secret_args.env.as_deref(),
+                    args.secret_args.interpolate,
+                )?
+            };
 
-                    Some(secrets)
-                } else {
-                    trace!("No secrets were loaded");
-                    None
-                }
+            if let Some(ref secrets) = secrets {
+                trace!(keys = ?secrets.keys(), "available secrets");
             } else {
                 trace!("No secrets file was found");
-                None
             }
+
+            secrets.map(|secrets| secrets.into_iter().collect())
         } else {
             None
         };
@@ -2327,7 +2890,7 @@ impl Shuttle {
             }
         }
 
-        let archive = self.make_archive(args.secret_args.secrets.clone(), self.beta)?;
+        let archive = self.make_archive(&args.secret_args, self.beta)?;
 
         if let Some(path) = args.output_archive {
             eprintln!("Writing archive to {}...", path.display());
@@ -2415,6 +2978,7 @@ impl Shuttle {
                 self.ctx.project_name(),
                 &deployment.id.to_string(),
                 LogsRange::All,
+                &LogsFilter::default(),
             )
             .await
             .map_err(|err| {
@@ -2539,6 +3103,7 @@ impl Shuttle {
                         self.ctx.project_name(),
                         &deployment.id.to_string(),
                         LogsRange::All,
+                        &LogsFilter::default(),
                     )
                     .await
                     .map_err(|err| {
@@ -2586,6 +3151,12 @@ impl Shuttle {
                         "State: Crashed - Deployment crashed after startup.".red()
                     );
                 }
+                shuttle_common::deployment::State::Restarting => {
+                    println!(
+                        "{}",
+                        "State: Restarting - Deployment crashed after startup, and is being restarted by its restart policy.".yellow()
+                    );
+                }
                 state => {
                     debug!("deployment logs stream received state: {state} when it expected to receive running state");
                     println!(
@@ -2614,9 +3185,13 @@ impl Shuttle {
         Ok(CommandOutcome::Ok)
     }
 
-    async fn project_start(&self, idle_minutes: u64) -> Result<CommandOutcome> {
+    async fn project_start(
+        &self,
+        idle_minutes: u64,
+        wake: Option<WakeConfig>,
+    ) -> Result<CommandOutcome> {
         let client = self.client.as_ref().unwrap();
-        let config = &project::Config { idle_minutes };
+        let config = &project::Config { idle_minutes, wake };
 
         let p = self.ctx.project_name();
         wait_with_spinner(500, |i, pb| async move {
@@ -2685,11 +3260,15 @@ impl Shuttle {
         Ok(CommandOutcome::Ok)
     }
 
-    async fn project_restart(&self, idle_minutes: u64) -> Result<CommandOutcome> {
+    async fn project_restart(
+        &self,
+        idle_minutes: u64,
+        wake: Option<WakeConfig>,
+    ) -> Result<CommandOutcome> {
         self.project_stop()
             .await
             .map_err(suggestions::project::project_restart_failure)?;
-        self.project_start(idle_minutes)
+        self.project_start(idle_minutes, wake)
             .await
             .map_err(suggestions::project::project_restart_failure)?;
 
@@ -2698,44 +3277,46 @@ impl Shuttle {
 
     async fn projects_list(&self, table_args: TableArgs) -> Result<CommandOutcome> {
         let client = self.client.as_ref().unwrap();
+        let structured = self.output != OutputMode::Table;
 
-        let projects_table = if self.beta {
-            project::get_projects_table_beta(
-                &client
-                    .get_projects_list_beta()
-                    .await
-                    .map_err(|err| {
-                        suggestions::project::project_request_failure(
-                            err,
-                            "Getting projects list failed",
-                            false,
-                            "getting the projects list fails repeatedly",
-                        )
-                    })?
-                    .projects,
-                table_args.raw,
-            )
-        } else {
-            project::get_projects_table(
-                &client.get_projects_list().await.map_err(|err| {
+        let (projects, projects_table) = if self.beta {
+            let projects = client
+                .get_projects_list_beta()
+                .await
+                .map_err(|err| {
                     suggestions::project::project_request_failure(
                         err,
                         "Getting projects list failed",
                         false,
                         "getting the projects list fails repeatedly",
                     )
-                })?,
-                table_args.raw,
-            )
+                })?
+                .projects;
+            let table = project::get_projects_table_beta(&projects, table_args.raw);
+            (serde_json::to_value(projects)?, table)
+        } else {
+            let projects = client.get_projects_list().await.map_err(|err| {
+                suggestions::project::project_request_failure(
+                    err,
+                    "Getting projects list failed",
+                    false,
+                    "getting the projects list fails repeatedly",
+                )
+            })?;
+            let table = project::get_projects_table(&projects, table_args.raw);
+            (serde_json::to_value(projects)?, table)
         };
 
-        println!("{}", "Personal Projects".bold());
-        println!("{projects_table}\n");
+        if !structured {
+            println!("{}", "Personal Projects".bold());
+            println!("{projects_table}\n");
+        }
 
         let teams = client.get_teams_list().await?;
+        let mut team_projects_list = Vec::new();
 
         for team in teams {
-            let team_projects_table = if self.beta {
+            let (team_projects, team_projects_table) = if self.beta {
                 let team_projects = client
                     .get_team_projects_list_beta(&team.id)
                     .await
@@ -2748,7 +3329,8 @@ impl Shuttle {
                         )
                     })?
                     .projects;
-                project::get_projects_table_beta(&team_projects, table_args.raw)
+                let table = project::get_projects_table_beta(&team_projects, table_args.raw);
+                (serde_json::to_value(team_projects)?, table)
             } else {
                 let team_projects =
                     client
@@ -2762,11 +3344,29 @@ impl Shuttle {
                                 "getting the team projects list fails repeatedly",
                             )
                         })?;
-                project::get_projects_table(&team_projects, table_args.raw)
+                let table = project::get_projects_table(&team_projects, table_args.raw);
+                (serde_json::to_value(team_projects)?, table)
             };
 
-            println!("{}", format!("{}'s Projects", team.display_name).bold());
-            println!("{team_projects_table}\n");
+            if structured {
+                team_projects_list.push(serde_json::json!({
+                    "team": team,
+                    "projects": team_projects,
+                }));
+            } else {
+                println!("{}", format!("{}'s Projects", team.display_name).bold());
+                println!("{team_projects_table}\n");
+            }
+        }
+
+        if structured {
+            return print_structured(
+                &serde_json::json!({
+                    "projects": projects,
+                    "teams": team_projects_list,
+                }),
+                self.output,
+            );
         }
 
         Ok(CommandOutcome::Ok)
@@ -2811,11 +3411,19 @@ impl Shuttle {
                     )
                 })?;
             println!(
-                "{project}\nIdle minutes: {}",
+                "{project}\nIdle minutes: {}\nContainer limits: {}\nTCP port: {}",
                 project
                     .idle_minutes
                     .map(|i| i.to_string())
-                    .unwrap_or("<unknown>".to_owned())
+                    .unwrap_or("<unknown>".to_owned()),
+                project
+                    .container_limits
+                    .map(|limits| limits.to_string())
+                    .unwrap_or("<unknown>".to_owned()),
+                project
+                    .tcp_port
+                    .map(|port| port.to_string())
+                    .unwrap_or("<none>".to_owned())
             );
         }
 
@@ -2939,7 +3547,51 @@ impl Shuttle {
         Ok(CommandOutcome::Ok)
     }
 
-    fn make_archive(&self, secrets_file: Option<PathBuf>, zip: bool) -> Result<Vec<u8>> {
+    async fn project_tcp_enable(&self) -> Result<CommandOutcome> {
+        let client = self.client.as_ref().unwrap();
+        let port = client
+            .enable_project_tcp(self.ctx.project_name())
+            .await
+            .map_err(|err| {
+                suggestions::project::project_request_failure(
+                    err,
+                    "Enabling TCP failed",
+                    true,
+                    "enabling TCP fails repeatedly",
+                )
+            })?;
+
+        println!(
+            "Raw TCP connections to {}.shuttleapp.rs:{port} are forwarded to the service of this project",
+            self.ctx.project_name()
+        );
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    async fn project_tcp_disable(&self) -> Result<CommandOutcome> {
+        let client = self.client.as_ref().unwrap();
+        let port = client
+            .disable_project_tcp(self.ctx.project_name())
+            .await
+            .map_err(|err| {
+                suggestions::project::project_request_failure(
+                    err,
+                    "Disabling TCP failed",
+                    true,
+                    "disabling TCP fails repeatedly",
+                )
+            })?;
+
+        match port {
+            Some(port) => println!("Released TCP port {port}"),
+            None => println!("This project does not have a TCP port"),
+        }
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    fn make_archive(&self, secret_args: &SecretsArgs, zip: bool) -> Result<Vec<u8>> {
         let include_patterns = self.ctx.assets();
 
         let working_directory = self.ctx.working_directory();
@@ -2973,13 +3625,6 @@ impl Shuttle {
 
         let mut globs = GlobSetBuilder::new();
 
-        if let Some(secrets_file) = secrets_file.clone() {
-            entries.push(secrets_file);
-        } else {
-            // Default: Include all Secrets.toml files
-            globs.add(Glob::new("**/Secrets.toml").unwrap());
-        }
-
         // User provided includes
         if let Some(rules) = include_patterns {
             for r in rules {
@@ -2999,6 +3644,18 @@ impl Shuttle {
             }
         }
 
+        // Secrets files are merged below, and only the result is uploaded
+        entries.retain(|path| {
+            !secrets::is_secrets_file(path) && secret_args.secrets.as_ref() != Some(path)
+        });
+
+        // zip file puts all files in root, tar puts all files nested in a dir at root level
+        let prefix = if zip {
+            working_directory
+        } else {
+            working_directory.parent().context("get parent dir")?
+        };
+
         let mut archive_files = BTreeMap::new();
         for path in entries {
             // It's not possible to add a directory to an archive
@@ -3008,26 +3665,57 @@ impl Shuttle {
                 continue;
             }
 
-            // zip file puts all files in root, tar puts all files nested in a dir at root level
-            let prefix = if zip {
-                working_directory
-            } else {
-                working_directory.parent().context("get parent dir")?
-            };
-            let mut name = path
+            let name = path
                 .strip_prefix(prefix)
                 .context("strip prefix of path")?
                 .to_owned();
 
-            // if this is the custom secrets file, rename it to Secrets.toml
-            if secrets_file.as_ref().is_some_and(|sf| sf == &path) {
-                name.pop();
-                name.push("Secrets.toml");
-            }
-
             archive_files.insert(path, name);
         }
 
+        // The merged secrets of each directory with secrets files, as a flat Secrets.toml
+        let mut secrets_files = BTreeMap::new();
+        if let Some(ref secrets_file) = secret_args.secrets {
+            // The custom secrets file is placed as Secrets.toml in its directory
+            let dir = secrets_file.parent().context("get secrets file dir")?;
+            secrets_files.insert(
+                dir.to_owned(),
+                secrets::load_file(secrets_file, secret_args.interpolate)?,
+            );
+        } else {
+            for entry in walkdir::WalkDir::new(working_directory)
+                .into_iter()
+                .filter_entry(|entry| entry.file_name() != "target" && entry.file_name() != ".git")
+            {
+                let path = entry.context("list dir")?.into_path();
+                if !secrets::is_secrets_file(&path) {
+                    continue;
+                }
+                let dir = path.parent().context("get secrets file dir")?;
+                if secrets_files.contains_key(dir) {
+                    continue;
+                }
+                if let Some(secrets) =
+                    secrets::load_layered(dir, secret_args.env.as_deref(), secret_args.interpolate)?
+                {
+                    secrets_files.insert(dir.to_owned(), secrets);
+                }
+            }
+        }
+        let secrets_files = secrets_files
+            .into_iter()
+            .map(|(dir, secrets)| {
+                let name = dir
+                    .join(secrets::SECRETS_FILE)
+                    .strip_prefix(prefix)
+                    .context("strip prefix of path")?
+                    .to_owned();
+                let contents = toml::to_string(&secrets).context("serializing secrets")?;
+
+                Ok((name, contents.into_bytes()))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
         if archive_files.is_empty() {
             error!("No files included in upload. Aborting...");
             bail!("No files included in upload.");
@@ -3046,6 +3734,14 @@ impl Shuttle {
                 File::open(path)?.read_to_end(&mut b)?;
                 zip.write_all(&b)?;
             }
+            for (name, contents) in secrets_files {
+                debug!("Packing merged secrets as {name:?}");
+                zip.start_file(
+                    name.to_str().expect("valid filename"),
+                    FileOptions::default(),
+                )?;
+                zip.write_all(&contents)?;
+            }
             let r = zip.finish().context("finish encoding zip archive")?;
 
             r.into_inner()
@@ -3057,6 +3753,14 @@ impl Shuttle {
                 debug!("Packing {path:?}");
                 tar.append_path_with_name(path, name)?;
             }
+            for (name, contents) in secrets_files {
+                debug!("Packing merged secrets as {name:?}");
+                let mut header = tar::Header::new_gnu();
+                header.set_size(contents.len() as u64);
+                header.set_mode(0o644);
+                header.set_cksum();
+                tar.append_data(&mut header, name, contents.as_slice())?;
+            }
             let encoder = tar.into_inner().context("get encoder from tar archive")?;
 
             encoder.finish().context("finish encoding tar archive")?
@@ -3214,6 +3918,25 @@ where
     Ok(cleanup())
 }
 
+fn print_log_item(log: &LogItem, raw: bool, output: OutputMode) {
+    match output {
+        OutputMode::Table if raw => println!("{}", log.get_raw_line()),
+        OutputMode::Table => println!("{log}"),
+        OutputMode::Json | OutputMode::Yaml => println!("{}", log.to_json_line()),
+    }
+}
+
+/// Print a command result for the JSON and YAML output modes
+fn print_structured<T: Serialize>(value: &T, output: OutputMode) -> Result<CommandOutcome> {
+    match output {
+        OutputMode::Table => bail!("command results can only be printed as JSON or YAML here"),
+        OutputMode::Json => println!("{}", serde_json::to_string_pretty(value)?),
+        OutputMode::Yaml => print!("{}", serde_yaml::to_string(value)?),
+    }
+
+    Ok(CommandOutcome::Ok)
+}
+
 fn create_spinner() -> ProgressBar {
     let pb = indicatif::ProgressBar::new_spinner();
     pb.enable_steady_tick(std::time::Duration::from_millis(250));
@@ -3272,9 +3995,7 @@ mod tests {
         let mut shuttle = Shuttle::new().unwrap();
         shuttle.load_project(&project_args).unwrap();
 
-        let archive = shuttle
-            .make_archive(deploy_args.secret_args.secrets, zip)
-            .unwrap();
+        let archive = shuttle.make_archive(&deploy_args.secret_args, zip).unwrap();
 
         if zip {
             let mut zip = ZipArchive::new(Cursor::new(archive)).unwrap();
@@ -3351,12 +4072,36 @@ mod tests {
         entries.sort();
         assert_eq!(entries, expected);
 
+        // environment overlays are merged into Secrets.toml instead of being uploaded
+        fs::write(
+            working_directory.join("Secrets.staging.toml"),
+            "KEY = 'staging'",
+        )
+        .unwrap();
+        let mut entries = get_archive_entries(
+            project_args.clone(),
+            DeployArgs {
+                secret_args: SecretsArgs {
+                    secrets: None,
+                    env: Some("staging".to_owned()),
+                    interpolate: false,
+                },
+                ..Default::default()
+            },
+            false,
+        );
+        entries.sort();
+        assert_eq!(entries, expected);
+        fs::remove_file(working_directory.join("Secrets.staging.toml")).unwrap();
+
         fs::remove_file(working_directory.join("Secrets.toml")).unwrap();
         let mut entries = get_archive_entries(
             project_args,
             DeployArgs {
                 secret_args: SecretsArgs {
                     secrets: Some(working_directory.join("Secrets.toml.example")),
+                    env: None,
+                    interpolate: false,
                 },
                 ..Default::default()
             },
@@ -1,9 +1,13 @@
-use std::{collections::HashMap, io::stdout, time::Duration};
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::stdout,
+    time::Duration,
+};
 
 use anyhow::Result;
 use async_trait::async_trait;
 use bollard::{
-    container::{Config, CreateContainerOptions, StartContainerOptions},
+    container::{Config, CreateContainerOptions, RemoveContainerOptions, StartContainerOptions},
     exec::{CreateExecOptions, CreateExecResults},
     image::CreateImageOptions,
     models::{CreateImageInfo, HostConfig, PortBinding, ProgressDetail},
@@ -19,6 +23,7 @@ use futures::StreamExt;
 use portpicker::pick_unused_port;
 use shuttle_common::{
     database::{self, AwsRdsEngine, SharedEngine},
+    resource::is_valid_resource_name,
     ContainerRequest, ContainerResponse, Secret,
 };
 use shuttle_proto::provisioner::{
@@ -94,54 +99,38 @@ impl LocalProvisioner {
         &self,
         container_name: &str,
         image: &str,
-        port: &str,
+        ports: &[String],
         env: Option<Vec<String>>,
     ) -> Result<ContainerInspectResponse, Status> {
         match self.docker.inspect_container(container_name, None).await {
-            Ok(container) => {
+            Ok(container) if binds_ports(&container, ports) => {
                 trace!("found container {container_name}");
                 Ok(container)
             }
-            Err(bollard::errors::Error::DockerResponseServerError {
-                status_code: 404, ..
-            }) => {
-                self.pull_image(image).await.expect("failed to pull image");
-                trace!("will create container {container_name}");
-                let options = Some(CreateContainerOptions {
-                    name: container_name,
-                    platform: None,
-                });
-                let mut port_bindings = HashMap::new();
-                let host_port = pick_unused_port().expect("system to have a free port");
-                port_bindings.insert(
-                    port.to_string(),
-                    Some(vec![PortBinding {
-                        host_port: Some(host_port.to_string()),
-                        ..Default::default()
-                    }]),
+            Ok(_) => {
+                // The container was made by a run that asked for other ports
+                trace!(
+                    "container {container_name} does not bind the requested ports, recreating it"
                 );
-                let host_config = HostConfig {
-                    port_bindings: Some(port_bindings),
-                    ..Default::default()
-                };
-
-                let config: Config<String> = Config {
-                    image: Some(image.to_string()),
-                    env,
-                    host_config: Some(host_config),
-                    ..Default::default()
-                };
-
                 self.docker
-                    .create_container(options, config)
+                    .remove_container(
+                        container_name,
+                        Some(RemoveContainerOptions {
+                            force: true,
+                            ..Default::default()
+                        }),
+                    )
                     .await
-                    .expect("to be able to create container");
+                    .map_err(|error| Status::internal(error.to_string()))?;
 
-                Ok(self
-                    .docker
-                    .inspect_container(container_name, None)
+                self.create_container(container_name, image, ports, env)
+                    .await
+            }
+            Err(bollard::errors::Error::DockerResponseServerError {
+                status_code: 404, ..
+            }) => {
+                self.create_container(container_name, image, ports, env)
                     .await
-                    .expect("container to be created"))
             }
             Err(error) => {
                 error!("Got unexpected error while inspecting docker container: {error}");
@@ -153,17 +142,79 @@ impl LocalProvisioner {
         }
     }
 
+    async fn create_container(
+        &self,
+        container_name: &str,
+        image: &str,
+        ports: &[String],
+        env: Option<Vec<String>>,
+    ) -> Result<ContainerInspectResponse, Status> {
+        self.pull_image(image).await.expect("failed to pull image");
+        trace!("will create container {container_name}");
+        let options = Some(CreateContainerOptions {
+            name: container_name,
+            platform: None,
+        });
+        let mut port_bindings = HashMap::new();
+        let mut exposed_ports = HashMap::new();
+        for port in ports {
+            let host_port = pick_unused_port().expect("system to have a free port");
+            port_bindings.insert(
+                port.to_string(),
+                Some(vec![PortBinding {
+                    host_port: Some(host_port.to_string()),
+                    ..Default::default()
+                }]),
+            );
+            // Needed for images that don't declare the port with `EXPOSE`
+            exposed_ports.insert(port.to_string(), HashMap::new());
+        }
+        let host_config = HostConfig {
+            port_bindings: Some(port_bindings),
+            ..Default::default()
+        };
+
+        let config: Config<String> = Config {
+            image: Some(image.to_string()),
+            env,
+            exposed_ports: Some(exposed_ports),
+            host_config: Some(host_config),
+            ..Default::default()
+        };
+
+        self.docker
+            .create_container(options, config)
+            .await
+            .expect("to be able to create container");
+
+        Ok(self
+            .docker
+            .inspect_container(container_name, None)
+            .await
+            .expect("container to be created"))
+    }
+
     async fn get_db_connection_string(
         &self,
         project_name: &str,
         db_type: Type,
         db_name: Option<String>,
+        resource_name: Option<String>,
     ) -> Result<DatabaseResponse, Status> {
         trace!("getting sql string for project '{project_name}'");
 
+        if let Some(name) = &resource_name {
+            if !is_valid_resource_name(name) {
+                return Err(Status::invalid_argument(format!(
+                    "invalid resource name '{name}': use up to 32 lowercase letters and digits"
+                )));
+            }
+        }
+
         let database_name = match db_type {
             database::Type::AwsRds(_) => db_name.unwrap_or_else(|| project_name.to_string()),
             database::Type::Shared(SharedEngine::MongoDb) => "admin".to_string(),
+            database::Type::Shared(SharedEngine::Redis) => "0".to_string(),
             _ => project_name.to_string(),
         };
 
@@ -177,10 +228,13 @@ impl LocalProvisioner {
             env,
             is_ready_cmd,
         } = db_type_to_config(db_type, &database_name);
-        let container_name = format!("shuttle_{project_name}_{type}");
+        let container_name = match &resource_name {
+            Some(name) => format!("shuttle_{project_name}_{type}_{name}"),
+            None => format!("shuttle_{project_name}_{type}"),
+        };
 
         let container = self
-            .get_container(&container_name, &image, &port, env)
+            .get_container(&container_name, &image, &[port.clone()], env)
             .await?;
 
         let host_port = self.get_container_first_host_port(&container, &port);
@@ -219,20 +273,94 @@ impl LocalProvisioner {
             env,
             image,
             port,
+            extra_ports,
+            ready_cmd,
         } = req;
 
         let container_name = format!("shuttle_{project_name}_{container_name}");
+        let ports: Vec<String> = std::iter::once(port).chain(extra_ports).collect();
 
         let container = self
-            .get_container(&container_name, &image, &port, Some(env))
+            .get_container(&container_name, &image, &ports, Some(env))
             .await?;
 
-        let host_port = self.get_container_first_host_port(&container, &port);
+        let host_ports: BTreeMap<String, String> = ports
+            .iter()
+            .map(|port| {
+                (
+                    port.clone(),
+                    self.get_container_first_host_port(&container, port),
+                )
+            })
+            .collect();
+        let host_port = host_ports[&ports[0]].clone();
 
         self.start_container_if_not_running(&container, &container_name, &container_name)
             .await;
 
-        Ok(ContainerResponse { host_port })
+        if !ready_cmd.is_empty() {
+            self.wait_for_exec_success(&container_name, ready_cmd)
+                .await?;
+        }
+
+        Ok(ContainerResponse {
+            host_port,
+            host_ports,
+        })
+    }
+
+    /// Run a command in a container until it exits successfully, giving up after a minute
+    async fn wait_for_exec_success(
+        &self,
+        container_name: &str,
+        ready_cmd: Vec<String>,
+    ) -> Result<(), Status> {
+        for _ in 0..120 {
+            trace!("waiting for '{container_name}' to be ready");
+
+            let config = CreateExecOptions {
+                cmd: Some(ready_cmd.clone()),
+                attach_stdout: Some(true),
+                attach_stderr: Some(true),
+                ..Default::default()
+            };
+
+            let CreateExecResults { id } = self
+                .docker
+                .create_exec(container_name, config)
+                .await
+                .map_err(|error| Status::internal(error.to_string()))?;
+
+            let ready_result = self
+                .docker
+                .start_exec(&id, None)
+                .await
+                .map_err(|error| Status::internal(error.to_string()))?;
+
+            // Drain the output so that the command has exited before inspecting it
+            if let bollard::exec::StartExecResults::Attached { mut output, .. } = ready_result {
+                while let Some(line) = output.next().await {
+                    trace!("line: {:?}", line);
+                }
+            }
+
+            let exit_code = self
+                .docker
+                .inspect_exec(&id)
+                .await
+                .map_err(|error| Status::internal(error.to_string()))?
+                .exit_code;
+
+            if exit_code == Some(0) {
+                return Ok(());
+            }
+
+            sleep(Duration::from_millis(500)).await;
+        }
+
+        Err(Status::deadline_exceeded(format!(
+            "container '{container_name}' did not become ready in time"
+        )))
     }
 
     async fn wait_for_ready(
@@ -327,12 +455,13 @@ impl Provisioner for LocalProvisioner {
             project_name,
             db_type,
             db_name,
+            resource_name,
         } = request.into_inner();
 
         let db_type: Option<Type> = db_type.unwrap().into();
 
         let res = self
-            .get_db_connection_string(&project_name, db_type.unwrap(), db_name)
+            .get_db_connection_string(&project_name, db_type.unwrap(), db_name, resource_name)
             .await?;
 
         Ok(Response::new(res))
@@ -350,6 +479,25 @@ impl Provisioner for LocalProvisioner {
     }
 }
 
+/// Whether every one of `ports` is bound to a host port on the container
+fn binds_ports(container: &ContainerInspectResponse, ports: &[String]) -> bool {
+    let Some(port_bindings) = container
+        .host_config
+        .as_ref()
+        .and_then(|host_config| host_config.port_bindings.as_ref())
+    else {
+        return false;
+    };
+
+    ports.iter().all(|port| {
+        port_bindings
+            .get(port)
+            .and_then(Option::as_ref)
+            .and_then(|bindings| bindings.first())
+            .is_some_and(|binding| binding.host_port.is_some())
+    })
+}
+
 fn print_layers(layers: &Vec<CreateImageInfo>) {
     for info in layers {
         stdout()
@@ -437,6 +585,17 @@ fn db_type_to_config(db_type: Type, database_name: &str) -> EngineConfig {
                 "db".to_string(),
             ],
         },
+        Type::Shared(SharedEngine::Redis) => EngineConfig {
+            r#type: "shared_redis".to_string(),
+            image: "docker.io/library/redis:7.2".to_string(),
+            engine: "redis".to_string(),
+            // The default user has no password set, so any password is accepted
+            username: "default".to_string(),
+            password: "redis".to_string().into(),
+            port: "6379/tcp".to_string(),
+            env: None,
+            is_ready_cmd: vec!["redis-cli".to_string(), "ping".to_string()],
+        },
         Type::AwsRds(AwsRdsEngine::Postgres) => EngineConfig {
             r#type: "aws_rds_postgres".to_string(),
             image: "docker.io/library/postgres:13.4".to_string(),
@@ -578,6 +737,7 @@ pub mod beta {
                     config: serde_json::Value::Null,
                     r#type: resource::Type::Secrets,
                     data: serde_json::to_value(&state.secrets).unwrap(),
+                    name: None,
                 })
                 .unwrap()
             }
@@ -598,6 +758,7 @@ pub mod beta {
                                         project_name: state.project_name.clone(),
                                         db_type: Some(db_type.into()),
                                         db_name: config.db_name,
+                                        resource_name: shuttle_resource.name,
                                     }))
                                     .await
                                     .context("Failed to start database container. Make sure that a Docker engine is running.")?
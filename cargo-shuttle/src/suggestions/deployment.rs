@@ -50,3 +50,19 @@ pub fn stop_deployment_failure(err: anyhow::Error) -> anyhow::Error {
     println!("cargo shuttle project restart");
     err
 }
+
+pub fn rollback_deployment_failure(err: anyhow::Error) -> anyhow::Error {
+    println!();
+    println!("{}", "Rolling back to the deployment failed".red());
+    println!();
+    println!("Please check that the deployment finished building by listing your deployments:");
+    println!();
+    println!("cargo shuttle deployment list");
+    println!();
+    println!(
+        "If rolling back fails repeatedly, please try restarting your project before rolling back again or contacting the team on the Discord server:"
+    );
+    println!();
+    println!("cargo shuttle project restart");
+    err
+}
@@ -0,0 +1,168 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use anyhow::{Context, Result};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::overrides::OverrideBuilder;
+use ignore::WalkBuilder;
+use shuttle_common::constants::{EXECUTABLE_DIRNAME, STORAGE_DIRNAME};
+use tokio::time::{sleep, Duration};
+use tracing::trace;
+
+/// How often the project files are checked for changes
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Directories that never trigger a reload, since builds and runs write to them
+const IGNORED_DIRS: [&str; 4] = [".git", "target", EXECUTABLE_DIRNAME, STORAGE_DIRNAME];
+
+/// Modification times of the watched files in a project
+type Snapshot = BTreeMap<PathBuf, Option<SystemTime>>;
+
+/// Watches the files of a project for changes.
+///
+/// Like for deployment archives, everything that is not ignored by `.gitignore` or `.ignore` is watched, plus
+/// the files matching the `assets` globs in `Shuttle.toml`.
+pub struct Watcher {
+    working_directory: PathBuf,
+    assets: GlobSet,
+    snapshot: Snapshot,
+}
+
+impl Watcher {
+    pub fn new(working_directory: &Path, assets: Option<&Vec<String>>) -> Result<Self> {
+        let mut globs = GlobSetBuilder::new();
+        for pattern in assets.into_iter().flatten() {
+            globs.add(Glob::new(pattern).context(format!("parsing glob pattern {pattern:?}"))?);
+        }
+        let assets = globs.build().context("building asset globs")?;
+        let snapshot = snapshot(working_directory, &assets)?;
+
+        Ok(Self {
+            working_directory: working_directory.to_owned(),
+            assets,
+            snapshot,
+        })
+    }
+
+    /// Wait until a watched file is added, removed or modified
+    pub async fn changed(&mut self) -> Result<()> {
+        loop {
+            sleep(POLL_INTERVAL).await;
+
+            let current = self.poll().await?;
+            if current == self.snapshot {
+                continue;
+            }
+
+            // Editors and formatters often write several files in a row, so wait for them to settle
+            let mut settled = current;
+            loop {
+                sleep(POLL_INTERVAL).await;
+                let current = self.poll().await?;
+                if current == settled {
+                    break;
+                }
+                settled = current;
+            }
+            trace!("detected changes in the watched files");
+            self.snapshot = settled;
+
+            return Ok(());
+        }
+    }
+
+    async fn poll(&self) -> Result<Snapshot> {
+        let working_directory = self.working_directory.clone();
+        let assets = self.assets.clone();
+
+        tokio::task::spawn_blocking(move || snapshot(&working_directory, &assets))
+            .await
+            .context("joining file watcher task")?
+    }
+}
+
+fn snapshot(working_directory: &Path, assets: &GlobSet) -> Result<Snapshot> {
+    let mut files = Snapshot::new();
+
+    let mut overrides = OverrideBuilder::new(working_directory);
+    for dir in IGNORED_DIRS {
+        overrides
+            .add(&format!("!{dir}/"))
+            .context(format!("adding override `!{dir}/`"))?;
+    }
+    let overrides = overrides.build().context("building watch override rules")?;
+
+    for entry in WalkBuilder::new(working_directory)
+        .hidden(false)
+        .require_git(false)
+        .overrides(overrides)
+        .build()
+    {
+        let path = entry.context("list dir entry")?.into_path();
+        if path.is_file() {
+            let modified = path.metadata().and_then(|m| m.modified()).ok();
+            files.insert(path, modified);
+        }
+    }
+
+    if !assets.is_empty() {
+        let walker = walkdir::WalkDir::new(working_directory)
+            .into_iter()
+            .filter_entry(|entry| {
+                entry.depth() != 1
+                    || !IGNORED_DIRS
+                        .iter()
+                        .any(|dir| entry.file_name() == *dir && entry.file_type().is_dir())
+            });
+        for entry in walker {
+            let entry = entry.context("list dir")?;
+            let path = entry.path();
+            if entry.file_type().is_file()
+                && assets.is_match(
+                    path.strip_prefix(working_directory)
+                        .context("strip prefix of path")?,
+                )
+            {
+                let modified = entry.metadata().ok().and_then(|m| m.modified().ok());
+                files.insert(path.to_owned(), modified);
+            }
+        }
+    }
+
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn watches_project_files_and_assets() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::write(root.join(".gitignore"), "ignored\ndist/\n").unwrap();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("src").join("main.rs"), "").unwrap();
+        fs::write(root.join("ignored"), "").unwrap();
+        fs::create_dir_all(root.join("dist")).unwrap();
+        fs::write(root.join("dist").join("index.html"), "").unwrap();
+        fs::create_dir_all(root.join("target")).unwrap();
+        fs::write(root.join("target").join("binary"), "").unwrap();
+
+        let assets = GlobSetBuilder::new()
+            .add(Glob::new("dist/*").unwrap())
+            .build()
+            .unwrap();
+        let files = snapshot(root, &assets).unwrap();
+        let mut names: Vec<_> = files
+            .keys()
+            .map(|path| path.strip_prefix(root).unwrap().to_str().unwrap())
+            .collect();
+        names.sort();
+
+        assert_eq!(names, vec![".gitignore", "dist/index.html", "src/main.rs"]);
+    }
+}
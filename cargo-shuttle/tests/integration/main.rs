@@ -1,7 +1,7 @@
 mod init;
 mod run;
 
-use cargo_shuttle::{Command, CommandOutcome, ProjectArgs, Shuttle, ShuttleArgs};
+use cargo_shuttle::{Command, CommandOutcome, OutputMode, ProjectArgs, Shuttle, ShuttleArgs};
 use std::path::Path;
 
 /// creates a `cargo-shuttle` run instance with some reasonable defaults set.
@@ -23,6 +23,7 @@ async fn cargo_shuttle_command(
                 offline: false,
                 debug: false,
                 beta: false,
+                output: OutputMode::Table,
                 cmd,
             },
             false,
@@ -1,4 +1,4 @@
-use cargo_shuttle::{Command, ProjectArgs, RunArgs, Shuttle, ShuttleArgs};
+use cargo_shuttle::{Command, OutputMode, ProjectArgs, RunArgs, Shuttle, ShuttleArgs};
 use portpicker::pick_unused_port;
 use std::{fs::canonicalize, process::exit, time::Duration};
 use tokio::time::sleep;
@@ -31,6 +31,7 @@ pub async fn cargo_shuttle_run(working_directory: &str, external: bool) -> Strin
         external,
         release: false,
         raw: false,
+        watch: false,
         secret_args: Default::default(),
     };
 
@@ -44,6 +45,7 @@ pub async fn cargo_shuttle_run(working_directory: &str, external: bool) -> Strin
             offline: false,
             debug: false,
             beta: false,
+            output: OutputMode::Table,
             cmd: Command::Run(run_args),
         },
         false,
@@ -3,6 +3,7 @@ pub mod logger;
 pub mod permit_pdp;
 pub mod postgres;
 pub mod provisioner;
+pub mod resource_recorder;
 
 use shuttle_common::claims::{AccountTier, Claim, Scope};
 
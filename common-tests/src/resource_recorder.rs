@@ -0,0 +1,30 @@
+use std::{
+    net::{Ipv4Addr, SocketAddr},
+    time::Duration,
+};
+
+use portpicker::pick_unused_port;
+use shuttle_proto::resource_recorder::{
+    self,
+    resource_recorder_server::{ResourceRecorder, ResourceRecorderServer},
+};
+use tonic::transport::Server;
+
+pub async fn get_mocked_resource_recorder_client(
+    resource_recorder: impl ResourceRecorder,
+) -> resource_recorder::Client {
+    let resource_recorder_addr =
+        SocketAddr::new(Ipv4Addr::LOCALHOST.into(), pick_unused_port().unwrap());
+    let resource_recorder_uri = format!("http://{}", resource_recorder_addr);
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(ResourceRecorderServer::new(resource_recorder))
+            .serve(resource_recorder_addr)
+            .await
+    });
+
+    // Wait for the resource recorder server to start before creating a client.
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    resource_recorder::get_client(resource_recorder_uri.parse().unwrap()).await
+}
@@ -15,4 +15,7 @@ pub struct CertificateResponse {
     pub subject: String,
     pub serial_hex: String,
     pub not_after: String,
+    /// Why the last automatic renewal failed, if it did
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub renewal_error: Option<String>,
 }
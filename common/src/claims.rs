@@ -16,7 +16,7 @@ use opentelemetry::global;
 use opentelemetry_http::HeaderInjector;
 use pin_project::pin_project;
 use serde::{Deserialize, Serialize};
-use strum::{EnumMessage, EnumString};
+use strum::{Display, EnumMessage, EnumString};
 use tower::{Layer, Service};
 use tracing::{error, trace, Span};
 use tracing_opentelemetry::OpenTelemetrySpanExt;
@@ -32,8 +32,9 @@ const ISS: &str = "shuttle";
 
 /// The scope of operations that can be performed on shuttle
 /// Every scope defaults to read and will use a suffix for updating tasks
-#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq, EnumMessage)]
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq, EnumMessage, EnumString, Display)]
 #[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
 pub enum Scope {
     /// Read the details, such as status and address, of a deployment
     Deployment,
@@ -55,6 +56,7 @@ pub enum Scope {
 
     /// Create and delete projects
     #[serde(rename = "project_create")] // compatibility
+    #[strum(to_string = "project_create")]
     ProjectWrite,
 
     /// Create more projects than the free tier default
@@ -42,6 +42,17 @@ pub const fn default_idle_minutes() -> u64 {
     DEFAULT_IDLE_MINUTES
 }
 
+/// How long requests wait for an idle project to wake up
+pub const DEFAULT_WAKE_TIMEOUT_SECS: u64 = 60;
+
+/// The longest requests can be made to wait for an idle project to wake up
+pub const MAX_WAKE_TIMEOUT_SECS: u64 = 300;
+
+/// Function to set [DEFAULT_WAKE_TIMEOUT_SECS] as a serde default
+pub const fn default_wake_timeout_secs() -> u64 {
+    DEFAULT_WAKE_TIMEOUT_SECS
+}
+
 /// The port that deployer tells the runtime to expose its service on
 pub const DEPLOYER_SERVICE_HTTP_PORT: u16 = 8000;
 
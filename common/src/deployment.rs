@@ -17,6 +17,7 @@ pub enum State {
     Completed,
     Stopped,
     Crashed,
+    Restarting,
     Unknown,
 }
 
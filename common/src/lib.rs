@@ -27,7 +27,7 @@ pub mod templates;
 #[cfg(feature = "tracing")]
 pub mod tracing;
 
-use std::fmt::Debug;
+use std::{collections::BTreeMap, fmt::Debug};
 
 use anyhow::bail;
 use serde::{Deserialize, Serialize};
@@ -240,7 +240,7 @@ impl DatabaseInfoBeta {
 }
 
 /// Used to request a container from the local run provisioner
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct ContainerRequest {
     pub project_name: String,
     /// Type of container, used in the container name. ex "qdrant"
@@ -249,16 +249,34 @@ pub struct ContainerRequest {
     pub image: String,
     /// The internal port that the container should expose. ex. "6334/tcp"
     pub port: String,
+    /// Any additional internal ports that the container should expose. ex. ["6333/tcp"]
+    #[serde(default)]
+    pub extra_ports: Vec<String>,
     /// list of "KEY=value" strings
     pub env: Vec<String>,
+    /// Command to execute in the container to check if it is ready.
+    /// The container is considered ready once the command exits successfully.
+    /// No check is done if this is empty.
+    #[serde(default)]
+    pub ready_cmd: Vec<String>,
 }
 
 /// Response from requesting a container from the local run provisioner
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ContainerResponse {
     /// The port that the container exposes to the host.
     /// Is a string for parity with the Docker respose.
     pub host_port: String,
+    /// The host port for every exposed internal port, keyed by the internal port. ex. "6334/tcp"
+    #[serde(default)]
+    pub host_ports: BTreeMap<String, String>,
+}
+
+impl ContainerResponse {
+    /// Get the host port that an internal port of the container is exposed on
+    pub fn host_port_for(&self, port: &str) -> Option<&str> {
+        self.host_ports.get(port).map(String::as_str)
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
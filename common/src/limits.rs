@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+#[cfg(feature = "models")]
+use crate::models::project::ContainerLimits;
 use crate::{
     claims::AccountTier,
     constants::limits::{MAX_PROJECTS_DEFAULT, MAX_PROJECTS_EXTRA},
@@ -69,3 +71,19 @@ impl From<AccountTier> for Limits {
         }
     }
 }
+
+#[cfg(feature = "models")]
+impl From<AccountTier> for ContainerLimits {
+    fn from(value: AccountTier) -> Self {
+        match value {
+            // Same as the limits projects had before they depended on the tier
+            AccountTier::Basic
+            | AccountTier::PendingPaymentPro
+            | AccountTier::CancelledPro
+            | AccountTier::Deployer
+            | AccountTier::Pro
+            | AccountTier::Team
+            | AccountTier::Admin => Self::new(6, 4, 4),
+        }
+    }
+}
@@ -41,6 +41,34 @@ pub enum LogsRange {
     All,
 }
 
+/// Conditions deployment log lines have to match, on top of the [`LogsRange`]
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct LogsFilter {
+    /// Only logs from this time onwards
+    pub since: Option<DateTime<Utc>>,
+    /// Only logs from before this time
+    pub until: Option<DateTime<Utc>>,
+    /// Only logs containing this text
+    pub grep: Option<String>,
+    /// Match `grep` as a regular expression instead of as plain text
+    #[serde(default)]
+    pub regex: bool,
+    /// Only logs from this source, e.g. "deployer" or "runtime"
+    pub source: Option<String>,
+    /// Only logs at this level or above, e.g. "warn"
+    pub level: Option<String>,
+}
+
+impl LogsFilter {
+    pub fn is_empty(&self) -> bool {
+        self.since.is_none()
+            && self.until.is_none()
+            && self.grep.is_none()
+            && self.source.is_none()
+            && self.level.is_none()
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct LogItem {
     /// Deployment id
@@ -155,6 +183,30 @@ impl std::fmt::Display for LogItem {
     }
 }
 
+/// The JSON form of a [`LogItem`], used for NDJSON log output
+#[cfg(feature = "display")]
+#[derive(Serialize)]
+struct LogItemJson<'a> {
+    timestamp: &'a DateTime<Utc>,
+    deployment_id: &'a Uuid,
+    origin: String,
+    line: &'a str,
+}
+
+#[cfg(feature = "display")]
+impl LogItem {
+    /// Format this log item as a single line of JSON, for piping logs into other tools
+    pub fn to_json_line(&self) -> String {
+        serde_json::to_string(&LogItemJson {
+            timestamp: &self.timestamp,
+            deployment_id: &self.id,
+            origin: self.internal_origin.to_string(),
+            line: &self.line,
+        })
+        .expect("log item to serialize")
+    }
+}
+
 #[cfg(feature = "display")]
 pub trait ColoredLevel {
     fn colored(&self) -> StyledContent<&str>;
@@ -356,6 +408,21 @@ mod tests {
         });
     }
 
+    #[test]
+    fn log_item_json_line() {
+        let item = LogItem {
+            id: Uuid::nil(),
+            internal_origin: Backend::Runtime("my-app".to_string()),
+            timestamp: DateTime::from_timestamp(1_700_000_000, 0).unwrap(),
+            line: "Listening on \"0.0.0.0:8000\"".to_string(),
+        };
+
+        assert_eq!(
+            item.to_json_line(),
+            r#"{"timestamp":"2023-11-14T22:13:20Z","deployment_id":"00000000-0000-0000-0000-000000000000","origin":"Runtime","line":"Listening on \"0.0.0.0:8000\""}"#
+        );
+    }
+
     #[test]
     fn log_item_truncate() {
         let mut l = "öl".repeat(100);
@@ -87,7 +87,7 @@ impl State {
             State::Running => "green",
             State::Completed | State::Stopped => "blue",
             State::Crashed => "red",
-            State::Unknown => "yellow",
+            State::Restarting | State::Unknown => "yellow",
         }
     }
 }
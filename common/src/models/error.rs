@@ -258,6 +258,10 @@ pub struct ProjectNotReady;
 #[error("Project is running but is not responding correctly. Try to restart it")]
 pub struct ProjectUnavailable;
 
+#[derive(Debug, Error)]
+#[error("Project is waking up from being idle. Try again in a few seconds")]
+pub struct ProjectWakingUp;
+
 #[derive(Debug, Error)]
 #[error("Project '{0}' not found. Make sure you are the owner of this project name. Run `cargo shuttle project start` to create a new project.")]
 pub struct ProjectNotFound(pub String);
@@ -3,6 +3,7 @@ pub mod deployment;
 pub mod error;
 pub mod project;
 pub mod resource;
+pub mod secret;
 pub mod service;
 pub mod stats;
 pub mod team;
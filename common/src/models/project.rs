@@ -12,6 +12,7 @@ use crossterm::style::Stylize;
 use serde::{Deserialize, Serialize};
 use strum::EnumString;
 
+use crate::constants::{default_wake_timeout_secs, DEFAULT_WAKE_TIMEOUT_SECS};
 use crate::deployment::EcsState;
 
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
@@ -24,6 +25,71 @@ pub struct Response {
     pub owner: Owner,
     /// Whether the calling user is an admin in this project
     pub is_admin: bool,
+    /// Resources of the project container, if it has been created
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub container_limits: Option<ContainerLimits>,
+    /// Port of the proxy that forwards raw TCP connections to the project, if it has one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tcp_port: Option<u16>,
+}
+
+const GIB: u64 = 1024 * 1024 * 1024;
+const CPU_PERIOD: u64 = 100_000;
+
+/// Resources a project container is allowed to use
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ContainerLimits {
+    /// Hard memory limit, in bytes
+    pub memory: u64,
+    /// Soft memory limit that is enforced when the host is low on memory, in bytes
+    pub memory_reservation: u64,
+    /// CPU time the container can use per CPU period of 100ms, in microseconds
+    pub cpu_quota: u64,
+}
+
+impl ContainerLimits {
+    pub fn new(memory_gib: u64, memory_reservation_gib: u64, cpus: u64) -> Self {
+        Self {
+            memory: memory_gib.saturating_mul(GIB),
+            memory_reservation: memory_reservation_gib.saturating_mul(GIB),
+            cpu_quota: cpus.saturating_mul(CPU_PERIOD),
+        }
+    }
+
+    /// The period that [`ContainerLimits::cpu_quota`] is a share of, in microseconds
+    pub fn cpu_period(&self) -> u64 {
+        CPU_PERIOD
+    }
+
+    /// Check that these limits can be applied to a container
+    pub fn validate(&self) -> Result<(), String> {
+        if self.memory == 0 || self.memory_reservation == 0 || self.cpu_quota == 0 {
+            return Err("memory, memory reservation and CPUs must all be above zero".to_string());
+        }
+
+        if self.memory_reservation > self.memory {
+            return Err("memory reservation cannot be above the memory limit".to_string());
+        }
+
+        // Docker takes signed values and sets the swap limit to twice the memory
+        if self.memory > i64::MAX as u64 / 2 || self.cpu_quota > i64::MAX as u64 {
+            return Err("limits are too large".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for ContainerLimits {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:.1} GiB memory ({:.1} GiB reserved), {:.2} CPUs",
+            self.memory as f64 / GIB as f64,
+            self.memory_reservation as f64 / GIB as f64,
+            self.cpu_quota as f64 / CPU_PERIOD as f64,
+        )
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
@@ -211,6 +277,30 @@ impl State {
 #[derive(Deserialize, Serialize)]
 pub struct Config {
     pub idle_minutes: u64,
+    /// Only changes how the project wakes up when it is set, so older clients keep the current settings
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wake: Option<WakeConfig>,
+}
+
+/// How requests to an idle project are handled while it wakes up
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct WakeConfig {
+    /// Seconds a request waits for the project to wake up
+    #[serde(default = "default_wake_timeout_secs")]
+    pub timeout: u64,
+    /// Answer the requests that are still waiting after the timeout with a "waking up" page and a `Retry-After`
+    /// header, instead of an error
+    #[serde(default)]
+    pub waking_response: bool,
+}
+
+impl Default for WakeConfig {
+    fn default() -> Self {
+        Self {
+            timeout: DEFAULT_WAKE_TIMEOUT_SECS,
+            waking_response: false,
+        }
+    }
 }
 
 #[derive(Clone, Deserialize, Serialize, Debug, PartialEq)]
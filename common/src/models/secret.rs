@@ -0,0 +1,118 @@
+use std::collections::BTreeMap;
+
+use comfy_table::{
+    presets::{NOTHING, UTF8_BORDERS_ONLY},
+    Cell, CellAlignment, ContentArrangement, Table,
+};
+use crossterm::style::Stylize;
+use serde::{Deserialize, Serialize};
+
+use crate::secrets::SecretStore;
+
+/// Changes to make to the secrets of a service
+#[derive(Deserialize, Serialize, Default, Debug)]
+pub struct SecretsUpdateRequest {
+    /// Secrets to add or change
+    #[serde(default)]
+    pub set: BTreeMap<String, String>,
+    /// Keys of the secrets to remove
+    #[serde(default)]
+    pub unset: Vec<String>,
+    /// Restart the running deployment so that it picks up the changes
+    #[serde(default)]
+    pub restart: bool,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct SecretsUpdateResponse {
+    /// The secrets of the service after the update
+    pub secrets: SecretStore,
+    /// Whether the running deployment was restarted
+    pub restarted: bool,
+}
+
+/// The secrets as plain key-value pairs, with the values redacted unless `show_secrets` is set
+pub fn secrets_map(secrets: &SecretStore, show_secrets: bool) -> BTreeMap<String, String> {
+    secrets
+        .secrets
+        .iter()
+        .map(|(key, value)| {
+            let value = if show_secrets {
+                value.expose()
+            } else {
+                value.redacted()
+            };
+            (key.clone(), value.to_owned())
+        })
+        .collect()
+}
+
+pub fn get_secrets_table(
+    secrets: &SecretStore,
+    service_name: &str,
+    raw: bool,
+    show_secrets: bool,
+) -> String {
+    if secrets.secrets.is_empty() {
+        return if raw {
+            format!("{service_name} has no secrets\n")
+        } else {
+            format!("{}\n", format!("{service_name} has no secrets").bold())
+        };
+    }
+
+    let mut table = Table::new();
+    if raw {
+        table
+            .load_preset(NOTHING)
+            .set_content_arrangement(ContentArrangement::Disabled)
+            .set_header(vec![
+                Cell::new("Key").set_alignment(CellAlignment::Left),
+                Cell::new("Value").set_alignment(CellAlignment::Left),
+            ]);
+    } else {
+        table
+            .load_preset(UTF8_BORDERS_ONLY)
+            .set_content_arrangement(ContentArrangement::Disabled)
+            .set_header(vec![
+                Cell::new("Key").set_alignment(CellAlignment::Center),
+                Cell::new("Value").set_alignment(CellAlignment::Center),
+            ]);
+    }
+
+    for (key, value) in &secrets.secrets {
+        let value = if show_secrets {
+            value.expose()
+        } else {
+            value.redacted()
+        };
+        table.add_row(vec![key.as_str(), value]);
+    }
+
+    format!("These secrets can be accessed by {service_name}\n{table}\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::secrets::Secret;
+
+    #[test]
+    fn redacts_secret_values() {
+        let secrets = SecretStore::new(BTreeMap::from([(
+            "API_KEY".to_string(),
+            Secret::new("VERYSECRET".to_string()),
+        )]));
+
+        let table = get_secrets_table(&secrets, "my-service", true, false);
+        assert!(table.contains("API_KEY"));
+        assert!(table.contains("********"));
+        assert!(!table.contains("VERYSECRET"));
+
+        let table = get_secrets_table(&secrets, "my-service", true, true);
+        assert!(table.contains("VERYSECRET"));
+
+        assert_eq!(secrets_map(&secrets, false)["API_KEY"], "********");
+        assert_eq!(secrets_map(&secrets, true)["API_KEY"], "VERYSECRET");
+    }
+}
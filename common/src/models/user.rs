@@ -1,4 +1,8 @@
 use chrono::{DateTime, Utc};
+use comfy_table::{
+    presets::{NOTHING, UTF8_BORDERS_ONLY},
+    Cell, CellAlignment, ContentArrangement, Table,
+};
 use serde::{Deserialize, Serialize};
 use strum::{Display, EnumString};
 
@@ -39,3 +43,61 @@ pub enum SubscriptionType {
     Pro,
     Rds,
 }
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ApiKeyRequest {
+    pub name: String,
+    /// The scopes the key can use, as named in the token claims (e.g. `deployment_push`)
+    pub scopes: Vec<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ApiKeyResponse {
+    pub name: String,
+    /// The key itself, which is only returned when it is created
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    pub scopes: Vec<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+pub fn get_api_keys_table(keys: &[ApiKeyResponse], raw: bool) -> String {
+    let mut table = Table::new();
+
+    if raw {
+        table
+            .load_preset(NOTHING)
+            .set_content_arrangement(ContentArrangement::Disabled)
+            .set_header(vec![
+                Cell::new("Name").set_alignment(CellAlignment::Left),
+                Cell::new("Scopes").set_alignment(CellAlignment::Left),
+                Cell::new("Expires").set_alignment(CellAlignment::Left),
+                Cell::new("Created").set_alignment(CellAlignment::Left),
+            ]);
+    } else {
+        table
+            .load_preset(UTF8_BORDERS_ONLY)
+            .set_content_arrangement(ContentArrangement::Disabled)
+            .set_header(vec![
+                Cell::new("Name"),
+                Cell::new("Scopes"),
+                Cell::new("Expires"),
+                Cell::new("Created"),
+            ]);
+    }
+
+    for key in keys {
+        table.add_row(vec![
+            key.name.clone(),
+            key.scopes.join(", "),
+            key.expires_at
+                .map(|expires_at| expires_at.format("%Y-%m-%dT%H:%M:%SZ").to_string())
+                .unwrap_or_else(|| "never".to_string()),
+            key.created_at.format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+        ]);
+    }
+
+    table.to_string()
+}
@@ -19,6 +19,10 @@ pub struct ProvisionResourceRequest {
 
     /// Arbitrary extra data to include in this resource
     pub custom: Value,
+
+    /// Optional name to tell apart multiple instances of the same resource type
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
 }
 
 impl ProvisionResourceRequest {
@@ -28,8 +32,26 @@ impl ProvisionResourceRequest {
             r#type,
             config,
             custom,
+            name: None,
         }
     }
+
+    /// Set the name of this resource instance
+    pub fn with_name(mut self, name: Option<String>) -> Self {
+        self.name = name;
+
+        self
+    }
+}
+
+/// Check that a resource name is short, lowercase and alphanumeric,
+/// since it ends up in database, role and container names
+pub fn is_valid_resource_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 32
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit())
 }
 
 /// Helper for deserializing
@@ -79,6 +101,10 @@ pub struct Response {
 
     /// The data associated with this resource. Use the `r#type` to know how to parse this data.
     pub data: Value,
+
+    /// The name of this resource instance, if it is not the default one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
 }
 
 impl Response {
@@ -222,6 +248,7 @@ mod test {
             Type::Database(database::Type::AwsRds(database::AwsRdsEngine::MariaDB)),
             Type::Database(database::Type::Shared(database::SharedEngine::Postgres)),
             Type::Database(database::Type::Shared(database::SharedEngine::MongoDb)),
+            Type::Database(database::Type::Shared(database::SharedEngine::Redis)),
             Type::Secrets,
             Type::Persist,
             Type::Container,
@@ -232,4 +259,16 @@ mod test {
             assert_eq!(input, actual, ":{} should map back to itself", input);
         }
     }
+
+    #[test]
+    fn valid_resource_names() {
+        assert!(is_valid_resource_name("analytics"));
+        assert!(is_valid_resource_name("db2"));
+
+        assert!(!is_valid_resource_name(""));
+        assert!(!is_valid_resource_name("Analytics"));
+        assert!(!is_valid_resource_name("app-db"));
+        assert!(!is_valid_resource_name("app_db"));
+        assert!(!is_valid_resource_name(&"a".repeat(33)));
+    }
 }
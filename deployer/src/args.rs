@@ -52,6 +52,10 @@ pub struct Args {
     #[clap(long, default_value = "/tmp")]
     pub artifacts_path: PathBuf,
 
+    /// Maximum size of the build cache shared by all builds, in megabytes
+    #[clap(long, default_value = "10000")]
+    pub build_cache_size: u64,
+
     /// Add an auth layer to deployer for local development
     #[arg(long)]
     pub local: bool,
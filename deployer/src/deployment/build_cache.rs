@@ -0,0 +1,388 @@
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use ring::digest::{Context, SHA256};
+use tracing::{debug, instrument, trace, warn};
+use uuid::Uuid;
+
+/// Marks which cache key a target directory was built with
+const KEY_FILE: &str = ".shuttle-cache-key";
+
+/// Touched every time a cache entry is used, so that the least recently used entries are evicted first
+const LAST_USED_FILE: &str = ".last-used";
+
+/// A content-addressed cache of compiled dependencies, shared by all the builds of the deployer.
+///
+/// Entries are copies of a target directory, keyed by the hash of the `Cargo.lock` and the Rust toolchain that
+/// built it. A project whose target directory is missing or was built for other dependencies starts from a cached
+/// copy, so that cleaning a project or changing its dependencies back does not recompile everything.
+#[derive(Clone)]
+pub struct BuildCache {
+    path: PathBuf,
+    max_size: u64,
+}
+
+/// What [`BuildCache::restore`] found for a project
+#[derive(Debug, PartialEq, Eq)]
+pub enum CacheStatus {
+    /// The target directory was already built with the same dependencies
+    Fresh,
+    /// The target directory was restored from the cache
+    Restored,
+    /// Nothing is cached for these dependencies yet
+    Miss,
+}
+
+/// Total size of the cache on disk
+#[derive(Debug, PartialEq, Eq)]
+pub struct CacheUsage {
+    pub entries: usize,
+    pub size: u64,
+}
+
+impl BuildCache {
+    pub fn new(path: PathBuf, max_size: u64) -> Self {
+        Self { path, max_size }
+    }
+
+    /// The cache key of a project, or `None` if it has no `Cargo.lock` to key on
+    #[instrument(skip_all)]
+    pub async fn key(project_path: &Path) -> io::Result<Option<String>> {
+        let lockfile = match tokio::fs::read(project_path.join("Cargo.lock")).await {
+            Ok(lockfile) => lockfile,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(error),
+        };
+        // Ran in the project so that a rust-toolchain file in it is respected
+        let toolchain = tokio::process::Command::new("rustc")
+            .arg("-vV")
+            .current_dir(project_path)
+            .output()
+            .await?
+            .stdout;
+
+        let mut context = Context::new(&SHA256);
+        context.update(&lockfile);
+        context.update(&toolchain);
+        let key = context
+            .finish()
+            .as_ref()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+
+        Ok(Some(key))
+    }
+
+    /// Get the target directory of a project ready for a build with the dependencies of `key`
+    #[instrument(skip(self, project_path))]
+    pub async fn restore(&self, key: &str, project_path: &Path) -> io::Result<CacheStatus> {
+        let target = project_path.join("target");
+        let entry = self.path.join(key);
+        let key = key.to_owned();
+
+        tokio::task::spawn_blocking(move || {
+            if fs::read_to_string(target.join(KEY_FILE)).is_ok_and(|built| built == key) {
+                return Ok(CacheStatus::Fresh);
+            }
+            if !entry.is_dir() {
+                return Ok(CacheStatus::Miss);
+            }
+
+            trace!("restoring target directory from the cache");
+            if target.exists() {
+                fs::remove_dir_all(&target)?;
+            }
+            copy_dir(&entry, &target)?;
+            let _ = fs::remove_file(target.join(LAST_USED_FILE));
+            touch(&entry.join(LAST_USED_FILE))?;
+
+            Ok(CacheStatus::Restored)
+        })
+        .await?
+    }
+
+    /// Record that a project was built with the dependencies of `key`, and cache its target directory if
+    /// nothing is cached for them yet
+    #[instrument(skip(self, project_path))]
+    pub async fn store(&self, key: &str, project_path: &Path) -> io::Result<()> {
+        let target = project_path.join("target");
+        let entry = self.path.join(key);
+        let key = key.to_owned();
+        let cache = self.clone();
+
+        tokio::task::spawn_blocking(move || {
+            fs::write(target.join(KEY_FILE), key)?;
+            if entry.is_dir() {
+                touch(&entry.join(LAST_USED_FILE))?;
+                return Ok(());
+            }
+
+            debug!("adding target directory to the build cache");
+            // Copy to a temporary directory first so that a failed copy never leaves a partial entry behind. Every
+            // build gets its own, since builds with the same dependencies can be stored at the same time.
+            let partial = entry.with_extension(format!("{}.partial", Uuid::new_v4().simple()));
+            let stored = copy_dir(&target, &partial)
+                .and_then(|()| touch(&partial.join(LAST_USED_FILE)))
+                .and_then(|()| fs::rename(&partial, &entry));
+            if let Err(error) = stored {
+                let _ = fs::remove_dir_all(&partial);
+                // Another build with the same dependencies added the entry first
+                if entry.is_dir() {
+                    return Ok(());
+                }
+                return Err(error);
+            }
+
+            cache.evict_to_max_size()
+        })
+        .await?
+    }
+
+    /// Remove the cache entry of `key`. Returns whether there was one.
+    #[instrument(skip(self))]
+    pub async fn evict(&self, key: &str) -> io::Result<bool> {
+        let entry = self.path.join(key);
+        if !entry.is_dir() {
+            return Ok(false);
+        }
+        tokio::fs::remove_dir_all(entry).await?;
+
+        Ok(true)
+    }
+
+    pub async fn usage(&self) -> io::Result<CacheUsage> {
+        let cache = self.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let entries = cache.entries()?;
+            Ok(CacheUsage {
+                entries: entries.len(),
+                size: entries.iter().map(|entry| entry.size).sum(),
+            })
+        })
+        .await?
+    }
+
+    fn entries(&self) -> io::Result<Vec<Entry>> {
+        let dir = match fs::read_dir(&self.path) {
+            Ok(dir) => dir,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(error) => return Err(error),
+        };
+
+        let mut entries = Vec::new();
+        for entry in dir {
+            let path = entry?.path();
+            // Skip copies that are still in progress
+            if !path.is_dir() || path.extension().is_some() {
+                continue;
+            }
+            let last_used = fs::metadata(path.join(LAST_USED_FILE))
+                .and_then(|metadata| metadata.modified())
+                .unwrap_or(SystemTime::UNIX_EPOCH);
+            let size = dir_size(&path)?;
+            entries.push(Entry {
+                path,
+                last_used,
+                size,
+            });
+        }
+
+        Ok(entries)
+    }
+
+    fn evict_to_max_size(&self) -> io::Result<()> {
+        let mut entries = self.entries()?;
+        let mut size: u64 = entries.iter().map(|entry| entry.size).sum();
+
+        // Most recently used last
+        entries.sort_by_key(|entry| entry.last_used);
+        for entry in entries {
+            if size <= self.max_size {
+                break;
+            }
+            debug!(path = %entry.path.display(), "evicting least recently used build cache entry");
+            if let Err(error) = fs::remove_dir_all(&entry.path) {
+                warn!(
+                    error = &error as &dyn std::error::Error,
+                    "failed to evict build cache entry"
+                );
+                continue;
+            }
+            size -= entry.size;
+        }
+
+        Ok(())
+    }
+}
+
+struct Entry {
+    path: PathBuf,
+    last_used: SystemTime,
+    size: u64,
+}
+
+/// Copy a directory recursively, keeping the modification times that cargo uses to tell whether outputs are fresh
+fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let destination = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &destination)?;
+        } else {
+            fs::copy(entry.path(), &destination)?;
+            let modified = entry.metadata()?.modified()?;
+            File::options()
+                .write(true)
+                .open(&destination)?
+                .set_modified(modified)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn touch(path: &Path) -> io::Result<()> {
+    File::create(path)?.set_modified(SystemTime::now())
+}
+
+fn dir_size(path: &Path) -> io::Result<u64> {
+    let mut size = 0;
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        size += if metadata.is_dir() {
+            dir_size(&entry.path())?
+        } else {
+            metadata.len()
+        };
+    }
+
+    Ok(size)
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::Builder;
+
+    use super::*;
+
+    fn build(project_path: &Path, artifact: &str) {
+        fs::create_dir_all(project_path.join("target/release")).unwrap();
+        fs::write(project_path.join("target/release/artifact"), artifact).unwrap();
+    }
+
+    #[tokio::test]
+    async fn restores_target_directories() {
+        let root = Builder::new().prefix("build-cache").tempdir().unwrap();
+        let cache = BuildCache::new(root.path().join("cache"), u64::MAX);
+        let project = root.path().join("project");
+
+        assert_eq!(
+            cache.restore("a", &project).await.unwrap(),
+            CacheStatus::Miss
+        );
+        build(&project, "built with a");
+        cache.store("a", &project).await.unwrap();
+        assert_eq!(
+            cache.restore("a", &project).await.unwrap(),
+            CacheStatus::Fresh
+        );
+
+        // Changing the dependencies misses, and changing them back restores the first build
+        assert_eq!(
+            cache.restore("b", &project).await.unwrap(),
+            CacheStatus::Miss
+        );
+        build(&project, "built with b");
+        cache.store("b", &project).await.unwrap();
+        assert_eq!(
+            cache.restore("a", &project).await.unwrap(),
+            CacheStatus::Restored
+        );
+        assert_eq!(
+            fs::read_to_string(project.join("target/release/artifact")).unwrap(),
+            "built with a"
+        );
+
+        // A cleaned project is restored too
+        fs::remove_dir_all(project.join("target")).unwrap();
+        assert_eq!(
+            cache.restore("b", &project).await.unwrap(),
+            CacheStatus::Restored
+        );
+
+        assert_eq!(cache.usage().await.unwrap().entries, 2);
+        assert!(cache.evict("a").await.unwrap());
+        assert!(!cache.evict("a").await.unwrap());
+        assert_eq!(cache.usage().await.unwrap().entries, 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn stores_the_same_key_concurrently() {
+        let root = Builder::new().prefix("build-cache").tempdir().unwrap();
+        let cache = BuildCache::new(root.path().join("cache"), u64::MAX);
+        let first = root.path().join("first");
+        let second = root.path().join("second");
+
+        for project in [&first, &second] {
+            build(project, "built with a");
+            for dependency in 0..200 {
+                let deps = project.join("target/release/deps");
+                fs::create_dir_all(&deps).unwrap();
+                fs::write(deps.join(format!("dependency-{dependency}")), "compiled").unwrap();
+            }
+        }
+
+        let (first_stored, second_stored) =
+            tokio::join!(cache.store("a", &first), cache.store("a", &second));
+        first_stored.unwrap();
+        second_stored.unwrap();
+
+        // One entry, and no copies left behind
+        assert_eq!(cache.usage().await.unwrap().entries, 1);
+        assert_eq!(fs::read_dir(root.path().join("cache")).unwrap().count(), 1);
+        fs::remove_dir_all(first.join("target")).unwrap();
+        assert_eq!(
+            cache.restore("a", &first).await.unwrap(),
+            CacheStatus::Restored
+        );
+        assert_eq!(
+            fs::read_dir(first.join("target/release/deps"))
+                .unwrap()
+                .count(),
+            200
+        );
+    }
+
+    #[tokio::test]
+    async fn evicts_least_recently_used() {
+        let root = Builder::new().prefix("build-cache").tempdir().unwrap();
+        // Only fits one entry
+        let cache = BuildCache::new(root.path().join("cache"), 20);
+        let project = root.path().join("project");
+
+        build(&project, "built with a");
+        cache.store("a", &project).await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        build(&project, "built with b");
+        cache.store("b", &project).await.unwrap();
+
+        assert_eq!(
+            cache.usage().await.unwrap(),
+            CacheUsage {
+                entries: 1,
+                // The artifact and the cache key
+                size: "built with b".len() as u64 + 1,
+            }
+        );
+        assert_eq!(
+            cache.restore("a", &project).await.unwrap(),
+            CacheStatus::Miss
+        );
+    }
+}
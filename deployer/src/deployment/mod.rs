@@ -1,4 +1,5 @@
 use std::{
+    collections::HashMap,
     path::{Path, PathBuf},
     sync::Arc,
 };
@@ -6,23 +7,29 @@ use std::{
 use shuttle_common::log::LogRecorder;
 use shuttle_proto::{logger, provisioner};
 use tokio::{
-    sync::{mpsc, Mutex},
+    sync::{mpsc, Mutex, OwnedMutexGuard},
     task::JoinSet,
 };
 use tracing::{instrument, Span};
 use tracing_opentelemetry::OpenTelemetrySpanExt;
+use ulid::Ulid;
 use uuid::Uuid;
 
+mod build_cache;
 pub mod gateway_client;
+mod proxy;
 mod queue;
 mod run;
 pub mod state_change_layer;
 
+use self::build_cache::BuildCache;
 use self::gateway_client::BuildQueueClient;
 use crate::{
     persistence::{resource::ResourceManager, State},
     RuntimeManager,
 };
+pub use build_cache::CacheUsage;
+pub use proxy::ServiceProxy;
 pub use queue::Queued;
 pub use run::{ActiveDeploymentsGetter, Built};
 
@@ -34,10 +41,12 @@ pub struct DeploymentManagerBuilder<LR, ADG, RM, QC> {
     logs_fetcher: Option<logger::Client>,
     active_deployment_getter: Option<ADG>,
     artifacts_path: Option<PathBuf>,
+    build_cache_size: Option<u64>,
     runtime_manager: Option<Arc<Mutex<RuntimeManager>>>,
     resource_manager: Option<RM>,
     queue_client: Option<QC>,
     provisioner_client: Option<provisioner::Client>,
+    service_proxy: Option<ServiceProxy>,
 }
 
 impl<LR, ADG, RM, QC> DeploymentManagerBuilder<LR, ADG, RM, QC>
@@ -77,6 +86,13 @@ where
         self
     }
 
+    /// Maximum size of the build cache in bytes
+    pub fn build_cache_size(mut self, build_cache_size: u64) -> Self {
+        self.build_cache_size = Some(build_cache_size);
+
+        self
+    }
+
     pub fn queue_client(mut self, queue_client: QC) -> Self {
         self.queue_client = Some(queue_client);
 
@@ -95,6 +111,13 @@ where
         self
     }
 
+    /// Proxy which gets switched over to each deployment once it is healthy
+    pub fn service_proxy(mut self, service_proxy: ServiceProxy) -> Self {
+        self.service_proxy = Some(service_proxy);
+
+        self
+    }
+
     /// Creates two Tokio tasks, one for building queued services, the other for
     /// executing/deploying built services. Two multi-producer, single consumer
     /// channels are also created which are for moving on-going service
@@ -107,6 +130,7 @@ where
             .active_deployment_getter
             .expect("an active deployment getter to be set");
         let artifacts_path = self.artifacts_path.expect("artifacts path to be set");
+        let build_cache_size = self.build_cache_size.expect("a build cache size to be set");
         let queue_client = self.queue_client.expect("a queue client to be set");
         let runtime_manager = self.runtime_manager.expect("a runtime manager to be set");
         let resource_manager = self.resource_manager.expect("a resource manager to be set");
@@ -114,11 +138,14 @@ where
         let provisioner_client = self
             .provisioner_client
             .expect("a provisioner client to be set");
+        let service_proxy = self.service_proxy.expect("a service proxy to be set");
 
         let (queue_send, queue_recv) = mpsc::channel(QUEUE_BUFFER_SIZE);
         let (run_send, run_recv) = mpsc::channel(RUN_BUFFER_SIZE);
 
         let builds_path = artifacts_path.join("shuttle-builds");
+        let build_cache =
+            BuildCache::new(artifacts_path.join("shuttle-build-cache"), build_cache_size);
 
         let run_send_clone = run_send.clone();
         let mut set = JoinSet::new();
@@ -130,6 +157,7 @@ where
             build_log_recorder,
             queue_client,
             builds_path.clone(),
+            build_cache.clone(),
         ));
         // Run queue. Waits for built deployments and runs them.
         set.spawn(run::task(
@@ -139,6 +167,7 @@ where
             resource_manager,
             builds_path.clone(),
             provisioner_client,
+            service_proxy,
         ));
 
         DeploymentManager {
@@ -148,6 +177,8 @@ where
             logs_fetcher,
             _join_set: Arc::new(Mutex::new(set)),
             builds_path,
+            build_cache,
+            secrets_locks: Default::default(),
         }
     }
 }
@@ -160,6 +191,8 @@ pub struct DeploymentManager {
     logs_fetcher: logger::Client,
     _join_set: Arc<Mutex<JoinSet<()>>>,
     builds_path: PathBuf,
+    build_cache: BuildCache,
+    secrets_locks: Arc<std::sync::Mutex<HashMap<Ulid, Arc<Mutex<()>>>>>,
 }
 
 /// ```no-test
@@ -185,10 +218,12 @@ impl DeploymentManager {
             logs_fetcher: None,
             active_deployment_getter: None,
             artifacts_path: None,
+            build_cache_size: None,
             runtime_manager: None,
             resource_manager: None,
             queue_client: None,
             provisioner_client: None,
+            service_proxy: None,
         }
     }
 
@@ -216,6 +251,61 @@ impl DeploymentManager {
         self.builds_path.as_path()
     }
 
+    /// Size of the build cache, after evicting the dependencies of the current `Cargo.lock` of the service if
+    /// `evict` is set. Returns whether anything was evicted.
+    pub async fn build_cache_usage(
+        &self,
+        service_name: &str,
+        evict: bool,
+    ) -> std::io::Result<(CacheUsage, bool)> {
+        let mut evicted = false;
+        if evict {
+            if let Some(key) = BuildCache::key(&self.builds_path.join(service_name)).await? {
+                evicted = self.build_cache.evict(&key).await?;
+            }
+        }
+
+        Ok((self.build_cache.usage().await?, evicted))
+    }
+
+    /// Remove the cached resources of the deployments of a service, so that they are loaded again on the next start
+    pub async fn clear_resources_cache(&self, service_name: &str) -> std::io::Result<()> {
+        let executables_path = self
+            .builds_path
+            .join(service_name)
+            .join(shuttle_common::constants::EXECUTABLE_DIRNAME);
+        let mut entries = match tokio::fs::read_dir(&executables_path).await {
+            Ok(entries) => entries,
+            // Nothing has been built yet
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(error) => return Err(error),
+        };
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if path
+                .extension()
+                .is_some_and(|extension| extension == "resources")
+            {
+                tokio::fs::remove_file(path).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Hold the lock on the secrets of a service, so that concurrent updates don't overwrite each other's changes
+    pub async fn lock_secrets(&self, service_id: &Ulid) -> OwnedMutexGuard<()> {
+        let lock = self
+            .secrets_locks
+            .lock()
+            .expect("secrets locks to not be poisoned")
+            .entry(*service_id)
+            .or_default()
+            .clone();
+
+        lock.lock_owned().await
+    }
+
     pub fn logs_fetcher(&self) -> &logger::Client {
         &self.logs_fetcher
     }
@@ -0,0 +1,210 @@
+use std::{io, net::SocketAddr, sync::Arc, time::Duration};
+
+use tokio::{
+    net::{TcpListener, TcpStream},
+    sync::watch,
+    time::timeout,
+};
+use tracing::{debug, info, trace, warn};
+
+/// How long a connection waits for a deployment to take the traffic when none is running
+const TARGET_WAIT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Forwards the connections on the port the gateway routes to, to the runtime of the active deployment.
+///
+/// The runtimes listen on a port of their own, so that a new deployment can be started and checked while the
+/// current one keeps serving. Switching the target only affects new connections: the ones to the old runtime are
+/// served until it stops.
+///
+/// The port is only open while a healthy deployment takes the traffic, which is how the gateway knows that a project
+/// it woke up is ready.
+#[derive(Clone)]
+pub struct ServiceProxy {
+    target: Arc<watch::Sender<Option<SocketAddr>>>,
+}
+
+impl Default for ServiceProxy {
+    fn default() -> Self {
+        Self {
+            target: Arc::new(watch::channel(None).0),
+        }
+    }
+}
+
+impl ServiceProxy {
+    pub async fn serve(self, address: SocketAddr) -> io::Result<()> {
+        let mut target = self.target.subscribe();
+
+        loop {
+            if target.wait_for(Option::is_some).await.is_err() {
+                return Ok(());
+            }
+            let listener = TcpListener::bind(address).await?;
+            info!(%address, "service proxy listening");
+
+            tokio::select! {
+                result = self.clone().serve_listener(listener) => result?,
+                _ = target.wait_for(Option::is_none) => {
+                    info!(%address, "no deployment takes the traffic, service proxy stopped listening");
+                }
+            }
+        }
+    }
+
+    async fn serve_listener(self, listener: TcpListener) -> io::Result<()> {
+        loop {
+            let (inbound, peer) = listener.accept().await?;
+            let target = self.target.subscribe();
+
+            tokio::spawn(async move {
+                let Some(target) = wait_for_target(target).await else {
+                    debug!(%peer, "no deployment took the traffic in time, dropping connection");
+                    return;
+                };
+                if let Err(error) = forward(inbound, target).await {
+                    trace!(
+                        error = &error as &dyn std::error::Error,
+                        %peer,
+                        %target,
+                        "proxied connection ended"
+                    );
+                }
+            });
+        }
+    }
+
+    /// Send new connections to the runtime at `target`
+    pub fn set_target(&self, target: SocketAddr) {
+        info!(%target, "switching service traffic");
+        self.target.send_replace(Some(target));
+    }
+
+    /// Stop sending new connections to `target`, if it is the runtime getting them
+    pub fn clear_target(&self, target: SocketAddr) {
+        self.target.send_if_modified(|current| {
+            if *current == Some(target) {
+                *current = None;
+                true
+            } else {
+                false
+            }
+        });
+    }
+
+    pub fn target(&self) -> Option<SocketAddr> {
+        *self.target.borrow()
+    }
+}
+
+async fn wait_for_target(mut target: watch::Receiver<Option<SocketAddr>>) -> Option<SocketAddr> {
+    timeout(TARGET_WAIT_TIMEOUT, async move {
+        loop {
+            if let Some(address) = *target.borrow_and_update() {
+                return Some(address);
+            }
+            target.changed().await.ok()?;
+        }
+    })
+    .await
+    .ok()
+    .flatten()
+}
+
+async fn forward(mut inbound: TcpStream, target: SocketAddr) -> io::Result<()> {
+    let mut outbound = match TcpStream::connect(target).await {
+        Ok(outbound) => outbound,
+        Err(error) => {
+            warn!(
+                error = &error as &dyn std::error::Error,
+                %target,
+                "failed to connect to the service"
+            );
+            return Err(error);
+        }
+    };
+    tokio::io::copy_bidirectional(&mut inbound, &mut outbound).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::*;
+
+    /// A service that answers every connection with its name
+    async fn service(name: &'static str) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                stream.write_all(name.as_bytes()).await.unwrap();
+            }
+        });
+
+        address
+    }
+
+    async fn request(proxy: SocketAddr) -> String {
+        let mut stream = TcpStream::connect(proxy).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+
+        response
+    }
+
+    #[tokio::test]
+    async fn switches_between_deployments() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let proxy = ServiceProxy::default();
+        tokio::spawn(proxy.clone().serve_listener(listener));
+
+        let old = service("old").await;
+        let new = service("new").await;
+
+        // Connections wait for a deployment to start
+        let waiting = tokio::spawn(request(address));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        proxy.set_target(old);
+        assert_eq!(waiting.await.unwrap(), "old");
+
+        proxy.set_target(new);
+        assert_eq!(request(address).await, "new");
+
+        // Stopping a deployment that no longer gets the traffic does not affect the new one
+        proxy.clear_target(old);
+        assert_eq!(proxy.target(), Some(new));
+        proxy.clear_target(new);
+        assert_eq!(proxy.target(), None);
+    }
+
+    #[tokio::test]
+    async fn only_listens_while_a_deployment_takes_the_traffic() {
+        let address = TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let proxy = ServiceProxy::default();
+        tokio::spawn(proxy.clone().serve(address));
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(TcpStream::connect(address).await.is_err());
+
+        let service = service("healthy").await;
+        proxy.set_target(service);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(request(address).await, "healthy");
+
+        proxy.clear_target(service);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(TcpStream::connect(address).await.is_err());
+
+        proxy.set_target(service);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(request(address).await, "healthy");
+    }
+}
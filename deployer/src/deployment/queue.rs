@@ -14,7 +14,9 @@ use shuttle_common::{
     log::LogRecorder,
     LogItem,
 };
-use shuttle_service::builder::{build_workspace, BuiltService};
+use shuttle_service::builder::{
+    build_workspace, BuildConfig, BuiltService, TestConfig, TestWaitError,
+};
 use tar::Archive;
 use tokio::{
     fs,
@@ -27,7 +29,9 @@ use tracing_opentelemetry::OpenTelemetrySpanExt;
 use ulid::Ulid;
 use uuid::Uuid;
 
+use super::build_cache::{BuildCache, CacheStatus};
 use super::gateway_client::BuildQueueClient;
+use super::run::stored_config_path;
 use super::{Built, QueueReceiver, RunSender, State};
 use crate::error::{Error, Result, TestError};
 
@@ -37,6 +41,7 @@ pub async fn task(
     log_recorder: impl LogRecorder,
     queue_client: impl BuildQueueClient,
     builds_path: PathBuf,
+    build_cache: BuildCache,
 ) {
     info!("Queue task started");
 
@@ -52,6 +57,7 @@ pub async fn task(
                 let log_recorder = log_recorder.clone();
                 let queue_client = queue_client.clone();
                 let builds_path = builds_path.clone();
+                let build_cache = build_cache.clone();
 
                 tasks.spawn(async move {
                     let parent_cx = global::get_text_map_propagator(|propagator| {
@@ -76,6 +82,7 @@ pub async fn task(
                             .handle(
                                 log_recorder,
                                 builds_path.as_path(),
+                                &build_cache,
                             )
                             .await
                         {
@@ -175,7 +182,12 @@ pub struct Queued {
 
 impl Queued {
     #[instrument(name = "Building project", skip_all, fields(deployment_id = %self.id, state = %State::Building))]
-    async fn handle(self, log_recorder: impl LogRecorder, builds_path: &Path) -> Result<Built> {
+    async fn handle(
+        self,
+        log_recorder: impl LogRecorder,
+        builds_path: &Path,
+        build_cache: &BuildCache,
+    ) -> Result<Built> {
         let project_path = builds_path.join(&self.service_name);
 
         info!("Extracting files");
@@ -196,15 +208,27 @@ impl Queued {
             }
         });
         let project_path = project_path.canonicalize()?;
+        let cache_key = restore_build_cache(build_cache, &project_path, &tx).await;
         // Currently returns the first found shuttle service in a given workspace.
         let built_service = build_deployment(&project_path, tx.clone()).await?;
+        if let Some(key) = cache_key {
+            if let Err(error) = build_cache.store(&key, &project_path).await {
+                warn!(
+                    error = &error as &dyn std::error::Error,
+                    "failed to update the build cache"
+                );
+            }
+        }
 
         // Get the Secrets.toml from the shuttle service in the workspace.
         let secrets = get_secrets(&built_service).await?;
 
         if self.will_run_tests {
             info!("Running tests before starting up");
-            run_pre_deploy_tests(&project_path, tx).await?;
+            let test_config = TestConfig::from_project(&project_path).map_err(TestError::Config)?;
+            let build_config =
+                BuildConfig::from_project(&project_path).map_err(TestError::Config)?;
+            run_pre_deploy_tests(&project_path, &test_config, &build_config, tx).await?;
         }
 
         info!("Moving built executable");
@@ -217,6 +241,11 @@ impl Queued {
             &self.id,
         )
         .await?;
+        store_deployment_config(
+            &project_path,
+            &stored_config_path(&built_service.workspace_path, &self.id),
+        )
+        .await?;
 
         let built = Built {
             id: self.id,
@@ -316,13 +345,65 @@ async fn extract_tar_gz_data(data: impl Read, dest: impl AsRef<Path>) -> Result<
     Ok(())
 }
 
+/// Prepare the target directory from the build cache, and report in the build logs whether it was hit. Returns the
+/// cache key to store the build under.
+///
+/// The build cache only speeds builds up, so it is skipped if anything goes wrong with it.
+async fn restore_build_cache(
+    build_cache: &BuildCache,
+    project_path: &Path,
+    tx: &tokio::sync::mpsc::Sender<String>,
+) -> Option<String> {
+    let key = match BuildCache::key(project_path).await {
+        Ok(Some(key)) => key,
+        Ok(None) => {
+            send_build_status(tx, "No Cargo.lock found, so the build cache is not used").await;
+            return None;
+        }
+        Err(error) => {
+            warn!(
+                error = &error as &dyn std::error::Error,
+                "failed to compute the build cache key"
+            );
+            return None;
+        }
+    };
+
+    let status = match build_cache.restore(&key, project_path).await {
+        Ok(CacheStatus::Fresh) => "Build cache hit: dependencies are already built",
+        Ok(CacheStatus::Restored) => "Build cache hit: restored built dependencies",
+        Ok(CacheStatus::Miss) => "Build cache miss: building dependencies",
+        Err(error) => {
+            warn!(
+                error = &error as &dyn std::error::Error,
+                "failed to restore from the build cache"
+            );
+            return None;
+        }
+    };
+    send_build_status(tx, status).await;
+
+    Some(key)
+}
+
+async fn send_build_status(tx: &tokio::sync::mpsc::Sender<String>, status: &str) {
+    info!("{status}");
+    if let Err(err) = tx.send(status.to_owned()).await {
+        error!(
+            error = &err as &dyn std::error::Error,
+            "failed to send line"
+        );
+    }
+}
+
 #[instrument(skip(project_path, tx))]
 async fn build_deployment(
     project_path: &Path,
     tx: tokio::sync::mpsc::Sender<String>,
 ) -> Result<BuiltService> {
+    let config = BuildConfig::from_project(project_path).map_err(|e| Error::Build(e.into()))?;
     // Build in release mode, except for when testing, such as in CI
-    let runtimes = build_workspace(project_path, cfg!(not(test)), tx, true)
+    let runtimes = build_workspace(project_path, cfg!(not(test)), &config, tx, true)
         .await
         .map_err(|e| Error::Build(e.into()))?;
 
@@ -332,22 +413,29 @@ async fn build_deployment(
 #[instrument(skip(project_path, tx))]
 async fn run_pre_deploy_tests(
     project_path: &Path,
+    config: &TestConfig,
+    build_config: &BuildConfig,
     tx: tokio::sync::mpsc::Sender<String>,
 ) -> std::result::Result<(), TestError> {
     let project_path = project_path.to_owned();
 
     let mut cmd = tokio::process::Command::new("cargo");
     cmd.arg("test")
+        .arg("--jobs=4")
+        .arg("--color=always")
         // We set the tests to build with the release profile since deployments compile
         // with the release profile by default. This means crates don't need to be
         // recompiled in debug mode for the tests, reducing memory usage during deployment.
         // When running unit tests, it can compile in debug mode.
-        .arg(if cfg!(not(test)) { "--release" } else { "" })
-        .arg("--jobs=4")
-        .arg("--color=always")
+        // These go last, since test name filters may end with arguments to the test harness.
+        .args(config.cargo_test_args(build_config, cfg!(not(test))))
         .current_dir(project_path)
         .stdout(Stdio::piped())
-        .stderr(Stdio::piped());
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    if let Some(rustflags) = build_config.rustflags_env() {
+        cmd.env("RUSTFLAGS", rustflags);
+    }
 
     // Spawn the command and make two readers, that read lines from stdout and stderr and send
     // them to the same receiver. This is only needed when the output of both streams are wanted.
@@ -377,7 +465,13 @@ async fn run_pre_deploy_tests(
             });
         }
     });
-    let status = handle.wait().await.map_err(TestError::Run)?;
+    let status = config
+        .wait_for_tests(&mut handle)
+        .await
+        .map_err(|err| match err {
+            TestWaitError::Io(err) => TestError::Run(err),
+            TestWaitError::Timeout(limit) => TestError::Timeout(limit),
+        })?;
 
     if status.success() {
         Ok(())
@@ -406,6 +500,18 @@ async fn copy_executable(
     Ok(())
 }
 
+/// Keep the Shuttle.toml of a deployment with its executable, for when it is run again after newer uploads
+async fn store_deployment_config(project_path: &Path, to_directory: &Path) -> Result<()> {
+    fs::create_dir_all(to_directory).await?;
+
+    let shuttle_toml = project_path.join("Shuttle.toml");
+    if fs::try_exists(&shuttle_toml).await? {
+        fs::copy(shuttle_toml, to_directory.join("Shuttle.toml")).await?;
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use std::{
@@ -415,7 +521,7 @@ mod tests {
         path::Path,
     };
 
-    use shuttle_service::builder::BuiltService;
+    use shuttle_service::builder::{BuiltService, TestConfig};
     use tempfile::Builder;
     use tokio::fs;
     use uuid::Uuid;
@@ -532,14 +638,39 @@ ff0e55bda1ff01000000000000000000e0079c01ff12a55500280000",
 
         let failure_project_path = root.join("tests/resources/tests-fail");
         assert!(matches!(
-            super::run_pre_deploy_tests(&failure_project_path, tx.clone()).await,
+            super::run_pre_deploy_tests(
+                &failure_project_path,
+                &Default::default(),
+                &Default::default(),
+                tx.clone()
+            )
+            .await,
             Err(TestError::Failed)
         ));
 
+        // The failing test is filtered out
+        let config = TestConfig {
+            filters: vec!["does_not_exist".to_owned()],
+            ..Default::default()
+        };
+        super::run_pre_deploy_tests(
+            &failure_project_path,
+            &config,
+            &Default::default(),
+            tx.clone(),
+        )
+        .await
+        .unwrap();
+
         let pass_project_path = root.join("tests/resources/tests-pass");
-        super::run_pre_deploy_tests(&pass_project_path, tx)
-            .await
-            .unwrap();
+        super::run_pre_deploy_tests(
+            &pass_project_path,
+            &Default::default(),
+            &Default::default(),
+            tx,
+        )
+        .await
+        .unwrap();
     }
 
     #[tokio::test]
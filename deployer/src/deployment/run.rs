@@ -3,7 +3,11 @@ use std::{
     future::Future,
     net::{Ipv4Addr, SocketAddr},
     path::{Path, PathBuf},
-    sync::Arc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
 };
 
 use anyhow::{anyhow, bail, Context};
@@ -12,7 +16,7 @@ use opentelemetry::global;
 use serde::de::DeserializeOwned;
 use shuttle_common::{
     claims::Claim,
-    constants::{DEPLOYER_SERVICE_HTTP_PORT, EXECUTABLE_DIRNAME, RESOURCE_SCHEMA_VERSION},
+    constants::{EXECUTABLE_DIRNAME, RESOURCE_SCHEMA_VERSION},
     deployment::{
         DEPLOYER_END_MSG_COMPLETED, DEPLOYER_END_MSG_CRASHED, DEPLOYER_END_MSG_STARTUP_ERR,
         DEPLOYER_END_MSG_STOPPED, DEPLOYER_RUNTIME_START_FAILED, DEPLOYER_RUNTIME_START_RESPONSE,
@@ -27,24 +31,34 @@ use shuttle_proto::{
         self, LoadRequest, StartRequest, StopReason, SubscribeStopRequest, SubscribeStopResponse,
     },
 };
-use shuttle_service::{Environment, ShuttleResourceOutput};
+use shuttle_service::{
+    builder::{HealthCheckConfig, RestartConfig},
+    Environment, ShuttleResourceOutput,
+};
 use tokio::{
-    sync::Mutex,
+    net::TcpStream,
+    sync::{watch, Mutex},
     task::{JoinHandle, JoinSet},
+    time::{sleep, timeout},
 };
 use tonic::{Code, Request};
-use tracing::{debug, debug_span, error, info, instrument, warn, Instrument};
+use tracing::{debug, debug_span, error, info, instrument, trace, warn, Instrument};
 use tracing_opentelemetry::OpenTelemetrySpanExt;
 use ulid::Ulid;
 use uuid::Uuid;
 
-use super::{RunReceiver, State};
+use super::{RunReceiver, ServiceProxy, State};
 use crate::{
     error::{Error, Result},
     persistence::resource::ResourceManager,
     RuntimeManager,
 };
 
+/// How long a service without a health check path gets to open its port before it takes the traffic anyway
+const PORT_OPEN_TIMEOUT: Duration = Duration::from_secs(5);
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_millis(500);
+const HEALTH_CHECK_REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
 /// Run a task which takes runnable deploys from a channel and starts them up on our runtime
 /// A deploy is killed when it receives a signal from the kill channel
 pub async fn task(
@@ -54,6 +68,7 @@ pub async fn task(
     resource_manager: impl ResourceManager,
     builds_path: PathBuf,
     provisioner_client: provisioner::Client,
+    service_proxy: ServiceProxy,
 ) {
     info!("Run task started");
 
@@ -67,41 +82,10 @@ pub async fn task(
                 info!("Built deployment at the front of run queue: {id}");
                 let resource_manager = resource_manager.clone();
                 let builds_path = builds_path.clone();
-
-                let old_deployments_killer = kill_old_deployments(
-                    built.service_id,
-                    id,
-                    active_deployment_getter.clone(),
-                    runtime_manager.clone(),
-                );
-                let runtime_manager_clone = runtime_manager.clone();
-                let cleanup = move |response: Option<SubscribeStopResponse>| {
-                    debug!(response = ?response,  "stop client response: ");
-
-                    if let Some(response) = response {
-                        match StopReason::try_from(response.reason).unwrap_or_default() {
-                            StopReason::Request => stopped_cleanup(&id),
-                            StopReason::End => completed_cleanup(&id),
-                            StopReason::Crash => crashed_cleanup(
-                                &id,
-                                runtime_manager_clone,
-                                Error::Run(anyhow::Error::msg(response.message).into()),
-                            )
-                        }
-                    } else {
-                        crashed_cleanup(
-                            &id,
-                            runtime_manager_clone,
-                            Error::Runtime(anyhow::anyhow!(
-                                "stop subscribe channel stopped unexpectedly"
-                            )),
-                        );
-                    }
-
-                };
-
+                let active_deployment_getter = active_deployment_getter.clone();
                 let runtime_manager = runtime_manager.clone();
                 let provisioner_client = provisioner_client.clone();
+                let service_proxy = service_proxy.clone();
                 set.spawn(async move {
                     let parent_cx = global::get_text_map_propagator(|propagator| {
                         propagator.extract(&built.tracing_context)
@@ -110,22 +94,95 @@ pub async fn task(
                     span.set_parent(parent_cx);
 
                     async move {
-                        match built
-                            .handle(
-                                resource_manager,
-                                runtime_manager,
-                                old_deployments_killer,
-                                cleanup,
-                                builds_path.as_path(),
-                                provisioner_client,
+                        let restart_config = RestartConfig::from_project(&deployment_config_path(
+                            &builds_path.join(&built.service_name),
+                            &id,
+                        ))
+                        .unwrap_or_else(|error| {
+                            warn!(
+                                error = %error,
+                                "failed to read the restart policy, crashed deployments won't be restarted"
+                            );
+                            RestartConfig::default()
+                        });
+                        // Killing a deployment also cancels its restart, even when it is waiting for one
+                        let mut killed = runtime_manager.lock().await.watch_kill(id);
+                        let mut attempt = 0;
+
+                        loop {
+                            let old_deployments_killer = kill_old_deployments(
+                                built.service_id,
+                                id,
+                                active_deployment_getter.clone(),
+                                runtime_manager.clone(),
+                            );
+                            let crashed = Arc::new(AtomicBool::new(false));
+                            let cleanup =
+                                stop_cleanup(id, runtime_manager.clone(), crashed.clone());
+
+                            match built
+                                .clone()
+                                .handle(
+                                    resource_manager.clone(),
+                                    runtime_manager.clone(),
+                                    old_deployments_killer,
+                                    cleanup,
+                                    builds_path.as_path(),
+                                    provisioner_client.clone(),
+                                    service_proxy.clone(),
+                                )
+                                .await
+                            {
+                                Ok(handle) => handle
+                                    .await
+                                    .expect("the call to run in built.handle to be done"),
+                                Err(err) => {
+                                    start_crashed_cleanup(&id, err);
+                                    // A restart that fails to start counts as a crash too, but a deployment that
+                                    // never started has nothing to restart
+                                    crashed.store(attempt > 0, Ordering::SeqCst);
+                                }
+                            };
+
+                            if !crashed.load(Ordering::SeqCst) {
+                                break;
+                            }
+                            attempt += 1;
+                            let Some(backoff) = restart_config.backoff(attempt) else {
+                                break;
+                            };
+                            if !restart_backoff(
+                                &id,
+                                attempt,
+                                restart_config.max_attempts,
+                                backoff,
+                                &mut killed,
                             )
                             .await
-                        {
-                            Ok(handle) => handle
+                            {
+                                break;
+                            }
+
+                            // A deployment made while this one was down takes precedence over restarting it
+                            match active_deployment_getter
+                                .get_active_deployments(&built.service_id)
                                 .await
-                                .expect("the call to run in built.handle to be done"),
-                            Err(err) => start_crashed_cleanup(&id, err),
-                        };
+                            {
+                                Ok(active) if active.iter().all(|active| *active == id) => {}
+                                Ok(_) => {
+                                    superseded_cleanup(&id);
+                                    break;
+                                }
+                                Err(error) => {
+                                    start_crashed_cleanup(
+                                        &id,
+                                        Error::OldCleanup(Box::new(error)),
+                                    );
+                                    break;
+                                }
+                            }
+                        }
+                        runtime_manager.lock().await.unwatch_kill(&id, &killed);
 
                         info!("deployment done");
                     }
@@ -176,6 +233,69 @@ async fn kill_old_deployments(
     Ok(())
 }
 
+/// Cleanup for when a deployment stops, which flags whether it crashed
+fn stop_cleanup(
+    id: Uuid,
+    runtime_manager: Arc<Mutex<RuntimeManager>>,
+    crashed: Arc<AtomicBool>,
+) -> impl FnOnce(Option<SubscribeStopResponse>) + Send + 'static {
+    move |response: Option<SubscribeStopResponse>| {
+        debug!(response = ?response, "stop client response: ");
+
+        if let Some(response) = response {
+            match StopReason::try_from(response.reason).unwrap_or_default() {
+                StopReason::Request => stopped_cleanup(&id),
+                StopReason::End => completed_cleanup(&id),
+                StopReason::Crash => {
+                    crashed.store(true, Ordering::SeqCst);
+                    crashed_cleanup(
+                        &id,
+                        runtime_manager,
+                        Error::Run(anyhow::Error::msg(response.message).into()),
+                    )
+                }
+            }
+        } else {
+            crashed.store(true, Ordering::SeqCst);
+            crashed_cleanup(
+                &id,
+                runtime_manager,
+                Error::Runtime(anyhow::anyhow!(
+                    "stop subscribe channel stopped unexpectedly"
+                )),
+            );
+        }
+    }
+}
+
+/// Wait before restarting a crashed deployment. Returns `false` if the deployment was killed in the meantime.
+#[instrument(name = "Restarting crashed deployment", skip(_id, killed), fields(deployment_id = %_id, state = %State::Restarting))]
+async fn restart_backoff(
+    _id: &Uuid,
+    attempt: u32,
+    max_attempts: u32,
+    backoff: Duration,
+    killed: &mut watch::Receiver<bool>,
+) -> bool {
+    info!(
+        "restarting in {}s (attempt {attempt} of {max_attempts})",
+        backoff.as_secs()
+    );
+
+    tokio::select! {
+        _ = sleep(backoff) => true,
+        _ = killed.wait_for(|killed| *killed) => {
+            info!("the deployment was stopped, so it is not restarted");
+            false
+        }
+    }
+}
+
+#[instrument(name = "Cancelling restart", skip(_id), fields(deployment_id = %_id, state = %State::Stopped))]
+fn superseded_cleanup(_id: &Uuid) {
+    info!("a newer deployment is running, so this one is not restarted");
+}
+
 #[instrument(name = "Cleaning up completed deployment", skip(_id), fields(deployment_id = %_id, state = %State::Completed))]
 fn completed_cleanup(_id: &Uuid) {
     info!("{}", DEPLOYER_END_MSG_COMPLETED);
@@ -223,6 +343,25 @@ pub trait ActiveDeploymentsGetter: Clone + Send + Sync + 'static {
 }
 
 #[derive(Clone, Debug)]
+/// The directory next to the executable of a deployment that has the Shuttle.toml it was built with, so that it
+/// runs with its own settings when it is started again after newer deployments
+pub(crate) fn stored_config_path(project_path: &Path, id: &Uuid) -> PathBuf {
+    project_path
+        .join(EXECUTABLE_DIRNAME)
+        .join(format!("{id}.config"))
+}
+
+/// Where to read the `[deploy]` settings of a deployment from. Deployments built before their Shuttle.toml was
+/// stored use the one of the latest upload.
+fn deployment_config_path(project_path: &Path, id: &Uuid) -> PathBuf {
+    let stored = stored_config_path(project_path, id);
+    if stored.is_dir() {
+        stored
+    } else {
+        project_path.to_path_buf()
+    }
+}
+
 pub struct Built {
     pub id: Uuid, // Deployment id
     pub service_name: String,
@@ -241,10 +380,11 @@ impl Built {
         self,
         mut resource_manager: impl ResourceManager,
         runtime_manager: Arc<Mutex<RuntimeManager>>,
-        kill_old_deployments: impl Future<Output = Result<()>>,
+        kill_old_deployments: impl Future<Output = Result<()>> + Send + 'static,
         cleanup: impl FnOnce(Option<SubscribeStopResponse>) + Send + 'static,
         builds_path: &Path,
         provisioner_client: provisioner::Client,
+        service_proxy: ServiceProxy,
     ) -> Result<JoinHandle<()>> {
         let project_path = builds_path.join(&self.service_name);
         // This is the path to the users project with an embedded runtime.
@@ -255,8 +395,14 @@ impl Built {
             .join(EXECUTABLE_DIRNAME)
             .join(format!("{}.resources", self.id));
 
-        // Let the runtime expose its HTTP port
-        let address = SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), DEPLOYER_SERVICE_HTTP_PORT);
+        // Let the runtime expose its HTTP port on a staging port, so that the running deployment keeps the traffic
+        // until this one is healthy
+        let port = portpicker::pick_unused_port()
+            .ok_or_else(|| Error::Start("failed to find a port for the service".to_string()))?;
+        let address = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), port);
+        let health_check =
+            HealthCheckConfig::from_project(&deployment_config_path(&project_path, &self.id))
+                .map_err(|err| Error::Load(format!("{err:#}")))?;
 
         let runtime_client = runtime_manager
             .lock()
@@ -352,13 +498,14 @@ impl Built {
             resources
         };
 
-        kill_old_deployments.await?;
-
         let handler = tokio::spawn(run(
             self.id,
             self.service_name,
             runtime_client,
             address,
+            health_check,
+            service_proxy,
+            kill_old_deployments,
             cleanup,
             resources,
         ));
@@ -418,15 +565,20 @@ fn log(ty: &resource::Type, msg: &str) {
     info!("[Resource][{}] {}", ty, msg);
 }
 
-/// If an old resource with matching type + config and valid data exists, return it
+/// If an old resource with matching type + name + config and valid data exists, return it
 fn get_cached_output<T: DeserializeOwned>(
     shuttle_resource_type: &Type,
+    name: &Option<String>,
     config: &serde_json::Value,
     prev_resources: &[resource::Response],
 ) -> Option<T> {
     prev_resources
         .iter()
-        .find(|resource| resource.r#type == *shuttle_resource_type && resource.config == *config)
+        .find(|resource| {
+            resource.r#type == *shuttle_resource_type
+                && resource.name == *name
+                && resource.config == *config
+        })
         .and_then(|resource| {
             let cached_output = resource.data.clone();
             log(shuttle_resource_type, "Found cached output");
@@ -504,7 +656,7 @@ async fn provision(
                 // through the provisioner, which is something we don't support currently. If there will be
                 // config fields that are relevant for provisioner updates on top of resources, they should
                 // be cached.
-                let output = get_cached_output(&shuttle_resource.r#type, &serde_json::Value::Null, prev_resources.as_slice());
+                let output = get_cached_output(&shuttle_resource.r#type, &shuttle_resource.name, &serde_json::Value::Null, prev_resources.as_slice());
                 let output = match output {
                     Some(o) => o,
                     None => {
@@ -514,6 +666,7 @@ async fn provision(
                             project_name: project_name.to_string(),
                             db_type: Some(db_type.into()),
                             db_name: config.db_name,
+                            resource_name: shuttle_resource.name.clone(),
                             // other relevant config fields would go here
                         });
                         req.extensions_mut().insert(claim.clone());
@@ -532,6 +685,7 @@ async fn provision(
                     // For now, this is "null" for all database types
                     config: serde_json::to_vec(&serde_json::Value::Null).expect("to serialize struct"),
                     data: serde_json::to_vec(&output).expect("to serialize struct"),
+                    name: shuttle_resource.name.clone().unwrap_or_default(),
                 });
                 *bytes = serde_json::to_vec(&ShuttleResourceOutput {
                     output,
@@ -546,6 +700,7 @@ async fn provision(
                     r#type: shuttle_resource.r#type.to_string(),
                     config: serde_json::to_vec(&serde_json::Value::Null).expect("to serialize struct"),
                     data: serde_json::to_vec(&new_secrets).expect("to serialize struct"),
+                    name: String::new(),
                 });
                 *bytes = serde_json::to_vec(&ShuttleResourceOutput {
                     output: new_secrets.clone(),
@@ -560,10 +715,16 @@ async fn provision(
                     r#type: shuttle_resource.r#type.to_string(),
                     config: serde_json::to_vec(&serde_json::Value::Null).expect("to serialize struct"),
                     data: serde_json::to_vec(&serde_json::Value::Null).expect("to serialize struct"),
+                    name: String::new(),
                 });
             }
             resource::Type::Container => {
-                bail!("Containers can't be requested during deployment");
+                // Containers are started by the local provisioner in `cargo shuttle run` only
+                bail!(
+                    "Containers can't be requested during deployment. \
+                    The `Container` resource is only available in local runs, \
+                    so use a hosted alternative for this resource when deploying."
+                );
             }
         }
     }
@@ -580,12 +741,16 @@ async fn provision(
     Ok(resources)
 }
 
-#[instrument(name = "Starting service", skip(runtime_client, cleanup, resources), fields(deployment_id = %id, state = %State::Running))]
+#[instrument(name = "Starting service", skip(runtime_client, health_check, service_proxy, kill_old_deployments, cleanup, resources), fields(deployment_id = %id, state = %State::Running))]
+#[allow(clippy::too_many_arguments)]
 async fn run(
     id: Uuid,
     service_name: String,
     mut runtime_client: runtime::Client,
     address: SocketAddr,
+    health_check: HealthCheckConfig,
+    service_proxy: ServiceProxy,
+    kill_old_deployments: impl Future<Output = Result<()>>,
     cleanup: impl FnOnce(Option<SubscribeStopResponse>) + Send + 'static,
     resources: Vec<Vec<u8>>,
 ) {
@@ -620,8 +785,38 @@ async fn run(
                 info!("{}", DEPLOYER_RUNTIME_START_FAILED);
             }
 
+            // The running deployment keeps the traffic until this one is healthy, or stops before that
+            let stopped = tokio::select! {
+                stopped = stream.message() => Some(stopped),
+                healthy = wait_until_healthy(address, &health_check) => match healthy {
+                    Ok(()) => None,
+                    Err(message) => Some(Ok(Some(SubscribeStopResponse {
+                        reason: StopReason::Crash as i32,
+                        message,
+                    }))),
+                },
+            };
+
             // Wait for stop reason
-            match stream.message().await {
+            let stopped = match stopped {
+                Some(stopped) => stopped,
+                None => {
+                    info!("service is healthy, switching traffic over to it");
+                    service_proxy.set_target(address);
+                    if let Err(error) = kill_old_deployments.await {
+                        warn!(
+                            error = &error as &dyn std::error::Error,
+                            "failed to stop the old deployments"
+                        );
+                    }
+
+                    let stopped = stream.message().await;
+                    service_proxy.clear_target(address);
+                    stopped
+                }
+            };
+
+            match stopped {
                 Ok(reason) => cleanup(reason),
                 // Stream closed abruptly, most probably runtime crashed.
                 Err(err) => cleanup(Some(SubscribeStopResponse {
@@ -647,3 +842,51 @@ async fn run(
         }
     }
 }
+
+/// Wait for a started service to be ready to take the traffic: until its health check path responds without a server
+/// error, or until its port is open when it has no health check path
+async fn wait_until_healthy(
+    address: SocketAddr,
+    health_check: &HealthCheckConfig,
+) -> std::result::Result<(), String> {
+    let Some(path) = &health_check.path else {
+        // Services that don't serve on their port, like bots, are healthy once they are started
+        if timeout(PORT_OPEN_TIMEOUT, wait_for_port(address))
+            .await
+            .is_err()
+        {
+            debug!("service port did not open, switching traffic over anyway");
+        }
+        return Ok(());
+    };
+
+    let uri: hyper::Uri = format!("http://{address}{path}")
+        .parse()
+        .map_err(|_| format!("invalid health check path '{path}'"))?;
+    let client = hyper::Client::new();
+
+    timeout(health_check.timeout(), async {
+        loop {
+            match timeout(HEALTH_CHECK_REQUEST_TIMEOUT, client.get(uri.clone())).await {
+                Ok(Ok(response)) if !response.status().is_server_error() => return,
+                Ok(Ok(response)) => debug!(status = %response.status(), "service is not healthy yet"),
+                Ok(Err(error)) => trace!(error = %error, "service is not reachable yet"),
+                Err(_) => trace!("health check request timed out"),
+            }
+            sleep(HEALTH_CHECK_INTERVAL).await;
+        }
+    })
+    .await
+    .map_err(|_| {
+        format!(
+            "service did not respond healthy at '{path}' within {}s, the previous deployment keeps running",
+            health_check.timeout
+        )
+    })
+}
+
+async fn wait_for_port(address: SocketAddr) {
+    while TcpStream::connect(address).await.is_err() {
+        sleep(HEALTH_CHECK_INTERVAL).await;
+    }
+}
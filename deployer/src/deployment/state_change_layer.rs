@@ -128,6 +128,7 @@ impl Visit for NewStateVisitor {
 #[cfg(test)]
 mod tests {
     use std::{
+        collections::BTreeMap,
         fs::read_dir,
         path::PathBuf,
         sync::{Arc, Mutex},
@@ -135,13 +136,18 @@ mod tests {
     };
 
     use async_trait::async_trait;
-    use axum::body::Bytes;
+    use axum::{body::Bytes, Extension, Json};
+    use chrono::Utc;
     use ctor::ctor;
     use flate2::{write::GzEncoder, Compression};
+    use shuttle_backends::axum::CustomErrorPath;
     use shuttle_common::claims::Claim;
+    use shuttle_common::constants::EXECUTABLE_DIRNAME;
     use shuttle_common::log::LogRecorder;
+    use shuttle_common::models::secret::SecretsUpdateRequest;
     use shuttle_common_tests::{
         logger::get_mocked_logger_client, provisioner::get_mocked_provisioner_client,
+        resource_recorder::get_mocked_resource_recorder_client,
     };
     use shuttle_proto::{
         logger::{
@@ -152,7 +158,11 @@ mod tests {
             provisioner_server::Provisioner, DatabaseDeletionResponse, DatabaseRequest,
             DatabaseResponse, Ping, Pong,
         },
-        resource_recorder::{ResourceResponse, ResourcesResponse, ResultResponse},
+        resource_recorder::{
+            resource_recorder_server::ResourceRecorder, ProjectResourcesRequest, RecordRequest,
+            Resource, ResourceIds, ResourceResponse, ResourcesResponse, ResultResponse,
+            ServiceResourcesRequest,
+        },
     };
     use tokio::{select, sync::mpsc, time::sleep};
     use tokio_stream::wrappers::ReceiverStream;
@@ -164,9 +174,13 @@ mod tests {
     use crate::{
         deployment::{
             gateway_client::BuildQueueClient, ActiveDeploymentsGetter, Built, DeploymentManager,
-            Queued,
+            Queued, ServiceProxy,
+        },
+        handlers::{get_secrets, rollback_deployment, update_secrets, Error},
+        persistence::{
+            resource::ResourceManager, Deployment, DeploymentState, Persistence, State,
+            StateRecorder,
         },
-        persistence::{resource::ResourceManager, DeploymentState, State, StateRecorder},
         RuntimeManager,
     };
 
@@ -401,6 +415,7 @@ mod tests {
             &mut self,
             _service_id: &ulid::Ulid,
             _type: shuttle_common::resource::Type,
+            _name: Option<String>,
             _claim: Claim,
         ) -> Result<ResourceResponse, Self::Err> {
             Ok(ResourceResponse {
@@ -415,6 +430,7 @@ mod tests {
             _project_name: String,
             _service_id: &ulid::Ulid,
             _type: shuttle_common::resource::Type,
+            _name: Option<String>,
             _claim: Claim,
         ) -> Result<ResultResponse, Self::Err> {
             Ok(ResultResponse {
@@ -424,6 +440,97 @@ mod tests {
         }
     }
 
+    /// Keeps the last recorded version of every resource, like the real resource recorder
+    #[derive(Default)]
+    struct ResourceRecorderMock {
+        resources: Mutex<Vec<Resource>>,
+    }
+
+    #[async_trait]
+    impl ResourceRecorder for ResourceRecorderMock {
+        async fn record_resources(
+            &self,
+            request: Request<RecordRequest>,
+        ) -> Result<Response<ResultResponse>, Status> {
+            let RecordRequest {
+                project_id,
+                service_id,
+                resources,
+            } = request.into_inner();
+
+            let mut stored = self.resources.lock().unwrap();
+            for resource in resources {
+                stored.retain(|r| {
+                    !(r.service_id == service_id
+                        && r.r#type == resource.r#type
+                        && r.name == resource.name)
+                });
+                stored.push(Resource {
+                    project_id: project_id.clone(),
+                    service_id: service_id.clone(),
+                    r#type: resource.r#type,
+                    config: resource.config,
+                    data: resource.data,
+                    is_active: true,
+                    created_at: None,
+                    last_updated: None,
+                    name: resource.name,
+                });
+            }
+
+            Ok(Response::new(ResultResponse {
+                success: true,
+                message: Default::default(),
+            }))
+        }
+
+        async fn get_project_resources(
+            &self,
+            _request: Request<ProjectResourcesRequest>,
+        ) -> Result<Response<ResourcesResponse>, Status> {
+            panic!("no secrets tests should get the project resources");
+        }
+
+        async fn get_service_resources(
+            &self,
+            _request: Request<ServiceResourcesRequest>,
+        ) -> Result<Response<ResourcesResponse>, Status> {
+            panic!("no secrets tests should get the service resources");
+        }
+
+        async fn get_resource(
+            &self,
+            request: Request<ResourceIds>,
+        ) -> Result<Response<ResourceResponse>, Status> {
+            let ResourceIds {
+                service_id,
+                r#type,
+                name,
+                ..
+            } = request.into_inner();
+            let resource = self
+                .resources
+                .lock()
+                .unwrap()
+                .iter()
+                .find(|r| r.service_id == service_id && r.r#type == r#type && r.name == name)
+                .cloned();
+
+            Ok(Response::new(ResourceResponse {
+                success: resource.is_some(),
+                message: Default::default(),
+                resource,
+            }))
+        }
+
+        async fn delete_resource(
+            &self,
+            _request: Request<ResourceIds>,
+        ) -> Result<Response<ResultResponse>, Status> {
+            panic!("no secrets tests should delete a resource");
+        }
+    }
+
     async fn test_states(id: &Uuid, expected_states: Vec<MockStateLog>) {
         loop {
             let states = RECORDER.get_deployment_states(id);
@@ -668,6 +775,500 @@ mod tests {
         }
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn deployment_restarts_after_crashing() {
+        let deployment_manager = get_deployment_manager().await;
+
+        let queued = get_queue("restart-crash");
+        let id = queued.id;
+        deployment_manager.queue_push(queued).await;
+
+        let expected_states = vec![
+            MockStateLog {
+                id,
+                state: State::Queued,
+            },
+            MockStateLog {
+                id,
+                state: State::Building,
+            },
+            MockStateLog {
+                id,
+                state: State::Built,
+            },
+            MockStateLog {
+                id,
+                state: State::Loading,
+            },
+            MockStateLog {
+                id,
+                state: State::Running,
+            },
+            MockStateLog {
+                id,
+                state: State::Crashed,
+            },
+            MockStateLog {
+                id,
+                state: State::Restarting,
+            },
+            MockStateLog {
+                id,
+                state: State::Loading,
+            },
+            MockStateLog {
+                id,
+                state: State::Running,
+            },
+            MockStateLog {
+                id,
+                state: State::Crashed,
+            },
+            MockStateLog {
+                id,
+                state: State::Restarting,
+            },
+            MockStateLog {
+                id,
+                state: State::Loading,
+            },
+            MockStateLog {
+                id,
+                state: State::Running,
+            },
+            MockStateLog {
+                id,
+                state: State::Crashed,
+            },
+        ];
+        let test = test_states(&id, expected_states.clone());
+
+        select! {
+            _ = sleep(Duration::from_secs(STATE_TEST_TIMEOUT_SECS)) => {
+                let states = RECORDER.get_deployment_states(&id);
+                panic!("a crashed service should be restarted until it runs out of attempts: {:#?}", states);
+            }
+            _ = test => {}
+        }
+
+        // Both attempts are used up, so it stays down
+        sleep(Duration::from_secs(6)).await;
+        assert_eq!(RECORDER.get_deployment_states(&id), expected_states);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn deployment_killed_while_restarting() {
+        let deployment_manager = get_deployment_manager().await;
+
+        let queued = get_queue("restart-kill");
+        let id = queued.id;
+        deployment_manager.queue_push(queued).await;
+
+        let mut expected_states = vec![
+            MockStateLog {
+                id,
+                state: State::Queued,
+            },
+            MockStateLog {
+                id,
+                state: State::Building,
+            },
+            MockStateLog {
+                id,
+                state: State::Built,
+            },
+            MockStateLog {
+                id,
+                state: State::Loading,
+            },
+            MockStateLog {
+                id,
+                state: State::Running,
+            },
+            MockStateLog {
+                id,
+                state: State::Crashed,
+            },
+            MockStateLog {
+                id,
+                state: State::Restarting,
+            },
+        ];
+        let test = test_states(&id, expected_states.clone());
+
+        select! {
+            _ = sleep(Duration::from_secs(STATE_TEST_TIMEOUT_SECS)) => {
+                let states = RECORDER.get_deployment_states(&id);
+                panic!("a crashed service should wait to be restarted: {:#?}", states);
+            }
+            _ = test => {}
+        }
+
+        // Killing it during the backoff cancels the restart
+        deployment_manager.kill(id).await;
+        expected_states.push(MockStateLog {
+            id,
+            state: State::Stopped,
+        });
+        sleep(Duration::from_secs(7)).await;
+        assert_eq!(RECORDER.get_deployment_states(&id), expected_states);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn deployment_rolled_back_to() {
+        let deployment_manager = get_deployment_manager().await;
+        let (persistence, _) = Persistence::new_in_memory().await;
+
+        let queued = get_queue("sleep-async");
+        let id = queued.id;
+        let service = persistence
+            .get_or_create_service(&queued.service_name)
+            .await
+            .unwrap();
+        deployment_manager.queue_push(queued).await;
+
+        let mut expected_states = vec![
+            MockStateLog {
+                id,
+                state: State::Queued,
+            },
+            MockStateLog {
+                id,
+                state: State::Building,
+            },
+            MockStateLog {
+                id,
+                state: State::Built,
+            },
+            MockStateLog {
+                id,
+                state: State::Loading,
+            },
+            MockStateLog {
+                id,
+                state: State::Running,
+            },
+        ];
+        let test = test_states(&id, expected_states.clone());
+
+        select! {
+            _ = sleep(Duration::from_secs(STATE_TEST_TIMEOUT_SECS)) => {
+                let states = RECORDER.get_deployment_states(&id);
+                panic!("states should go into 'Running' for a valid service: {:#?}", states);
+            },
+            _ = test => {}
+        };
+
+        deployment_manager.kill(id).await;
+        expected_states.extend([
+            MockStateLog {
+                id,
+                state: State::Stopped,
+            },
+            MockStateLog {
+                id,
+                state: State::Stopped,
+            },
+        ]);
+        let test = test_states(&id, expected_states.clone());
+
+        select! {
+            _ = sleep(Duration::from_secs(60)) => {
+                let states = RECORDER.get_deployment_states(&id);
+                panic!("states should go into 'Stopped' for a valid service: {:#?}", states);
+            },
+            _ = test => {}
+        };
+
+        let executables_path = deployment_manager
+            .builds_path()
+            .join(&service.name)
+            .join(EXECUTABLE_DIRNAME);
+        let resources_path = executables_path.join(format!("{id}.resources"));
+        let resources_written = std::fs::metadata(&resources_path)
+            .unwrap()
+            .modified()
+            .unwrap();
+        assert!(executables_path.join(format!("{id}.config")).is_dir());
+
+        persistence
+            .insert_deployment(&Deployment {
+                id,
+                service_id: service.id,
+                state: State::Stopped,
+                last_update: Utc::now(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        let Json(response) = rollback_deployment(
+            Extension(persistence),
+            Extension(deployment_manager.clone()),
+            Extension(Claim::default()),
+            CustomErrorPath((service.name.clone(), id)),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.id, id);
+
+        // Started from the stored executable and resources, without building or loading the resources again
+        expected_states.extend([
+            MockStateLog {
+                id,
+                state: State::Built,
+            },
+            MockStateLog {
+                id,
+                state: State::Loading,
+            },
+            MockStateLog {
+                id,
+                state: State::Running,
+            },
+        ]);
+        let test = test_states(&id, expected_states.clone());
+
+        select! {
+            _ = sleep(Duration::from_secs(60)) => {
+                let states = RECORDER.get_deployment_states(&id);
+                panic!("a rolled back deployment should go into 'Running' again: {:#?}", states);
+            },
+            _ = test => {}
+        };
+        assert_eq!(
+            std::fs::metadata(&resources_path)
+                .unwrap()
+                .modified()
+                .unwrap(),
+            resources_written
+        );
+
+        deployment_manager.kill(id).await;
+    }
+
+    #[tokio::test]
+    async fn rollback_rejects_deployments_it_cannot_run() {
+        let deployment_manager = get_deployment_manager().await;
+        let (persistence, _) = Persistence::new_in_memory().await;
+        let service = persistence
+            .get_or_create_service("deploy-layer-rollback")
+            .await
+            .unwrap();
+
+        let rollback = |id| {
+            rollback_deployment(
+                Extension(persistence.clone()),
+                Extension(deployment_manager.clone()),
+                Extension(Claim::default()),
+                CustomErrorPath((service.name.clone(), id)),
+            )
+        };
+
+        // A stopped deployment that was never built, so has no executable
+        let id = Uuid::new_v4();
+        persistence
+            .insert_deployment(&Deployment {
+                id,
+                service_id: service.id,
+                state: State::Stopped,
+                last_update: Utc::now(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert!(matches!(
+            rollback(id).await,
+            Err(Error::BadRequest(message)) if message.contains("no stored build")
+        ));
+
+        // Deployments that are still on their way to running, or already are, even with an executable
+        let executables_path = deployment_manager
+            .builds_path()
+            .join(&service.name)
+            .join(EXECUTABLE_DIRNAME);
+        std::fs::create_dir_all(&executables_path).unwrap();
+        for state in [
+            State::Queued,
+            State::Building,
+            State::Built,
+            State::Loading,
+            State::Running,
+            State::Restarting,
+        ] {
+            let id = Uuid::new_v4();
+            std::fs::write(executables_path.join(id.to_string()), "not run").unwrap();
+            persistence
+                .insert_deployment(&Deployment {
+                    id,
+                    service_id: service.id,
+                    state,
+                    last_update: Utc::now(),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+
+            assert!(
+                matches!(rollback(id).await, Err(Error::BadRequest(_))),
+                "{state} should be rejected"
+            );
+        }
+
+        // Deployments of other services are not found
+        let other = persistence
+            .get_or_create_service("deploy-layer-rollback-other")
+            .await
+            .unwrap();
+        assert!(matches!(
+            rollback_deployment(
+                Extension(persistence.clone()),
+                Extension(deployment_manager.clone()),
+                Extension(Claim::default()),
+                CustomErrorPath((other.name, id)),
+            )
+            .await,
+            Err(Error::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn secrets_are_updated() {
+        let deployment_manager = get_deployment_manager().await;
+        let (mut persistence, _) = Persistence::new_in_memory().await;
+        persistence.set_resource_recorder_client(
+            get_mocked_resource_recorder_client(ResourceRecorderMock::default()).await,
+        );
+        let service = persistence
+            .get_or_create_service("secrets-update")
+            .await
+            .unwrap();
+
+        let update = |request| {
+            update_secrets(
+                Extension(persistence.clone()),
+                Extension(deployment_manager.clone()),
+                Extension(Claim::default()),
+                CustomErrorPath(("secrets".to_string(), service.name.clone())),
+                Json(request),
+            )
+        };
+        let get = || {
+            get_secrets(
+                Extension(persistence.clone()),
+                Extension(Claim::default()),
+                CustomErrorPath(("secrets".to_string(), service.name.clone())),
+            )
+        };
+
+        let Json(response) = update(SecretsUpdateRequest {
+            set: BTreeMap::from([
+                ("KEY".to_string(), "value".to_string()),
+                ("OTHER".to_string(), "other".to_string()),
+            ]),
+            // There is no active deployment to restart
+            restart: true,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        assert_eq!(response.secrets.get("KEY"), Some("value".to_string()));
+        assert_eq!(response.secrets.get("OTHER"), Some("other".to_string()));
+        assert!(!response.restarted);
+
+        let Json(response) = update(SecretsUpdateRequest {
+            set: BTreeMap::from([("KEY".to_string(), "changed".to_string())]),
+            unset: vec!["OTHER".to_string()],
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        assert_eq!(response.secrets.get("KEY"), Some("changed".to_string()));
+        assert_eq!(response.secrets.get("OTHER"), None);
+
+        let Json(secrets) = get().await.unwrap();
+        assert_eq!(secrets.get("KEY"), Some("changed".to_string()));
+        assert_eq!(secrets.get("OTHER"), None);
+
+        // Unsetting a secret that does not exist fails without changing the others
+        let response = update(SecretsUpdateRequest {
+            set: BTreeMap::from([("KEY".to_string(), "lost".to_string())]),
+            unset: vec!["MISSING".to_string()],
+            ..Default::default()
+        })
+        .await;
+        assert!(matches!(
+            response,
+            Err(Error::BadRequest(message)) if message.contains("MISSING")
+        ));
+        let Json(secrets) = get().await.unwrap();
+        assert_eq!(secrets.get("KEY"), Some("changed".to_string()));
+
+        // Concurrent updates don't overwrite each other's changes
+        let mut updates = tokio::task::JoinSet::new();
+        for i in 0..10 {
+            updates.spawn(update(SecretsUpdateRequest {
+                set: BTreeMap::from([(format!("KEY_{i}"), i.to_string())]),
+                ..Default::default()
+            }));
+        }
+        while let Some(response) = updates.join_next().await {
+            response.unwrap().unwrap();
+        }
+        let Json(secrets) = get().await.unwrap();
+        for i in 0..10 {
+            assert_eq!(secrets.get(&format!("KEY_{i}")), Some(i.to_string()));
+        }
+    }
+
+    #[tokio::test]
+    async fn secrets_update_restarts_the_active_deployment() {
+        let deployment_manager = get_deployment_manager().await;
+        let (mut persistence, _) = Persistence::new_in_memory().await;
+        persistence.set_resource_recorder_client(
+            get_mocked_resource_recorder_client(ResourceRecorderMock::default()).await,
+        );
+        let service = persistence
+            .get_or_create_service("secrets-restart")
+            .await
+            .unwrap();
+
+        let id = Uuid::new_v4();
+        persistence
+            .insert_deployment(&Deployment {
+                id,
+                service_id: service.id,
+                state: State::Running,
+                last_update: Utc::now(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        let Json(response) = update_secrets(
+            Extension(persistence),
+            Extension(deployment_manager),
+            Extension(Claim::default()),
+            CustomErrorPath(("secrets".to_string(), service.name)),
+            Json(SecretsUpdateRequest {
+                set: BTreeMap::from([("KEY".to_string(), "value".to_string())]),
+                restart: true,
+                ..Default::default()
+            }),
+        )
+        .await
+        .unwrap();
+        assert!(response.restarted);
+
+        // The active deployment is pushed to be run again
+        assert_eq!(
+            RECORDER.get_deployment_states(&id).first(),
+            Some(&MockStateLog {
+                id,
+                state: State::Built,
+            })
+        );
+    }
+
     #[tokio::test]
     async fn deployment_from_run() {
         let deployment_manager = get_deployment_manager().await;
@@ -747,11 +1348,13 @@ mod tests {
             .build_log_recorder(RECORDER.clone())
             .active_deployment_getter(StubActiveDeploymentGetter)
             .artifacts_path(PathBuf::from("/tmp"))
+            .build_cache_size(u64::MAX)
             .resource_manager(StubResourceManager)
             .log_fetcher(logger_client.clone())
             .runtime(get_runtime_manager(Batcher::wrap(logger_client)).await)
             .queue_client(StubBuildQueueClient)
             .provisioner_client(get_mocked_provisioner_client(ProvisionerMock).await)
+            .service_proxy(ServiceProxy::default())
             .build()
     }
 
@@ -42,6 +42,10 @@ pub enum TestError {
     Failed,
     #[error("Failed to run tests: {0}")]
     Run(#[from] std::io::Error),
+    #[error("The deployed application's tests did not finish within {} seconds", .0.as_secs())]
+    Timeout(std::time::Duration),
+    #[error("Failed to read the test configuration: {0}")]
+    Config(#[source] anyhow::Error),
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -21,6 +21,8 @@ pub enum Error {
     },
     #[error("{0}, try running `cargo shuttle deploy`")]
     NotFound(String),
+    #[error("{0}")]
+    BadRequest(String),
     #[error("Internal error: {0}")]
     Internal(#[from] anyhow::Error),
     #[error("Missing header: {0}")]
@@ -44,6 +46,7 @@ impl IntoResponse for Error {
     fn into_response(self) -> Response {
         let code = match self {
             Error::NotFound(_) => StatusCode::NOT_FOUND,
+            Error::BadRequest(_) => StatusCode::BAD_REQUEST,
             _ => {
                 // We only want to emit error events for internal errors, not e.g. 404s.
                 error!(
@@ -1,4 +1,6 @@
+use std::collections::BTreeMap;
 use std::str::FromStr;
+use std::time::SystemTime;
 
 use anyhow::anyhow;
 use async_trait::async_trait;
@@ -14,6 +16,7 @@ use axum::routing::{delete, get, post, Router};
 use axum::Json;
 use chrono::{SecondsFormat, Utc};
 use hyper::{Request, StatusCode, Uri};
+use prost_types::Timestamp;
 use serde::{de::DeserializeOwned, Deserialize};
 use shuttle_service::builder::clean_crate;
 use tonic::Code;
@@ -28,13 +31,19 @@ use shuttle_backends::{
 };
 use shuttle_common::{
     claims::{Claim, Scope},
+    constants::EXECUTABLE_DIRNAME,
+    log::LogsFilter,
     models::deployment::{DeploymentRequest, CREATE_SERVICE_BODY_LIMIT, GIT_STRINGS_MAX_LENGTH},
+    models::secret::{SecretsUpdateRequest, SecretsUpdateResponse},
+    secrets::{Secret, SecretStore},
     LogItem,
 };
 use shuttle_proto::logger::LogsRequest;
+use shuttle_proto::resource_recorder::record_request;
+use ulid::Ulid;
 
 use crate::{
-    deployment::{DeploymentManager, Queued},
+    deployment::{Built, DeploymentManager, Queued},
     persistence::{resource::ResourceManager, Deployment, Persistence, State},
 };
 pub use {self::error::Error, self::error::Result, self::local::set_jwt_bearer};
@@ -55,6 +64,18 @@ struct LogsQuery {
     head: Option<u32>,
     tail: Option<u32>,
 }
+
+#[derive(Deserialize)]
+struct CleanQuery {
+    #[serde(default)]
+    evict_cache: bool,
+}
+
+#[derive(Deserialize)]
+struct ResourceQuery {
+    name: Option<String>,
+}
+
 #[derive(Clone)]
 pub struct RouterBuilder {
     router: Router,
@@ -94,6 +115,11 @@ impl RouterBuilder {
                 delete(delete_service_resource)
                     .layer(ScopedLayer::new(vec![Scope::ResourcesWrite])),
             )
+            .route(
+                "/projects/:project_name/services/:service_name/secrets",
+                get(get_secrets.layer(ScopedLayer::new(vec![Scope::Secret])))
+                    .put(update_secrets.layer(ScopedLayer::new(vec![Scope::SecretWrite]))),
+            )
             .route(
                 "/projects/:project_name/deployments",
                 get(get_deployments).layer(ScopedLayer::new(vec![Scope::Service])),
@@ -107,6 +133,10 @@ impl RouterBuilder {
                     // This is kept for compatibility.
                     .put(|| async move {}),
             )
+            .route(
+                "/projects/:project_name/deployments/:deployment_id/rollback",
+                post(rollback_deployment.layer(ScopedLayer::new(vec![Scope::DeploymentPush]))),
+            )
             .route(
                 "/projects/:project_name/ws/deployments/:deployment_id/logs",
                 get(get_logs_subscribe.layer(ScopedLayer::new(vec![Scope::Logs]))),
@@ -250,6 +280,7 @@ pub async fn delete_service_resource(
         String,
         String,
     )>,
+    Query(ResourceQuery { name }): Query<ResourceQuery>,
 ) -> Result<Json<()>> {
     let service = persistence
         .get_service_by_name(&service_name)
@@ -266,7 +297,7 @@ pub async fn delete_service_resource(
         })?;
 
     let get_resource_response = persistence
-        .get_resource(&service.id, r#type, claim.clone())
+        .get_resource(&service.id, r#type, name.clone(), claim.clone())
         .await?;
 
     if get_resource_response.resource.is_none() {
@@ -274,7 +305,7 @@ pub async fn delete_service_resource(
     }
 
     let delete_resource_response = persistence
-        .delete_resource(project_name, &service.id, r#type, claim)
+        .delete_resource(project_name, &service.id, r#type, name, claim)
         .await?;
 
     if !delete_resource_response.success {
@@ -284,6 +315,139 @@ pub async fn delete_service_resource(
     Ok(Json(()))
 }
 
+#[instrument(skip_all, fields(shuttle.project.name = %project_name, shuttle.service.name = %service_name))]
+pub async fn get_secrets(
+    Extension(mut persistence): Extension<Persistence>,
+    Extension(claim): Extension<Claim>,
+    CustomErrorPath((project_name, service_name)): CustomErrorPath<(String, String)>,
+) -> Result<Json<SecretStore>> {
+    let service = persistence
+        .get_service_by_name(&service_name)
+        .await?
+        .ok_or_else(|| Error::NotFound("service not found".to_string()))?;
+
+    let secrets = get_stored_secrets(&mut persistence, &service.id, claim).await?;
+
+    Ok(Json(into_secret_store(secrets)))
+}
+
+/// Change the secrets stored for a service. The running deployment keeps the old secrets until it is restarted.
+#[instrument(skip_all, fields(shuttle.project.name = %project_name, shuttle.service.name = %service_name))]
+pub async fn update_secrets(
+    Extension(mut persistence): Extension<Persistence>,
+    Extension(deployment_manager): Extension<DeploymentManager>,
+    Extension(claim): Extension<Claim>,
+    CustomErrorPath((project_name, service_name)): CustomErrorPath<(String, String)>,
+    Json(update): Json<SecretsUpdateRequest>,
+) -> Result<Json<SecretsUpdateResponse>> {
+    let service = persistence
+        .get_service_by_name(&service_name)
+        .await?
+        .ok_or_else(|| Error::NotFound("service not found".to_string()))?;
+
+    if update.set.keys().any(|key| key.is_empty()) {
+        return Err(Error::BadRequest("secret keys can't be empty".to_string()));
+    }
+
+    // Held until the response, so that concurrent updates of this service are applied one after the other
+    let _secrets_lock = deployment_manager.lock_secrets(&service.id).await;
+    let mut secrets = get_stored_secrets(&mut persistence, &service.id, claim.clone()).await?;
+    for key in &update.unset {
+        if secrets.remove(key).is_none() {
+            return Err(Error::BadRequest(format!(
+                "there is no secret named '{key}'"
+            )));
+        }
+    }
+    secrets.extend(update.set);
+
+    let response = persistence
+        .insert_resources(
+            vec![record_request::Resource {
+                r#type: shuttle_common::resource::Type::Secrets.to_string(),
+                config: serde_json::to_vec(&serde_json::Value::Null).expect("to serialize struct"),
+                data: serde_json::to_vec(&secrets).expect("to serialize struct"),
+                name: String::new(),
+            }],
+            &service.id,
+            claim.clone(),
+        )
+        .await?;
+    if !response.success {
+        return Err(anyhow!("Unable to save secrets in resource recorder").into());
+    }
+
+    // The cached resources hold the old secrets, so make the next start load them again
+    deployment_manager
+        .clear_resources_cache(&service.name)
+        .await
+        .map_err(anyhow::Error::from)?;
+
+    let mut restarted = false;
+    if update.restart {
+        if let Some(deployment) = persistence.get_active_deployment(&service.id).await? {
+            info!(
+                "Restarting deployment {} to apply new secrets",
+                deployment.id
+            );
+            deployment_manager.kill(deployment.id).await;
+            deployment_manager
+                .run_push(Built {
+                    id: deployment.id,
+                    service_name: service.name,
+                    service_id: service.id,
+                    project_id: persistence.project_id(),
+                    tracing_context: Default::default(),
+                    claim: Some(claim),
+                    // The stored secrets are loaded when the deployment starts
+                    secrets: Default::default(),
+                })
+                .await;
+            restarted = true;
+        }
+    }
+
+    Ok(Json(SecretsUpdateResponse {
+        secrets: into_secret_store(secrets),
+        restarted,
+    }))
+}
+
+async fn get_stored_secrets(
+    persistence: &mut Persistence,
+    service_id: &Ulid,
+    claim: Claim,
+) -> Result<BTreeMap<String, String>> {
+    let Some(resource) = persistence
+        .get_resource(
+            service_id,
+            shuttle_common::resource::Type::Secrets,
+            None,
+            claim,
+        )
+        .await?
+        .resource
+    else {
+        return Ok(BTreeMap::new());
+    };
+
+    let resource = shuttle_common::resource::Response::try_from(resource)
+        .map_err(|err| anyhow!("failed to parse secrets resource: {err}"))?;
+    let secrets = serde_json::from_value(resource.data)
+        .map_err(|err| anyhow!("failed to parse secrets data: {err}"))?;
+
+    Ok(secrets)
+}
+
+fn into_secret_store(secrets: BTreeMap<String, String>) -> SecretStore {
+    SecretStore::new(
+        secrets
+            .into_iter()
+            .map(|(key, value)| (key, Secret::new(value)))
+            .collect(),
+    )
+}
+
 #[instrument(skip_all, fields(shuttle.project.name = %project_name, shuttle.service.name = %service_name))]
 pub async fn create_service(
     Extension(persistence): Extension<Persistence>,
@@ -424,18 +588,78 @@ pub async fn delete_deployment(
     }
 }
 
+#[instrument(skip_all, fields(shuttle.project.name = %project_name, %deployment_id))]
+pub async fn rollback_deployment(
+    Extension(persistence): Extension<Persistence>,
+    Extension(deployment_manager): Extension<DeploymentManager>,
+    Extension(claim): Extension<Claim>,
+    CustomErrorPath((project_name, deployment_id)): CustomErrorPath<(String, Uuid)>,
+) -> Result<Json<shuttle_common::models::deployment::Response>> {
+    let service = persistence
+        .get_service_by_name(&project_name)
+        .await?
+        .ok_or_else(|| Error::NotFound("service not found".to_string()))?;
+    let deployment = persistence
+        .get_deployment(&deployment_id)
+        .await?
+        .filter(|deployment| deployment.service_id == service.id)
+        .ok_or_else(|| Error::NotFound("deployment not found".to_string()))?;
+
+    // Deployments that are still on their way to running would otherwise be started twice
+    if !matches!(
+        deployment.state,
+        State::Stopped | State::Completed | State::Crashed
+    ) {
+        return Err(Error::BadRequest(format!(
+            "only stopped, completed or crashed deployments can be rolled back to, this one is {}",
+            deployment.state
+        )));
+    }
+
+    // Only deployments that finished building have an executable to run again
+    let executable_path = deployment_manager
+        .builds_path()
+        .join(&service.name)
+        .join(EXECUTABLE_DIRNAME)
+        .join(deployment.id.to_string());
+    if !executable_path.exists() {
+        return Err(Error::BadRequest(
+            "this deployment has no stored build to roll back to".to_string(),
+        ));
+    }
+
+    info!("Rolling back to deployment {}", deployment.id);
+    let built = Built {
+        id: deployment.id,
+        service_name: service.name,
+        service_id: service.id,
+        project_id: persistence.project_id(),
+        tracing_context: Default::default(),
+        claim: Some(claim),
+        secrets: Default::default(),
+    };
+    // Moves the deployment to the built state, after which the run task starts it and stops the current one
+    deployment_manager.run_push(built).await;
+
+    let deployment = Deployment {
+        state: State::Built,
+        last_update: Utc::now(),
+        ..deployment
+    };
+
+    Ok(Json(deployment.into()))
+}
+
 #[instrument(skip_all, fields(shuttle.project.name = %project_name, %deployment_id))]
 pub async fn get_logs(
     Extension(deployment_manager): Extension<DeploymentManager>,
     Extension(claim): Extension<Claim>,
     CustomErrorPath((project_name, deployment_id)): CustomErrorPath<(String, Uuid)>,
     Query(LogsQuery { head, tail }): Query<LogsQuery>,
+    Query(filter): Query<LogsFilter>,
 ) -> Result<Json<Vec<LogItem>>> {
-    let mut logs_request: tonic::Request<LogsRequest> = tonic::Request::new(LogsRequest {
-        deployment_id: deployment_id.to_string(),
-        head,
-        tail,
-    });
+    let mut logs_request: tonic::Request<LogsRequest> =
+        tonic::Request::new(logs_request(deployment_id, head, tail, filter));
 
     logs_request.extensions_mut().insert(claim);
 
@@ -473,26 +697,46 @@ pub async fn get_logs_subscribe(
     Extension(claim): Extension<Claim>,
     CustomErrorPath((project_name, deployment_id)): CustomErrorPath<(String, Uuid)>,
     Query(LogsQuery { head, tail }): Query<LogsQuery>,
+    Query(filter): Query<LogsFilter>,
     ws_upgrade: ws::WebSocketUpgrade,
 ) -> axum::response::Response {
+    let request = logs_request(deployment_id, head, tail, filter);
     ws_upgrade.on_upgrade(move |s| {
-        logs_websocket_handler(s, deployment_manager, deployment_id, head, tail, claim)
+        logs_websocket_handler(s, deployment_manager, deployment_id, request, claim)
     })
 }
 
-async fn logs_websocket_handler(
-    mut s: WebSocket,
-    deployment_manager: DeploymentManager,
+fn logs_request(
     deployment_id: Uuid,
     head: Option<u32>,
     tail: Option<u32>,
-    claim: Claim,
-) {
-    let mut logs_request: tonic::Request<LogsRequest> = tonic::Request::new(LogsRequest {
+    filter: LogsFilter,
+) -> LogsRequest {
+    LogsRequest {
         deployment_id: deployment_id.to_string(),
         head,
         tail,
-    });
+        since: filter
+            .since
+            .map(|since| Timestamp::from(SystemTime::from(since))),
+        until: filter
+            .until
+            .map(|until| Timestamp::from(SystemTime::from(until))),
+        grep: filter.grep,
+        regex: filter.regex,
+        source: filter.source,
+        level: filter.level,
+    }
+}
+
+async fn logs_websocket_handler(
+    mut s: WebSocket,
+    deployment_manager: DeploymentManager,
+    deployment_id: Uuid,
+    request: LogsRequest,
+    claim: Claim,
+) {
+    let mut logs_request: tonic::Request<LogsRequest> = tonic::Request::new(request);
 
     logs_request.extensions_mut().insert(claim);
 
@@ -560,16 +804,30 @@ async fn logs_websocket_handler(
 pub async fn clean_project(
     Extension(deployment_manager): Extension<DeploymentManager>,
     CustomErrorPath(project_name): CustomErrorPath<String>,
+    Query(CleanQuery { evict_cache }): Query<CleanQuery>,
 ) -> Result<Json<String>> {
     clean_crate(
         deployment_manager
             .builds_path()
-            .join(project_name)
+            .join(&project_name)
             .as_path(),
     )
     .await?;
 
-    Ok(Json("Cleaning done".into()))
+    let (usage, evicted) = deployment_manager
+        .build_cache_usage(&project_name, evict_cache)
+        .await
+        .map_err(anyhow::Error::from)?;
+    let mut message = format!(
+        "Cleaning done. The build cache has {} entries using {:.1} MB",
+        usage.entries,
+        usage.size as f64 / 1_000_000f64,
+    );
+    if evicted {
+        message.push_str(", after evicting the dependencies of this project");
+    }
+
+    Ok(Json(message))
 }
 
 pub struct Rmp<T>(T);
@@ -1,9 +1,12 @@
-use std::sync::Arc;
+use std::{
+    net::{Ipv4Addr, SocketAddr},
+    sync::Arc,
+};
 
 pub use persistence::Persistence;
 pub use runtime_manager::RuntimeManager;
 use shuttle_backends::client::ServicesApiClient;
-use shuttle_common::log::LogRecorder;
+use shuttle_common::{constants::DEPLOYER_SERVICE_HTTP_PORT, log::LogRecorder};
 use shuttle_proto::{logger, provisioner};
 use tokio::sync::Mutex;
 use tracing::info;
@@ -18,7 +21,7 @@ mod runtime_manager;
 
 pub use crate::args::Args;
 pub use crate::deployment::state_change_layer::StateChangeLayer;
-use crate::deployment::{Built, DeploymentManager};
+use crate::deployment::{Built, DeploymentManager, ServiceProxy};
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
@@ -32,16 +35,23 @@ pub async fn start(
     let project_id = Ulid::from_string(args.project_id.as_str())
         .expect("to have a valid ULID as project_id arg");
 
+    // The gateway routes the service traffic to this port, and the proxy forwards it to the active deployment
+    let service_proxy = ServiceProxy::default();
+    let address = SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), DEPLOYER_SERVICE_HTTP_PORT);
+    tokio::spawn(service_proxy.clone().serve(address));
+
     // when _set is dropped once axum exits, the deployment tasks will be aborted.
     let deployment_manager = DeploymentManager::builder()
         .build_log_recorder(log_recorder)
         .active_deployment_getter(persistence.clone())
         .artifacts_path(args.artifacts_path)
+        .build_cache_size(args.build_cache_size * 1_000_000)
         .runtime(runtime_manager)
         .resource_manager(persistence.clone())
         .provisioner_client(provisioner::get_client(args.provisioner_address).await)
         .queue_client(ServicesApiClient::new(args.gateway_uri))
         .log_fetcher(log_fetcher)
+        .service_proxy(service_proxy)
         .build();
 
     persistence.cleanup_invalid_states().await.unwrap();
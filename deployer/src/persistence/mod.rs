@@ -95,7 +95,7 @@ impl Persistence {
     }
 
     #[cfg(test)]
-    async fn new_in_memory() -> (Self, JoinHandle<()>) {
+    pub(crate) async fn new_in_memory() -> (Self, JoinHandle<()>) {
         let pool = SqlitePool::connect_with(
             SqliteConnectOptions::from_str("sqlite::memory:")
                 .unwrap()
@@ -118,6 +118,11 @@ impl Persistence {
         (persistence, handle)
     }
 
+    #[cfg(test)]
+    pub(crate) fn set_resource_recorder_client(&mut self, client: resource_recorder::Client) {
+        self.resource_recorder_client = Some(client);
+    }
+
     async fn configure(
         pool: SqlitePool,
         resource_recorder_uri: Uri,
@@ -221,10 +226,12 @@ impl Persistence {
             .map_err(Error::from)
     }
 
+    /// The latest deployment of a service that is running, or that crashed and is waiting to be restarted
     pub async fn get_active_deployment(&self, service_id: &Ulid) -> Result<Option<Deployment>> {
-        sqlx::query_as("SELECT * FROM deployments WHERE service_id = ? AND state = ? ORDER BY last_update DESC")
+        sqlx::query_as("SELECT * FROM deployments WHERE service_id = ? AND state IN (?, ?) ORDER BY last_update DESC")
             .bind(service_id.to_string())
             .bind(State::Running)
+            .bind(State::Restarting)
             .fetch_optional(&self.pool)
             .await
             .map_err(Error::from)
@@ -232,12 +239,13 @@ impl Persistence {
 
     // Clean up all invalid states inside persistence
     pub async fn cleanup_invalid_states(&self) -> Result<()> {
-        sqlx::query("UPDATE deployments SET state = ? WHERE state IN(?, ?, ?, ?)")
+        sqlx::query("UPDATE deployments SET state = ? WHERE state IN(?, ?, ?, ?, ?)")
             .bind(State::Stopped)
             .bind(State::Queued)
             .bind(State::Built)
             .bind(State::Building)
             .bind(State::Loading)
+            .bind(State::Restarting)
             .execute(&self.pool)
             .await?;
 
@@ -404,6 +412,7 @@ impl ResourceManager for Persistence {
                         r#type: res.r#type.to_string(),
                         config: res.config.to_string().into_bytes(),
                         data: res.data.to_string().into_bytes(),
+                        name: String::new(),
                     })
                     .collect();
 
@@ -449,12 +458,14 @@ impl ResourceManager for Persistence {
         &mut self,
         service_id: &Ulid,
         r#type: shuttle_common::resource::Type,
+        name: Option<String>,
         claim: Claim,
     ) -> Result<ResourceResponse> {
         let mut req = tonic::Request::new(ResourceIds {
             project_id: self.project_id.to_string(),
             service_id: service_id.to_string(),
             r#type: r#type.to_string(),
+            name: name.unwrap_or_default(),
         });
         req.extensions_mut().insert(claim);
 
@@ -473,6 +484,7 @@ impl ResourceManager for Persistence {
         project_name: String,
         service_id: &Ulid,
         resource_type: shuttle_common::resource::Type,
+        name: Option<String>,
         claim: Claim,
     ) -> Result<ResultResponse> {
         if let Type::Database(db_type) = resource_type {
@@ -481,6 +493,7 @@ impl ResourceManager for Persistence {
                     project_name,
                     db_type: Some(db_type.into()),
                     db_name: None,
+                    resource_name: name.clone(),
                 });
                 req.extensions_mut().insert(claim.clone());
 
@@ -495,6 +508,7 @@ impl ResourceManager for Persistence {
             project_id: self.project_id.to_string(),
             service_id: service_id.to_string(),
             r#type: resource_type.to_string(),
+            name: name.unwrap_or_default(),
         });
         req.extensions_mut().insert(claim);
 
@@ -518,10 +532,11 @@ impl ActiveDeploymentsGetter for Persistence {
         service_id: &Ulid,
     ) -> std::result::Result<Vec<Uuid>, Self::Err> {
         let ids: Vec<_> = sqlx::query_as::<_, Deployment>(
-            "SELECT * FROM deployments WHERE service_id = ? AND state = ?",
+            "SELECT * FROM deployments WHERE service_id = ? AND state IN (?, ?)",
         )
         .bind(service_id.to_string())
         .bind(State::Running)
+        .bind(State::Restarting)
         .fetch_all(&self.pool)
         .await
         .map_err(Error::from)?
@@ -929,6 +944,7 @@ mod tests {
         let service_id = add_service_named(&p.pool, "service-name").await.unwrap();
         let id_1 = Uuid::new_v4();
         let id_2 = Uuid::new_v4();
+        let id_3 = Uuid::new_v4();
 
         for deployment in &[
             Deployment {
@@ -966,13 +982,20 @@ mod tests {
                 last_update: Utc.with_ymd_and_hms(2022, 4, 25, 4, 42, 32).unwrap(),
                 ..Default::default()
             },
+            Deployment {
+                id: id_3,
+                service_id,
+                state: State::Restarting,
+                last_update: Utc.with_ymd_and_hms(2022, 4, 25, 4, 45, 12).unwrap(),
+                ..Default::default()
+            },
         ] {
             p.insert_deployment(deployment).await.unwrap();
         }
 
         let actual = p.get_active_deployments(&service_id).await.unwrap();
 
-        assert_eq!(actual, vec![id_1, id_2]);
+        assert_eq!(actual, vec![id_1, id_2, id_3]);
     }
 
     async fn add_service(pool: &SqlitePool) -> Result<Ulid> {
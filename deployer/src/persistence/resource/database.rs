@@ -21,6 +21,7 @@ pub enum AwsRdsType {
 pub enum SharedType {
     Postgres,
     MongoDb,
+    Redis,
 }
 
 impl From<Type> for shuttle_common::database::Type {
@@ -47,6 +48,7 @@ impl From<SharedType> for shuttle_common::database::SharedEngine {
         match shared_type {
             SharedType::Postgres => Self::Postgres,
             SharedType::MongoDb => Self::MongoDb,
+            SharedType::Redis => Self::Redis,
         }
     }
 }
@@ -75,6 +77,7 @@ impl From<shuttle_common::database::SharedEngine> for SharedType {
         match shared_type {
             shuttle_common::database::SharedEngine::Postgres => Self::Postgres,
             shuttle_common::database::SharedEngine::MongoDb => Self::MongoDb,
+            shuttle_common::database::SharedEngine::Redis => Self::Redis,
         }
     }
 }
@@ -28,6 +28,7 @@ pub trait ResourceManager: Clone + Send + Sync + 'static {
         &mut self,
         service_id: &ulid::Ulid,
         r#type: Type,
+        name: Option<String>,
         claim: Claim,
     ) -> Result<ResourceResponse, Self::Err>;
     async fn delete_resource(
@@ -35,6 +36,7 @@ pub trait ResourceManager: Clone + Send + Sync + 'static {
         project_name: String,
         service_id: &ulid::Ulid,
         r#type: Type,
+        name: Option<String>,
         claim: Claim,
     ) -> Result<ResultResponse, Self::Err>;
 }
@@ -65,6 +67,7 @@ impl From<Resource> for shuttle_common::resource::Response {
             r#type: resource.r#type,
             config: resource.config,
             data: resource.data,
+            name: None,
         }
     }
 }
@@ -29,6 +29,9 @@ pub enum State {
     /// Something in the deployment process failed
     Crashed,
 
+    /// Deployment crashed, and is waiting to be started again by its restart policy
+    Restarting,
+
     /// We never expect this state and entering this state should be considered a bug
     #[default]
     Unknown,
@@ -51,6 +54,7 @@ impl From<State> for shuttle_common::deployment::State {
             State::Completed => Self::Completed,
             State::Stopped => Self::Stopped,
             State::Crashed => Self::Crashed,
+            State::Restarting => Self::Restarting,
             State::Unknown => Self::Unknown,
         }
     }
@@ -67,6 +71,7 @@ impl From<shuttle_common::deployment::State> for State {
             shuttle_common::deployment::State::Completed => Self::Completed,
             shuttle_common::deployment::State::Stopped => Self::Stopped,
             shuttle_common::deployment::State::Crashed => Self::Crashed,
+            shuttle_common::deployment::State::Restarting => Self::Restarting,
             shuttle_common::deployment::State::Unknown => Self::Unknown,
         }
     }
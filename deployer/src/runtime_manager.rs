@@ -13,17 +13,25 @@ use shuttle_proto::{
     runtime::{self, StopRequest},
 };
 use shuttle_service::runner;
-use tokio::{io::AsyncBufReadExt, io::BufReader, process, sync::Mutex};
+use tokio::{
+    io::AsyncBufReadExt,
+    io::BufReader,
+    process,
+    sync::{watch, Mutex},
+};
 use tracing::{debug, error, info, trace, warn};
 use uuid::Uuid;
 
 type Runtimes = Arc<std::sync::Mutex<HashMap<Uuid, (process::Child, runtime::Client)>>>;
+type KillWatchers = Arc<std::sync::Mutex<HashMap<Uuid, watch::Sender<bool>>>>;
 
 /// Manager that can start up multiple runtimes. This is needed so that two runtimes can be up when a new deployment is made:
 /// One runtime for the new deployment being loaded; another for the currently active deployment
 #[derive(Clone)]
 pub struct RuntimeManager {
     runtimes: Runtimes,
+    /// Deployments that want to know when they are killed, even while they have no runtime
+    kill_watchers: KillWatchers,
     logger_client: Batcher<logger::Client>,
 }
 
@@ -31,6 +39,7 @@ impl RuntimeManager {
     pub fn new(logger_client: Batcher<logger::Client>) -> Arc<Mutex<Self>> {
         Arc::new(Mutex::new(Self {
             runtimes: Default::default(),
+            kill_watchers: Default::default(),
             logger_client,
         }))
     }
@@ -108,8 +117,32 @@ impl RuntimeManager {
         }
     }
 
+    /// Watch for a deployment being killed, such as to not restart it once it crashed.
+    /// The watch lasts until [Self::unwatch_kill] is called for the deployment.
+    pub fn watch_kill(&mut self, id: Uuid) -> watch::Receiver<bool> {
+        let (sender, receiver) = watch::channel(false);
+        self.kill_watchers.lock().unwrap().insert(id, sender);
+
+        receiver
+    }
+
+    /// Stop a watch from [Self::watch_kill], unless a newer run of the deployment replaced it
+    pub fn unwatch_kill(&mut self, id: &Uuid, killed: &watch::Receiver<bool>) {
+        let mut kill_watchers = self.kill_watchers.lock().unwrap();
+        if kill_watchers
+            .get(id)
+            .is_some_and(|sender| sender.subscribe().same_channel(killed))
+        {
+            kill_watchers.remove(id);
+        }
+    }
+
     /// Send a kill / stop signal for a deployment to its running runtime
     pub async fn kill(&mut self, id: &Uuid) -> bool {
+        if let Some(killed) = self.kill_watchers.lock().unwrap().get(id) {
+            killed.send_replace(true);
+        }
+
         let value = self.runtimes.lock().unwrap().remove(id);
 
         let Some((mut process, mut runtime_client)) = value else {
@@ -125,6 +158,7 @@ impl RuntimeManager {
         };
         trace!(?response, "stop deployment response");
 
+        // The runtime only responds once the service has been given its grace period to shut down
         let _ = process.start_kill();
 
         response.into_inner().success
@@ -0,0 +1,13 @@
+struct MyService;
+
+#[shuttle_runtime::async_trait]
+impl shuttle_runtime::Service for MyService {
+    async fn bind(mut self, _: std::net::SocketAddr) -> Result<(), shuttle_runtime::Error> {
+        panic!("panic in bind");
+    }
+}
+
+#[shuttle_runtime::main]
+async fn restart_crash() -> Result<MyService, shuttle_runtime::Error> {
+    Ok(MyService)
+}
@@ -24,7 +24,10 @@ use ulid::Ulid;
 use uuid::Uuid;
 
 use shuttle_deployer::{
-    deployment::Built, error, persistence::resource::ResourceManager, RuntimeManager,
+    deployment::{Built, ServiceProxy},
+    error,
+    persistence::resource::ResourceManager,
+    RuntimeManager,
 };
 
 const RESOURCES_PATH: &str = "tests/resources";
@@ -100,6 +103,7 @@ impl ResourceManager for StubResourceManager {
         _project_name: String,
         _service_id: &Ulid,
         _resource_type: shuttle_common::resource::Type,
+        _name: Option<String>,
         _claim: Claim,
     ) -> Result<ResultResponse, Self::Err> {
         Ok(ResultResponse {
@@ -112,6 +116,7 @@ impl ResourceManager for StubResourceManager {
         &mut self,
         _service_id: &ulid::Ulid,
         _resource_type: shuttle_common::resource::Type,
+        _name: Option<String>,
         _claim: Claim,
     ) -> Result<ResourceResponse, Self::Err> {
         Ok(ResourceResponse {
@@ -149,6 +154,7 @@ async fn can_be_killed() {
             handle_cleanup,
             path.as_path(),
             get_mocked_provisioner_client(ProvisionerMock).await,
+            ServiceProxy::default(),
         )
         .await
         .unwrap();
@@ -191,6 +197,7 @@ async fn self_stop() {
             handle_cleanup,
             path.as_path(),
             get_mocked_provisioner_client(ProvisionerMock).await,
+            ServiceProxy::default(),
         )
         .await
         .unwrap();
@@ -221,6 +228,7 @@ async fn panic_in_load() {
             handle_cleanup,
             path.as_path(),
             get_mocked_provisioner_client(ProvisionerMock).await,
+            ServiceProxy::default(),
         )
         .await;
     println!("{:?}", x);
@@ -256,6 +264,7 @@ async fn panic_in_main() {
             handle_cleanup,
             path.as_path(),
             get_mocked_provisioner_client(ProvisionerMock).await,
+            ServiceProxy::default(),
         )
         .await
         .unwrap();
@@ -297,6 +306,7 @@ async fn panic_in_bind() {
             handle_cleanup,
             path.as_path(),
             get_mocked_provisioner_client(ProvisionerMock).await,
+            ServiceProxy::default(),
         )
         .await
         .unwrap();
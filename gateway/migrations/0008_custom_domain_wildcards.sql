@@ -0,0 +1,3 @@
+-- Wildcard custom domains also route all their subdomains to the project
+ALTER TABLE custom_domains
+ADD COLUMN wildcard BOOLEAN NOT NULL DEFAULT false;
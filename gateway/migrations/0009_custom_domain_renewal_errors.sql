@@ -0,0 +1,3 @@
+-- Why the last automatic renewal of the certificate failed, cleared when it is renewed
+ALTER TABLE custom_domains
+ADD COLUMN renewal_error TEXT;
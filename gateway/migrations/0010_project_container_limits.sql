@@ -0,0 +1,3 @@
+-- Resources an admin set for a project, which take precedence over the ones of the owner's account tier
+ALTER TABLE projects
+ADD COLUMN container_limits JSON;
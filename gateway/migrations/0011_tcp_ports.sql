@@ -0,0 +1,5 @@
+-- Ports of the gateway that forward raw TCP connections to a project
+CREATE TABLE IF NOT EXISTS tcp_ports (
+  port INTEGER PRIMARY KEY,
+  project_id TEXT NOT NULL UNIQUE REFERENCES projects (project_id)
+);
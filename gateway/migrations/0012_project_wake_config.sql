@@ -0,0 +1,3 @@
+-- How requests to an idle project are handled while it wakes up, defaults are used when not set
+ALTER TABLE projects
+ADD COLUMN wake_config JSON;
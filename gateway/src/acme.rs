@@ -2,7 +2,7 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
 
-use fqdn::FQDN;
+use fqdn::{Fqdn, FQDN};
 use http::StatusCode;
 use instant_acme::{
     Account, AccountCredentials, Authorization, AuthorizationStatus, Challenge, ChallengeType,
@@ -15,34 +15,83 @@ use tokio::sync::Mutex;
 use tokio::time::sleep;
 use tracing::{error, trace, warn};
 
+use crate::dns::Dns01Provider;
+
 const MAX_RETRIES: usize = 15;
 const MAX_RETRIES_CERTIFICATE_FETCHING: usize = 5;
 
+/// How long to give a human to insert a DNS-01 challenge record when no [Dns01Provider] is set up
+const MANUAL_DNS01_DELAY: Duration = Duration::from_secs(60);
+
 #[derive(Debug, Eq, PartialEq)]
 pub struct CustomDomain {
     pub fqdn: FQDN,
     pub project_name: ProjectName,
     pub certificate: String,
     pub private_key: String,
+    /// Whether the certificate also covers the subdomains of `fqdn`, which are then routed to the project too
+    pub wildcard: bool,
+    /// Why the last automatic renewal of the certificate failed, if it did
+    pub renewal_error: Option<String>,
 }
 
-/// An ACME client implementation that completes Http01 challenges
+/// An ACME client implementation that completes Http01 challenges, and Dns01 challenges through a [Dns01Provider]
 /// It is safe to clone this type as it functions as a singleton
 #[derive(Clone, Default)]
-pub struct AcmeClient(Arc<Mutex<HashMap<String, KeyAuthorization>>>);
+pub struct AcmeClient {
+    http01: Arc<Mutex<HashMap<String, KeyAuthorization>>>,
+    dns01: Option<Dns01>,
+}
+
+#[derive(Clone)]
+struct Dns01 {
+    provider: Arc<dyn Dns01Provider>,
+    propagation_delay: Duration,
+}
+
+/// A challenge whose response is published, waiting for the ACME server to check it
+struct PendingChallenge {
+    url: String,
+    response: ChallengeResponse,
+}
+
+enum ChallengeResponse {
+    Http01 { token: String },
+    Dns01 { domain: String, value: String },
+    ManualDns01,
+}
 
 impl AcmeClient {
     pub fn new() -> Self {
         Self::default()
     }
 
+    /// Complete Dns01 challenges by publishing their records with `provider`, waiting `propagation_delay` for the
+    /// records to reach all the authoritative servers of a domain
+    pub fn with_dns01_provider(
+        mut self,
+        provider: impl Dns01Provider + 'static,
+        propagation_delay: Duration,
+    ) -> Self {
+        self.dns01 = Some(Dns01 {
+            provider: Arc::new(provider),
+            propagation_delay,
+        });
+        self
+    }
+
+    /// Whether Dns01 challenges are completed without a human, which wildcard custom domains need
+    pub fn automates_dns01(&self) -> bool {
+        self.dns01.is_some()
+    }
+
     async fn add_http01_challenge_authorization(&self, token: String, key: KeyAuthorization) {
         trace!(token, "saving acme http01 challenge");
-        self.0.lock().await.insert(token, key);
+        self.http01.lock().await.insert(token, key);
     }
 
     pub async fn get_http01_challenge_authorization(&self, token: &str) -> Option<String> {
-        self.0
+        self.http01
             .lock()
             .await
             .get(token)
@@ -51,7 +100,7 @@ impl AcmeClient {
 
     async fn remove_http01_challenge_authorization(&self, token: &str) {
         trace!(token, "removing acme http01 challenge");
-        self.0.lock().await.remove(token);
+        self.http01.lock().await.remove(token);
     }
 
     /// Create a new ACME account that can be restored by using the deserialization
@@ -92,20 +141,23 @@ impl AcmeClient {
         Ok(credentials)
     }
 
-    /// Create an ACME-signed certificate and return it and its
+    /// Create an ACME-signed certificate for all the `identifiers` and return it and its
     /// associated PEM-encoded private key
     pub async fn create_certificate(
         &self,
-        identifier: &str,
+        identifiers: &[String],
         challenge_type: ChallengeType,
         credentials: AccountCredentials<'_>,
     ) -> Result<(String, String), AcmeClientError> {
-        trace!(identifier, "requesting acme certificate");
+        trace!(?identifiers, "requesting acme certificate");
 
         let mut order = AccountWrapper::from(credentials)
             .0
             .new_order(&NewOrder {
-                identifiers: &[Identifier::Dns(identifier.to_string())],
+                identifiers: &identifiers
+                    .iter()
+                    .map(|identifier| Identifier::Dns(identifier.clone()))
+                    .collect::<Vec<_>>(),
             })
             .await
             .map_err(|error| {
@@ -124,17 +176,13 @@ impl AcmeClient {
             AcmeClientError::AuthorizationCreation
         })?;
 
-        // There should only ever be 1 authorization as we only provide 1 domain at a time
-        debug_assert!(authorizations.len() == 1);
-        let authorization = &authorizations[0];
-
-        trace!(?authorization, "got authorization");
+        trace!(?authorizations, "got authorizations");
 
-        self.complete_challenge(challenge_type, authorization, &mut order)
+        self.complete_challenges(challenge_type, &authorizations, &mut order)
             .await?;
 
         let certificate = {
-            let mut params = CertificateParams::new(vec![identifier.to_owned()]);
+            let mut params = CertificateParams::new(identifiers.to_vec());
             params.distinguished_name = DistinguishedName::new();
             Certificate::from_params(params).map_err(|error| {
                 error!(
@@ -181,6 +229,30 @@ impl AcmeClient {
         ))
     }
 
+    /// Create the certificate of a custom domain, which also covers its subdomains if it is a `wildcard`
+    pub async fn create_custom_domain_certificate(
+        &self,
+        fqdn: &Fqdn,
+        wildcard: bool,
+        credentials: AccountCredentials<'_>,
+    ) -> Result<(String, String), AcmeClientError> {
+        if wildcard {
+            // Wildcard identifiers can only be validated with Dns01 challenges
+            if !self.automates_dns01() {
+                return Err(AcmeClientError::Dns01NotAutomated);
+            }
+            self.create_certificate(
+                &[fqdn.to_string(), format!("*.{fqdn}")],
+                ChallengeType::Dns01,
+                credentials,
+            )
+            .await
+        } else {
+            self.create_certificate(&[fqdn.to_string()], ChallengeType::Http01, credentials)
+                .await
+        }
+    }
+
     fn find_challenge(
         ty: ChallengeType,
         authorization: &Authorization,
@@ -193,7 +265,8 @@ impl AcmeClient {
                 let error = AcmeClientError::MissingChallenge;
                 error!(
                     error = &error as &dyn std::error::Error,
-                    "http-01 challenge not found"
+                    challenge_type = ?ty,
+                    "challenge not found"
                 );
                 error
             })
@@ -244,86 +317,152 @@ impl AcmeClient {
         Ok(())
     }
 
-    async fn complete_challenge(
+    /// Publish the responses to the challenges of all the authorizations, and wait for the ACME server to check them
+    async fn complete_challenges(
         &self,
         ty: ChallengeType,
-        authorization: &Authorization,
+        authorizations: &[Authorization],
         order: &mut Order,
     ) -> Result<(), AcmeClientError> {
-        // Don't complete challenge for orders that are already valid
-        if let AuthorizationStatus::Valid = authorization.status {
-            return Ok(());
-        }
-        let challenge = Self::find_challenge(ty, authorization)?;
-        match ty {
-            ChallengeType::Http01 => self.complete_http01_challenge(challenge, order).await,
-            ChallengeType::Dns01 => {
-                self.complete_dns01_challenge(&authorization.identifier, challenge, order)
-                    .await
-            }
-            _ => Err(AcmeClientError::ChallengeNotSupported),
+        let mut pending = Vec::new();
+        let result = match self
+            .publish_challenges(ty, authorizations, order, &mut pending)
+            .await
+        {
+            Ok(()) => self.validate_challenges(&pending, order).await,
+            Err(error) => Err(error),
+        };
+
+        for challenge in pending {
+            self.remove_challenge(challenge).await;
         }
+
+        result
     }
 
-    async fn complete_dns01_challenge(
+    async fn publish_challenges(
         &self,
-        identifier: &Identifier,
-        challenge: &Challenge,
+        ty: ChallengeType,
+        authorizations: &[Authorization],
         order: &mut Order,
+        pending: &mut Vec<PendingChallenge>,
     ) -> Result<(), AcmeClientError> {
-        let Identifier::Dns(domain) = identifier;
-
-        let digest = order.key_authorization(challenge).dns_value();
-        warn!("dns-01 challenge: _acme-challenge.{domain} 300 IN TXT \"{digest}\"");
-
-        // Wait 60 secs to insert the record manually and for it to
-        // propagate before moving on
-        sleep(Duration::from_secs(60)).await;
+        for authorization in authorizations {
+            // Don't complete challenges for authorizations that are already valid
+            if let AuthorizationStatus::Valid = authorization.status {
+                continue;
+            }
+            let challenge = Self::find_challenge(ty, authorization)?;
+            trace!(?challenge, "will complete challenge");
+
+            let response = match ty {
+                ChallengeType::Http01 => {
+                    self.add_http01_challenge_authorization(
+                        challenge.token.clone(),
+                        order.key_authorization(challenge),
+                    )
+                    .await;
+                    ChallengeResponse::Http01 {
+                        token: challenge.token.clone(),
+                    }
+                }
+                ChallengeType::Dns01 => {
+                    let Identifier::Dns(domain) = &authorization.identifier;
+                    let value = order.key_authorization(challenge).dns_value();
+
+                    match &self.dns01 {
+                        Some(Dns01 { provider, .. }) => {
+                            provider
+                                .set_challenge(domain, &value)
+                                .await
+                                .map_err(|error| {
+                                    error!(
+                                        error = &error as &dyn std::error::Error,
+                                        "failed to publish dns-01 challenge record"
+                                    );
+                                    AcmeClientError::DnsUpdate
+                                })?;
+                            ChallengeResponse::Dns01 {
+                                domain: domain.clone(),
+                                value,
+                            }
+                        }
+                        None => {
+                            warn!(
+                                "dns-01 challenge: _acme-challenge.{domain} 300 IN TXT \"{value}\""
+                            );
+                            ChallengeResponse::ManualDns01
+                        }
+                    }
+                }
+                _ => return Err(AcmeClientError::ChallengeNotSupported),
+            };
 
-        order
-            .set_challenge_ready(&challenge.url)
-            .await
-            .map_err(|error| {
-                error!(
-                    error = &error as &dyn std::error::Error,
-                    "failed to mark challenge as ready"
-                );
-                AcmeClientError::SetReadyFailed
-            })?;
+            pending.push(PendingChallenge {
+                url: challenge.url.clone(),
+                response,
+            });
+        }
 
-        self.wait_for_termination(order).await
+        Ok(())
     }
 
-    async fn complete_http01_challenge(
+    async fn validate_challenges(
         &self,
-        challenge: &Challenge,
+        pending: &[PendingChallenge],
         order: &mut Order,
     ) -> Result<(), AcmeClientError> {
-        trace!(?challenge, "will complete challenge");
-
-        self.add_http01_challenge_authorization(
-            challenge.token.clone(),
-            order.key_authorization(challenge),
-        )
-        .await;
-
-        order
-            .set_challenge_ready(&challenge.url)
-            .await
-            .map_err(|error| {
-                error!(
-                    error = &error as &dyn std::error::Error,
-                    "failed to mark challenge as ready"
-                );
-                AcmeClientError::SetReadyFailed
-            })?;
+        if pending.is_empty() {
+            return Ok(());
+        }
 
-        let res = self.wait_for_termination(order).await;
+        // Give the DNS records time to propagate before the ACME server looks them up
+        let delay = pending
+            .iter()
+            .map(|challenge| match (&challenge.response, &self.dns01) {
+                (ChallengeResponse::Dns01 { .. }, Some(dns01)) => dns01.propagation_delay,
+                // Wait to insert the record manually and for it to propagate
+                (ChallengeResponse::ManualDns01, _) => MANUAL_DNS01_DELAY,
+                _ => Duration::ZERO,
+            })
+            .max()
+            .unwrap_or_default();
+        sleep(delay).await;
+
+        for challenge in pending {
+            order
+                .set_challenge_ready(&challenge.url)
+                .await
+                .map_err(|error| {
+                    error!(
+                        error = &error as &dyn std::error::Error,
+                        "failed to mark challenge as ready"
+                    );
+                    AcmeClientError::SetReadyFailed
+                })?;
+        }
 
-        self.remove_http01_challenge_authorization(&challenge.token)
-            .await;
+        self.wait_for_termination(order).await
+    }
 
-        res
+    async fn remove_challenge(&self, challenge: PendingChallenge) {
+        match challenge.response {
+            ChallengeResponse::Http01 { token } => {
+                self.remove_http01_challenge_authorization(&token).await
+            }
+            ChallengeResponse::Dns01 { domain, value } => {
+                let Some(Dns01 { provider, .. }) = &self.dns01 else {
+                    return;
+                };
+                if let Err(error) = provider.clear_challenge(&domain, &value).await {
+                    warn!(
+                        error = &error as &dyn std::error::Error,
+                        "failed to remove dns-01 challenge record"
+                    );
+                }
+            }
+            ChallengeResponse::ManualDns01 => {}
+        }
     }
 }
 
@@ -358,6 +497,8 @@ pub enum AcmeClientError {
     OrderFinalizing,
     MissingChallenge,
     ChallengeNotSupported,
+    Dns01NotAutomated,
+    DnsUpdate,
     Serializing,
     SetReadyFailed,
 }
@@ -1,4 +1,3 @@
-use std::io::Cursor;
 use std::net::SocketAddr;
 use std::ops::Sub;
 use std::sync::Arc;
@@ -10,13 +9,13 @@ use axum::handler::Handler;
 use axum::http::Request;
 use axum::middleware::{self, from_extractor};
 use axum::response::Response;
-use axum::routing::{any, delete, get, post};
+use axum::routing::{any, delete, get, post, put};
 use axum::{Json as AxumJson, Router};
 use fqdn::FQDN;
 use futures::Future;
 use http::header::AUTHORIZATION;
 use http::{request, HeaderValue, Method, StatusCode, Uri};
-use instant_acme::{AccountCredentials, ChallengeType};
+use instant_acme::AccountCredentials;
 use serde::{Deserialize, Serialize};
 use shuttle_backends::auth::{AuthPublicKey, JwtAuthenticationLayer, ScopedLayer};
 use shuttle_backends::axum::CustomErrorPath;
@@ -33,7 +32,7 @@ use shuttle_common::models::error::{
 };
 use shuttle_common::models::{admin::ProjectResponse, project, stats};
 use shuttle_common::models::{service, team};
-use shuttle_common::{deployment, VersionInfo};
+use shuttle_common::{certificate::CertificateResponse, deployment, VersionInfo};
 use shuttle_proto::provisioner::provisioner_client::ProvisionerClient;
 use shuttle_proto::provisioner::Ping;
 use tokio::sync::mpsc::Sender;
@@ -54,7 +53,10 @@ use crate::api::tracing::project_name_tracing_layer;
 use crate::auth::ScopedUser;
 use crate::service::{ContainerSettings, GatewayService};
 use crate::task::{self, BoxedTask};
-use crate::tls::{GatewayCertResolver, RENEWAL_VALIDITY_THRESHOLD_IN_DAYS};
+use crate::tcp::TcpPorts;
+use crate::tls::{
+    self, CertificateDetails, GatewayCertResolver, RENEWAL_VALIDITY_THRESHOLD_IN_DAYS,
+};
 use crate::worker::WORKER_QUEUE_SIZE;
 use crate::{DockerContext, AUTH_CLIENT};
 
@@ -112,6 +114,8 @@ async fn get_project(
 ) -> Result<AxumJson<project::Response>, ApiError> {
     let project = service.find_project_by_name(&scope).await?;
     let idle_minutes = project.state.idle_minutes();
+    let container_limits = project.state.container_limits();
+    let tcp_port = service.tcp_port(&scope).await?;
     let owner = service
         .permit_client
         .get_project_owner(&claim.sub, &project.id)
@@ -129,6 +133,8 @@ async fn get_project(
         idle_minutes,
         owner,
         is_admin,
+        container_limits,
+        tcp_port,
     };
 
     Ok(AxumJson(response))
@@ -156,6 +162,8 @@ async fn get_projects_list(
         }
         let project = service.find_project_by_id(&proj_id).await?;
         let idle_minutes = project.state.idle_minutes();
+        let container_limits = project.state.container_limits();
+        let tcp_port = service.tcp_port(&project.name).await?;
         let owner = service
             .permit_client
             .get_project_owner(&sub, &proj_id)
@@ -173,6 +181,8 @@ async fn get_projects_list(
             idle_minutes,
             owner,
             is_admin,
+            container_limits,
+            tcp_port,
         };
         projects.push(response);
     }
@@ -205,6 +215,12 @@ async fn create_project(
         service.has_capacity(is_cch_project, &claim.tier).await?;
     }
 
+    // Limits set by an admin are kept when the project is destroyed and created again
+    let container_limits = service
+        .container_limits_override(&project_name)
+        .await?
+        .unwrap_or_else(|| claim.tier.into());
+
     let project = service
         .create_project(
             project_name.clone(),
@@ -216,9 +232,16 @@ async fn create_project(
             } else {
                 config.idle_minutes
             },
+            container_limits,
         )
         .await?;
+    if let Some(wake) = config.wake {
+        service.set_wake_config(&project_name, wake).await?;
+    }
     let idle_minutes = project.state.idle_minutes();
+    let container_limits = project.state.container_limits();
+    // Kept when the project is destroyed and created again
+    let tcp_port = service.tcp_port(&project_name).await?;
 
     service
         .new_task()
@@ -235,6 +258,8 @@ async fn create_project(
         idle_minutes,
         owner: project::Owner::User(claim.sub),
         is_admin: true,
+        container_limits,
+        tcp_port,
     };
 
     Ok(AxumJson(response))
@@ -253,6 +278,8 @@ async fn destroy_project(
 ) -> Result<AxumJson<project::Response>, ApiError> {
     let project = service.find_project_by_name(&project_name).await?;
     let idle_minutes = project.state.idle_minutes();
+    let container_limits = project.state.container_limits();
+    let tcp_port = service.tcp_port(&project_name).await?;
     let owner = service
         .permit_client
         .get_project_owner(&claim.sub, &project.id)
@@ -270,6 +297,8 @@ async fn destroy_project(
         idle_minutes,
         owner,
         is_admin,
+        container_limits,
+        tcp_port,
     };
 
     if response.state == shuttle_common::models::project::State::Destroyed {
@@ -359,6 +388,7 @@ async fn delete_project(
             deployment::State::Running
                 | deployment::State::Completed
                 | deployment::State::Crashed
+                | deployment::State::Restarting
                 | deployment::State::Stopped
         )
     });
@@ -391,10 +421,15 @@ async fn delete_project(
     for resource in resources {
         info!(?resource, "deleting resource");
         let resource_type = resource.r#type.to_string();
-        let res = project_caller.delete_resource(&resource_type).await?;
+        let res = project_caller
+            .delete_resource(&resource_type, resource.name.as_deref())
+            .await?;
 
         if res.status() != StatusCode::OK {
-            delete_fails.push(resource_type)
+            delete_fails.push(match resource.name {
+                Some(name) => format!("{resource_type} ({name})"),
+                None => resource_type,
+            })
         }
     }
 
@@ -568,6 +603,8 @@ async fn get_team_projects(
         }
         let project = service.find_project_by_id(&proj_id).await?;
         let idle_minutes = project.state.idle_minutes();
+        let container_limits = project.state.container_limits();
+        let tcp_port = service.tcp_port(&project.name).await?;
         let owner = service
             .permit_client
             .get_project_owner(&sub, &proj_id)
@@ -585,6 +622,8 @@ async fn get_team_projects(
             idle_minutes,
             owner,
             is_admin,
+            container_limits,
+            tcp_port,
         });
     }
 
@@ -833,25 +872,98 @@ async fn create_acme_account(
     Ok(AxumJson(res))
 }
 
-#[instrument(skip_all, fields(shuttle.project.name = %project_name, %fqdn))]
+#[instrument(skip_all, fields(shuttle.project.name = %scope))]
+async fn enable_project_tcp(
+    State(RouterState { service, .. }): State<RouterState>,
+    Extension(tcp_ports): Extension<Arc<TcpPorts>>,
+    ScopedUser { scope, .. }: ScopedUser,
+) -> Result<AxumJson<u16>, ApiError> {
+    let port = service.allocate_tcp_port(&scope, tcp_ports.range()).await?;
+    tcp_ports.open(port).await.map_err(|error| {
+        error!(
+            error = &error as &dyn std::error::Error,
+            port, "failed to open TCP port"
+        );
+        ApiError::internal("Failed to open the TCP port")
+    })?;
+
+    Ok(AxumJson(port))
+}
+
+#[instrument(skip_all, fields(shuttle.project.name = %scope))]
+async fn disable_project_tcp(
+    State(RouterState { service, .. }): State<RouterState>,
+    Extension(tcp_ports): Extension<Arc<TcpPorts>>,
+    ScopedUser { scope, .. }: ScopedUser,
+) -> Result<AxumJson<Option<u16>>, ApiError> {
+    let port = service.release_tcp_port(&scope).await?;
+    if let Some(port) = port {
+        tcp_ports.close(port);
+    }
+
+    Ok(AxumJson(port))
+}
+
+#[instrument(skip_all, fields(shuttle.project.name = %scope))]
+async fn get_certificates(
+    State(RouterState { service, .. }): State<RouterState>,
+    ScopedUser { scope, .. }: ScopedUser,
+) -> Result<AxumJson<Vec<CertificateResponse>>, ApiError> {
+    let certificates = service
+        .iter_custom_domains_for_project(scope.as_str())
+        .await?
+        .map(
+            |CustomDomain {
+                 fqdn,
+                 certificate,
+                 wildcard,
+                 renewal_error,
+                 ..
+             }| {
+                let details = CertificateDetails::from_pem(&certificate)?;
+                let subject = if wildcard {
+                    format!("{fqdn}, *.{fqdn}")
+                } else {
+                    fqdn.to_string()
+                };
+
+                Ok(CertificateResponse {
+                    subject,
+                    serial_hex: details.serial_hex,
+                    not_after: details.not_after.to_string(),
+                    renewal_error,
+                })
+            },
+        )
+        .collect::<Result<_, tls::Error>>()?;
+
+    Ok(AxumJson(certificates))
+}
+
+#[derive(Deserialize)]
+struct CustomDomainParams {
+    /// Also serve all the subdomains of the custom domain
+    wildcard: Option<bool>,
+}
+
+#[instrument(skip_all, fields(shuttle.project.name = %project_name, %fqdn, ?wildcard))]
 async fn request_custom_domain_acme_certificate(
     State(RouterState { service, .. }): State<RouterState>,
     Extension(acme_client): Extension<AcmeClient>,
     Extension(resolver): Extension<Arc<GatewayCertResolver>>,
     CustomErrorPath((project_name, fqdn)): CustomErrorPath<(ProjectName, String)>,
+    Query(CustomDomainParams { wildcard }): Query<CustomDomainParams>,
     AxumJson(credentials): AxumJson<AccountCredentials<'_>>,
 ) -> Result<String, ApiError> {
     let fqdn: FQDN = fqdn.parse().map_err(|_| InvalidCustomDomain)?;
+    let wildcard = wildcard.unwrap_or_default();
 
     let (certs, private_key) = service
-        .create_custom_domain_certificate(&fqdn, &acme_client, &project_name, credentials)
+        .create_custom_domain_certificate(&fqdn, &acme_client, &project_name, credentials, wildcard)
         .await?;
 
-    let mut buf = Vec::new();
-    buf.extend(certs.as_bytes());
-    buf.extend(private_key.as_bytes());
     resolver
-        .serve_pem(&fqdn.to_string(), Cursor::new(buf))
+        .serve_custom_domain_pem(&fqdn, wildcard, &certs, &private_key)
         .await?;
     Ok(format!(
         r#""New certificate created for {} project.""#,
@@ -870,9 +982,12 @@ async fn renew_custom_domain_acme_certificate(
     let fqdn: FQDN = fqdn.parse().map_err(|_| InvalidCustomDomain)?;
     // Try retrieve the current certificate if any.
     match service.project_details_for_custom_domain(&fqdn).await {
+        // Renewing a subdomain of a wildcard custom domain renews the wildcard custom domain
         Ok(CustomDomain {
+            fqdn,
             mut certificate,
             private_key,
+            wildcard,
             ..
         }) => {
             certificate.push('\n');
@@ -900,21 +1015,24 @@ async fn renew_custom_domain_acme_certificate(
             // Renew only when the difference is `None` (meaning certificate expired) or we're within the last 30 days of validity.
             if diff.whole_days() <= RENEWAL_VALIDITY_THRESHOLD_IN_DAYS {
                 return match acme_client
-                    .create_certificate(&fqdn.to_string(), ChallengeType::Http01, credentials)
+                    .create_custom_domain_certificate(&fqdn, wildcard, credentials)
                     .await
                 {
                     // If successfully created, save the certificate in memory to be
                     // served in the future.
                     Ok((certs, private_key)) => {
                         service
-                            .create_custom_domain(&project_name, &fqdn, &certs, &private_key)
+                            .create_custom_domain(
+                                &project_name,
+                                &fqdn,
+                                &certs,
+                                &private_key,
+                                wildcard,
+                            )
                             .await?;
 
-                        let mut buf = Vec::new();
-                        buf.extend(certs.as_bytes());
-                        buf.extend(private_key.as_bytes());
                         resolver
-                            .serve_pem(&fqdn.to_string(), Cursor::new(buf))
+                            .serve_custom_domain_pem(&fqdn, wildcard, &certs, &private_key)
                             .await?;
                         Ok(format!(
                             r#""Certificate renewed for {} project.""#,
@@ -997,6 +1115,19 @@ async fn get_projects(
     Ok(AxumJson(projects))
 }
 
+#[instrument(skip(service, container_limits), fields(shuttle.project.name = %project_name))]
+async fn set_project_container_limits(
+    State(RouterState { service, .. }): State<RouterState>,
+    CustomErrorPath(project_name): CustomErrorPath<ProjectName>,
+    AxumJson(container_limits): AxumJson<Option<project::ContainerLimits>>,
+) -> Result<(), ApiError> {
+    service
+        .set_container_limits_override(&project_name, container_limits)
+        .await?;
+
+    Ok(())
+}
+
 async fn change_project_owner(
     State(RouterState { service, .. }): State<RouterState>,
     Path((project_name, new_user_id)): Path<(String, String)>,
@@ -1063,6 +1194,18 @@ impl ApiBuilder {
         self
     }
 
+    pub fn with_tcp_ports(mut self, tcp_ports: Arc<TcpPorts>) -> Self {
+        self.router = self
+            .router
+            .route(
+                "/projects/:project_name/tcp",
+                post(enable_project_tcp.layer(ScopedLayer::new(vec![Scope::ProjectWrite])))
+                    .delete(disable_project_tcp.layer(ScopedLayer::new(vec![Scope::ProjectWrite]))),
+            )
+            .layer(Extension(tcp_ports));
+        self
+    }
+
     pub fn with_service(mut self, service: Arc<GatewayService>) -> Self {
         self.service = Some(service);
         self
@@ -1106,6 +1249,10 @@ impl ApiBuilder {
                 "/projects/change-owner/:project_name/:new_user_id",
                 get(change_project_owner),
             )
+            .route(
+                "/projects/:project_name/limits",
+                put(set_project_container_limits),
+            )
             .route("/revive", post(revive_projects))
             .route("/destroy", post(destroy_projects))
             .route("/idle-cch", post(idle_cch_projects))
@@ -1126,6 +1273,10 @@ impl ApiBuilder {
                 delete(delete_project.layer(ScopedLayer::new(vec![Scope::ProjectWrite]))),
             )
             .route("/projects/name/:project_name", get(check_project_name))
+            .route(
+                "/projects/:project_name/certificates",
+                get(get_certificates.layer(ScopedLayer::new(vec![Scope::Project]))),
+            )
             .route(
                 // catch these deployer endpoints for extra metrics or processing before/after being proxied
                 "/projects/:project_name/services/:service_name",
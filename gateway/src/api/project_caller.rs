@@ -185,11 +185,23 @@ impl ProjectCaller {
     }
 
     /// Delete a resource used by the project
-    pub async fn delete_resource(&self, r#type: &str) -> Result<Response<Body>, Error> {
+    pub async fn delete_resource(
+        &self,
+        r#type: &str,
+        name: Option<&str>,
+    ) -> Result<Response<Body>, Error> {
         let project_name = &self.project_name;
+        let query = name
+            .map(|name| {
+                let query = url::form_urlencoded::Serializer::new(String::new())
+                    .append_pair("name", name)
+                    .finish();
+                format!("?{query}")
+            })
+            .unwrap_or_default();
 
         self.call(
-            &format!("/projects/{project_name}/services/{project_name}/resources/{type}"),
+            &format!("/projects/{project_name}/services/{project_name}/resources/{type}{query}"),
             Method::DELETE,
         )
         .await
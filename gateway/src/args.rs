@@ -1,4 +1,4 @@
-use std::{net::SocketAddr, path::PathBuf};
+use std::{net::SocketAddr, ops::RangeInclusive, path::PathBuf};
 
 use clap::{Parser, Subcommand, ValueEnum};
 use fqdn::FQDN;
@@ -43,10 +43,70 @@ pub struct StartArgs {
     /// The origin to allow CORS requests from
     #[arg(long, default_value = "https://console.shuttle.rs")]
     pub cors_origin: String,
+    /// Renew custom domain certificates automatically when they expire within this many days
+    #[arg(long, default_value = "30")]
+    pub certificate_renewal_window: i64,
+    /// How many requests can wait for an idle project to wake up at once. The ones over this are told to retry later
+    #[arg(long, default_value = "100")]
+    pub wake_queue_size: usize,
     #[command(flatten)]
     pub context: ServiceArgs,
     #[command(flatten)]
     pub permit: PermitArgs,
+    #[command(flatten)]
+    pub dns01: Dns01Args,
+    #[command(flatten)]
+    pub tcp: TcpArgs,
+}
+
+/// Completes the DNS-01 challenges of ACME with RFC 2136 dynamic updates, instead of waiting for a human
+#[derive(clap::Args, Debug, Clone, Default)]
+pub struct Dns01Args {
+    /// Address of the DNS server to send the challenge records to
+    #[arg(long, requires = "rfc2136_zone")]
+    pub rfc2136_server: Option<SocketAddr>,
+    /// Zone the challenge records are put in. Domains outside of it should have their
+    /// `_acme-challenge` record be a CNAME to `<domain>.<zone>`
+    #[arg(long)]
+    pub rfc2136_zone: Option<String>,
+    /// Name of the TSIG key to sign the updates with
+    #[arg(long, requires = "rfc2136_tsig_secret")]
+    pub rfc2136_tsig_key_name: Option<String>,
+    /// Base64 encoded secret of the HMAC-SHA256 TSIG key
+    #[arg(long, requires = "rfc2136_tsig_key_name")]
+    pub rfc2136_tsig_secret: Option<String>,
+    /// Seconds to wait for the challenge records to reach all the name servers of a domain
+    #[arg(long, default_value = "30")]
+    pub dns01_propagation_delay: u64,
+}
+
+/// Exposes the services of projects over raw TCP, for protocols other than HTTP. UDP is not forwarded.
+#[derive(clap::Args, Debug, Clone, Default)]
+pub struct TcpArgs {
+    /// Ports to give to projects that expose raw TCP, like `10000-10999`. They are opened on the IP of the user proxy
+    #[arg(long, value_parser = parse_port_range)]
+    pub tcp_ports: Option<RangeInclusive<u16>>,
+    /// Address to accept TCP connections with TLS on. They are decrypted with the certificates of the user proxy,
+    /// and forwarded to the project named by their SNI
+    #[arg(long)]
+    pub tcp_tls: Option<SocketAddr>,
+}
+
+fn parse_port_range(s: &str) -> Result<RangeInclusive<u16>, String> {
+    let (start, end) = s
+        .split_once('-')
+        .ok_or_else(|| "port range should look like `<first>-<last>`".to_string())?;
+    let start: u16 = start
+        .parse()
+        .map_err(|error| format!("invalid first port: {error}"))?;
+    let end: u16 = end
+        .parse()
+        .map_err(|error| format!("invalid last port: {error}"))?;
+    if start > end {
+        return Err("the first port should not be after the last one".to_string());
+    }
+
+    Ok(start..=end)
 }
 
 #[derive(clap::Args, Debug, Clone)]
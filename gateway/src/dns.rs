@@ -0,0 +1,428 @@
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ring::hmac;
+use thiserror::Error;
+use tokio::net::UdpSocket;
+use tokio::time::timeout;
+use tracing::{debug, trace};
+
+const TYPE_SOA: u16 = 6;
+const TYPE_TXT: u16 = 16;
+const TYPE_TSIG: u16 = 250;
+const CLASS_IN: u16 = 1;
+const CLASS_NONE: u16 = 254;
+const CLASS_ANY: u16 = 255;
+const OPCODE_UPDATE: u16 = 5;
+
+const TSIG_ALGORITHM: &str = "hmac-sha256";
+/// Seconds of clock skew that the server allows for signed updates
+const TSIG_FUDGE: u16 = 300;
+
+const CHALLENGE_TTL: u32 = 60;
+const UPDATE_TIMEOUT: Duration = Duration::from_secs(5);
+const UPDATE_ATTEMPTS: usize = 3;
+
+#[derive(Error, Debug)]
+pub enum DnsError {
+    #[error("failed to talk to the DNS server: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("the DNS server did not answer in time")]
+    Timeout,
+    #[error("got an invalid response from the DNS server")]
+    InvalidResponse,
+    #[error("the DNS server rejected the update with {0}")]
+    Rejected(&'static str),
+    #[error("'{0}' is not a valid DNS name")]
+    InvalidName(String),
+    #[error("TXT record value is too long")]
+    InvalidValue,
+    #[error("TSIG secret is not valid base64")]
+    InvalidSecret,
+}
+
+/// Publishes the TXT records that prove control of a domain for ACME DNS-01 challenges
+#[async_trait]
+pub trait Dns01Provider: Send + Sync {
+    /// Publish `value` for the DNS-01 challenge of `domain`
+    async fn set_challenge(&self, domain: &str, value: &str) -> Result<(), DnsError>;
+
+    /// Remove a value that was published by [`Dns01Provider::set_challenge`]
+    async fn clear_challenge(&self, domain: &str, value: &str) -> Result<(), DnsError>;
+}
+
+/// A key to sign DNS updates with, as in the `key` statement of BIND
+pub struct TsigKey {
+    name: String,
+    key: hmac::Key,
+}
+
+impl TsigKey {
+    /// A HMAC-SHA256 key with a base64 encoded `secret`
+    pub fn new(name: &str, secret: &str) -> Result<Self, DnsError> {
+        let name = normalize(name);
+        encode_name(&mut Vec::new(), &name)?;
+        let secret = STANDARD
+            .decode(secret)
+            .map_err(|_| DnsError::InvalidSecret)?;
+
+        Ok(Self {
+            name,
+            key: hmac::Key::new(hmac::HMAC_SHA256, &secret),
+        })
+    }
+}
+
+/// Completes DNS-01 challenges with RFC 2136 dynamic updates to an authoritative server, such as BIND or Knot.
+///
+/// Domains inside `zone` get their `_acme-challenge` records updated directly. Custom domains outside of it have to
+/// delegate their challenges to the zone with a CNAME from `_acme-challenge.<domain>` to `<domain>.<zone>`.
+pub struct Rfc2136Provider {
+    server: SocketAddr,
+    zone: String,
+    tsig: Option<TsigKey>,
+}
+
+impl Rfc2136Provider {
+    pub fn new(server: SocketAddr, zone: &str) -> Result<Self, DnsError> {
+        let zone = normalize(zone);
+        encode_name(&mut Vec::new(), &zone)?;
+
+        Ok(Self {
+            server,
+            zone,
+            tsig: None,
+        })
+    }
+
+    /// Sign the updates with a TSIG key, which most servers require
+    pub fn with_tsig(mut self, key: TsigKey) -> Self {
+        self.tsig = Some(key);
+        self
+    }
+
+    /// Name of the TXT record for the challenge of `domain`
+    pub fn record_name(&self, domain: &str) -> String {
+        let domain = normalize(domain.trim_start_matches("*."));
+
+        if domain == self.zone || domain.ends_with(&format!(".{}", self.zone)) {
+            format!("_acme-challenge.{domain}")
+        } else {
+            format!("{domain}.{}", self.zone)
+        }
+    }
+
+    async fn update(&self, change: Change<'_>) -> Result<(), DnsError> {
+        let id = rand::random();
+        let mut message = update_message(id, &self.zone, change)?;
+        if let Some(key) = &self.tsig {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs();
+            sign(&mut message, key, now)?;
+        }
+
+        let local: SocketAddr = if self.server.is_ipv4() {
+            (Ipv4Addr::UNSPECIFIED, 0).into()
+        } else {
+            (Ipv6Addr::UNSPECIFIED, 0).into()
+        };
+        let socket = UdpSocket::bind(local).await?;
+        socket.connect(self.server).await?;
+
+        // Updates are idempotent, so they are safe to send again when the answer is lost
+        let mut response = [0; 512];
+        for attempt in 1..=UPDATE_ATTEMPTS {
+            socket.send(&message).await?;
+            match timeout(UPDATE_TIMEOUT, socket.recv(&mut response)).await {
+                Ok(len) => return check_response(id, &response[..len?]),
+                Err(_) => trace!(attempt, "dns update timed out"),
+            }
+        }
+
+        Err(DnsError::Timeout)
+    }
+}
+
+#[async_trait]
+impl Dns01Provider for Rfc2136Provider {
+    async fn set_challenge(&self, domain: &str, value: &str) -> Result<(), DnsError> {
+        let name = self.record_name(domain);
+        debug!(name, "adding dns-01 challenge record");
+
+        self.update(Change::Add { name: &name, value }).await
+    }
+
+    async fn clear_challenge(&self, domain: &str, value: &str) -> Result<(), DnsError> {
+        let name = self.record_name(domain);
+        debug!(name, "removing dns-01 challenge record");
+
+        self.update(Change::Delete { name: &name, value }).await
+    }
+}
+
+enum Change<'a> {
+    Add { name: &'a str, value: &'a str },
+    Delete { name: &'a str, value: &'a str },
+}
+
+fn normalize(name: &str) -> String {
+    name.trim_end_matches('.').to_ascii_lowercase()
+}
+
+fn encode_name(buf: &mut Vec<u8>, name: &str) -> Result<(), DnsError> {
+    let start = buf.len();
+    for label in name.split('.').filter(|label| !label.is_empty()) {
+        if label.len() > 63 {
+            return Err(DnsError::InvalidName(name.to_owned()));
+        }
+        buf.push(label.len() as u8);
+        buf.extend(label.as_bytes());
+    }
+    buf.push(0);
+
+    if buf.len() - start > 255 {
+        return Err(DnsError::InvalidName(name.to_owned()));
+    }
+
+    Ok(())
+}
+
+/// An RFC 2136 update message that adds or deletes a single TXT record
+fn update_message(id: u16, zone: &str, change: Change) -> Result<Vec<u8>, DnsError> {
+    let (name, value, class, ttl) = match change {
+        Change::Add { name, value } => (name, value, CLASS_IN, CHALLENGE_TTL),
+        // Deleting a record from an RRset is done with class NONE
+        Change::Delete { name, value } => (name, value, CLASS_NONE, 0),
+    };
+    if value.len() > 255 {
+        return Err(DnsError::InvalidValue);
+    }
+
+    let mut message = Vec::with_capacity(128);
+    message.extend(id.to_be_bytes());
+    message.extend((OPCODE_UPDATE << 11).to_be_bytes());
+    // One zone, no prerequisites, one update and no additional records
+    for count in [1u16, 0, 1, 0] {
+        message.extend(count.to_be_bytes());
+    }
+
+    encode_name(&mut message, zone)?;
+    message.extend(TYPE_SOA.to_be_bytes());
+    message.extend(CLASS_IN.to_be_bytes());
+
+    encode_name(&mut message, name)?;
+    message.extend(TYPE_TXT.to_be_bytes());
+    message.extend(class.to_be_bytes());
+    message.extend(ttl.to_be_bytes());
+    message.extend((value.len() as u16 + 1).to_be_bytes());
+    message.push(value.len() as u8);
+    message.extend(value.as_bytes());
+
+    Ok(message)
+}
+
+/// Append a TSIG record (RFC 8945) that signs the message
+fn sign(message: &mut Vec<u8>, key: &TsigKey, time_signed: u64) -> Result<(), DnsError> {
+    let time_signed = &time_signed.to_be_bytes()[2..];
+
+    // The MAC covers the unsigned message followed by these fields of the TSIG record
+    let mut variables = Vec::new();
+    encode_name(&mut variables, &key.name)?;
+    variables.extend(CLASS_ANY.to_be_bytes());
+    variables.extend(0u32.to_be_bytes());
+    encode_name(&mut variables, TSIG_ALGORITHM)?;
+    variables.extend(time_signed);
+    variables.extend(TSIG_FUDGE.to_be_bytes());
+    // No error and no other data
+    variables.extend([0; 4]);
+
+    let mut context = hmac::Context::with_key(&key.key);
+    context.update(message);
+    context.update(&variables);
+    let mac = context.sign();
+    let mac = mac.as_ref();
+
+    let mut rdata = Vec::new();
+    encode_name(&mut rdata, TSIG_ALGORITHM)?;
+    rdata.extend(time_signed);
+    rdata.extend(TSIG_FUDGE.to_be_bytes());
+    rdata.extend((mac.len() as u16).to_be_bytes());
+    rdata.extend(mac);
+    // The original id, no error and no other data
+    rdata.extend_from_slice(&message[..2]);
+    rdata.extend([0; 4]);
+
+    encode_name(message, &key.name)?;
+    message.extend(TYPE_TSIG.to_be_bytes());
+    message.extend(CLASS_ANY.to_be_bytes());
+    message.extend(0u32.to_be_bytes());
+    message.extend((rdata.len() as u16).to_be_bytes());
+    message.extend(rdata);
+
+    let additional = u16::from_be_bytes([message[10], message[11]]) + 1;
+    message[10..12].copy_from_slice(&additional.to_be_bytes());
+
+    Ok(())
+}
+
+fn check_response(id: u16, response: &[u8]) -> Result<(), DnsError> {
+    let is_response = response.get(2).is_some_and(|flags| flags & 0x80 != 0);
+    if response.len() < 12 || response[..2] != id.to_be_bytes() || !is_response {
+        return Err(DnsError::InvalidResponse);
+    }
+
+    match response[3] & 0x0f {
+        0 => Ok(()),
+        1 => Err(DnsError::Rejected("FORMERR")),
+        2 => Err(DnsError::Rejected("SERVFAIL")),
+        5 => Err(DnsError::Rejected("REFUSED")),
+        8 => Err(DnsError::Rejected("NXRRSET")),
+        // Also what servers answer when the TSIG signature does not check out
+        9 => Err(DnsError::Rejected("NOTAUTH")),
+        10 => Err(DnsError::Rejected("NOTZONE")),
+        _ => Err(DnsError::Rejected("an error")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    fn read_name(message: &[u8], pos: &mut usize) -> String {
+        let mut labels = Vec::new();
+        loop {
+            let len = message[*pos] as usize;
+            *pos += 1;
+            if len == 0 {
+                return labels.join(".");
+            }
+            labels.push(String::from_utf8(message[*pos..*pos + len].to_vec()).unwrap());
+            *pos += len;
+        }
+    }
+
+    fn read_u16(message: &[u8], pos: usize) -> u16 {
+        u16::from_be_bytes([message[pos], message[pos + 1]])
+    }
+
+    /// A DNS server that checks the signature of updates and keeps the values of the TXT records they change
+    async fn dns_server(secret: &'static [u8]) -> (SocketAddr, Arc<Mutex<Vec<String>>>) {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = socket.local_addr().unwrap();
+        let records = Arc::new(Mutex::new(Vec::new()));
+
+        let server_records = records.clone();
+        tokio::spawn(async move {
+            let key = hmac::Key::new(hmac::HMAC_SHA256, secret);
+            let mut buf = [0; 512];
+            loop {
+                let (len, peer) = socket.recv_from(&mut buf).await.unwrap();
+                let message = &buf[..len];
+
+                // Skip the zone and read the update
+                let mut pos = 12;
+                read_name(message, &mut pos);
+                pos += 4;
+                assert_eq!(
+                    read_name(message, &mut pos),
+                    "_acme-challenge.test.example.com"
+                );
+                let class = read_u16(message, pos + 2);
+                let rdata_len = read_u16(message, pos + 8) as usize;
+                pos += 10;
+                let value = String::from_utf8(message[pos + 1..pos + rdata_len].to_vec()).unwrap();
+                pos += rdata_len;
+
+                // The MAC covers the message without the TSIG record, and then all the TSIG fields except for
+                // its type, the MAC itself and the original id
+                let mut signed = message[..pos].to_vec();
+                signed[11] -= 1;
+                let key_name_start = pos;
+                read_name(message, &mut pos);
+                signed.extend(&message[key_name_start..pos]);
+                signed.extend(&message[pos + 2..pos + 8]);
+                pos += 10;
+                let algorithm_start = pos;
+                read_name(message, &mut pos);
+                pos += 8;
+                signed.extend(&message[algorithm_start..pos]);
+                let mac_len = read_u16(message, pos) as usize;
+                let mac = &message[pos + 2..pos + 2 + mac_len];
+                signed.extend(&message[pos + 2 + mac_len + 2..]);
+
+                let rcode = if hmac::verify(&key, &signed, mac).is_ok() {
+                    let mut records = server_records.lock().unwrap();
+                    match class {
+                        CLASS_IN => records.push(value),
+                        CLASS_NONE => records.retain(|record| *record != value),
+                        _ => unreachable!(),
+                    }
+                    0
+                } else {
+                    9
+                };
+
+                let mut response = message[..12].to_vec();
+                response[2] |= 0x80;
+                response[3] = rcode;
+                socket.send_to(&response, peer).await.unwrap();
+            }
+        });
+
+        (addr, records)
+    }
+
+    #[tokio::test]
+    async fn updates_challenge_records() {
+        let (addr, records) = dns_server(b"secret").await;
+        let provider = Rfc2136Provider::new(addr, "example.com.")
+            .unwrap()
+            .with_tsig(TsigKey::new("shuttle", &STANDARD.encode("secret")).unwrap());
+
+        provider
+            .set_challenge("test.example.com", "abcd")
+            .await
+            .unwrap();
+        provider
+            .set_challenge("test.example.com", "efgh")
+            .await
+            .unwrap();
+        assert_eq!(*records.lock().unwrap(), vec!["abcd", "efgh"]);
+
+        provider
+            .clear_challenge("test.example.com", "abcd")
+            .await
+            .unwrap();
+        assert_eq!(*records.lock().unwrap(), vec!["efgh"]);
+
+        let provider = Rfc2136Provider::new(addr, "example.com")
+            .unwrap()
+            .with_tsig(TsigKey::new("shuttle", &STANDARD.encode("wrong")).unwrap());
+        assert!(matches!(
+            provider.set_challenge("test.example.com", "ijkl").await,
+            Err(DnsError::Rejected("NOTAUTH"))
+        ));
+    }
+
+    #[test]
+    fn delegates_custom_domains() {
+        let provider =
+            Rfc2136Provider::new("127.0.0.1:53".parse().unwrap(), "Example.com").unwrap();
+
+        assert_eq!(
+            provider.record_name("*.example.com"),
+            "_acme-challenge.example.com"
+        );
+        assert_eq!(
+            provider.record_name("api.example.com."),
+            "_acme-challenge.api.example.com"
+        );
+        assert_eq!(provider.record_name("custom.org"), "custom.org.example.com");
+    }
+}
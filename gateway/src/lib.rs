@@ -19,10 +19,12 @@ pub mod acme;
 pub mod api;
 pub mod args;
 pub mod auth;
+pub mod dns;
 pub mod project;
 pub mod proxy;
 pub mod service;
 pub mod task;
+pub mod tcp;
 pub mod tls;
 pub mod worker;
 
@@ -428,6 +430,8 @@ pub mod tests {
                 bouncer,
                 use_tls: UseTls::Disable,
                 cors_origin: "http://localhost:3001".to_string(),
+                certificate_renewal_window: 30,
+                wake_queue_size: 100,
                 context: ServiceArgs {
                     docker_host,
                     image,
@@ -469,6 +473,8 @@ pub mod tests {
                     permit_env: Default::default(),     // TODO: will need mock?
                     permit_api_key: Default::default(), // TODO: will need mock?
                 },
+                dns01: Default::default(),
+                tcp: Default::default(),
             };
 
             let settings = ContainerSettings::builder().from_args(&args.context).await;
@@ -1088,7 +1094,8 @@ pub mod tests {
             .with_service(Arc::clone(&service))
             .with_task_sender(log_out)
             .with_public(world.fqdn())
-            .with_user_proxy_binding_to(world.args.user);
+            .with_user_proxy_binding_to(world.args.user)
+            .with_wake_queue_size(world.args.wake_queue_size);
 
         let _gateway = tokio::spawn(async move {
             tokio::select! {
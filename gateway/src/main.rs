@@ -1,5 +1,4 @@
 use std::collections::BTreeMap;
-use std::io::Cursor;
 use std::path::PathBuf;
 use std::str::FromStr;
 use std::sync::Arc;
@@ -19,9 +18,12 @@ use shuttle_gateway::acme::{AcmeClient, CustomDomain};
 use shuttle_gateway::api::latest::{ApiBuilder, SVC_DEGRADED_THRESHOLD};
 use shuttle_gateway::args::{Args, Commands, UseTls};
 use shuttle_gateway::args::{StartArgs, SyncArgs};
+use shuttle_gateway::dns::{Rfc2136Provider, TsigKey};
 use shuttle_gateway::proxy::UserServiceBuilder;
 use shuttle_gateway::service::{GatewayService, MIGRATIONS};
-use shuttle_gateway::tls::make_tls_acceptor;
+use shuttle_gateway::task::RenewCertificates;
+use shuttle_gateway::tcp::{TcpPorts, TcpProxy};
+use shuttle_gateway::tls::{make_tcp_tls_acceptor, make_tls_acceptor};
 use shuttle_gateway::worker::{Worker, WORKER_QUEUE_SIZE};
 
 #[tokio::main(flavor = "multi_thread")]
@@ -183,7 +185,28 @@ async fn start(
         }
     });
 
-    let acme_client = AcmeClient::new();
+    let mut acme_client = AcmeClient::new();
+    if let Some(server) = args.dns01.rfc2136_server {
+        let zone = args
+            .dns01
+            .rfc2136_zone
+            .as_deref()
+            .expect("a zone to be given with the RFC 2136 server");
+        let mut provider =
+            Rfc2136Provider::new(server, zone).expect("to have a valid RFC 2136 zone");
+        if let (Some(name), Some(secret)) = (
+            &args.dns01.rfc2136_tsig_key_name,
+            &args.dns01.rfc2136_tsig_secret,
+        ) {
+            provider =
+                provider.with_tsig(TsigKey::new(name, secret).expect("to have a valid TSIG key"));
+        }
+        info!(%server, zone, "completing DNS-01 challenges with RFC 2136 updates");
+        acme_client = acme_client.with_dns01_provider(
+            provider,
+            Duration::from_secs(args.dns01.dns01_propagation_delay),
+        );
+    }
 
     let mut api_builder = ApiBuilder::new()
         .with_service(Arc::clone(&gateway))
@@ -191,12 +214,34 @@ async fn start(
         .with_posthog_client(posthog_client)
         .binding_to(args.control);
 
+    let tcp_proxy = Arc::new(TcpProxy::new(
+        gateway.clone(),
+        sender.clone(),
+        args.context.proxy_fqdn.clone(),
+    ));
+    if let Some(range) = args.tcp.tcp_ports {
+        let tcp_ports = Arc::new(TcpPorts::new(tcp_proxy.clone(), args.user.ip(), range));
+
+        // Keep serving the ports that were given to projects before
+        for port in gateway.iter_tcp_ports().await.unwrap() {
+            if let Err(error) = tcp_ports.open(port).await {
+                error!(
+                    error = &error as &dyn std::error::Error,
+                    port, "failed to open TCP port"
+                );
+            }
+        }
+
+        api_builder = api_builder.with_tcp_ports(tcp_ports);
+    }
+
     let mut user_builder = UserServiceBuilder::new()
         .with_service(Arc::clone(&gateway))
         .with_task_sender(sender)
         .with_public(args.context.proxy_fqdn.clone())
         .with_user_proxy_binding_to(args.user)
-        .with_bouncer(args.bouncer);
+        .with_bouncer(args.bouncer)
+        .with_wake_queue_size(args.wake_queue_size);
 
     if let UseTls::Enable = args.use_tls {
         let (resolver, tls_acceptor) = make_tls_acceptor();
@@ -207,22 +252,63 @@ async fn start(
 
         api_builder = api_builder.with_acme(acme_client.clone(), resolver.clone());
 
+        if let Some(address) = args.tcp.tcp_tls {
+            let acceptor = make_tcp_tls_acceptor(resolver.clone());
+            tokio::spawn(async move {
+                if let Err(error) = tcp_proxy.serve_tls(address, acceptor).await {
+                    error!(
+                        error = &error as &dyn std::error::Error,
+                        "TCP proxy with TLS exited"
+                    );
+                }
+            });
+        }
+
         for CustomDomain {
             fqdn,
             certificate,
             private_key,
+            wildcard,
             ..
         } in gateway.iter_custom_domains().await.unwrap()
         {
-            let mut buf = Vec::new();
-            buf.extend(certificate.as_bytes());
-            buf.extend(private_key.as_bytes());
             resolver
-                .serve_pem(&fqdn.to_string(), Cursor::new(buf))
+                .serve_custom_domain_pem(&fqdn, wildcard, &certificate, &private_key)
                 .await
                 .unwrap();
         }
 
+        // Every hour queue a renewal of the custom domain certificates that are about to expire. They get a
+        // worker of their own, since renewals can take minutes and should not hold up the project tasks.
+        let renewal_worker = Worker::new();
+        let renewal_sender = renewal_worker.sender();
+        tokio::spawn(renewal_worker.start());
+        tokio::spawn({
+            let gateway = gateway.clone();
+            let acme_client = acme_client.clone();
+            let resolver = resolver.clone();
+            let window = args.certificate_renewal_window;
+            async move {
+                let mut interval = tokio::time::interval(Duration::from_secs(60 * 60));
+                interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+                loop {
+                    interval.tick().await;
+
+                    let task = RenewCertificates::new(
+                        gateway.clone(),
+                        acme_client.clone(),
+                        resolver.clone(),
+                        window,
+                    );
+                    if renewal_sender.send(Box::new(task)).await.is_err() {
+                        error!("the certificate renewal worker has stopped");
+                        break;
+                    }
+                }
+            }
+        });
+
         tokio::spawn(async move {
             // Make sure we have a certificate for ourselves.
             let certs = gateway
@@ -235,6 +321,9 @@ async fn start(
         });
     } else {
         warn!("TLS is disabled in the proxy service. This is only acceptable in testing, and should *never* be used in deployments.");
+        if args.tcp.tcp_tls.is_some() {
+            warn!("TCP connections with TLS are not accepted since TLS is disabled");
+        }
     };
 
     let api_handle = api_builder
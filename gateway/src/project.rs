@@ -25,8 +25,10 @@ use serde::de::DeserializeOwned;
 use serde::{Deserialize, Deserializer, Serialize};
 use shuttle_backends::headers::X_SHUTTLE_ADMIN_SECRET;
 use shuttle_backends::project_name::ProjectName;
+use shuttle_common::claims::AccountTier;
 use shuttle_common::constants::{default_idle_minutes, DEFAULT_IDLE_MINUTES};
 use shuttle_common::models::error::ApiError;
+use shuttle_common::models::project::ContainerLimits;
 use shuttle_common::models::service;
 use thiserror::Error;
 use tokio::time::{sleep, timeout};
@@ -195,6 +197,16 @@ pub trait ContainerInspectResponseExt {
     fn initial_key(&self) -> Result<String, ProjectError> {
         self.find_arg_and_then("--admin-secret", str::to_owned)
     }
+
+    fn container_limits(&self) -> Option<ContainerLimits> {
+        let host_config = self.container().host_config.as_ref()?;
+
+        Some(ContainerLimits {
+            memory: host_config.memory?.try_into().ok()?,
+            memory_reservation: host_config.memory_reservation?.try_into().ok()?,
+            cpu_quota: host_config.cpu_quota?.try_into().ok()?,
+        })
+    }
 }
 
 impl ContainerInspectResponseExt for ContainerInspectResponse {
@@ -440,6 +452,15 @@ impl Project {
     pub fn idle_minutes(&self) -> Option<u64> {
         self.container().map(|container| container.idle_minutes())
     }
+
+    pub fn container_limits(&self) -> Option<ContainerLimits> {
+        match self {
+            Self::Creating(creating) => Some(creating.container_limits),
+            _ => self
+                .container()
+                .and_then(|container| container.container_limits()),
+        }
+    }
 }
 
 impl From<Project> for shuttle_common::models::project::State {
@@ -770,6 +791,14 @@ pub struct ProjectCreating {
     /// Label set on container as to how many minutes to wait before a project is considered idle
     #[serde(default = "default_idle_minutes")]
     idle_minutes: u64,
+    /// Resources the container is allowed to use
+    #[serde(default = "legacy_container_limits")]
+    container_limits: ContainerLimits,
+}
+
+/// The limits that every project had before they depended on the account tier
+fn legacy_container_limits() -> ContainerLimits {
+    ContainerLimits::new(6, 4, 4)
 }
 
 impl ProjectCreating {
@@ -787,6 +816,7 @@ impl ProjectCreating {
             from: None,
             recreate_count: 0,
             idle_minutes,
+            container_limits: AccountTier::default().into(),
         }
     }
 
@@ -798,6 +828,9 @@ impl ProjectCreating {
         let project_id = container.project_id()?;
         let idle_minutes = container.idle_minutes();
         let initial_key = container.initial_key()?;
+        let container_limits = container
+            .container_limits()
+            .unwrap_or_else(legacy_container_limits);
 
         Ok(Self {
             project_name,
@@ -807,6 +840,7 @@ impl ProjectCreating {
             from: Some(container),
             recreate_count,
             idle_minutes,
+            container_limits,
         })
     }
 
@@ -829,6 +863,11 @@ impl ProjectCreating {
         self
     }
 
+    pub fn with_container_limits(mut self, container_limits: ContainerLimits) -> Self {
+        self.container_limits = container_limits;
+        self
+    }
+
     pub fn project_name(&self) -> &ProjectName {
         &self.project_name
     }
@@ -872,6 +911,7 @@ impl ProjectCreating {
             project_name,
             image,
             idle_minutes,
+            container_limits,
             ..
         } = &self;
 
@@ -926,11 +966,11 @@ impl ProjectCreating {
                 "Type": "volume"
             }],
             // https://docs.docker.com/config/containers/resource_constraints/#memory
-            "Memory": 6442450000i64, // 6 GiB hard limit
-            "MemoryReservation": 4295000000i64, // 4 GiB soft limit, applied if host is low on memory
+            "Memory": container_limits.memory, // hard limit
+            "MemoryReservation": container_limits.memory_reservation, // soft limit, applied if host is low on memory
             // https://docs.docker.com/config/containers/resource_constraints/#cpu
-            "CpuPeriod": 100000i64,
-            "CpuQuota": 400000i64,
+            "CpuPeriod": container_limits.cpu_period(),
+            "CpuQuota": container_limits.cpu_quota,
             "ExtraHosts": extra_hosts,
         });
 
@@ -2059,6 +2099,7 @@ pub mod tests {
                 from: None,
                 recreate_count: 0,
                 idle_minutes: 0,
+                container_limits: AccountTier::Basic.into(),
             }),
             #[assertion = "Container created, attach network"]
             Ok(Project::Attaching(ProjectAttaching {
@@ -1,18 +1,19 @@
+use std::collections::HashMap;
 use std::future::Future;
 use std::io;
 use std::net::{IpAddr, SocketAddr};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 use axum::extract::{ConnectInfo, Path, State};
 use axum::headers::{HeaderMapExt, Host};
-use axum::response::{IntoResponse, Response};
+use axum::response::{Html, IntoResponse, Response};
 use axum::routing::any;
 use axum_server::accept::DefaultAcceptor;
 use axum_server::tls_rustls::RustlsAcceptor;
 use fqdn::{fqdn, FQDN};
 use futures::prelude::*;
-use http::header::SERVER;
+use http::header::{ACCEPT, RETRY_AFTER, SERVER};
 use http::{HeaderValue, StatusCode};
 use hyper::body::{Body, HttpBody};
 use hyper::client::connect::dns::GaiResolver;
@@ -27,11 +28,13 @@ use shuttle_backends::headers::XShuttleProject;
 use shuttle_backends::project_name::ProjectName;
 use shuttle_common::constants::DEPLOYER_SERVICE_HTTP_PORT;
 use shuttle_common::models::error::{
-    ApiError, InvalidProjectName, ProjectNotReady, ProjectUnavailable,
+    ApiError, InvalidProjectName, ProjectNotReady, ProjectUnavailable, ProjectWakingUp,
 };
+use shuttle_common::models::project::WakeConfig;
 use thiserror::Error;
 use tokio::net::TcpSocket;
 use tokio::sync::mpsc::Sender;
+use tokio::sync::{watch, Semaphore};
 use tracing::{debug, debug_span, error, field, trace, Instrument};
 use tracing_opentelemetry::OpenTelemetrySpanExt;
 
@@ -43,6 +46,12 @@ static PROXY_CLIENT: Lazy<ReverseProxy<HttpConnector<GaiResolver>>> =
     Lazy::new(|| ReverseProxy::new(Client::new()));
 static SERVER_HEADER: Lazy<HeaderValue> = Lazy::new(|| "shuttle.rs".parse().unwrap());
 
+/// Seconds clients are asked to wait before retrying a request to a project that is waking up
+const WAKING_RETRY_AFTER_SECS: u64 = 5;
+
+/// Longest wait between checks of whether the service port of a project is open
+const SERVICE_PORT_MAX_BACKOFF: Duration = Duration::from_millis(500);
+
 #[derive(Debug, Error)]
 enum Error {
     #[error("The 'Host' header is invalid")]
@@ -59,12 +68,16 @@ enum Error {
 
     #[error(transparent)]
     Service(#[from] service::Error),
+
+    /// Waking up the project failed, with the safe message of the error
+    #[error("{0}")]
+    WakeFailed(String),
 }
 
-impl IntoResponse for Error {
-    fn into_response(self) -> Response {
-        // Make the exposed error safe first
-        let message = match self {
+impl Error {
+    /// The message of the error, which is safe to expose
+    fn safe_message(self) -> String {
+        match self {
             Error::BadHost => self.to_string(),
             Error::InvalidProjectName(e) => e.to_string(),
             Error::ProjectNotReady(e) => e.to_string(),
@@ -74,10 +87,15 @@ impl IntoResponse for Error {
 
                 error.message
             }
-        };
+            Error::WakeFailed(message) => message,
+        }
+    }
+}
 
+impl IntoResponse for Error {
+    fn into_response(self) -> Response {
         // Use a custom 600 status code to distinguish between proxy errors and project errors
-        (StatusCode::from_u16(600).unwrap(), message).into_response()
+        (StatusCode::from_u16(600).unwrap(), self.safe_message()).into_response()
     }
 }
 
@@ -87,6 +105,168 @@ pub struct ProxyState {
     public: FQDN,
     project_cache: CacheManager<IpAddr>,
     domain_cache: CacheManager<ProjectName>,
+    wakeups: Wakeups,
+}
+
+/// The idle projects that are being woken up by the requests to them
+#[derive(Clone)]
+struct Wakeups {
+    wakeups: Arc<Mutex<HashMap<ProjectName, Wakeup>>>,
+    /// How many requests can wait for a project at once
+    queue_size: usize,
+}
+
+/// An idle project being woken up by the requests to it
+#[derive(Clone)]
+struct Wakeup {
+    /// Address of the project once it is up, or the message of the error that stopped it from waking up
+    result: watch::Receiver<Option<Result<IpAddr, String>>>,
+    /// Limits how many requests wait for the project at once
+    queue: Arc<Semaphore>,
+    config: WakeConfig,
+}
+
+#[derive(Debug, PartialEq)]
+enum Woken {
+    Up(IpAddr),
+    StillWaking,
+}
+
+impl Wakeups {
+    fn new(queue_size: usize) -> Self {
+        Self {
+            wakeups: Default::default(),
+            queue_size,
+        }
+    }
+
+    /// The wake up of a project, if one is in progress
+    fn get(&self, project_name: &ProjectName) -> Option<Wakeup> {
+        self.wakeups.lock().unwrap().get(project_name).cloned()
+    }
+
+    /// Join the wake up of a project, or start one with `wake_up` if none is in progress
+    fn get_or_start<F>(&self, project_name: &ProjectName, config: WakeConfig, wake_up: F) -> Wakeup
+    where
+        F: Future<Output = Result<IpAddr, Error>> + Send + 'static,
+    {
+        self.wakeups
+            .lock()
+            .unwrap()
+            .entry(project_name.clone())
+            .or_insert_with(|| {
+                let (sender, result) = watch::channel(None);
+                let wakeups = self.wakeups.clone();
+                let project_name = project_name.clone();
+
+                let span = debug_span!("wake up", shuttle.project.name = %project_name);
+                tokio::spawn(
+                    async move {
+                        let woken = wake_up.await;
+
+                        sender.send_replace(Some(woken.map_err(Error::safe_message)));
+                        wakeups.lock().unwrap().remove(&project_name);
+                    }
+                    .instrument(span),
+                );
+
+                Wakeup {
+                    result,
+                    queue: Arc::new(Semaphore::new(self.queue_size)),
+                    config,
+                }
+            })
+            .clone()
+    }
+}
+
+impl Wakeup {
+    /// Wait for the project to be up, unless the queue is full or the wake up takes longer than its timeout
+    async fn wait(self) -> Result<Woken, Error> {
+        let Ok(_permit) = self.queue.clone().try_acquire_owned() else {
+            debug!("too many requests are waiting for the project to wake up");
+            return if self.config.waking_response {
+                Ok(Woken::StillWaking)
+            } else {
+                Err(ProjectUnavailable.into())
+            };
+        };
+
+        let mut result = self.result.clone();
+        let timeout = Duration::from_secs(self.config.timeout);
+        let woken = tokio::time::timeout(timeout, result.wait_for(Option::is_some)).await;
+        match woken {
+            Ok(Ok(woken)) => match woken.as_ref().unwrap() {
+                Ok(ip) => Ok(Woken::Up(*ip)),
+                Err(message) => Err(Error::WakeFailed(message.clone())),
+            },
+            // The wake up was dropped without a result
+            Ok(Err(_)) => Err(ProjectUnavailable.into()),
+            Err(_) if self.config.waking_response => Ok(Woken::StillWaking),
+            Err(_) => Err(ProjectUnavailable.into()),
+        }
+    }
+}
+
+impl ProxyState {
+    /// Find the address of a project, waking it up if it is idle.
+    ///
+    /// All the requests to an idle project wait for the same wake up, but only `wake_queue_size` of them at once.
+    async fn wake(&self, project_name: &ProjectName) -> Result<Woken, Error> {
+        let wakeup = match self.wakeups.get(project_name) {
+            Some(wakeup) => wakeup,
+            None => {
+                let project = self.gateway.find_project_by_name(project_name).await?;
+                if !project.state.is_stopped() {
+                    let ip = project.state.target_ip().ok_or(ProjectNotReady)?;
+
+                    return Ok(Woken::Up(ip));
+                }
+
+                let config = self.gateway.wake_config(project_name).await?;
+                self.wakeups.get_or_start(
+                    project_name,
+                    config,
+                    wake_up(
+                        self.gateway.clone(),
+                        self.task_sender.clone(),
+                        project_name.clone(),
+                        config,
+                    ),
+                )
+            }
+        };
+
+        wakeup.wait().await
+    }
+}
+
+/// Start an idle project and wait for its service to take requests.
+///
+/// The service port only opens once a healthy deployment takes the traffic, so the requests queued for the wake up are
+/// not let through to a project that cannot answer them yet.
+async fn wake_up(
+    gateway: Arc<GatewayService>,
+    task_sender: Sender<BoxedTask>,
+    project_name: ProjectName,
+    config: WakeConfig,
+) -> Result<IpAddr, Error> {
+    let (project, was_stopped) = gateway
+        .find_or_start_project(&project_name, task_sender)
+        .await?;
+    let ip = project.state.target_ip().ok_or(ProjectNotReady)?;
+
+    if was_stopped {
+        debug!("project waking up, checking service port");
+
+        // Requests are still sent to the project if its port does not open in time
+        let timeout = Duration::from_secs(config.timeout);
+        if !wait_for_service_port(ip, timeout).await {
+            debug!("service port did not open in time");
+        }
+    }
+
+    Ok(ip)
 }
 
 async fn proxy(
@@ -139,41 +319,16 @@ async fn proxy(
     let target_ip = if let Some(ip) = state.project_cache.get(project_name.as_str()) {
         ip
     } else {
-        let (proj, was_stopped) = state
-            .gateway
-            .find_or_start_project(&project_name, state.task_sender.clone())
-            .await?;
-        let ip = proj.state.target_ip().ok_or(ProjectNotReady)?;
+        let ip = match state.wake(&project_name).instrument(span.clone()).await? {
+            Woken::Up(ip) => ip,
+            Woken::StillWaking => return Ok(waking_response(&req)),
+        };
         state.project_cache.insert(
             project_name.as_str(),
             ip,
             std::time::Duration::from_millis(1000),
         );
 
-        if was_stopped {
-            // wait until service has started and opens its port, give up after 10s
-            span.in_scope(|| debug!("project waking up, checking service port"));
-
-            let addr = SocketAddr::new(ip, DEPLOYER_SERVICE_HTTP_PORT);
-            let _ = tokio::time::timeout(Duration::from_secs(10), async move {
-                let mut ms = 5;
-                loop {
-                    if let Ok(socket) = TcpSocket::new_v4() {
-                        if socket.connect(addr).await.is_ok() {
-                            debug!("service port detected open");
-                            break;
-                        }
-                    }
-                    trace!("waiting for service port to open");
-                    // exponential backoff
-                    tokio::time::sleep(Duration::from_millis(ms)).await;
-                    ms *= 2;
-                }
-            })
-            .instrument(span.clone())
-            .await;
-        }
-
         ip
     };
     let target_url = format!("http://{}:{}", target_ip, DEPLOYER_SERVICE_HTTP_PORT);
@@ -200,6 +355,65 @@ async fn proxy(
     Ok(Response::from_parts(parts, body))
 }
 
+/// Tell the client to come back once the project is awake, with a page for browsers and JSON for everything else
+fn waking_response(req: &Request<Body>) -> Response {
+    let wants_html = req
+        .headers()
+        .get(ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .is_some_and(|accept| accept.contains("text/html"));
+
+    let mut response = if wants_html {
+        let page = format!(
+            r#"<!DOCTYPE html>
+<html>
+<head><meta http-equiv="refresh" content="{WAKING_RETRY_AFTER_SECS}"><title>Waking up</title></head>
+<body><p>{ProjectWakingUp}</p></body>
+</html>
+"#
+        );
+
+        (StatusCode::SERVICE_UNAVAILABLE, Html(page)).into_response()
+    } else {
+        let error = ApiError::unavailable(ProjectWakingUp);
+
+        (StatusCode::SERVICE_UNAVAILABLE, axum::Json(error)).into_response()
+    };
+    response
+        .headers_mut()
+        .insert(RETRY_AFTER, HeaderValue::from(WAKING_RETRY_AFTER_SECS));
+    response.headers_mut().insert(SERVER, SERVER_HEADER.clone());
+
+    response
+}
+
+/// Wait until the service of a project that is waking up opens its port, returning whether it did before `timeout`.
+///
+/// The deployer only opens the port once a deployment is healthy, so an open port means the project can take requests.
+pub(crate) async fn wait_for_service_port(ip: IpAddr, timeout: Duration) -> bool {
+    wait_for_port(SocketAddr::new(ip, DEPLOYER_SERVICE_HTTP_PORT), timeout).await
+}
+
+async fn wait_for_port(addr: SocketAddr, timeout: Duration) -> bool {
+    tokio::time::timeout(timeout, async move {
+        let mut backoff = Duration::from_millis(5);
+        loop {
+            if let Ok(socket) = TcpSocket::new_v4() {
+                if socket.connect(addr).await.is_ok() {
+                    debug!("service port detected open");
+                    break;
+                }
+            }
+            trace!("waiting for service port to open");
+            // exponential backoff, capped so that a port opening late in a long timeout is found quickly
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(SERVICE_PORT_MAX_BACKOFF);
+        }
+    })
+    .await
+    .is_ok()
+}
+
 #[derive(Clone)]
 pub struct Bouncer {
     gateway: Arc<GatewayService>,
@@ -243,6 +457,7 @@ pub struct UserServiceBuilder {
     bouncer_binds_to: Option<SocketAddr>,
     user_binds_to: Option<SocketAddr>,
     public: Option<FQDN>,
+    wake_queue_size: Option<usize>,
 }
 
 impl UserServiceBuilder {
@@ -285,6 +500,11 @@ impl UserServiceBuilder {
         self
     }
 
+    pub fn with_wake_queue_size(mut self, wake_queue_size: usize) -> Self {
+        self.wake_queue_size = Some(wake_queue_size);
+        self
+    }
+
     pub fn serve(self) -> impl Future<Output = Result<(), io::Error>> {
         let service = self.service.expect("a GatewayService is required");
         let task_sender = self.task_sender.expect("a task sender is required");
@@ -292,6 +512,7 @@ impl UserServiceBuilder {
         let user_binds_to = self
             .user_binds_to
             .expect("a socket address to bind to is required");
+        let wake_queue_size = self.wake_queue_size.expect("a wake queue size is required");
 
         let router = axum::Router::new()
             .fallback(proxy) // catch all routes
@@ -301,6 +522,7 @@ impl UserServiceBuilder {
                 public: public.clone(),
                 project_cache: CacheManager::new(1024),
                 domain_cache: CacheManager::new(256),
+                wakeups: Wakeups::new(wake_queue_size),
             }));
         let user_proxy =
             axum::ServiceExt::into_make_service_with_connect_info::<SocketAddr>(router);
@@ -377,3 +599,183 @@ impl UserServiceBuilder {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use http::header::CONTENT_TYPE;
+
+    use super::*;
+
+    const IP: IpAddr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+
+    fn config(timeout: u64, waking_response: bool) -> WakeConfig {
+        WakeConfig {
+            timeout,
+            waking_response,
+        }
+    }
+
+    /// Wait until `count` requests are waiting for `wakeup`
+    async fn waiting(wakeup: &Wakeup, queue_size: usize, count: usize) {
+        while queue_size - wakeup.queue.available_permits() < count {
+            tokio::task::yield_now().await;
+        }
+    }
+
+    #[tokio::test]
+    async fn requests_share_a_single_wakeup() {
+        let wakeups = Wakeups::new(100);
+        let matrix: ProjectName = "matrix".parse().unwrap();
+        let started = Arc::new(AtomicUsize::new(0));
+        let (release, released) = watch::channel(false);
+
+        let mut requests = Vec::new();
+        for _ in 0..10 {
+            let started = started.clone();
+            let mut released = released.clone();
+            let wakeup = wakeups.get_or_start(&matrix, config(60, false), async move {
+                started.fetch_add(1, Ordering::SeqCst);
+                released.wait_for(|released| *released).await.unwrap();
+                Ok(IP)
+            });
+            requests.push(tokio::spawn(wakeup.wait()));
+        }
+
+        release.send_replace(true);
+        for request in requests {
+            assert_eq!(request.await.unwrap().unwrap(), Woken::Up(IP));
+        }
+        assert_eq!(started.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn failed_wakeups_are_errors() {
+        let wakeups = Wakeups::new(100);
+        let matrix: ProjectName = "matrix".parse().unwrap();
+
+        let wakeup = wakeups.get_or_start(&matrix, config(60, true), async {
+            Err(ProjectNotReady.into())
+        });
+
+        assert!(matches!(
+            wakeup.wait().await,
+            Err(Error::WakeFailed(message)) if message == ProjectNotReady.to_string()
+        ));
+    }
+
+    #[tokio::test]
+    async fn requests_over_the_queue_size_are_turned_away() {
+        let wakeups = Wakeups::new(2);
+        let matrix: ProjectName = "matrix".parse().unwrap();
+        let reloaded: ProjectName = "reloaded".parse().unwrap();
+
+        // Told to retry with a waking response
+        let wakeup = wakeups.get_or_start(&reloaded, config(60, true), future::pending());
+        let waiters = [
+            tokio::spawn(wakeup.clone().wait()),
+            tokio::spawn(wakeup.clone().wait()),
+        ];
+        waiting(&wakeup, 2, 2).await;
+        assert_eq!(wakeup.wait().await.unwrap(), Woken::StillWaking);
+        waiters.iter().for_each(|waiter| waiter.abort());
+
+        // Unavailable without one
+        let wakeup = wakeups.get_or_start(&matrix, config(60, false), future::pending());
+        let first = tokio::spawn(wakeup.clone().wait());
+        let second = tokio::spawn(wakeup.clone().wait());
+        waiting(&wakeup, 2, 2).await;
+        assert!(matches!(
+            wakeup.clone().wait().await,
+            Err(Error::ProjectUnavailable(_))
+        ));
+
+        // Room is made once a waiting request is done
+        first.abort();
+        let _ = first.await;
+        let third = tokio::spawn(wakeup.clone().wait());
+        waiting(&wakeup, 2, 2).await;
+        assert!(!third.is_finished());
+
+        second.abort();
+        third.abort();
+    }
+
+    #[tokio::test]
+    async fn requests_time_out_waiting_for_the_wakeup() {
+        let wakeups = Wakeups::new(100);
+        let matrix: ProjectName = "matrix".parse().unwrap();
+        let reloaded: ProjectName = "reloaded".parse().unwrap();
+
+        let wakeup = wakeups.get_or_start(&matrix, config(1, true), future::pending());
+        assert_eq!(wakeup.wait().await.unwrap(), Woken::StillWaking);
+
+        let wakeup = wakeups.get_or_start(&reloaded, config(1, false), future::pending());
+        assert!(matches!(
+            wakeup.wait().await,
+            Err(Error::ProjectUnavailable(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn service_ports_opening_late_are_found_quickly() {
+        let addr = SocketAddr::new(
+            Ipv4Addr::LOCALHOST.into(),
+            portpicker::pick_unused_port().unwrap(),
+        );
+        let delay = Duration::from_secs(3);
+
+        let opened = tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
+            let opened_at = tokio::time::Instant::now();
+            let _ = listener.accept().await;
+            opened_at
+        });
+
+        assert!(wait_for_port(addr, Duration::from_secs(60)).await);
+        let found_after = opened.await.unwrap().elapsed();
+        assert!(
+            found_after <= SERVICE_PORT_MAX_BACKOFF + Duration::from_millis(100),
+            "found {found_after:?} after the port opened"
+        );
+    }
+
+    #[tokio::test]
+    async fn waking_response_matches_accept() {
+        let browser = Request::builder()
+            .header(ACCEPT, "text/html,application/xhtml+xml,*/*;q=0.8")
+            .body(Body::empty())
+            .unwrap();
+        let client = Request::builder()
+            .header(ACCEPT, "application/json")
+            .body(Body::empty())
+            .unwrap();
+        let no_accept = Request::builder().body(Body::empty()).unwrap();
+
+        let response = waking_response(&browser);
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers()[RETRY_AFTER], "5");
+        assert!(response.headers()[CONTENT_TYPE]
+            .to_str()
+            .unwrap()
+            .starts_with("text/html"));
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(body.contains(r#"<meta http-equiv="refresh" content="5">"#));
+        assert!(body.contains(&ProjectWakingUp.to_string()));
+
+        for request in [client, no_accept] {
+            let response = waking_response(&request);
+            assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+            assert_eq!(response.headers()[RETRY_AFTER], "5");
+            assert_eq!(response.headers()[CONTENT_TYPE], "application/json");
+            let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+            let error: ApiError = serde_json::from_slice(&body).unwrap();
+            assert_eq!(error.status_code, 503);
+            assert_eq!(error.message, ProjectWakingUp.to_string());
+        }
+    }
+}
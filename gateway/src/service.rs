@@ -1,6 +1,7 @@
 use std::io;
 use std::io::Cursor;
 use std::net::Ipv4Addr;
+use std::ops::RangeInclusive;
 use std::path::PathBuf;
 use std::str::FromStr;
 use std::sync::Arc;
@@ -9,7 +10,7 @@ use axum::body::Body;
 use axum::headers::{Authorization, HeaderMapExt};
 use axum::http::Request;
 use axum::response::Response;
-use bollard::container::StatsOptions;
+use bollard::container::{StatsOptions, UpdateContainerOptions};
 use bollard::{Docker, API_DEFAULT_VERSION};
 use fqdn::{Fqdn, FQDN};
 use http::{StatusCode, Uri};
@@ -25,11 +26,11 @@ use shuttle_backends::client::{permit, PermissionsDal};
 use shuttle_backends::headers::XShuttleAdminSecret;
 use shuttle_backends::project_name::ProjectName;
 use shuttle_common::claims::AccountTier;
-use shuttle_common::constants::SHUTTLE_IDLE_DOCS_URL;
+use shuttle_common::constants::{MAX_WAKE_TIMEOUT_SECS, SHUTTLE_IDLE_DOCS_URL};
 use shuttle_common::models::error::{
     ApiError, ProjectNotFound, ProjectNotReady, ProjectUnavailable,
 };
-use shuttle_common::models::project::State;
+use shuttle_common::models::project::{ContainerLimits, State, WakeConfig};
 use shuttle_common::models::user::UserId;
 use sqlx::error::DatabaseError;
 use sqlx::migrate::Migrator;
@@ -49,7 +50,7 @@ use crate::acme::{AcmeClient, AcmeClientError, CustomDomain};
 use crate::args::ServiceArgs;
 use crate::project::{Project, ProjectCreating, ProjectError, IS_HEALTHY_TIMEOUT};
 use crate::task::{self, BoxedTask, TaskBuilder};
-use crate::tls::ChainAndPrivateKey;
+use crate::tls::{ChainAndPrivateKey, GatewayCertResolver};
 use crate::worker::TaskRouter;
 use crate::{
     DockerContext, DockerStatsSource, ProjectDetails, AUTH_CLIENT, DOCKER_STATS_PATH_CGROUP_V1,
@@ -101,6 +102,12 @@ pub enum Error {
 
     #[error("Our server is at capacity and cannot serve your request at this time. Please try again in a few minutes.")]
     CapacityLimit,
+
+    #[error("There are no TCP ports left to give to projects. Please try again later.")]
+    NoTcpPortAvailable,
+
+    #[error("Invalid container limits: {0}")]
+    InvalidContainerLimits(String),
 }
 
 impl From<Error> for ApiError {
@@ -132,6 +139,8 @@ impl From<Error> for ApiError {
             Error::CustomDomainNotFound => StatusCode::NOT_FOUND,
             Error::AcmeClient(e) => return e.into(),
             Error::CapacityLimit => StatusCode::SERVICE_UNAVAILABLE,
+            Error::NoTcpPortAvailable => StatusCode::SERVICE_UNAVAILABLE,
+            Error::InvalidContainerLimits(_) => StatusCode::BAD_REQUEST,
         };
 
         Self {
@@ -595,6 +604,118 @@ impl GatewayService {
         Ok(())
     }
 
+    /// The resources an admin set for a project, which take precedence over the ones of the account tier
+    pub async fn container_limits_override(
+        &self,
+        project_name: &ProjectName,
+    ) -> Result<Option<ContainerLimits>, Error> {
+        let container_limits =
+            query("SELECT container_limits FROM projects WHERE project_name = ?1")
+                .bind(project_name)
+                .fetch_optional(&self.db)
+                .await?
+                .map(|row| row.try_get::<Option<SqlxJson<ContainerLimits>>, _>("container_limits"))
+                .transpose()?
+                .flatten()
+                .map(|limits| limits.0);
+
+        Ok(container_limits)
+    }
+
+    /// Set or clear the resources of a project. New limits are applied to its container right away, while clearing
+    /// them only takes effect when the project is created again.
+    pub async fn set_container_limits_override(
+        &self,
+        project_name: &ProjectName,
+        container_limits: Option<ContainerLimits>,
+    ) -> Result<(), Error> {
+        if let Some(container_limits) = container_limits {
+            container_limits
+                .validate()
+                .map_err(Error::InvalidContainerLimits)?;
+        }
+
+        let result = query("UPDATE projects SET container_limits = ?1 WHERE project_name = ?2")
+            .bind(container_limits.map(SqlxJson))
+            .bind(project_name)
+            .execute(&self.db)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(ProjectNotFound(project_name.to_string()).into());
+        }
+
+        let Some(container_limits) = container_limits else {
+            return Ok(());
+        };
+        match self.find_project_by_name(project_name).await?.state {
+            Project::Creating(creating) => {
+                let project = Project::Creating(creating.with_container_limits(container_limits));
+                self.update_project(project_name, &project).await?;
+            }
+            project => {
+                if let Some(container_id) = project.container().and_then(|container| container.id) {
+                    // Validated limits fit in the signed values Docker takes
+                    let options = UpdateContainerOptions::<String> {
+                        memory: Some(container_limits.memory as i64),
+                        memory_reservation: Some(container_limits.memory_reservation as i64),
+                        // Docker otherwise keeps the swap limit of the old memory limit, which is lower than the new one
+                        memory_swap: Some(container_limits.memory as i64 * 2),
+                        cpu_period: Some(container_limits.cpu_period() as i64),
+                        cpu_quota: Some(container_limits.cpu_quota as i64),
+                        ..Default::default()
+                    };
+                    self.context
+                        .docker()
+                        .update_container(&container_id, options)
+                        .await
+                        .map_err(|err| {
+                            Error::InternalSafe(format!(
+                                "failed to update the project container: {err}"
+                            ))
+                        })?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub async fn wake_config(&self, project_name: &ProjectName) -> Result<WakeConfig, Error> {
+        let wake_config = query("SELECT wake_config FROM projects WHERE project_name = ?1")
+            .bind(project_name)
+            .fetch_optional(&self.db)
+            .await?
+            .ok_or_else(|| ProjectNotFound(project_name.to_string()))?
+            .try_get::<Option<SqlxJson<WakeConfig>>, _>("wake_config")?
+            .map(|wake_config| wake_config.0)
+            .unwrap_or_default();
+
+        Ok(wake_config)
+    }
+
+    pub async fn set_wake_config(
+        &self,
+        project_name: &ProjectName,
+        wake_config: WakeConfig,
+    ) -> Result<(), Error> {
+        // Requests would otherwise hold on to connections for as long as the client asks
+        let wake_config = WakeConfig {
+            timeout: wake_config.timeout.clamp(1, MAX_WAKE_TIMEOUT_SECS),
+            ..wake_config
+        };
+
+        let result = query("UPDATE projects SET wake_config = ?1 WHERE project_name = ?2")
+            .bind(SqlxJson(wake_config))
+            .bind(project_name)
+            .execute(&self.db)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(ProjectNotFound(project_name.to_string()).into());
+        }
+
+        Ok(())
+    }
+
     pub async fn user_id_from_project(&self, project_name: &ProjectName) -> Result<UserId, Error> {
         query("SELECT user_id FROM projects WHERE project_name = ?1")
             .bind(project_name)
@@ -637,6 +758,7 @@ impl GatewayService {
         is_admin: bool,
         can_create_project: bool,
         idle_minutes: u64,
+        container_limits: ContainerLimits,
     ) -> Result<FindProjectPayload, Error> {
         if let Some(row) = query(
             r#"
@@ -675,7 +797,8 @@ impl GatewayService {
                         ))
                     })?,
                     idle_minutes,
-                );
+                )
+                .with_container_limits(container_limits);
                 let project = Project::Creating(creating);
                 self.update_project(&project_name, &project).await?;
                 Ok(FindProjectPayload {
@@ -722,8 +845,14 @@ impl GatewayService {
             // Attempt to create a new one. This will fail
             // outright if the project already exists (this happens if
             // it belongs to another account).
-            self.insert_project(project_name, Ulid::new(), user_id, idle_minutes)
-                .await
+            self.insert_project(
+                project_name,
+                Ulid::new(),
+                user_id,
+                idle_minutes,
+                container_limits,
+            )
+            .await
         } else {
             Err(Error::TooManyProjects)
         }
@@ -745,13 +874,15 @@ impl GatewayService {
         project_id: Ulid,
         user_id: &UserId,
         idle_minutes: u64,
+        container_limits: ContainerLimits,
     ) -> Result<FindProjectPayload, Error> {
         let project = SqlxJson(Project::Creating(
             ProjectCreating::new_with_random_initial_key(
                 project_name.clone(),
                 project_id,
                 idle_minutes,
-            ),
+            )
+            .with_container_limits(container_limits),
         ));
 
         let mut transaction = self.db.begin().await?;
@@ -805,6 +936,11 @@ impl GatewayService {
             .execute(&mut *transaction)
             .await?;
 
+        query("DELETE FROM tcp_ports WHERE project_id = ?1")
+            .bind(&project_id)
+            .execute(&mut *transaction)
+            .await?;
+
         query("DELETE FROM projects WHERE project_name = ?1")
             .bind(project_name)
             .execute(&mut *transaction)
@@ -823,6 +959,7 @@ impl GatewayService {
         fqdn: &Fqdn,
         certs: &str,
         private_key: &str,
+        wildcard: bool,
     ) -> Result<(), Error> {
         let project_id = query("SELECT project_id FROM projects WHERE project_name = ?1")
             .bind(project_name)
@@ -830,11 +967,12 @@ impl GatewayService {
             .await?
             .get::<String, _>("project_id");
 
-        query("INSERT OR REPLACE INTO custom_domains (fqdn, project_id, certificate, private_key) VALUES (?1, ?2, ?3, ?4)")
+        query("INSERT OR REPLACE INTO custom_domains (fqdn, project_id, certificate, private_key, wildcard) VALUES (?1, ?2, ?3, ?4, ?5)")
             .bind(fqdn.to_string())
             .bind(project_id)
             .bind(certs)
             .bind(private_key)
+            .bind(wildcard)
             .execute(&self.db)
             .await?;
 
@@ -842,7 +980,7 @@ impl GatewayService {
     }
 
     pub async fn iter_custom_domains(&self) -> Result<impl Iterator<Item = CustomDomain>, Error> {
-        query("SELECT fqdn, project_name, certificate, private_key FROM custom_domains AS cd JOIN projects AS p ON cd.project_id = p.project_id")
+        query("SELECT fqdn, project_name, certificate, private_key, wildcard, renewal_error FROM custom_domains AS cd JOIN projects AS p ON cd.project_id = p.project_id")
             .fetch_all(&self.db)
             .await
             .map(|res| {
@@ -851,17 +989,55 @@ impl GatewayService {
                     project_name: row.try_get("project_name").unwrap(),
                     certificate: row.get("certificate"),
                     private_key: row.get("private_key"),
+                    wildcard: row.get("wildcard"),
+                    renewal_error: row.get("renewal_error"),
                 })
             })
             .map_err(Error::from)
     }
 
+    pub async fn iter_custom_domains_for_project(
+        &self,
+        project_name: &str,
+    ) -> Result<impl Iterator<Item = CustomDomain>, Error> {
+        query("SELECT fqdn, project_name, certificate, private_key, wildcard, renewal_error FROM custom_domains AS cd JOIN projects AS p ON cd.project_id = p.project_id WHERE p.project_name = ?1")
+            .bind(project_name)
+            .fetch_all(&self.db)
+            .await
+            .map(|res| {
+                res.into_iter().map(|row| CustomDomain {
+                    fqdn: row.get::<&str, _>("fqdn").parse().unwrap(),
+                    project_name: row.try_get("project_name").unwrap(),
+                    certificate: row.get("certificate"),
+                    private_key: row.get("private_key"),
+                    wildcard: row.get("wildcard"),
+                    renewal_error: row.get("renewal_error"),
+                })
+            })
+            .map_err(Error::from)
+    }
+
+    /// Record why the automatic renewal of the certificate of a custom domain failed
+    pub async fn set_custom_domain_renewal_error(
+        &self,
+        fqdn: &Fqdn,
+        renewal_error: &str,
+    ) -> Result<(), Error> {
+        query("UPDATE custom_domains SET renewal_error = ?1 WHERE fqdn = ?2")
+            .bind(renewal_error)
+            .bind(fqdn.to_string())
+            .execute(&self.db)
+            .await?;
+
+        Ok(())
+    }
+
     pub async fn find_custom_domain_for_project(
         &self,
         project_name: &str,
     ) -> Result<Option<CustomDomain>, Error> {
         let custom_domain = query(
-            "SELECT fqdn, project_name, certificate, private_key FROM custom_domains AS cd JOIN projects AS p ON cd.project_id = p.project_id WHERE p.project_name = ?1",
+            "SELECT fqdn, project_name, certificate, private_key, wildcard, renewal_error FROM custom_domains AS cd JOIN projects AS p ON cd.project_id = p.project_id WHERE p.project_name = ?1",
         )
         .bind(project_name)
         .fetch_optional(&self.db)
@@ -871,19 +1047,25 @@ impl GatewayService {
             project_name: row.try_get("project_name").unwrap(),
             certificate: row.get("certificate"),
             private_key: row.get("private_key"),
+            wildcard: row.get("wildcard"),
+            renewal_error: row.get("renewal_error"),
         });
 
         Ok(custom_domain)
     }
 
+    /// Find the custom domain serving `fqdn`: either `fqdn` itself, or the wildcard custom domain of its parent
     pub async fn project_details_for_custom_domain(
         &self,
         fqdn: &Fqdn,
     ) -> Result<CustomDomain, Error> {
+        let fqdn = fqdn.to_string();
+        let parent = fqdn.split_once('.').map(|(_, parent)| parent);
         let custom_domain = query(
-            "SELECT fqdn, project_name, certificate, private_key FROM custom_domains AS cd JOIN projects AS p ON cd.project_id = p.project_id WHERE fqdn = ?1",
+            "SELECT fqdn, project_name, certificate, private_key, wildcard, renewal_error FROM custom_domains AS cd JOIN projects AS p ON cd.project_id = p.project_id WHERE fqdn = ?1 OR (wildcard AND fqdn = ?2) ORDER BY fqdn = ?1 DESC LIMIT 1",
         )
-        .bind(fqdn.to_string())
+        .bind(&fqdn)
+        .bind(parent)
         .fetch_optional(&self.db)
         .await?
         .map(|row| CustomDomain {
@@ -891,11 +1073,91 @@ impl GatewayService {
             project_name: row.try_get("project_name").unwrap(),
             certificate: row.get("certificate"),
             private_key: row.get("private_key"),
+            wildcard: row.get("wildcard"),
+            renewal_error: row.get("renewal_error"),
         })
         .ok_or_else(|| Error::CustomDomainNotFound)?;
         Ok(custom_domain)
     }
 
+    /// Give a project one of `ports` to expose raw TCP on, or return the one it already has
+    pub async fn allocate_tcp_port(
+        &self,
+        project_name: &ProjectName,
+        ports: RangeInclusive<u16>,
+    ) -> Result<u16, Error> {
+        let project_id = query("SELECT project_id FROM projects WHERE project_name = ?1")
+            .bind(project_name)
+            .fetch_optional(&self.db)
+            .await?
+            .ok_or_else(|| ProjectNotFound(project_name.to_string()))?
+            .get::<String, _>("project_id");
+
+        // Find the first free port and take it in one statement, so concurrent calls cannot pick the same port. This
+        // does nothing when the project already has a port.
+        query(
+            "INSERT INTO tcp_ports (port, project_id)
+            WITH RECURSIVE candidates (port) AS (SELECT ?1 UNION ALL SELECT port + 1 FROM candidates WHERE port < ?2)
+            SELECT port, ?3 FROM candidates WHERE port NOT IN (SELECT port FROM tcp_ports) LIMIT 1
+            ON CONFLICT DO NOTHING",
+        )
+        .bind(ports.start())
+        .bind(ports.end())
+        .bind(&project_id)
+        .execute(&self.db)
+        .await?;
+
+        query("SELECT port FROM tcp_ports WHERE project_id = ?1")
+            .bind(&project_id)
+            .fetch_optional(&self.db)
+            .await?
+            .map(|row| row.get("port"))
+            .ok_or(Error::NoTcpPortAvailable)
+    }
+
+    /// Take the TCP port of a project back, returning the port it had
+    pub async fn release_tcp_port(&self, project_name: &ProjectName) -> Result<Option<u16>, Error> {
+        let port = self.tcp_port(project_name).await?;
+
+        query("DELETE FROM tcp_ports WHERE project_id IN (SELECT project_id FROM projects WHERE project_name = ?1)")
+            .bind(project_name)
+            .execute(&self.db)
+            .await?;
+
+        Ok(port)
+    }
+
+    pub async fn tcp_port(&self, project_name: &str) -> Result<Option<u16>, Error> {
+        let port = query("SELECT port FROM tcp_ports AS tp JOIN projects AS p ON tp.project_id = p.project_id WHERE p.project_name = ?1")
+            .bind(project_name)
+            .fetch_optional(&self.db)
+            .await?
+            .map(|row| row.get("port"));
+
+        Ok(port)
+    }
+
+    pub async fn project_for_tcp_port(&self, port: u16) -> Result<Option<ProjectName>, Error> {
+        let project_name = query("SELECT project_name FROM tcp_ports AS tp JOIN projects AS p ON tp.project_id = p.project_id WHERE tp.port = ?1")
+            .bind(port)
+            .fetch_optional(&self.db)
+            .await?
+            .map(|row| row.try_get("project_name"))
+            .transpose()?;
+
+        Ok(project_name)
+    }
+
+    pub async fn iter_tcp_ports(&self) -> Result<impl Iterator<Item = u16>, Error> {
+        let ports = query("SELECT port FROM tcp_ports")
+            .fetch_all(&self.db)
+            .await?
+            .into_iter()
+            .map(|row| row.get("port"));
+
+        Ok(ports)
+    }
+
     pub async fn iter_projects_detailed(
         &self,
     ) -> Result<impl Iterator<Item = ProjectDetails>, Error> {
@@ -914,24 +1176,30 @@ impl GatewayService {
     /// Returns the current certificate as a pair of the chain and private key.
     /// If the pair doesn't exist for a specific project, create both the certificate
     /// and the custom domain it will represent.
+    ///
+    /// A `wildcard` certificate also covers all the subdomains of `fqdn`. Those can only be
+    /// validated with Dns01 challenges, so the `acme_client` needs to automate them.
     pub async fn create_custom_domain_certificate(
         &self,
         fqdn: &Fqdn,
         acme_client: &AcmeClient,
         project_name: &ProjectName,
         creds: AccountCredentials<'_>,
+        wildcard: bool,
     ) -> Result<(String, String), Error> {
         match self.project_details_for_custom_domain(fqdn).await {
+            // Don't hand out the certificate of a wildcard parent domain as the one of this domain
             Ok(CustomDomain {
+                fqdn: found,
                 certificate,
                 private_key,
                 ..
-            }) => Ok((certificate, private_key)),
-            Err(Error::CustomDomainNotFound) => {
+            }) if found.to_string() == fqdn.to_string() => Ok((certificate, private_key)),
+            Ok(_) | Err(Error::CustomDomainNotFound) => {
                 let (certs, private_key) = acme_client
-                    .create_certificate(&fqdn.to_string(), ChallengeType::Http01, creds)
+                    .create_custom_domain_certificate(fqdn, wildcard, creds)
                     .await?;
-                self.create_custom_domain(project_name, fqdn, &certs, &private_key)
+                self.create_custom_domain(project_name, fqdn, &certs, &private_key, wildcard)
                     .await?;
                 Ok((certs, private_key))
             }
@@ -939,6 +1207,71 @@ impl GatewayService {
         }
     }
 
+    /// Renew the certificates served for custom domains that expire within `window_in_days`, and serve the new
+    /// ones right away. Failed renewals are recorded with their custom domain, and retried on the next run.
+    pub async fn renew_custom_domain_certificates(
+        &self,
+        acme: &AcmeClient,
+        resolver: &GatewayCertResolver,
+        window_in_days: i64,
+    ) -> Result<(), Error> {
+        if !self.state_dir.join("acme.json").exists() {
+            warn!("no ACME credentials found, skipping custom domain certificate renewals");
+            return Ok(());
+        }
+
+        for CustomDomain {
+            fqdn,
+            project_name,
+            wildcard,
+            ..
+        } in self.iter_custom_domains().await?
+        {
+            let Some(details) = resolver.certificate_details(&fqdn.to_string()).await else {
+                warn!(%fqdn, "custom domain certificate is not loaded, skipping its renewal");
+                continue;
+            };
+            if !details.needs_renewal(window_in_days) {
+                continue;
+            }
+
+            info!(%fqdn, not_after = %details.not_after, "renewing custom domain certificate");
+            let renewed = match acme
+                .create_custom_domain_certificate(&fqdn, wildcard, self.credentials())
+                .await
+            {
+                Ok((certs, private_key)) => match self
+                    .create_custom_domain(&project_name, &fqdn, &certs, &private_key, wildcard)
+                    .await
+                {
+                    Ok(()) => resolver
+                        .serve_custom_domain_pem(&fqdn, wildcard, &certs, &private_key)
+                        .await
+                        .map_err(|error| error.to_string()),
+                    Err(error) => Err(error.to_string()),
+                },
+                Err(error) => Err(error.to_string()),
+            };
+
+            // One failed domain should not hold up the renewal of the others
+            if let Err(renewal_error) = renewed {
+                error!(%fqdn, renewal_error, "failed to renew custom domain certificate");
+                if let Err(error) = self
+                    .set_custom_domain_renewal_error(&fqdn, &renewal_error)
+                    .await
+                {
+                    error!(
+                        error = &error as &dyn std::error::Error,
+                        %fqdn,
+                        "failed to record the custom domain renewal error"
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     pub async fn create_certificate<'a>(
         &self,
         acme: &AcmeClient,
@@ -950,7 +1283,7 @@ impl GatewayService {
         // Use ::Dns01 challenge because that's the only supported
         // challenge type for wildcard domains.
         let (chain, private_key) = acme
-            .create_certificate(&identifier, ChallengeType::Dns01, creds)
+            .create_certificate(&[identifier], ChallengeType::Dns01, creds)
             .await
             .unwrap();
 
@@ -1310,7 +1643,14 @@ pub mod tests {
         };
 
         let project = svc
-            .create_project(matrix.clone(), &neo, false, true, 0)
+            .create_project(
+                matrix.clone(),
+                &neo,
+                false,
+                true,
+                0,
+                AccountTier::Basic.into(),
+            )
             .await
             .unwrap();
 
@@ -1343,17 +1683,31 @@ pub mod tests {
 
         // Test project pagination, first create 20 projects.
         for p in (0..20).map(|p| format!("matrix-{p}")) {
-            svc.create_project(p.parse().unwrap(), &admin, true, true, 0)
-                .await
-                .unwrap();
+            svc.create_project(
+                p.parse().unwrap(),
+                &admin,
+                true,
+                true,
+                0,
+                AccountTier::Basic.into(),
+            )
+            .await
+            .unwrap();
         }
 
         // Creating a project with can_create_project set to false should fail.
         assert!(matches!(
-            svc.create_project("final-one".parse().unwrap(), &admin, false, false, 0)
-                .await
-                .err()
-                .unwrap(),
+            svc.create_project(
+                "final-one".parse().unwrap(),
+                &admin,
+                false,
+                false,
+                0,
+                AccountTier::Basic.into(),
+            )
+            .await
+            .err()
+            .unwrap(),
             Error::TooManyProjects
         ));
 
@@ -1413,24 +1767,45 @@ pub mod tests {
 
         // If recreated by a different user
         assert!(matches!(
-            svc.create_project(matrix.clone(), &trinity, false, true, 0)
-                .await,
+            svc.create_project(
+                matrix.clone(),
+                &trinity,
+                false,
+                true,
+                0,
+                AccountTier::Basic.into(),
+            )
+            .await,
             Err(Error::ProjectAlreadyExists)
         ));
 
         // If recreated by the same user
         assert!(matches!(
-            svc.create_project(matrix.clone(), &neo, false, true, 0)
-                .await
-                .unwrap()
-                .state,
+            svc.create_project(
+                matrix.clone(),
+                &neo,
+                false,
+                true,
+                0,
+                AccountTier::Basic.into(),
+            )
+            .await
+            .unwrap()
+            .state,
             Project::Creating(_),
         ));
 
         // If recreated by the same user again while it's running
         assert!(matches!(
-            svc.create_project(matrix.clone(), &neo, false, true, 0)
-                .await,
+            svc.create_project(
+                matrix.clone(),
+                &neo,
+                false,
+                true,
+                0,
+                AccountTier::Basic.into(),
+            )
+            .await,
             Err(Error::OwnProjectAlreadyExists(_))
         ));
 
@@ -1451,17 +1826,31 @@ pub mod tests {
 
         // If recreated by an admin
         assert!(matches!(
-            svc.create_project(matrix.clone(), &admin, true, true, 0)
-                .await
-                .unwrap()
-                .state,
+            svc.create_project(
+                matrix.clone(),
+                &admin,
+                true,
+                true,
+                0,
+                AccountTier::Basic.into(),
+            )
+            .await
+            .unwrap()
+            .state,
             Project::Creating(_)
         ));
 
         // If recreated by an admin again while it's running
         assert!(matches!(
-            svc.create_project(matrix.clone(), &admin, true, true, 0)
-                .await,
+            svc.create_project(
+                matrix.clone(),
+                &admin,
+                true,
+                true,
+                0,
+                AccountTier::Basic.into(),
+            )
+            .await,
             Err(Error::OwnProjectAlreadyExists(_))
         ));
 
@@ -1476,7 +1865,7 @@ pub mod tests {
 
         // It can be re-created by anyone, with the same project name
         assert!(matches!(
-            svc.create_project(matrix, &trinity, false, true, 0)
+            svc.create_project(matrix, &trinity, false, true, 0, AccountTier::Basic.into())
                 .await
                 .unwrap()
                 .state,
@@ -1485,7 +1874,7 @@ pub mod tests {
     }
 
     #[tokio::test]
-    async fn service_create_ready_kill_restart_docker() {
+    async fn service_create_project_with_container_limits() {
         let world = World::new().await;
         let svc = Arc::new(
             GatewayService::init(
@@ -1500,10 +1889,249 @@ pub mod tests {
 
         let neo: UserId = "neo".to_owned();
         let matrix: ProjectName = "matrix".parse().unwrap();
+        let basic = ContainerLimits::from(AccountTier::Basic);
+        let custom = ContainerLimits::new(16, 8, 8);
 
-        svc.create_project(matrix.clone(), &neo, false, true, 0)
+        let project = svc
+            .create_project(matrix.clone(), &neo, false, true, 0, basic)
             .await
             .unwrap();
+        assert_eq!(project.state.container_limits(), Some(basic));
+        assert_eq!(svc.container_limits_override(&matrix).await.unwrap(), None);
+
+        // A project without a container yet gets the new limits when it is created
+        svc.set_container_limits_override(&matrix, Some(custom))
+            .await
+            .unwrap();
+        assert_eq!(
+            svc.container_limits_override(&matrix).await.unwrap(),
+            Some(custom)
+        );
+        assert_eq!(
+            svc.find_project_by_name(&matrix)
+                .await
+                .unwrap()
+                .state
+                .container_limits(),
+            Some(custom)
+        );
+
+        svc.set_container_limits_override(&matrix, None)
+            .await
+            .unwrap();
+        assert_eq!(svc.container_limits_override(&matrix).await.unwrap(), None);
+
+        assert!(matches!(
+            svc.set_container_limits_override(&"not-matrix".parse().unwrap(), Some(custom))
+                .await,
+            Err(Error::ProjectNotFound(_))
+        ));
+
+        // Limits that cannot be applied to a container are rejected without being stored
+        for invalid in [
+            ContainerLimits::new(0, 0, 2),
+            ContainerLimits::new(4, 2, 0),
+            ContainerLimits::new(4, 8, 2),
+            ContainerLimits::new(u64::MAX, 2, 2),
+        ] {
+            assert!(matches!(
+                svc.set_container_limits_override(&matrix, Some(invalid))
+                    .await,
+                Err(Error::InvalidContainerLimits(_))
+            ));
+        }
+        assert_eq!(svc.container_limits_override(&matrix).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn service_wake_config() {
+        let world = World::new().await;
+        let svc = Arc::new(
+            GatewayService::init(
+                world.args(),
+                world.pool(),
+                "".into(),
+                Box::<PermissionsMock>::default(),
+            )
+            .await
+            .unwrap(),
+        );
+
+        let neo: UserId = "neo".to_owned();
+        let matrix: ProjectName = "matrix".parse().unwrap();
+        svc.create_project(
+            matrix.clone(),
+            &neo,
+            false,
+            true,
+            0,
+            AccountTier::Basic.into(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            svc.wake_config(&matrix).await.unwrap(),
+            WakeConfig::default()
+        );
+
+        let wake_config = WakeConfig {
+            timeout: 5,
+            waking_response: true,
+        };
+        svc.set_wake_config(&matrix, wake_config).await.unwrap();
+        assert_eq!(svc.wake_config(&matrix).await.unwrap(), wake_config);
+
+        // Timeouts are kept within bounds
+        for (timeout, clamped) in [(0, 1), (MAX_WAKE_TIMEOUT_SECS + 1, MAX_WAKE_TIMEOUT_SECS)] {
+            svc.set_wake_config(
+                &matrix,
+                WakeConfig {
+                    timeout,
+                    waking_response: true,
+                },
+            )
+            .await
+            .unwrap();
+            assert_eq!(svc.wake_config(&matrix).await.unwrap().timeout, clamped);
+        }
+
+        assert!(matches!(
+            svc.wake_config(&"not-matrix".parse().unwrap()).await,
+            Err(Error::ProjectNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn service_allocate_tcp_ports() {
+        let world = World::new().await;
+        let svc = Arc::new(
+            GatewayService::init(
+                world.args(),
+                world.pool(),
+                "".into(),
+                Box::<PermissionsMock>::default(),
+            )
+            .await
+            .unwrap(),
+        );
+
+        let neo: UserId = "neo".to_owned();
+        let matrix: ProjectName = "matrix".parse().unwrap();
+        let reloaded: ProjectName = "reloaded".parse().unwrap();
+        let revolutions: ProjectName = "revolutions".parse().unwrap();
+        for project_name in [&matrix, &reloaded, &revolutions] {
+            svc.create_project(
+                project_name.clone(),
+                &neo,
+                false,
+                true,
+                0,
+                AccountTier::Basic.into(),
+            )
+            .await
+            .unwrap();
+        }
+
+        assert_eq!(svc.tcp_port(&matrix).await.unwrap(), None);
+        assert_eq!(
+            svc.allocate_tcp_port(&matrix, 10000..=10001).await.unwrap(),
+            10000
+        );
+        // A project keeps its port
+        assert_eq!(
+            svc.allocate_tcp_port(&matrix, 10000..=10001).await.unwrap(),
+            10000
+        );
+        assert_eq!(
+            svc.allocate_tcp_port(&reloaded, 10000..=10001)
+                .await
+                .unwrap(),
+            10001
+        );
+        assert!(matches!(
+            svc.allocate_tcp_port(&revolutions, 10000..=10001).await,
+            Err(Error::NoTcpPortAvailable)
+        ));
+        assert_eq!(
+            svc.project_for_tcp_port(10001).await.unwrap(),
+            Some(reloaded.clone())
+        );
+
+        assert_eq!(svc.release_tcp_port(&matrix).await.unwrap(), Some(10000));
+        assert_eq!(svc.release_tcp_port(&matrix).await.unwrap(), None);
+        assert_eq!(svc.project_for_tcp_port(10000).await.unwrap(), None);
+        assert_eq!(
+            svc.allocate_tcp_port(&revolutions, 10000..=10001)
+                .await
+                .unwrap(),
+            10000
+        );
+
+        // Deleting a project frees its port
+        svc.delete_project(&reloaded).await.unwrap();
+        assert_eq!(
+            svc.iter_tcp_ports().await.unwrap().collect::<Vec<_>>(),
+            [10000]
+        );
+
+        assert!(matches!(
+            svc.allocate_tcp_port(&"not-matrix".parse().unwrap(), 10000..=10001)
+                .await,
+            Err(Error::ProjectNotFound(_))
+        ));
+
+        // Concurrent calls never give out the same port
+        let resurrections: ProjectName = "resurrections".parse().unwrap();
+        let animatrix: ProjectName = "animatrix".parse().unwrap();
+        for project_name in [&resurrections, &animatrix] {
+            svc.create_project(
+                project_name.clone(),
+                &neo,
+                false,
+                true,
+                0,
+                AccountTier::Basic.into(),
+            )
+            .await
+            .unwrap();
+        }
+        let (first, second) = tokio::join!(
+            svc.allocate_tcp_port(&resurrections, 20000..=20001),
+            svc.allocate_tcp_port(&animatrix, 20000..=20001),
+        );
+        let mut ports = [first.unwrap(), second.unwrap()];
+        ports.sort();
+        assert_eq!(ports, [20000, 20001]);
+    }
+
+    #[tokio::test]
+    async fn service_create_ready_kill_restart_docker() {
+        let world = World::new().await;
+        let svc = Arc::new(
+            GatewayService::init(
+                world.args(),
+                world.pool(),
+                "".into(),
+                Box::<PermissionsMock>::default(),
+            )
+            .await
+            .unwrap(),
+        );
+
+        let neo: UserId = "neo".to_owned();
+        let matrix: ProjectName = "matrix".parse().unwrap();
+
+        svc.create_project(
+            matrix.clone(),
+            &neo,
+            false,
+            true,
+            0,
+            AccountTier::Basic.into(),
+        )
+        .await
+        .unwrap();
 
         let mut task = svc.new_task().project(matrix.clone()).build();
 
@@ -1571,11 +2199,18 @@ pub mod tests {
         ));
 
         let _ = svc
-            .create_project(project_name.clone(), &account, false, true, 0)
+            .create_project(
+                project_name.clone(),
+                &account,
+                false,
+                true,
+                0,
+                AccountTier::Basic.into(),
+            )
             .await
             .unwrap();
 
-        svc.create_custom_domain(&project_name, &domain, certificate, private_key)
+        svc.create_custom_domain(&project_name, &domain, certificate, private_key, false)
             .await
             .unwrap();
 
@@ -1592,7 +2227,7 @@ pub mod tests {
         let certificate = "dummy certificate update";
         let private_key = "dummy private key update";
 
-        svc.create_custom_domain(&project_name, &domain, certificate, private_key)
+        svc.create_custom_domain(&project_name, &domain, certificate, private_key, false)
             .await
             .unwrap();
 
@@ -1606,6 +2241,82 @@ pub mod tests {
         assert_eq!(custom_domain.private_key, private_key);
     }
 
+    #[tokio::test]
+    async fn service_find_wildcard_custom_domain() {
+        let world = World::new().await;
+        let svc = Arc::new(
+            GatewayService::init(
+                world.args(),
+                world.pool(),
+                "".into(),
+                Box::<PermissionsMock>::default(),
+            )
+            .await
+            .unwrap(),
+        );
+
+        let account: UserId = "neo".to_owned();
+        let matrix: ProjectName = "matrix".parse().unwrap();
+        let zion: ProjectName = "zion".parse().unwrap();
+        let domain: FQDN = "the.matrix".parse().unwrap();
+        let subdomain: FQDN = "neo.the.matrix".parse().unwrap();
+
+        for project_name in [&matrix, &zion] {
+            svc.create_project(
+                project_name.clone(),
+                &account,
+                false,
+                true,
+                0,
+                AccountTier::Basic.into(),
+            )
+            .await
+            .unwrap();
+        }
+
+        svc.create_custom_domain(&matrix, &domain, "certificate", "private key", false)
+            .await
+            .unwrap();
+        assert!(matches!(
+            svc.project_details_for_custom_domain(&subdomain)
+                .await
+                .unwrap_err(),
+            Error::CustomDomainNotFound
+        ));
+
+        // Subdomains are routed to the project of their wildcard parent
+        svc.create_custom_domain(&matrix, &domain, "certificate", "private key", true)
+            .await
+            .unwrap();
+        let custom_domain = svc
+            .project_details_for_custom_domain(&subdomain)
+            .await
+            .unwrap();
+        assert_eq!(custom_domain.fqdn, domain);
+        assert_eq!(custom_domain.project_name, matrix);
+        assert!(custom_domain.wildcard);
+
+        // Unless they are a custom domain of their own
+        svc.create_custom_domain(&zion, &subdomain, "certificate", "private key", false)
+            .await
+            .unwrap();
+        let custom_domain = svc
+            .project_details_for_custom_domain(&subdomain)
+            .await
+            .unwrap();
+        assert_eq!(custom_domain.fqdn, subdomain);
+        assert_eq!(custom_domain.project_name, zion);
+
+        // Only direct subdomains are covered
+        let nested: FQDN = "trinity.neo.the.matrix".parse().unwrap();
+        assert!(matches!(
+            svc.project_details_for_custom_domain(&nested)
+                .await
+                .unwrap_err(),
+            Error::CustomDomainNotFound
+        ));
+    }
+
     #[tokio::test]
     async fn service_create_custom_domain_destroy_recreate_project() {
         let world = World::new().await;
@@ -1634,11 +2345,18 @@ pub mod tests {
         ));
 
         let _ = svc
-            .create_project(project_name.clone(), &account, false, true, 0)
+            .create_project(
+                project_name.clone(),
+                &account,
+                false,
+                true,
+                0,
+                AccountTier::Basic.into(),
+            )
             .await
             .unwrap();
 
-        svc.create_custom_domain(&project_name, &domain, certificate, private_key)
+        svc.create_custom_domain(&project_name, &domain, certificate, private_key, false)
             .await
             .unwrap();
 
@@ -1652,7 +2370,14 @@ pub mod tests {
         assert!(matches!(work.poll(()).await, TaskResult::Done(())));
 
         let recreated_project = svc
-            .create_project(project_name.clone(), &account, false, true, 0)
+            .create_project(
+                project_name.clone(),
+                &account,
+                false,
+                true,
+                0,
+                AccountTier::Basic.into(),
+            )
             .await
             .unwrap();
 
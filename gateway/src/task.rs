@@ -19,8 +19,10 @@ use tracing::{error, field, info_span, trace, warn, Instrument, Span};
 use tracing_opentelemetry::OpenTelemetrySpanExt;
 use ulid::Ulid;
 
+use crate::acme::AcmeClient;
 use crate::project::{self, *};
 use crate::service::{self, GatewayContext, GatewayService};
+use crate::tls::GatewayCertResolver;
 use crate::worker::TaskRouter;
 use crate::State;
 
@@ -400,6 +402,47 @@ impl Task<ProjectContext> for DeleteProject {
     }
 }
 
+/// Renew the custom domain certificates that are about to expire, with
+/// [GatewayService::renew_custom_domain_certificates]
+pub struct RenewCertificates {
+    service: Arc<GatewayService>,
+    acme: AcmeClient,
+    resolver: Arc<GatewayCertResolver>,
+    window_in_days: i64,
+}
+
+impl RenewCertificates {
+    pub fn new(
+        service: Arc<GatewayService>,
+        acme: AcmeClient,
+        resolver: Arc<GatewayCertResolver>,
+        window_in_days: i64,
+    ) -> Self {
+        Self {
+            service,
+            acme,
+            resolver,
+            window_in_days,
+        }
+    }
+}
+
+#[async_trait]
+impl Task<()> for RenewCertificates {
+    type Output = ();
+
+    async fn poll(&mut self, _ctx: ()) -> TaskResult<Self::Output> {
+        match self
+            .service
+            .renew_custom_domain_certificates(&self.acme, &self.resolver, self.window_in_days)
+            .await
+        {
+            Ok(()) => TaskResult::Done(()),
+            Err(error) => TaskResult::Err(error.into()),
+        }
+    }
+}
+
 pub struct TaskHandle {
     rx: oneshot::Receiver<()>,
 }
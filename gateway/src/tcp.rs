@@ -0,0 +1,346 @@
+use std::collections::HashMap;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum_server::accept::{Accept, DefaultAcceptor};
+use axum_server::tls_rustls::RustlsAcceptor;
+use fqdn::FQDN;
+use shuttle_backends::project_name::ProjectName;
+use shuttle_common::constants::DEPLOYER_SERVICE_HTTP_PORT;
+use shuttle_common::models::error::{InvalidProjectName, ProjectNotReady};
+use thiserror::Error;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc::Sender;
+use tokio::task::JoinHandle;
+use tracing::{debug, debug_span, error, info, Instrument};
+
+use crate::proxy::wait_for_service_port;
+use crate::service::{self, GatewayService};
+use crate::task::BoxedTask;
+
+const ACCEPT_BACKOFF_START: Duration = Duration::from_millis(5);
+const ACCEPT_BACKOFF_MAX: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Error)]
+enum Error {
+    #[error("The connection is not for a project")]
+    NoProject,
+
+    #[error(transparent)]
+    InvalidProjectName(#[from] InvalidProjectName),
+
+    #[error(transparent)]
+    ProjectNotReady(#[from] ProjectNotReady),
+
+    #[error(transparent)]
+    Service(#[from] service::Error),
+
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// Forwards raw TCP connections to the services of projects, for protocols other than HTTP.
+///
+/// Projects are found either by the port a connection came in on, or by the server name of a TLS connection.
+pub struct TcpProxy {
+    gateway: Arc<GatewayService>,
+    task_sender: Sender<BoxedTask>,
+    public: FQDN,
+}
+
+impl TcpProxy {
+    pub fn new(gateway: Arc<GatewayService>, task_sender: Sender<BoxedTask>, public: FQDN) -> Self {
+        Self {
+            gateway,
+            task_sender,
+            public,
+        }
+    }
+
+    /// Accept TLS connections on `address`, and forward them decrypted to the project named by their SNI: either
+    /// `<project>.<public>` or one of the custom domains of the project
+    pub async fn serve_tls(
+        self: Arc<Self>,
+        address: SocketAddr,
+        acceptor: RustlsAcceptor<DefaultAcceptor>,
+    ) -> io::Result<()> {
+        let listener = TcpListener::bind(address).await?;
+        info!(%address, "accepting TCP connections with TLS");
+
+        loop {
+            let (stream, peer) = accept(&listener).await;
+            let proxy = self.clone();
+            let acceptor = acceptor.clone();
+
+            tokio::spawn(
+                async move {
+                    let result = async {
+                        let (stream, _) = acceptor.accept(stream, ()).await?;
+                        let server_name = stream
+                            .get_ref()
+                            .1
+                            .server_name()
+                            .ok_or(Error::NoProject)?
+                            .to_owned();
+                        let project_name = proxy.project_for_server_name(&server_name).await?;
+
+                        proxy.forward(stream, &project_name).await
+                    }
+                    .await;
+
+                    if let Err(error) = result {
+                        debug!(
+                            error = &error as &dyn std::error::Error,
+                            "TLS connection ended"
+                        );
+                    }
+                }
+                .instrument(debug_span!("tcp proxy", %peer)),
+            );
+        }
+    }
+
+    async fn project_for_server_name(&self, server_name: &str) -> Result<ProjectName, Error> {
+        let fqdn = FQDN::from_str(server_name).map_err(|_| Error::NoProject)?;
+
+        if fqdn.is_subdomain_of(&self.public) && fqdn.depth() - self.public.depth() == 1 {
+            let project_name = fqdn
+                .labels()
+                .next()
+                .unwrap()
+                .parse()
+                .map_err(|_| InvalidProjectName)?;
+
+            Ok(project_name)
+        } else {
+            let custom_domain = self
+                .gateway
+                .project_details_for_custom_domain(&fqdn)
+                .await?;
+
+            Ok(custom_domain.project_name)
+        }
+    }
+
+    /// Forward `inbound` to the service of a project, waking the project up if it is idle
+    async fn forward<S>(&self, mut inbound: S, project_name: &ProjectName) -> Result<(), Error>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        let (project, was_stopped) = self
+            .gateway
+            .find_or_start_project(project_name, self.task_sender.clone())
+            .await?;
+        let ip = project.state.target_ip().ok_or(ProjectNotReady)?;
+
+        if was_stopped {
+            debug!(shuttle.project.name = %project_name, "project waking up, checking service port");
+
+            let wake_config = self.gateway.wake_config(project_name).await?;
+            wait_for_service_port(ip, Duration::from_secs(wake_config.timeout)).await;
+        }
+
+        let mut outbound =
+            TcpStream::connect(SocketAddr::new(ip, DEPLOYER_SERVICE_HTTP_PORT)).await?;
+        tokio::io::copy_bidirectional(&mut inbound, &mut outbound).await?;
+
+        Ok(())
+    }
+}
+
+/// The ports projects get to receive raw TCP connections on, as is
+pub struct TcpPorts {
+    proxy: Arc<TcpProxy>,
+    ip: IpAddr,
+    range: RangeInclusive<u16>,
+    listeners: Mutex<HashMap<u16, JoinHandle<()>>>,
+}
+
+impl TcpPorts {
+    /// Listen on `ip`, giving ports of `range` to projects
+    pub fn new(proxy: Arc<TcpProxy>, ip: IpAddr, range: RangeInclusive<u16>) -> Self {
+        Self {
+            proxy,
+            ip,
+            range,
+            listeners: Default::default(),
+        }
+    }
+
+    pub fn range(&self) -> RangeInclusive<u16> {
+        self.range.clone()
+    }
+
+    /// Start forwarding the connections to `port` to the project it is given to. Does nothing if the port is
+    /// already open.
+    pub async fn open(&self, port: u16) -> io::Result<()> {
+        if self
+            .listeners
+            .lock()
+            .unwrap()
+            .get(&port)
+            .is_some_and(|listener| !listener.is_finished())
+        {
+            return Ok(());
+        }
+
+        let address = SocketAddr::new(self.ip, port);
+        let listener = TcpListener::bind(address).await?;
+        info!(%address, "accepting TCP connections");
+
+        let handle = tokio::spawn(serve_port(self.proxy.clone(), listener, port));
+        if let Some(previous) = self.listeners.lock().unwrap().insert(port, handle) {
+            previous.abort();
+        }
+
+        Ok(())
+    }
+
+    /// Stop accepting connections on `port`. The connections that are open are kept.
+    pub fn close(&self, port: u16) {
+        if let Some(listener) = self.listeners.lock().unwrap().remove(&port) {
+            info!(port, "no longer accepting TCP connections");
+            listener.abort();
+        }
+    }
+}
+
+async fn serve_port(proxy: Arc<TcpProxy>, listener: TcpListener, port: u16) {
+    loop {
+        let (stream, peer) = accept(&listener).await;
+        let proxy = proxy.clone();
+
+        tokio::spawn(
+            async move {
+                // The port may have been given to another project since it was opened
+                let result = match proxy.gateway.project_for_tcp_port(port).await {
+                    Ok(Some(project_name)) => proxy.forward(stream, &project_name).await,
+                    Ok(None) => Err(Error::NoProject),
+                    Err(error) => Err(error.into()),
+                };
+
+                if let Err(error) = result {
+                    debug!(
+                        error = &error as &dyn std::error::Error,
+                        "TCP connection ended"
+                    );
+                }
+            }
+            .instrument(debug_span!("tcp proxy", port, %peer)),
+        );
+    }
+}
+
+/// Accept the next connection on `listener`. Errors like running out of file descriptors are retried with a backoff,
+/// so they do not turn into a busy loop.
+async fn accept(listener: &TcpListener) -> (TcpStream, SocketAddr) {
+    let mut backoff = ACCEPT_BACKOFF_START;
+
+    loop {
+        match listener.accept().await {
+            Ok(accepted) => return accepted,
+            Err(error) => {
+                error!(
+                    error = &error as &dyn std::error::Error,
+                    "failed to accept TCP connection, retrying in {backoff:?}"
+                );
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(ACCEPT_BACKOFF_MAX);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use shuttle_backends::test_utils::gateway::PermissionsMock;
+    use shuttle_common::claims::AccountTier;
+    use tokio::sync::mpsc::channel;
+
+    use super::*;
+    use crate::tests::World;
+
+    #[tokio::test]
+    async fn project_for_server_name() {
+        let world = World::new().await;
+        let gateway = Arc::new(
+            GatewayService::init(
+                world.args(),
+                world.pool(),
+                "".into(),
+                Box::<PermissionsMock>::default(),
+            )
+            .await
+            .unwrap(),
+        );
+        let (task_sender, _task_receiver) = channel(1);
+        let proxy = TcpProxy::new(gateway.clone(), task_sender, world.fqdn());
+
+        let matrix: ProjectName = "matrix".parse().unwrap();
+        let domain: FQDN = "neo.the.matrix".parse().unwrap();
+        gateway
+            .create_project(
+                matrix.clone(),
+                &"neo".to_owned(),
+                false,
+                true,
+                0,
+                AccountTier::Basic.into(),
+            )
+            .await
+            .unwrap();
+        gateway
+            .create_custom_domain(
+                &matrix,
+                &domain,
+                "dummy certificate",
+                "dummy private key",
+                false,
+            )
+            .await
+            .unwrap();
+
+        // The project subdomain of the proxy
+        assert_eq!(
+            proxy
+                .project_for_server_name(&format!("matrix.{}", world.fqdn()))
+                .await
+                .unwrap(),
+            matrix
+        );
+
+        // A custom domain of the project
+        assert_eq!(
+            proxy
+                .project_for_server_name("neo.the.matrix")
+                .await
+                .unwrap(),
+            matrix
+        );
+
+        // Neither a project subdomain nor a custom domain
+        for server_name in [
+            world.fqdn().to_string(),
+            format!("deep.matrix.{}", world.fqdn()),
+            "agent.smith".to_string(),
+        ] {
+            assert!(matches!(
+                proxy.project_for_server_name(&server_name).await,
+                Err(Error::Service(service::Error::CustomDomainNotFound))
+            ));
+        }
+
+        // A reserved name
+        assert!(matches!(
+            proxy
+                .project_for_server_name(&format!("shuttle.{}", world.fqdn()))
+                .await,
+            Err(Error::InvalidProjectName(_))
+        ));
+    }
+}
@@ -1,11 +1,12 @@
 use std::collections::HashMap;
 use std::fs::File;
-use std::io::{BufReader, Read, Write};
+use std::io::{BufReader, Cursor, Read, Write};
 use std::path::Path;
 use std::sync::Arc;
 
 use axum_server::accept::DefaultAcceptor;
 use axum_server::tls_rustls::{RustlsAcceptor, RustlsConfig};
+use fqdn::Fqdn;
 use futures::executor::block_on;
 use http::StatusCode;
 use pem::Pem;
@@ -17,6 +18,9 @@ use shuttle_common::models::error::ApiError;
 use thiserror::Error;
 use tokio::runtime::Handle;
 use tokio::sync::RwLock;
+use x509_parser::parse_x509_certificate;
+use x509_parser::pem::parse_x509_pem;
+use x509_parser::time::ASN1Time;
 
 #[derive(Error, Debug)]
 pub enum Error {
@@ -31,6 +35,9 @@ pub enum Error {
 
     #[error("failed to get signing key: {0}")]
     SigningKey(#[from] sign::SignError),
+
+    #[error("failed to parse certificate: {0}")]
+    Certificate(String),
 }
 
 impl From<Error> for ApiError {
@@ -45,6 +52,44 @@ impl From<Error> for ApiError {
 /// LetsEncrypt recommends to renew a certificate when its close to 30 days validity window.
 pub const RENEWAL_VALIDITY_THRESHOLD_IN_DAYS: i64 = 30;
 
+/// Details of the leaf certificate of a chain
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CertificateDetails {
+    pub serial_hex: String,
+    pub not_after: ASN1Time,
+}
+
+impl CertificateDetails {
+    pub fn from_der(der: &[u8]) -> Result<Self, Error> {
+        let (_, certificate) =
+            parse_x509_certificate(der).map_err(|err| Error::Certificate(err.to_string()))?;
+
+        Ok(Self {
+            serial_hex: certificate
+                .raw_serial()
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect(),
+            not_after: certificate.validity().not_after,
+        })
+    }
+
+    /// Get the details of the first certificate in a PEM chain
+    pub fn from_pem(pem: &str) -> Result<Self, Error> {
+        let (_, pem) =
+            parse_x509_pem(pem.as_bytes()).map_err(|err| Error::Certificate(err.to_string()))?;
+
+        Self::from_der(&pem.contents)
+    }
+
+    /// Whether the certificate expires within the renewal `window`
+    pub fn needs_renewal(&self, window_in_days: i64) -> bool {
+        // `None` means the certificate already expired
+        (self.not_after - ASN1Time::now())
+            .map_or(true, |validity| validity.whole_days() <= window_in_days)
+    }
+}
+
 #[derive(Clone)]
 pub struct ChainAndPrivateKey {
     chain: Vec<Certificate>,
@@ -124,6 +169,13 @@ impl GatewayCertResolver {
         self.keys.read().await.get(sni).cloned()
     }
 
+    /// Get the details of the certificate currently served for the given domain
+    pub async fn certificate_details(&self, sni: &str) -> Option<CertificateDetails> {
+        let key = self.get(sni).await?;
+
+        CertificateDetails::from_der(&key.cert.first()?.0).ok()
+    }
+
     pub async fn serve_default_der(&self, certs: ChainAndPrivateKey) -> Result<(), Error> {
         *self.default.write().await = Some(Arc::new(certs.into_certified_key()?));
         Ok(())
@@ -149,6 +201,27 @@ impl GatewayCertResolver {
         let certs = ChainAndPrivateKey::parse_pem(rd)?;
         self.serve_der(sni, certs).await
     }
+
+    /// Load the certificate of a custom domain, which is also served for all its subdomains
+    /// when it is a `wildcard` one.
+    pub async fn serve_custom_domain_pem(
+        &self,
+        fqdn: &Fqdn,
+        wildcard: bool,
+        certs: &str,
+        private_key: &str,
+    ) -> Result<(), Error> {
+        let mut buf = Vec::new();
+        buf.extend(certs.as_bytes());
+        buf.extend(private_key.as_bytes());
+        self.serve_pem(&fqdn.to_string(), Cursor::new(&buf)).await?;
+        if wildcard {
+            self.serve_pem(&format!("*.{fqdn}"), Cursor::new(&buf))
+                .await?;
+        }
+
+        Ok(())
+    }
 }
 
 impl ResolvesServerCert for GatewayCertResolver {
@@ -158,10 +231,15 @@ impl ResolvesServerCert for GatewayCertResolver {
         let _ = handle.enter();
         block_on(async move {
             if let Some(cert) = self.get(sni).await {
-                Some(cert)
-            } else {
-                self.default.read().await.clone()
+                return Some(cert);
             }
+            // A wildcard certificate of the parent domain covers this one
+            if let Some((_, parent)) = sni.split_once('.') {
+                if let Some(cert) = self.get(&format!("*.{parent}")).await {
+                    return Some(cert);
+                }
+            }
+            self.default.read().await.clone()
         })
     }
 }
@@ -179,3 +257,46 @@ pub fn make_tls_acceptor() -> (Arc<GatewayCertResolver>, RustlsAcceptor<DefaultA
 
     (resolver, RustlsAcceptor::new(rustls_config))
 }
+
+/// Acceptor for raw TCP connections, which serves the same certificates as the user proxy but does not
+/// negotiate an application protocol, since that is up to the service
+pub fn make_tcp_tls_acceptor(
+    resolver: Arc<GatewayCertResolver>,
+) -> RustlsAcceptor<DefaultAcceptor> {
+    let server_config = ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_cert_resolver(resolver as Arc<dyn ResolvesServerCert>);
+
+    RustlsAcceptor::new(RustlsConfig::from_config(Arc::new(server_config)))
+}
+
+#[cfg(test)]
+mod tests {
+    use rcgen::{date_time_ymd, Certificate, CertificateParams};
+
+    use super::*;
+
+    #[test]
+    fn certificate_details() {
+        let valid = rcgen::generate_simple_self_signed(vec!["neo.the.matrix".to_string()])
+            .unwrap()
+            .serialize_pem()
+            .unwrap();
+        let details = CertificateDetails::from_pem(&valid).unwrap();
+        assert!(!details.serial_hex.is_empty());
+        assert!(!details.needs_renewal(RENEWAL_VALIDITY_THRESHOLD_IN_DAYS));
+
+        let mut params = CertificateParams::new(vec!["neo.the.matrix".to_string()]);
+        params.not_before = date_time_ymd(1999, 3, 31);
+        params.not_after = date_time_ymd(2003, 5, 15);
+        let expired = Certificate::from_params(params)
+            .unwrap()
+            .serialize_pem()
+            .unwrap();
+        let details = CertificateDetails::from_pem(&expired).unwrap();
+        assert!(details.needs_renewal(RENEWAL_VALIDITY_THRESHOLD_IN_DAYS));
+
+        assert!(CertificateDetails::from_pem("not a certificate").is_err());
+    }
+}
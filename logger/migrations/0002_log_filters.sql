@@ -0,0 +1,8 @@
+-- Severity of the log line, from 0 (TRACE) to 4 (ERROR). Detected when the line is stored,
+-- and NULL for lines without a level (including the ones stored before this column was added).
+ALTER TABLE logs ADD COLUMN level SMALLINT;
+
+-- Most log queries filter on a deployment and order by time
+DROP INDEX IF EXISTS deployment_idx;
+CREATE INDEX deployment_timestamp_idx ON logs (deployment_id, tx_timestamp);
+CREATE INDEX deployment_level_idx ON logs (deployment_id, level);
@@ -1,9 +1,11 @@
 use core::fmt;
+use std::collections::VecDeque;
 use std::time::{Duration, SystemTime};
 
 use async_trait::async_trait;
 use chrono::NaiveDateTime;
 use prost_types::Timestamp;
+use regex::Regex;
 use shuttle_common::log::LogsRange;
 use shuttle_proto::logger::{LogItem, LogLine};
 use sqlx::{
@@ -13,6 +15,7 @@ use sqlx::{
 };
 use thiserror::Error;
 use tokio::sync::broadcast::{self, Sender};
+use tokio_stream::StreamExt;
 use tracing::{error, info, warn, Instrument, Span};
 
 use tonic::transport::Uri;
@@ -51,9 +54,119 @@ pub trait Dal {
         deployment_id: String,
         head: Option<u32>,
         tail: Option<u32>,
+        filter: &LogsFilter,
     ) -> Result<Vec<Log>, DalError>;
 }
 
+/// Conditions a log line has to match, on top of the requested range
+#[derive(Clone, Debug, Default)]
+pub struct LogsFilter {
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub grep: Option<Grep>,
+    pub source: Option<String>,
+    /// Minimum severity, as returned by [`level_severity`]
+    pub level: Option<i16>,
+}
+
+#[derive(Clone, Debug)]
+pub enum Grep {
+    Text(String),
+    Regex(Regex),
+}
+
+impl LogsFilter {
+    /// Check a new log line against this filter, for when it can't be done by the database
+    pub fn matches(&self, log: &Log) -> bool {
+        if self.since.is_some_and(|since| log.tx_timestamp < since)
+            || self.until.is_some_and(|until| log.tx_timestamp >= until)
+        {
+            return false;
+        }
+
+        if let Some(source) = &self.source {
+            if !log.shuttle_service_name.eq_ignore_ascii_case(source) {
+                return false;
+            }
+        }
+
+        if let Some(level) = self.level {
+            match log.level {
+                Some(log_level) if log_level >= level => {}
+                _ => return false,
+            }
+        }
+
+        self.grep_matches(&log.data)
+    }
+
+    /// Check the data of a log line against the text or pattern to look for.
+    ///
+    /// This is always done here rather than by the database, so that stored and followed logs match the same way, and
+    /// lines that are not valid UTF-8 are searched instead of failing the query.
+    fn grep_matches(&self, data: &[u8]) -> bool {
+        match &self.grep {
+            Some(Grep::Text(text)) => String::from_utf8_lossy(data).contains(text.as_str()),
+            Some(Grep::Regex(regex)) => regex.is_match(&String::from_utf8_lossy(data)),
+            None => true,
+        }
+    }
+
+    /// Add the `WHERE` clause for this filter to a query, except for the grep which is done by [`Self::grep_matches`]
+    fn push_where(&self, builder: &mut QueryBuilder<'_, sqlx::Postgres>, deployment_id: String) {
+        builder
+            .push(" WHERE deployment_id = ")
+            .push_bind(deployment_id);
+
+        if let Some(since) = self.since {
+            builder.push(" AND tx_timestamp >= ").push_bind(since);
+        }
+        if let Some(until) = self.until {
+            builder.push(" AND tx_timestamp < ").push_bind(until);
+        }
+        if let Some(source) = &self.source {
+            builder
+                .push(" AND lower(shuttle_service_name) = lower(")
+                .push_bind(source.clone())
+                .push(")");
+        }
+        if let Some(level) = self.level {
+            builder.push(" AND level >= ").push_bind(level);
+        }
+    }
+}
+
+/// Severity of a level name, from 0 for `TRACE` up to 4 for `ERROR`
+pub fn level_severity(level: &str) -> Option<i16> {
+    match level {
+        "TRACE" => Some(0),
+        "DEBUG" => Some(1),
+        "INFO" => Some(2),
+        "WARN" => Some(3),
+        "ERROR" => Some(4),
+        _ => None,
+    }
+}
+
+/// Find the level of a log line formatted by `tracing`, such as `2024-01-01T00:00:00Z  INFO my_crate: message`
+fn detect_level(data: &[u8]) -> Option<i16> {
+    // The level is near the start of the line
+    let start = String::from_utf8_lossy(&data[..data.len().min(128)]);
+
+    // Remove the color codes around the level
+    let mut line = String::with_capacity(start.len());
+    let mut chars = start.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            chars.find(char::is_ascii_alphabetic);
+        } else {
+            line.push(c);
+        }
+    }
+
+    line.split_whitespace().take(3).find_map(level_severity)
+}
+
 #[derive(Clone)]
 pub struct Postgres {
     pool: PgPool,
@@ -103,7 +216,7 @@ impl Postgres {
                 match rx.recv().await {
                     Ok((logs, parent_span)) => {
                         let mut builder = QueryBuilder::new(
-                            "INSERT INTO logs (deployment_id, shuttle_service_name, data, tx_timestamp)",
+                            "INSERT INTO logs (deployment_id, shuttle_service_name, data, tx_timestamp, level)",
                         );
 
                         parent_span.in_scope(|| {
@@ -123,7 +236,8 @@ impl Postgres {
                             b.push_bind(log.deployment_id)
                                 .push_bind(log.shuttle_service_name)
                                 .push_bind(log.data)
-                                .push_bind(log.tx_timestamp);
+                                .push_bind(log.tx_timestamp)
+                                .push_bind(log.level);
                         });
                         let query = builder.build();
 
@@ -161,6 +275,7 @@ impl Dal for Postgres {
         deployment_id: String,
         head: Option<u32>,
         tail: Option<u32>,
+        filter: &LogsFilter,
     ) -> Result<Vec<Log>, DalError> {
         let mode = match (head, tail) {
             (Some(len), None) => LogsRange::Head(len),
@@ -169,38 +284,81 @@ impl Dal for Postgres {
             _ => LogsRange::Tail(1000),
         };
 
-        let result = match mode {
+        if filter.grep.is_some() {
+            return self.get_grepped_logs(deployment_id, mode, filter).await;
+        }
+
+        let mut builder = QueryBuilder::new("");
+        match mode {
             LogsRange::Head(len) => {
-                sqlx::query_as("SELECT * FROM logs WHERE deployment_id = $1 ORDER BY tx_timestamp limit $2")
-                    .bind(deployment_id)
-                    .bind(len as i64)
-                    .fetch_all(&self.pool)
-                    .await?
+                builder.push("SELECT * FROM logs");
+                filter.push_where(&mut builder, deployment_id);
+                builder
+                    .push(" ORDER BY tx_timestamp limit ")
+                    .push_bind(len as i64);
             }
             LogsRange::Tail(len) => {
-                sqlx::query_as("SELECT * FROM (SELECT * FROM logs WHERE deployment_id = $1 ORDER BY tx_timestamp DESC limit $2) AS TAIL_TABLE ORDER BY tx_timestamp")
-                    .bind(deployment_id)
-                    .bind(len as i64)
-                    .fetch_all(&self.pool)
-                    .await?
+                builder.push("SELECT * FROM (SELECT * FROM logs");
+                filter.push_where(&mut builder, deployment_id);
+                builder
+                    .push(" ORDER BY tx_timestamp DESC limit ")
+                    .push_bind(len as i64)
+                    .push(") AS TAIL_TABLE ORDER BY tx_timestamp");
             }
             LogsRange::All => {
-                sqlx::query_as("SELECT * FROM logs WHERE deployment_id = $1 ORDER BY tx_timestamp")
-                    .bind(deployment_id)
-                    .fetch_all(&self.pool)
-                    .await?
+                builder.push("SELECT * FROM logs");
+                filter.push_where(&mut builder, deployment_id);
+                builder.push(" ORDER BY tx_timestamp");
             }
         };
+
+        let result = builder.build_query_as().fetch_all(&self.pool).await?;
+
         Ok(result)
     }
 }
 
+impl Postgres {
+    /// Get the logs matching a filter with a grep. The range is applied after the grep, so the lines are streamed
+    /// from the database and searched one by one.
+    async fn get_grepped_logs(
+        &self,
+        deployment_id: String,
+        mode: LogsRange,
+        filter: &LogsFilter,
+    ) -> Result<Vec<Log>, DalError> {
+        let mut builder = QueryBuilder::new("SELECT * FROM logs");
+        filter.push_where(&mut builder, deployment_id);
+        builder.push(" ORDER BY tx_timestamp");
+
+        let mut rows = builder.build_query_as::<Log>().fetch(&self.pool);
+        let mut logs = VecDeque::new();
+        while let Some(log) = rows.next().await {
+            let log = log?;
+            if !filter.grep_matches(&log.data) {
+                continue;
+            }
+
+            if matches!(mode, LogsRange::Head(len) if logs.len() >= len as usize) {
+                break;
+            }
+            logs.push_back(log);
+            if matches!(mode, LogsRange::Tail(len) if logs.len() > len as usize) {
+                logs.pop_front();
+            }
+        }
+
+        Ok(logs.into())
+    }
+}
+
 #[derive(Clone, Debug, FromRow)]
 pub struct Log {
     pub(crate) deployment_id: String,
     pub(crate) shuttle_service_name: String,
     pub(crate) tx_timestamp: DateTime<Utc>,
     pub(crate) data: Vec<u8>,
+    pub(crate) level: Option<i16>,
 }
 
 impl Log {
@@ -218,6 +376,7 @@ impl Log {
                 .unwrap_or_default(),
                 Utc,
             ),
+            level: detect_level(&log_line.data),
             data: log_line.data,
         })
     }
@@ -245,3 +404,25 @@ impl From<Log> for LogLine {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::detect_level;
+
+    #[test]
+    fn detects_tracing_levels() {
+        assert_eq!(detect_level(b" INFO shuttle_deployer: Building"), Some(2));
+        assert_eq!(
+            detect_level(b"2024-05-01T12:00:00.000000Z ERROR my_app: failed to connect"),
+            Some(4)
+        );
+        assert_eq!(
+            detect_level(
+                b"\x1b[2m2024-05-01T12:00:00.000000Z\x1b[0m \x1b[33m WARN\x1b[0m my_app: slow"
+            ),
+            Some(3)
+        );
+        assert_eq!(detect_level(b"Listening on 0.0.0.0:8000"), None);
+        assert_eq!(detect_level(b"request failed with status ERROR"), None);
+    }
+}
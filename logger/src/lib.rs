@@ -1,6 +1,9 @@
 use async_trait::async_trait;
-use dal::Log;
+use chrono::{DateTime, Utc};
+use dal::{level_severity, Grep, Log, LogsFilter};
 use dal::{Dal, DalError};
+use prost_types::Timestamp;
+use regex::Regex;
 use shuttle_backends::auth::VerifyClaim;
 use shuttle_common::claims::Scope;
 use shuttle_proto::logger::LogLine;
@@ -24,14 +27,54 @@ pub use dal::Postgres;
 pub enum Error {
     #[error("failed to interact with database: {0}")]
     Dal(#[from] DalError),
+    #[error("invalid logs filter: {0}")]
+    InvalidFilter(String),
 }
 
 impl From<Error> for Status {
     fn from(error: Error) -> Self {
-        Self::internal(error.to_string())
+        match error {
+            Error::InvalidFilter(_) => Self::invalid_argument(error.to_string()),
+            Error::Dal(_) => Self::internal(error.to_string()),
+        }
     }
 }
 
+/// Get the filters of a logs request
+fn logs_filter(request: &LogsRequest) -> Result<LogsFilter, Error> {
+    let grep = match &request.grep {
+        Some(pattern) if request.regex => Some(Grep::Regex(
+            Regex::new(pattern).map_err(|error| Error::InvalidFilter(error.to_string()))?,
+        )),
+        Some(text) => Some(Grep::Text(text.clone())),
+        None => None,
+    };
+    let level = request
+        .level
+        .as_deref()
+        .map(|level| {
+            level_severity(&level.to_uppercase())
+                .ok_or_else(|| Error::InvalidFilter(format!("unknown level '{level}'")))
+        })
+        .transpose()?;
+
+    Ok(LogsFilter {
+        since: request.since.clone().map(timestamp_to_datetime),
+        until: request.until.clone().map(timestamp_to_datetime),
+        grep,
+        source: request.source.clone(),
+        level,
+    })
+}
+
+fn timestamp_to_datetime(timestamp: Timestamp) -> DateTime<Utc> {
+    DateTime::from_timestamp(
+        timestamp.seconds,
+        timestamp.nanos.try_into().unwrap_or_default(),
+    )
+    .unwrap_or_default()
+}
+
 pub struct Service<D> {
     dal: D,
     logs_tx: Sender<(Vec<Log>, Span)>,
@@ -50,8 +93,9 @@ where
         deployment_id: String,
         head: Option<u32>,
         tail: Option<u32>,
+        filter: &LogsFilter,
     ) -> Result<Vec<LogLine>, Error> {
-        let logs = self.dal.get_logs(deployment_id, head, tail).await?;
+        let logs = self.dal.get_logs(deployment_id, head, tail, filter).await?;
 
         Ok(logs.into_iter().map(Into::into).collect())
     }
@@ -99,8 +143,9 @@ where
         request.verify(Scope::Logs)?;
 
         let request = request.into_inner();
+        let filter = logs_filter(&request)?;
         let log_items = self
-            .get_logs(request.deployment_id, request.head, request.tail)
+            .get_logs(request.deployment_id, request.head, request.tail, &filter)
             .await?;
         let result = LogsResponse { log_items };
 
@@ -118,15 +163,20 @@ where
 
         // Subscribe as soon as possible
         let mut logs_rx = self.logs_tx.subscribe();
+        let request = request.into_inner();
+        let filter = logs_filter(&request)?;
         let LogsRequest {
             deployment_id,
             head,
             tail,
-        } = request.into_inner();
+            ..
+        } = request;
         let (tx, rx) = mpsc::channel(1);
 
         // Get logs before stream was started
-        let logs = self.get_logs(deployment_id.clone(), head, tail).await?;
+        let logs = self
+            .get_logs(deployment_id.clone(), head, tail, &filter)
+            .await?;
 
         tokio::spawn(async move {
             let mut last = Default::default();
@@ -153,6 +203,7 @@ where
 
                         for log in logs {
                             if log.deployment_id == deployment_id
+                                && filter.matches(&log)
                                 && log.tx_timestamp.timestamp() >= last.seconds
                                 && log.tx_timestamp.timestamp_nanos_opt().unwrap_or_default()
                                     > last.nanos.into()
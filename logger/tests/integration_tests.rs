@@ -92,6 +92,7 @@ mod needs_docker {
                     deployment_id: deployment_id.into(),
                     head: None,
                     tail: None,
+                    ..Default::default()
                 }))
                 .await
                 .unwrap()
@@ -113,6 +114,161 @@ mod needs_docker {
         }
     }
 
+    #[tokio::test]
+    async fn get_filtered_logs() {
+        let logger_port = pick_unused_port().unwrap();
+        let deployment_id = "runtime-filter-logs-deployment-id";
+
+        let server = spawn_server(logger_port);
+
+        let test_future = tokio::spawn(async move {
+            // Ensure the DB has been created and server has started.
+            tokio::time::sleep(Duration::from_millis(300)).await;
+
+            let dst = format!("http://localhost:{logger_port}");
+            let mut client = LoggerClient::connect(dst).await.unwrap();
+
+            let log_line = |service_name: &str, secs: u64, data: &[u8]| LogItem {
+                deployment_id: deployment_id.to_string(),
+                log_line: Some(LogLine {
+                    service_name: service_name.to_string(),
+                    tx_timestamp: Some(Timestamp::from(
+                        SystemTime::UNIX_EPOCH
+                            .checked_add(Duration::from_secs(secs))
+                            .unwrap(),
+                    )),
+                    data: data.to_vec(),
+                }),
+            };
+            let stored_logs = vec![
+                log_line("Deployer", 10, b" INFO shuttle_deployer: Building"),
+                log_line("Runtime", 20, b" INFO my_app: started"),
+                log_line("Runtime", 30, b"ERROR my_app: thread 'main' panicked"),
+                log_line("Runtime", 40, b"plain output from println"),
+                log_line("Deployer", 50, b"\xff\xfe binary output before a panic"),
+            ];
+            let response = client
+                .store_logs(Request::new(StoreLogsRequest {
+                    logs: stored_logs.clone(),
+                }))
+                .await
+                .unwrap()
+                .into_inner();
+            assert!(response.success);
+
+            // Allow some time for the logs to be inserted into the DB
+            sleep(Duration::from_millis(500)).await;
+
+            let expected = |indices: &[usize]| {
+                indices
+                    .iter()
+                    .map(|i| stored_logs[*i].clone().log_line.unwrap())
+                    .collect::<Vec<LogLine>>()
+            };
+            let at = |secs: u64| {
+                Some(Timestamp::from(
+                    SystemTime::UNIX_EPOCH
+                        .checked_add(Duration::from_secs(secs))
+                        .unwrap(),
+                ))
+            };
+
+            let requests = [
+                (
+                    LogsRequest {
+                        since: at(20),
+                        until: at(40),
+                        ..Default::default()
+                    },
+                    expected(&[1, 2]),
+                ),
+                (
+                    LogsRequest {
+                        source: Some("runtime".to_string()),
+                        ..Default::default()
+                    },
+                    expected(&[1, 2, 3]),
+                ),
+                (
+                    LogsRequest {
+                        level: Some("error".to_string()),
+                        ..Default::default()
+                    },
+                    expected(&[2]),
+                ),
+                (
+                    LogsRequest {
+                        grep: Some("panic".to_string()),
+                        ..Default::default()
+                    },
+                    expected(&[2, 4]),
+                ),
+                (
+                    LogsRequest {
+                        grep: Some("panic".to_string()),
+                        tail: Some(1),
+                        ..Default::default()
+                    },
+                    expected(&[4]),
+                ),
+                (
+                    // Word boundaries are the same as when following logs
+                    LogsRequest {
+                        grep: Some(r"\bpanicked\b".to_string()),
+                        regex: true,
+                        ..Default::default()
+                    },
+                    expected(&[2]),
+                ),
+                (
+                    LogsRequest {
+                        grep: Some("^ INFO [a-z_]+: (Building|started)$".to_string()),
+                        regex: true,
+                        ..Default::default()
+                    },
+                    expected(&[0, 1]),
+                ),
+                (
+                    LogsRequest {
+                        tail: Some(1),
+                        level: Some("info".to_string()),
+                        ..Default::default()
+                    },
+                    expected(&[2]),
+                ),
+            ];
+
+            for (request, expected) in requests {
+                let logs = client
+                    .get_logs(Request::new(LogsRequest {
+                        deployment_id: deployment_id.into(),
+                        ..request
+                    }))
+                    .await
+                    .unwrap()
+                    .into_inner()
+                    .log_items;
+
+                assert_eq!(logs, expected);
+            }
+
+            let status = client
+                .get_logs(Request::new(LogsRequest {
+                    deployment_id: deployment_id.into(),
+                    level: Some("loud".to_string()),
+                    ..Default::default()
+                }))
+                .await
+                .unwrap_err();
+            assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        });
+
+        tokio::select! {
+            _ = server => panic!("server stopped first"),
+            result = test_future => result.expect("test should succeed")
+        }
+    }
+
     #[tokio::test]
     async fn get_stream_logs() {
         let logger_port = pick_unused_port().unwrap();
@@ -159,6 +315,7 @@ mod needs_docker {
                     deployment_id: deployment_id.into(),
                     head: None,
                     tail: None,
+                    ..Default::default()
                 }))
                 .await
                 .unwrap()
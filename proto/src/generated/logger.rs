@@ -19,6 +19,24 @@ pub struct LogsRequest {
     pub head: ::core::option::Option<u32>,
     #[prost(uint32, optional, tag = "3")]
     pub tail: ::core::option::Option<u32>,
+    /// Only logs from this time onwards
+    #[prost(message, optional, tag = "4")]
+    pub since: ::core::option::Option<::prost_types::Timestamp>,
+    /// Only logs from before this time
+    #[prost(message, optional, tag = "5")]
+    pub until: ::core::option::Option<::prost_types::Timestamp>,
+    /// Only logs containing this text
+    #[prost(string, optional, tag = "6")]
+    pub grep: ::core::option::Option<::prost::alloc::string::String>,
+    /// Match `grep` as a regular expression in the syntax of the Rust `regex` crate, instead of as plain text
+    #[prost(bool, tag = "7")]
+    pub regex: bool,
+    /// Only logs from this source, e.g. "deployer" or "runtime"
+    #[prost(string, optional, tag = "8")]
+    pub source: ::core::option::Option<::prost::alloc::string::String>,
+    /// Only logs at this level or above, e.g. "warn"
+    #[prost(string, optional, tag = "9")]
+    pub level: ::core::option::Option<::prost::alloc::string::String>,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -6,6 +6,9 @@ pub struct DatabaseRequest {
     /// Override the default db name. Only applies to RDS.
     #[prost(string, optional, tag = "2")]
     pub db_name: ::core::option::Option<::prost::alloc::string::String>,
+    /// Name of this database instance, when a project has several of the same type
+    #[prost(string, optional, tag = "3")]
+    pub resource_name: ::core::option::Option<::prost::alloc::string::String>,
     #[prost(oneof = "database_request::DbType", tags = "10, 11")]
     pub db_type: ::core::option::Option<database_request::DbType>,
 }
@@ -23,7 +26,7 @@ pub mod database_request {
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct Shared {
-    #[prost(oneof = "shared::Engine", tags = "1, 50")]
+    #[prost(oneof = "shared::Engine", tags = "1, 50, 51")]
     pub engine: ::core::option::Option<shared::Engine>,
 }
 /// Nested message and enum types in `Shared`.
@@ -35,6 +38,8 @@ pub mod shared {
         Postgres(::prost::alloc::string::String),
         #[prost(string, tag = "50")]
         Mongodb(::prost::alloc::string::String),
+        #[prost(string, tag = "51")]
+        Redis(::prost::alloc::string::String),
     }
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -19,6 +19,9 @@ pub mod record_request {
         pub config: ::prost::alloc::vec::Vec<u8>,
         #[prost(bytes = "vec", tag = "3")]
         pub data: ::prost::alloc::vec::Vec<u8>,
+        /// Tells apart multiple instances of the same type. Empty for the default instance.
+        #[prost(string, tag = "4")]
+        pub name: ::prost::alloc::string::String,
     }
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -70,6 +73,8 @@ pub struct ResourceIds {
     pub service_id: ::prost::alloc::string::String,
     #[prost(string, tag = "3")]
     pub r#type: ::prost::alloc::string::String,
+    #[prost(string, tag = "4")]
+    pub name: ::prost::alloc::string::String,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -90,6 +95,8 @@ pub struct Resource {
     pub created_at: ::core::option::Option<::prost_types::Timestamp>,
     #[prost(message, optional, tag = "8")]
     pub last_updated: ::core::option::Option<::prost_types::Timestamp>,
+    #[prost(string, tag = "9")]
+    pub name: ::prost::alloc::string::String,
 }
 /// Generated client implementations.
 pub mod resource_recorder_client {
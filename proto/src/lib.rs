@@ -38,6 +38,7 @@ pub mod provisioner {
                     let engine = match engine {
                         SharedEngine::Postgres => shared::Engine::Postgres(String::new()),
                         SharedEngine::MongoDb => shared::Engine::Mongodb(String::new()),
+                        SharedEngine::Redis => shared::Engine::Redis(String::new()),
                     };
                     database_request::DbType::Shared(Shared {
                         engine: Some(engine),
@@ -70,6 +71,7 @@ pub mod provisioner {
                     shared::Engine::Mongodb(_) => {
                         Some(database::Type::Shared(SharedEngine::MongoDb))
                     }
+                    shared::Engine::Redis(_) => Some(database::Type::Shared(SharedEngine::Redis)),
                 },
                 database_request::DbType::AwsRds(AwsRds {
                     engine: Some(engine),
@@ -206,6 +208,7 @@ pub mod resource_recorder {
                     .context(format!("{} resource config should be valid JSON", r#type))?,
                 data: serde_json::from_slice(&resource.data)
                     .context(format!("{} resource data should be valid JSON", r#type))?,
+                name: Some(resource.name).filter(|name| !name.is_empty()),
             };
 
             Ok(response)
@@ -226,6 +229,7 @@ pub mod resource_recorder {
                     .context(format!("{} resource config should be valid JSON", r#type))?,
                 data: serde_json::from_slice(&resource.data)
                     .context(format!("{} resource data should be valid JSON", r#type))?,
+                name: Some(resource.name).filter(|name| !name.is_empty()),
             };
 
             Ok(response)
@@ -25,6 +25,8 @@ pub enum Error {
     UnexpectedSqlx(#[from] sqlx::Error),
     #[error("unexpected mongodb error: {0}")]
     UnexpectedMongodb(#[from] mongodb::error::Error),
+    #[error("unexpected redis error: {0}")]
+    UnexpectedRedis(#[from] redis::RedisError),
     #[error("failed to create RDS instance: {0}")]
     CreateRDSInstance(#[from] SdkError<CreateDBInstanceError>),
     #[error("failed to get description of RDS instance: {0}")]
@@ -11,11 +11,13 @@ use aws_sdk_rds::{
 pub use error::Error;
 use mongodb::{bson::doc, options::ClientOptions};
 use rand::Rng;
+use redis::AsyncCommands;
 use shuttle_backends::auth::VerifyClaim;
 use shuttle_backends::client::ServicesApiClient;
 use shuttle_backends::project_name::ProjectName;
 use shuttle_backends::ClaimExt;
 use shuttle_common::claims::{Claim, Scope};
+use shuttle_common::resource::is_valid_resource_name;
 pub use shuttle_proto::provisioner::provisioner_server::ProvisionerServer;
 use shuttle_proto::provisioner::{
     aws_rds, database_request::DbType, provisioner_server::Provisioner, shared, AwsRds,
@@ -40,20 +42,25 @@ pub struct ShuttleProvisioner {
     pool: PgPool,
     rds_client: aws_sdk_rds::Client,
     mongodb_client: mongodb::Client,
+    redis_client: redis::Client,
     fqdn: String,
     internal_pg_address: String,
     internal_mongodb_address: String,
+    internal_redis_address: String,
     rr_client: Arc<Mutex<resource_recorder::Client>>,
     gateway_client: ServicesApiClient,
 }
 
 impl ShuttleProvisioner {
+    #[allow(clippy::too_many_arguments)]
     pub async fn new(
         shared_pg_uri: &str,
         shared_mongodb_uri: &str,
+        shared_redis_uri: &str,
         fqdn: String,
         internal_pg_address: String,
         internal_mongodb_address: String,
+        internal_redis_address: String,
         resource_recorder_uri: Uri,
         gateway_uri: Uri,
     ) -> Result<Self, Error> {
@@ -66,6 +73,8 @@ impl ShuttleProvisioner {
         let mongodb_options = ClientOptions::parse(shared_mongodb_uri).await?;
         let mongodb_client = mongodb::Client::with_options(mongodb_options)?;
 
+        let redis_client = redis::Client::open(shared_redis_uri)?;
+
         // Default timeout is too long so lowering it
         let timeout_config = timeout::TimeoutConfig::builder()
             .operation_timeout(Duration::from_secs(120))
@@ -87,9 +96,11 @@ impl ShuttleProvisioner {
             pool,
             rds_client,
             mongodb_client,
+            redis_client,
             fqdn,
             internal_pg_address,
             internal_mongodb_address,
+            internal_redis_address,
             rr_client: Arc::new(Mutex::new(rr_client)),
             gateway_client,
         })
@@ -130,6 +141,20 @@ impl ShuttleProvisioner {
                     port: "27017".to_string(),
                 })
             }
+            shared::Engine::Redis(_) => {
+                let (username, password) = self.shared_redis(project_name).await?;
+
+                Ok(DatabaseResponse {
+                    engine: "redis".to_string(),
+                    username,
+                    password,
+                    // Everyone shares the default logical database and is isolated by key prefix
+                    database_name: "0".to_string(),
+                    address_private: self.internal_redis_address.clone(),
+                    address_public: self.fqdn.clone(),
+                    port: "6379".to_string(),
+                })
+            }
         }
     }
 
@@ -261,16 +286,48 @@ impl ShuttleProvisioner {
         }
     }
 
+    async fn shared_redis(&self, project_name: &str) -> Result<(String, String), Error> {
+        let username = format!("user-{project_name}");
+        let password = generate_password();
+
+        let mut conn = self.redis_client.get_multiplexed_tokio_connection().await?;
+
+        // `ACL SETUSER` creates the user if it does not exist. Resetting it first cycles the
+        // password of an existing user and makes sure no stale permissions are left behind.
+        // The user can only touch keys and channels prefixed with its project name.
+        redis::cmd("ACL")
+            .arg("SETUSER")
+            .arg(&username)
+            .arg("reset")
+            .arg("on")
+            .arg(format!(">{password}"))
+            .arg(format!("~{project_name}:*"))
+            .arg(format!("&{project_name}:*"))
+            .arg("+@all")
+            .arg("-@admin")
+            .arg("-@dangerous")
+            .query_async::<_, ()>(&mut conn)
+            .await
+            .map_err(|e| Error::CreateRole(e.to_string()))?;
+
+        info!("set up shared redis user: {username}");
+
+        save_redis_acl(&mut conn).await;
+
+        Ok((username, password))
+    }
+
     async fn request_aws_rds(
         &self,
         project_name: &str,
         engine: aws_rds::Engine,
         database_name: &Option<String>,
+        resource_name: Option<&str>,
     ) -> Result<DatabaseResponse, Error> {
         let client = &self.rds_client;
 
         let password = generate_password();
-        let instance_name = format!("{}-{}", project_name, engine);
+        let instance_name = rds_instance_name(project_name, &engine, resource_name);
 
         debug!("trying to get AWS RDS instance: {instance_name}");
         let instance = client
@@ -358,6 +415,7 @@ impl ShuttleProvisioner {
         match engine {
             shared::Engine::Postgres(_) => self.delete_shared_postgres(project_name).await?,
             shared::Engine::Mongodb(_) => self.delete_shared_mongodb(project_name).await?,
+            shared::Engine::Redis(_) => self.delete_shared_redis(project_name).await?,
         }
         Ok(DatabaseDeletionResponse {})
     }
@@ -437,13 +495,63 @@ impl ShuttleProvisioner {
         Ok(())
     }
 
+    async fn delete_shared_redis(&self, project_name: &str) -> Result<(), Error> {
+        let username = format!("user-{project_name}");
+        let key_pattern = format!("{project_name}:*");
+
+        let mut conn = self.redis_client.get_multiplexed_tokio_connection().await?;
+
+        let deleted: u32 = redis::cmd("ACL")
+            .arg("DELUSER")
+            .arg(&username)
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| Error::DeleteRole(e.to_string()))?;
+
+        if deleted > 0 {
+            info!("dropped shared redis user: {username}");
+            save_redis_acl(&mut conn).await;
+        } else {
+            warn!("did not drop shared redis user: {username}. Does not exist.");
+        }
+
+        // Collect the keys first since modifying the keyspace while scanning can return duplicates
+        let keys: Vec<String> = {
+            let mut iter = conn
+                .scan_match::<_, String>(&key_pattern)
+                .await
+                .map_err(|e| Error::DeleteDB(e.to_string()))?;
+            let mut keys = Vec::new();
+            while let Some(key) = iter.next_item().await {
+                keys.push(key);
+            }
+            keys
+        };
+
+        for chunk in keys.chunks(1000) {
+            redis::cmd("UNLINK")
+                .arg(chunk)
+                .query_async::<_, ()>(&mut conn)
+                .await
+                .map_err(|e| Error::DeleteDB(e.to_string()))?;
+        }
+
+        info!(
+            "dropped {} keys from shared redis for: {project_name}",
+            keys.len()
+        );
+
+        Ok(())
+    }
+
     async fn delete_aws_rds(
         &self,
         project_name: &str,
         engine: aws_rds::Engine,
+        resource_name: Option<&str>,
     ) -> Result<DatabaseDeletionResponse, Error> {
         let client = &self.rds_client;
-        let instance_name = format!("{project_name}-{engine}");
+        let instance_name = rds_instance_name(project_name, &engine, resource_name);
 
         // Try to delete the db instance.
         client
@@ -490,11 +598,15 @@ impl Provisioner for ShuttleProvisioner {
         self.verify_ownership(&claim, &request.project_name).await?;
 
         let db_type = request.db_type.unwrap();
+        validate_resource_name(&db_type, request.resource_name.as_deref())?;
 
         let reply = match db_type {
             DbType::Shared(Shared { engine }) => {
-                self.request_shared_db(&request.project_name, engine.expect("engine to be set"))
-                    .await?
+                self.request_shared_db(
+                    &shared_instance_name(&request.project_name, request.resource_name.as_deref()),
+                    engine.expect("engine to be set"),
+                )
+                .await?
             }
             DbType::AwsRds(AwsRds { engine }) => {
                 {
@@ -528,6 +640,7 @@ impl Provisioner for ShuttleProvisioner {
                     &request.project_name,
                     engine.expect("engine to be set"),
                     &request.db_name,
+                    request.resource_name.as_deref(),
                 )
                 .await?
             }
@@ -550,15 +663,23 @@ impl Provisioner for ShuttleProvisioner {
         self.verify_ownership(&claim, &request.project_name).await?;
 
         let db_type = request.db_type.unwrap();
+        validate_resource_name(&db_type, request.resource_name.as_deref())?;
 
         let reply = match db_type {
             DbType::Shared(Shared { engine }) => {
-                self.delete_shared_db(&request.project_name, engine.expect("engine to be set"))
-                    .await?
+                self.delete_shared_db(
+                    &shared_instance_name(&request.project_name, request.resource_name.as_deref()),
+                    engine.expect("engine to be set"),
+                )
+                .await?
             }
             DbType::AwsRds(AwsRds { engine }) => {
-                self.delete_aws_rds(&request.project_name, engine.expect("engine to be set"))
-                    .await?
+                self.delete_aws_rds(
+                    &request.project_name,
+                    engine.expect("engine to be set"),
+                    request.resource_name.as_deref(),
+                )
+                .await?
             }
         };
 
@@ -571,6 +692,51 @@ impl Provisioner for ShuttleProvisioner {
     }
 }
 
+/// The name shared databases, roles and users are derived from.
+///
+/// Named instances append the resource name with an underscore. Project names can not contain
+/// underscores, so this can never clash with the default instance of another project.
+fn shared_instance_name(project_name: &str, resource_name: Option<&str>) -> String {
+    match resource_name {
+        Some(name) => format!("{project_name}_{name}"),
+        None => project_name.to_string(),
+    }
+}
+
+/// The identifier of an RDS instance, which can only contain letters, digits and hyphens.
+///
+/// Named instances append the resource name after the engine. Since resource names can not contain
+/// hyphens or be an engine name (see [`validate_resource_name`]), this can never clash with the
+/// instance of another project.
+fn rds_instance_name(
+    project_name: &str,
+    engine: &aws_rds::Engine,
+    resource_name: Option<&str>,
+) -> String {
+    match resource_name {
+        Some(name) => format!("{project_name}-{engine}-{name}"),
+        None => format!("{project_name}-{engine}"),
+    }
+}
+
+fn validate_resource_name(db_type: &DbType, resource_name: Option<&str>) -> Result<(), Status> {
+    let Some(name) = resource_name else {
+        return Ok(());
+    };
+
+    if !is_valid_resource_name(name) {
+        return Err(Status::invalid_argument("invalid resource name"));
+    }
+
+    if matches!(db_type, DbType::AwsRds(_)) && ["postgres", "mysql", "mariadb"].contains(&name) {
+        return Err(Status::invalid_argument(
+            "the resource name of an RDS database can not be an engine name",
+        ));
+    }
+
+    Ok(())
+}
+
 fn generate_password() -> String {
     rand::thread_rng()
         .sample_iter(&rand::distributions::Alphanumeric)
@@ -579,6 +745,21 @@ fn generate_password() -> String {
         .collect()
 }
 
+/// Persist the ACL users so that they survive a restart of the shared Redis server.
+/// This is best effort since the server might not have been started with an ACL file.
+async fn save_redis_acl(conn: &mut redis::aio::MultiplexedConnection) {
+    if let Err(error) = redis::cmd("ACL")
+        .arg("SAVE")
+        .query_async::<_, ()>(conn)
+        .await
+    {
+        warn!(
+            error = &error as &dyn std::error::Error,
+            "failed to save shared redis ACL file"
+        );
+    }
+}
+
 async fn wait_for_instance(
     client: &Client,
     name: &str,
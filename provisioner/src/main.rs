@@ -18,9 +18,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         port,
         shared_pg_uri,
         shared_mongodb_uri,
+        shared_redis_uri,
         fqdn,
         internal_pg_address,
         internal_mongodb_address,
+        internal_redis_address,
         auth_uri,
         gateway_uri,
         resource_recorder_uri,
@@ -30,9 +32,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let provisioner = ShuttleProvisioner::new(
         &shared_pg_uri,
         &shared_mongodb_uri,
+        &shared_redis_uri,
         fqdn.to_string(),
         internal_pg_address,
         internal_mongodb_address,
+        internal_redis_address,
         resource_recorder_uri,
         gateway_uri,
     )
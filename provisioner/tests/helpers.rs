@@ -7,6 +7,7 @@ use std::{
 
 const PG_CONTAINER_NAME: &str = "shuttle_provisioner_test_pg";
 const MONGODB_CONTAINER_NAME: &str = "shuttle_provisioner_test_mongodb";
+const REDIS_CONTAINER_NAME: &str = "shuttle_provisioner_test_redis";
 
 pub struct DockerInstance {
     pub container_name: &'static str,
@@ -17,21 +18,26 @@ struct Config<'a> {
     container_name: &'a str,
     image: &'a str,
     engine: &'a str,
+    username: &'a str,
     port: &'a str,
     env: Vec<&'a str>,
+    cmd: Vec<&'a str>,
     is_ready_cmd: Vec<&'a str>,
 }
 
 pub enum DbType {
     Postgres,
     MongoDb,
+    Redis,
 }
 
 impl DockerInstance {
     pub fn new(db_type: DbType) -> Self {
         let Config {
             engine,
+            username,
             env,
+            cmd,
             image,
             is_ready_cmd,
             port,
@@ -47,6 +53,8 @@ impl DockerInstance {
 
         args.push(image);
 
+        args.extend(cmd);
+
         Command::new("docker").args(args).spawn().unwrap();
 
         Self::wait_ready(Duration::from_secs(120), &is_ready_cmd);
@@ -57,7 +65,7 @@ impl DockerInstance {
 
         Self {
             container_name,
-            uri: format!("{engine}://{engine}:password@localhost:{host_port}"),
+            uri: format!("{engine}://{username}:password@localhost:{host_port}"),
         }
     }
 
@@ -105,19 +113,23 @@ impl From<DbType> for Config<'_> {
                 container_name: PG_CONTAINER_NAME,
                 image: "docker.io/library/postgres:14",
                 engine: "postgres",
+                username: "postgres",
                 port: "5432",
                 env: vec!["POSTGRES_PASSWORD=password"],
+                cmd: vec![],
                 is_ready_cmd: vec!["exec", PG_CONTAINER_NAME, "pg_isready"],
             },
             DbType::MongoDb => Config {
                 container_name: MONGODB_CONTAINER_NAME,
                 image: "docker.io/library/mongo:5.0.10",
                 engine: "mongodb",
+                username: "mongodb",
                 port: "27017",
                 env: vec![
                     "MONGO_INITDB_ROOT_USERNAME=mongodb",
                     "MONGO_INITDB_ROOT_PASSWORD=password",
                 ],
+                cmd: vec![],
                 is_ready_cmd: vec![
                     "exec",
                     MONGODB_CONTAINER_NAME,
@@ -127,6 +139,24 @@ impl From<DbType> for Config<'_> {
                     "db",
                 ],
             },
+            DbType::Redis => Config {
+                container_name: REDIS_CONTAINER_NAME,
+                image: "docker.io/library/redis:7.2",
+                engine: "redis",
+                username: "default",
+                port: "6379",
+                env: vec![],
+                cmd: vec!["redis-server", "--requirepass", "password"],
+                is_ready_cmd: vec![
+                    "exec",
+                    REDIS_CONTAINER_NAME,
+                    "redis-cli",
+                    "-a",
+                    "password",
+                    "--no-auth-warning",
+                    "ping",
+                ],
+            },
         }
     }
 }
@@ -179,3 +209,22 @@ pub fn exec_mongosh(command: &str, database_name: Option<&str>) -> String {
 
     String::from_utf8(output).unwrap().trim().to_string()
 }
+
+/// Execute a command in `redis-cli` via `docker exec`
+pub fn exec_redis_cli(command: &[&str]) -> String {
+    let output = Command::new("docker")
+        .args([
+            "exec",
+            REDIS_CONTAINER_NAME,
+            "redis-cli",
+            "-a",
+            "password",
+            "--no-auth-warning",
+        ])
+        .args(command)
+        .output()
+        .unwrap()
+        .stdout;
+
+    String::from_utf8(output).unwrap().trim().to_string()
+}
@@ -1,7 +1,7 @@
 mod helpers;
 
 use ctor::dtor;
-use helpers::{exec_mongosh, exec_psql, DbType, DockerInstance};
+use helpers::{exec_mongosh, exec_psql, exec_redis_cli, DbType, DockerInstance};
 use once_cell::sync::Lazy;
 use serde_json::Value;
 use shuttle_backends::test_utils::{
@@ -13,6 +13,7 @@ use tonic::transport::Uri;
 
 static PG: Lazy<DockerInstance> = Lazy::new(|| DockerInstance::new(DbType::Postgres));
 static MONGODB: Lazy<DockerInstance> = Lazy::new(|| DockerInstance::new(DbType::MongoDb));
+static REDIS: Lazy<DockerInstance> = Lazy::new(|| DockerInstance::new(DbType::Redis));
 
 async fn get_rr_uri() -> Uri {
     let port = get_mocked_resource_recorder().await;
@@ -30,6 +31,7 @@ async fn get_gateway_uri() -> Uri {
 fn cleanup() {
     PG.cleanup();
     MONGODB.cleanup();
+    REDIS.cleanup();
 }
 
 mod needs_docker {
@@ -42,7 +44,7 @@ mod needs_docker {
     use shuttle_proto::{
         provisioner::{
             aws_rds::Engine, database_request::DbType, provisioner_server::Provisioner, AwsRds,
-            DatabaseRequest,
+            DatabaseRequest, Shared,
         },
         resource_recorder::{self, record_request, RecordRequest},
     };
@@ -56,9 +58,11 @@ mod needs_docker {
         let provisioner = ShuttleProvisioner::new(
             &PG.uri,
             &MONGODB.uri,
+            &REDIS.uri,
             "fqdn".to_string(),
             "pg".to_string(),
             "mongodb".to_string(),
+            "redis".to_string(),
             rr_uri.clone(),
             get_gateway_uri().await,
         )
@@ -76,12 +80,14 @@ mod needs_docker {
                         r#type: "database::shared::postgres".to_string(),
                         config: serde_json::to_vec(&json!({"public": true})).unwrap(),
                         data: serde_json::to_vec(&json!({"username": "test"})).unwrap(),
+                        name: String::new(),
                     },
                     // Make one RDS record that already exists
                     record_request::Resource {
                         r#type: "database::aws_rds::mariadb".to_string(),
                         config: serde_json::to_vec(&json!({})).unwrap(),
                         data: serde_json::to_vec(&json!({"username": "maria"})).unwrap(),
+                        name: String::new(),
                     },
                 ],
             }))
@@ -94,6 +100,7 @@ mod needs_docker {
                 engine: Some(Engine::Postgres(Default::default())),
             })),
             db_name: Some("custom-name".to_string()),
+            resource_name: None,
         });
 
         // Add a claim that only allows for one RDS - the one that will be returned by r-r
@@ -121,9 +128,11 @@ mod needs_docker {
         let provisioner = ShuttleProvisioner::new(
             &PG.uri,
             &MONGODB.uri,
+            &REDIS.uri,
             "fqdn".to_string(),
             "pg".to_string(),
             "mongodb".to_string(),
+            "redis".to_string(),
             get_rr_uri().await,
             get_gateway_uri().await,
         )
@@ -146,14 +155,80 @@ mod needs_docker {
         );
     }
 
+    #[tokio::test]
+    async fn shared_db_named_instance() {
+        let provisioner = ShuttleProvisioner::new(
+            &PG.uri,
+            &MONGODB.uri,
+            &REDIS.uri,
+            "fqdn".to_string(),
+            "pg".to_string(),
+            "mongodb".to_string(),
+            "redis".to_string(),
+            get_rr_uri().await,
+            get_gateway_uri().await,
+        )
+        .await
+        .unwrap();
+
+        let claim = Claim::new(
+            "user-1".to_string(),
+            AccountTier::Basic.into(),
+            AccountTier::Basic,
+            Limits::default(),
+        )
+        .fill_token();
+
+        let mut req = Request::new(DatabaseRequest {
+            project_name: "user-1-project-2".to_string(),
+            db_type: Some(DbType::Shared(Shared {
+                engine: Some(shared::Engine::Postgres(String::new())),
+            })),
+            db_name: None,
+            resource_name: Some("analytics".to_string()),
+        });
+        req.extensions_mut().insert(claim.clone());
+
+        let response = provisioner
+            .provision_database(req)
+            .await
+            .unwrap()
+            .into_inner();
+
+        assert_eq!(response.username, "user-user-1-project-2_analytics");
+        assert_eq!(response.database_name, "db-user-1-project-2_analytics");
+
+        // The default instance of the project is left alone
+        assert_eq!(
+            exec_psql("SELECT rolname FROM pg_roles WHERE rolname = 'user-user-1-project-2'",),
+            ""
+        );
+
+        let mut req = Request::new(DatabaseRequest {
+            project_name: "user-1-project-2".to_string(),
+            db_type: Some(DbType::Shared(Shared {
+                engine: Some(shared::Engine::Postgres(String::new())),
+            })),
+            db_name: None,
+            resource_name: Some("not-valid".to_string()),
+        });
+        req.extensions_mut().insert(claim);
+
+        let err = provisioner.provision_database(req).await.unwrap_err();
+
+        assert_eq!(err.code(), Code::InvalidArgument);
+    }
+
     #[tokio::test]
     async fn shared_db_role_does_exist() {
         let provisioner = ShuttleProvisioner::new(
             &PG.uri,
             &MONGODB.uri,
+            &REDIS.uri,
             "fqdn".to_string(),
             "pg".to_string(),
             "mongodb".to_string(),
+            "redis".to_string(),
             get_rr_uri().await,
             get_gateway_uri().await,
         )
@@ -183,9 +258,11 @@ mod needs_docker {
         let provisioner = ShuttleProvisioner::new(
             &PG.uri,
             &MONGODB.uri,
+            &REDIS.uri,
             "fqdn".to_string(),
             "pg".to_string(),
             "mongodb".to_string(),
+            "redis".to_string(),
             get_rr_uri().await,
             get_gateway_uri().await,
         )
@@ -206,9 +283,11 @@ mod needs_docker {
         let provisioner = ShuttleProvisioner::new(
             &PG.uri,
             &MONGODB.uri,
+            &REDIS.uri,
             "fqdn".to_string(),
             "pg".to_string(),
             "mongodb".to_string(),
+            "redis".to_string(),
             get_rr_uri().await,
             get_gateway_uri().await,
         )
@@ -236,9 +315,11 @@ mod needs_docker {
         let provisioner = ShuttleProvisioner::new(
             &PG.uri,
             &MONGODB.uri,
+            &REDIS.uri,
             "fqdn".to_string(),
             "pg".to_string(),
             "mongodb".to_string(),
+            "redis".to_string(),
             get_rr_uri().await,
             get_gateway_uri().await,
         )
@@ -268,9 +349,11 @@ mod needs_docker {
         let provisioner = ShuttleProvisioner::new(
             &PG.uri,
             &MONGODB.uri,
+            &REDIS.uri,
             "fqdn".to_string(),
             "pg".to_string(),
             "mongodb".to_string(),
+            "redis".to_string(),
             get_rr_uri().await,
             get_gateway_uri().await,
         )
@@ -294,9 +377,11 @@ mod needs_docker {
         let provisioner = ShuttleProvisioner::new(
             &PG.uri,
             &MONGODB.uri,
+            &REDIS.uri,
             "fqdn".to_string(),
             "pg".to_string(),
             "mongodb".to_string(),
+            "redis".to_string(),
             get_rr_uri().await,
             get_gateway_uri().await,
         )
@@ -346,4 +431,65 @@ mod needs_docker {
         let user_cycled_key = &user["credentials"]["SCRAM-SHA-256"]["storedKey"];
         assert_ne!(user_stored_key, user_cycled_key);
     }
+
+    #[tokio::test]
+    async fn shared_redis_role_does_not_exist() {
+        let provisioner = ShuttleProvisioner::new(
+            &PG.uri,
+            &MONGODB.uri,
+            &REDIS.uri,
+            "fqdn".to_string(),
+            "pg".to_string(),
+            "mongodb".to_string(),
+            "redis".to_string(),
+            get_rr_uri().await,
+            get_gateway_uri().await,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(exec_redis_cli(&["ACL", "GETUSER", "user-not_exist"]), "");
+
+        let response = provisioner
+            .request_shared_db("not_exist", shared::Engine::Redis(String::new()))
+            .await
+            .unwrap();
+
+        assert_eq!(response.username, "user-not_exist");
+        assert_eq!(response.database_name, "0");
+
+        let user = exec_redis_cli(&["ACL", "GETUSER", "user-not_exist"]);
+        assert!(user.contains("~not_exist:*"));
+    }
+
+    #[tokio::test]
+    async fn shared_redis_role_does_exist() {
+        let provisioner = ShuttleProvisioner::new(
+            &PG.uri,
+            &MONGODB.uri,
+            &REDIS.uri,
+            "fqdn".to_string(),
+            "pg".to_string(),
+            "mongodb".to_string(),
+            "redis".to_string(),
+            get_rr_uri().await,
+            get_gateway_uri().await,
+        )
+        .await
+        .unwrap();
+
+        exec_redis_cli(&["ACL", "SETUSER", "user-exist", "on", ">secure_password"]);
+        let user_before = exec_redis_cli(&["ACL", "GETUSER", "user-exist"]);
+
+        provisioner
+            .request_shared_db("exist", shared::Engine::Redis(String::new()))
+            .await
+            .unwrap();
+
+        let user_after = exec_redis_cli(&["ACL", "GETUSER", "user-exist"]);
+
+        // Make sure password got cycled by comparing the password hashes
+        assert_ne!(user_before, user_after);
+        assert!(user_after.contains("~exist:*"));
+    }
 }
@@ -0,0 +1,4 @@
+-- The data of sensitive resources is encrypted with a data key of its own, which is in turn encrypted with a key of
+-- the recorder. Rows recorded before this are encrypted by the recorder when it starts, since SQL can not do it.
+ALTER TABLE resources ADD COLUMN key_id TEXT;   -- Identifier of the recorder key that encrypted data_key. NULL when data is not encrypted.
+ALTER TABLE resources ADD COLUMN data_key BLOB; -- Data key that encrypted data, itself encrypted with the recorder key.
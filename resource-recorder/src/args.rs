@@ -3,6 +3,8 @@ use std::{net::SocketAddr, path::PathBuf};
 use clap::Parser;
 use tonic::transport::Uri;
 
+use crate::encryption::EncryptionKey;
+
 #[derive(Parser, Debug)]
 pub struct Args {
     /// Address to bind to
@@ -20,4 +22,18 @@ pub struct Args {
     /// Address to reach gateway's control plane at
     #[clap(long, default_value = "http://gateway:8001")]
     pub gateway_uri: Uri,
+
+    /// Key to encrypt the data of sensitive resources, such as secrets and database credentials, with
+    #[arg(long, env = "SHUTTLE_RECORDER_ENCRYPTION_KEY", hide_env_values = true)]
+    pub encryption_key: EncryptionKey,
+
+    /// Keys that were used before the current one. They are needed to read the resources they encrypted until
+    /// `shuttle-admin recorder-key rotate` has moved those to the current key.
+    #[arg(
+        long,
+        env = "SHUTTLE_RECORDER_PREVIOUS_ENCRYPTION_KEYS",
+        value_delimiter = ',',
+        hide_env_values = true
+    )]
+    pub previous_encryption_keys: Vec<EncryptionKey>,
 }
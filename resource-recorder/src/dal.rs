@@ -1,5 +1,6 @@
 use std::{fmt, path::Path, str::FromStr, time::SystemTime};
 
+use crate::encryption::{EncryptionError, EncryptionKey, Keyring};
 use crate::Error;
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
@@ -22,6 +23,7 @@ pub enum DalError {
     ProjectId,
     ServiceId,
     Inactive,
+    Encryption(#[from] EncryptionError),
 }
 
 // We are not using the `thiserror`'s `#[error]` syntax to prevent sensitive details from bubbling up to the users.
@@ -40,6 +42,14 @@ impl fmt::Display for DalError {
             DalError::ProjectId => "resource belongs to a different project",
             DalError::ServiceId => "resource belongs to a different service",
             DalError::Inactive => "cannot add a resource that is inactive",
+            DalError::Encryption(error) => {
+                error!(
+                    error = error as &dyn std::error::Error,
+                    "resource encryption failed"
+                );
+
+                "failed to interact with recorder"
+            }
         };
 
         write!(f, "{msg}")
@@ -74,11 +84,14 @@ pub trait Dal {
 
 pub struct Sqlite {
     pool: SqlitePool,
+    keyring: Keyring,
 }
 
 impl Sqlite {
     /// This function creates all necessary tables and sets up a database connection pool.
-    pub async fn new(path: &str) -> Self {
+    ///
+    /// The data of sensitive resources is encrypted with the current key of `keyring`.
+    pub async fn new(path: &str, keyring: Keyring) -> Self {
         if !Path::new(path).exists() {
             sqlx::Sqlite::create_database(path).await.unwrap();
         }
@@ -102,22 +115,138 @@ impl Sqlite {
 
         let pool = SqlitePool::connect_with(sqlite_options).await.unwrap();
 
-        Self::from_pool(pool).await
+        Self::from_pool(pool, keyring).await
     }
 
+    /// An in-memory database that encrypts with a throwaway key
     #[allow(dead_code)]
     pub async fn new_in_memory() -> Self {
         let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
-        Self::from_pool(pool).await
+        let keyring = Keyring::new(EncryptionKey::generate().unwrap(), Vec::new());
+
+        Self::from_pool(pool, keyring).await
     }
 
-    async fn from_pool(pool: SqlitePool) -> Self {
+    async fn from_pool(pool: SqlitePool, keyring: Keyring) -> Self {
         MIGRATIONS.run(&pool).await.unwrap();
 
-        Self { pool }
+        let sqlite = Self { pool, keyring };
+        sqlite
+            .encrypt_existing()
+            .await
+            .expect("existing resources to be encrypted");
+
+        sqlite
+    }
+
+    /// Encrypt the sensitive resources that were recorded before their data was encrypted
+    async fn encrypt_existing(&self) -> Result<(), DalError> {
+        let rows: Vec<StoredResource> =
+            sqlx::query_as("SELECT * FROM resources WHERE key_id IS NULL")
+                .fetch_all(&self.pool)
+                .await?;
+
+        let mut transaction = self.pool.begin().await?;
+        let mut count = 0;
+        for StoredResource { resource, .. } in rows {
+            if !is_sensitive(&resource.r#type) {
+                continue;
+            }
+
+            let project_id = resource.project_id.expect("row to have a project id");
+            let service_id = resource.service_id.expect("row to have a service id");
+            let sealed = self.keyring.seal(
+                &aad(&project_id, &service_id, &resource.r#type, &resource.name),
+                &resource.data,
+            )?;
+
+            sqlx::query("UPDATE resources SET data = ?, key_id = ?, data_key = ? WHERE project_id = ? AND service_id = ? AND type = ? AND name = ?")
+                .bind(sealed.data)
+                .bind(sealed.key_id)
+                .bind(sealed.data_key)
+                .bind(project_id.to_string())
+                .bind(service_id.to_string())
+                .bind(resource.r#type)
+                .bind(resource.name)
+                .execute(&mut *transaction)
+                .await?;
+            count += 1;
+        }
+        transaction.commit().await?;
+
+        if count > 0 {
+            info!(count, "encrypted the data of existing resources");
+        }
+
+        Ok(())
+    }
+
+    /// Encrypt the data keys of all the resources with the current key, so that the previous keys are no longer
+    /// needed. Returns how many resources were rotated.
+    pub async fn rotate_keys(&self) -> Result<usize, DalError> {
+        let rows: Vec<StoredResource> = sqlx::query_as("SELECT * FROM resources WHERE key_id != ?")
+            .bind(self.keyring.current_id())
+            .fetch_all(&self.pool)
+            .await?;
+
+        let mut transaction = self.pool.begin().await?;
+        let mut count = 0;
+        for stored in rows {
+            let (Some(key_id), Some(data_key)) = (stored.key_id, stored.data_key) else {
+                continue;
+            };
+            let data_key = self.keyring.rewrap(&key_id, &data_key)?;
+            let resource = stored.resource;
+
+            sqlx::query("UPDATE resources SET key_id = ?, data_key = ? WHERE project_id = ? AND service_id = ? AND type = ? AND name = ?")
+                .bind(self.keyring.current_id())
+                .bind(data_key)
+                .bind(resource.project_id.expect("row to have a project id").to_string())
+                .bind(resource.service_id.expect("row to have a service id").to_string())
+                .bind(resource.r#type)
+                .bind(resource.name)
+                .execute(&mut *transaction)
+                .await?;
+            count += 1;
+        }
+        transaction.commit().await?;
+
+        Ok(count)
+    }
+
+    fn decrypt(&self, stored: StoredResource) -> Result<Resource, DalError> {
+        let StoredResource {
+            mut resource,
+            key_id,
+            data_key,
+        } = stored;
+
+        if let (Some(key_id), Some(data_key)) = (key_id, data_key) {
+            let aad = aad(
+                &resource.project_id.expect("row to have a project id"),
+                &resource.service_id.expect("row to have a service id"),
+                &resource.r#type,
+                &resource.name,
+            );
+            resource.data = self
+                .keyring
+                .open(&key_id, &data_key, &aad, &resource.data)?;
+        }
+
+        Ok(resource)
     }
 }
 
+/// Whether the data of a resource type holds credentials, and so is encrypted at rest
+fn is_sensitive(r#type: &Type) -> bool {
+    matches!(r#type, Type::Secrets | Type::Database(_))
+}
+
+/// Ties encrypted data to the resource it belongs to, so that it can not be moved to another row
+fn aad(project_id: &Ulid, service_id: &Ulid, r#type: &Type, name: &str) -> Vec<u8> {
+    format!("{project_id}/{service_id}/{type}/{name}").into_bytes()
+}
+
 #[async_trait]
 impl Dal for Sqlite {
     async fn add_resources(
@@ -152,12 +281,25 @@ impl Dal for Sqlite {
                 return Err(DalError::Inactive);
             }
 
-            sqlx::query("INSERT OR REPLACE INTO resources (project_id, service_id, type, config, data, is_active, last_updated) VALUES(?, ?, ?, ?, ?, ?, ?)")
+            let (data, key_id, data_key) = if is_sensitive(&resource.r#type) {
+                let sealed = self.keyring.seal(
+                    &aad(&project_id, &service_id, &resource.r#type, &resource.name),
+                    &resource.data,
+                )?;
+                (sealed.data, Some(sealed.key_id), Some(sealed.data_key))
+            } else {
+                (resource.data, None, None)
+            };
+
+            sqlx::query("INSERT OR REPLACE INTO resources (project_id, service_id, type, name, config, data, key_id, data_key, is_active, last_updated) VALUES(?, ?, ?, ?, ?, ?, ?, ?, ?, ?)")
             .bind(project_id.to_string())
             .bind(service_id.to_string())
             .bind(resource.r#type)
+            .bind(resource.name)
             .bind(resource.config)
-            .bind(resource.data)
+            .bind(data)
+            .bind(key_id)
+            .bind(data_key)
             .bind(resource.is_active)
             .bind(now)
             .execute(&mut *transaction)
@@ -170,39 +312,47 @@ impl Dal for Sqlite {
     }
 
     async fn get_project_resources(&self, project_id: Ulid) -> Result<Vec<Resource>, DalError> {
-        let result = sqlx::query_as(r#"SELECT * FROM resources WHERE project_id = ?"#)
-            .bind(project_id.to_string())
-            .fetch_all(&self.pool)
-            .await?;
-
-        Ok(result)
+        let result: Vec<StoredResource> =
+            sqlx::query_as(r#"SELECT * FROM resources WHERE project_id = ?"#)
+                .bind(project_id.to_string())
+                .fetch_all(&self.pool)
+                .await?;
+
+        result
+            .into_iter()
+            .map(|stored| self.decrypt(stored))
+            .collect()
     }
 
     async fn get_resource(
         &self,
         resource: resource_recorder::ResourceIds,
     ) -> Result<Option<Resource>, DalError> {
-        let result = sqlx::query_as(
-            "SELECT * FROM resources WHERE project_id = ? AND service_id = ? AND type = ?",
+        let result: Option<StoredResource> = sqlx::query_as(
+            "SELECT * FROM resources WHERE project_id = ? AND service_id = ? AND type = ? AND name = ?",
         )
         .bind(resource.project_id)
         .bind(resource.service_id)
         .bind(resource.r#type)
+        .bind(resource.name)
         .fetch_optional(&self.pool)
         .await?;
 
-        Ok(result)
+        result.map(|stored| self.decrypt(stored)).transpose()
     }
 
     async fn delete_resource(
         &self,
         resource: resource_recorder::ResourceIds,
     ) -> Result<(), DalError> {
-        sqlx::query("DELETE FROM resources WHERE project_id = ? AND service_id = ? AND type = ?")
-            .bind(resource.project_id)
-            .bind(resource.service_id)
-            .bind(resource.r#type)
-            .execute(&self.pool)
+        sqlx::query(
+            "DELETE FROM resources WHERE project_id = ? AND service_id = ? AND type = ? AND name = ?",
+        )
+        .bind(resource.project_id)
+        .bind(resource.service_id)
+        .bind(resource.r#type)
+        .bind(resource.name)
+        .execute(&self.pool)
             .await
             .map(|_| ())?;
 
@@ -215,6 +365,7 @@ pub struct Resource {
     project_id: Option<Ulid>,
     service_id: Option<Ulid>,
     r#type: Type,
+    name: String,
     data: Vec<u8>,
     config: Vec<u8>,
     is_active: bool,
@@ -234,6 +385,7 @@ impl FromRow<'_, SqliteRow> for Resource {
                     .map_err(|e| sqlx::Error::Decode(Box::new(e)))?,
             ),
             r#type: row.try_get("type")?,
+            name: row.try_get("name")?,
             data: row.try_get("data")?,
             config: row.try_get("config")?,
             is_active: row.try_get("is_active")?,
@@ -243,12 +395,29 @@ impl FromRow<'_, SqliteRow> for Resource {
     }
 }
 
+/// A row of the resources table, whose data is still encrypted if its type is sensitive
+struct StoredResource {
+    resource: Resource,
+    key_id: Option<String>,
+    data_key: Option<Vec<u8>>,
+}
+
+impl FromRow<'_, SqliteRow> for StoredResource {
+    fn from_row(row: &SqliteRow) -> Result<Self, sqlx::Error> {
+        Ok(Self {
+            resource: Resource::from_row(row)?,
+            key_id: row.try_get("key_id")?,
+            data_key: row.try_get("data_key")?,
+        })
+    }
+}
+
 impl TryFrom<record_request::Resource> for Resource {
     type Error = InvalidResourceType;
 
     fn try_from(value: record_request::Resource) -> Result<Self, Self::Error> {
         let r#type = value.r#type.parse()?;
-        Ok(Self::new(r#type, value.data, value.config))
+        Ok(Self::new(r#type, value.name, value.data, value.config))
     }
 }
 
@@ -269,6 +438,7 @@ impl From<Resource> for resource_recorder::Resource {
             is_active: value.is_active,
             created_at: Some(Timestamp::from(SystemTime::from(value.created_at))),
             last_updated: Some(Timestamp::from(SystemTime::from(value.last_updated))),
+            name: value.name,
         }
     }
 }
@@ -281,6 +451,7 @@ impl TryFrom<resource_recorder::Resource> for Resource {
             project_id: Some(value.project_id.parse()?),
             service_id: Some(value.service_id.parse()?),
             r#type: value.r#type.parse()?,
+            name: value.name,
             data: value.data,
             config: value.config,
             is_active: value.is_active,
@@ -294,11 +465,12 @@ impl TryFrom<resource_recorder::Resource> for Resource {
 
 impl Resource {
     /// Create a new type of resource
-    fn new(r#type: Type, data: Vec<u8>, config: Vec<u8>) -> Self {
+    fn new(r#type: Type, name: String, data: Vec<u8>, config: Vec<u8>) -> Self {
         Self {
             project_id: None,
             service_id: None,
             r#type,
+            name,
             data,
             config,
             is_active: true,
@@ -307,3 +479,64 @@ impl Resource {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn encrypts_sensitive_data_and_rotates_keys() {
+        let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+        let old = EncryptionKey::generate().unwrap();
+        let sqlite = Sqlite::from_pool(pool.clone(), Keyring::new(old.clone(), Vec::new())).await;
+
+        let project_id: Ulid = "00000000000000000000000001".parse().unwrap();
+        let service_id: Ulid = "00000000000000000000000001".parse().unwrap();
+        let secrets = br#"{"password":"brrrr"}"#.to_vec();
+        sqlite
+            .add_resources(
+                project_id,
+                service_id,
+                vec![
+                    Resource::new(Type::Secrets, String::new(), secrets.clone(), Vec::new()),
+                    Resource::new(Type::Persist, String::new(), b"{}".to_vec(), Vec::new()),
+                ],
+            )
+            .await
+            .unwrap();
+        // Recorded before the data was encrypted
+        sqlx::query("INSERT INTO resources (project_id, service_id, type, name, config, data, is_active) VALUES(?, ?, 'secrets', 'legacy', '', ?, true)")
+            .bind(project_id.to_string())
+            .bind(service_id.to_string())
+            .bind(secrets.clone())
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        // Restart with a new key, keeping the old one to read the existing data
+        let new = EncryptionKey::generate().unwrap();
+        let sqlite = Sqlite::from_pool(pool.clone(), Keyring::new(new.clone(), vec![old])).await;
+
+        let stored: Vec<(String, Vec<u8>, Option<String>)> =
+            sqlx::query_as("SELECT type, data, key_id FROM resources ORDER BY type, name")
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(stored[0], ("persist".to_string(), b"{}".to_vec(), None));
+        assert!(stored[1..]
+            .iter()
+            .all(|(_, data, key_id)| *data != secrets && key_id.is_some()));
+
+        assert_eq!(sqlite.rotate_keys().await.unwrap(), 1);
+        assert_eq!(sqlite.rotate_keys().await.unwrap(), 0);
+
+        // The old key is no longer needed
+        let sqlite = Sqlite::from_pool(pool, Keyring::new(new, Vec::new())).await;
+        let resources = sqlite.get_project_resources(project_id).await.unwrap();
+        assert_eq!(resources.len(), 3);
+        assert!(resources
+            .iter()
+            .filter(|resource| resource.r#type == Type::Secrets)
+            .all(|resource| resource.data == secrets));
+    }
+}
@@ -0,0 +1,243 @@
+use std::{fmt, str::FromStr};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ring::{
+    aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN},
+    digest::{digest, SHA256},
+    rand::{SecureRandom, SystemRandom},
+};
+use thiserror::Error;
+
+const KEY_LEN: usize = 32;
+
+#[derive(Error, Debug)]
+pub enum EncryptionError {
+    #[error("no encryption key with id '{0}' was given to the recorder")]
+    UnknownKey(String),
+    #[error("failed to generate random bytes")]
+    Random,
+    #[error("failed to encrypt data")]
+    Seal,
+    #[error("failed to decrypt data")]
+    Open,
+}
+
+/// A key of the recorder, which encrypts the data keys of the resources.
+///
+/// Given as 32 base64 encoded bytes, as printed by `shuttle-admin recorder-key generate`.
+#[derive(Clone)]
+pub struct EncryptionKey {
+    id: String,
+    key: [u8; KEY_LEN],
+}
+
+impl EncryptionKey {
+    /// Generate a new random key
+    pub fn generate() -> Result<Self, EncryptionError> {
+        Ok(Self::new(random()?))
+    }
+
+    fn new(key: [u8; KEY_LEN]) -> Self {
+        // Only the first bytes of the hash, which is plenty to tell a handful of keys apart
+        let id = digest(&SHA256, &key).as_ref()[..8]
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+
+        Self { id, key }
+    }
+
+    /// Identifier of the key, which is stored next to the data keys it encrypted
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// The key in the format it is given to the recorder
+    pub fn to_base64(&self) -> String {
+        STANDARD.encode(self.key)
+    }
+
+    fn seal(&self, aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, EncryptionError> {
+        seal(&self.key, aad, plaintext)
+    }
+
+    fn open(&self, aad: &[u8], sealed: &[u8]) -> Result<Vec<u8>, EncryptionError> {
+        open(&self.key, aad, sealed)
+    }
+}
+
+impl FromStr for EncryptionKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let key = STANDARD
+            .decode(s.trim())
+            .map_err(|error| format!("encryption key is not valid base64: {error}"))?
+            .try_into()
+            .map_err(|_| format!("encryption key should be {KEY_LEN} bytes long"))?;
+
+        Ok(Self::new(key))
+    }
+}
+
+impl fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "EncryptionKey({})", self.id)
+    }
+}
+
+/// Data encrypted with a fresh data key, which is in turn encrypted with a key of the recorder
+pub struct Sealed {
+    pub key_id: String,
+    pub data_key: Vec<u8>,
+    pub data: Vec<u8>,
+}
+
+/// The keys of the recorder: the current one encrypts all new data, and the previous ones can still decrypt the data
+/// they encrypted until it is rotated to the current key.
+#[derive(Clone, Debug)]
+pub struct Keyring {
+    current: EncryptionKey,
+    previous: Vec<EncryptionKey>,
+}
+
+impl Keyring {
+    pub fn new(current: EncryptionKey, previous: Vec<EncryptionKey>) -> Self {
+        Self { current, previous }
+    }
+
+    pub fn current_id(&self) -> &str {
+        self.current.id()
+    }
+
+    /// Encrypt `data` with a new data key. The `aad` is not encrypted, but is needed to decrypt it again.
+    pub fn seal(&self, aad: &[u8], data: &[u8]) -> Result<Sealed, EncryptionError> {
+        let data_key: [u8; KEY_LEN] = random()?;
+
+        Ok(Sealed {
+            key_id: self.current.id().to_owned(),
+            data_key: self.current.seal(&[], &data_key)?,
+            data: seal(&data_key, aad, data)?,
+        })
+    }
+
+    /// Decrypt data that was encrypted by [`Keyring::seal`]
+    pub fn open(
+        &self,
+        key_id: &str,
+        data_key: &[u8],
+        aad: &[u8],
+        data: &[u8],
+    ) -> Result<Vec<u8>, EncryptionError> {
+        let data_key = self.get(key_id)?.open(&[], data_key)?;
+
+        open(&data_key, aad, data)
+    }
+
+    /// Encrypt a data key with the current key instead of the one with `key_id`, leaving the data it encrypted as is
+    pub fn rewrap(&self, key_id: &str, data_key: &[u8]) -> Result<Vec<u8>, EncryptionError> {
+        let data_key = self.get(key_id)?.open(&[], data_key)?;
+
+        self.current.seal(&[], &data_key)
+    }
+
+    fn get(&self, key_id: &str) -> Result<&EncryptionKey, EncryptionError> {
+        std::iter::once(&self.current)
+            .chain(&self.previous)
+            .find(|key| key.id() == key_id)
+            .ok_or_else(|| EncryptionError::UnknownKey(key_id.to_owned()))
+    }
+}
+
+fn random<const N: usize>() -> Result<[u8; N], EncryptionError> {
+    let mut bytes = [0; N];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .map_err(|_| EncryptionError::Random)?;
+
+    Ok(bytes)
+}
+
+/// Encrypt with AES-256-GCM, putting the random nonce in front of the ciphertext
+fn seal(key: &[u8], aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, EncryptionError> {
+    let key = UnboundKey::new(&AES_256_GCM, key).map_err(|_| EncryptionError::Seal)?;
+    let nonce: [u8; NONCE_LEN] = random()?;
+
+    let mut in_out = plaintext.to_vec();
+    LessSafeKey::new(key)
+        .seal_in_place_append_tag(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::from(aad),
+            &mut in_out,
+        )
+        .map_err(|_| EncryptionError::Seal)?;
+
+    Ok([nonce.as_slice(), &in_out].concat())
+}
+
+fn open(key: &[u8], aad: &[u8], sealed: &[u8]) -> Result<Vec<u8>, EncryptionError> {
+    let key = UnboundKey::new(&AES_256_GCM, key).map_err(|_| EncryptionError::Open)?;
+    if sealed.len() < NONCE_LEN {
+        return Err(EncryptionError::Open);
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| EncryptionError::Open)?;
+
+    let mut in_out = ciphertext.to_vec();
+    let plaintext = LessSafeKey::new(key)
+        .open_in_place(nonce, Aad::from(aad), &mut in_out)
+        .map_err(|_| EncryptionError::Open)?;
+
+    Ok(plaintext.to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seals_and_rotates() {
+        let old = EncryptionKey::generate().unwrap();
+        let new = EncryptionKey::generate().unwrap();
+        assert_ne!(old.id(), new.id());
+
+        let keyring = Keyring::new(old.clone(), Vec::new());
+        let sealed = keyring.seal(b"secrets", b"hunter2").unwrap();
+        assert_ne!(sealed.data, b"hunter2");
+        assert_eq!(
+            keyring
+                .open(&sealed.key_id, &sealed.data_key, b"secrets", &sealed.data)
+                .unwrap(),
+            b"hunter2"
+        );
+        // The data is bound to its aad
+        assert!(keyring
+            .open(&sealed.key_id, &sealed.data_key, b"persist", &sealed.data)
+            .is_err());
+
+        let keyring = Keyring::new(new.clone(), vec![old.clone()]);
+        let data_key = keyring.rewrap(&sealed.key_id, &sealed.data_key).unwrap();
+
+        let keyring = Keyring::new(new, Vec::new());
+        assert!(keyring
+            .open(&sealed.key_id, &sealed.data_key, b"secrets", &sealed.data)
+            .is_err());
+        assert_eq!(
+            keyring
+                .open(keyring.current_id(), &data_key, b"secrets", &sealed.data)
+                .unwrap(),
+            b"hunter2"
+        );
+    }
+
+    #[test]
+    fn parses_keys() {
+        let key = EncryptionKey::generate().unwrap();
+        let parsed: EncryptionKey = key.to_base64().parse().unwrap();
+        assert_eq!(parsed.id(), key.id());
+        assert!(!format!("{parsed:?}").contains(&key.to_base64()));
+
+        assert!("not base64!".parse::<EncryptionKey>().is_err());
+        assert!(STANDARD.encode([0; 16]).parse::<EncryptionKey>().is_err());
+    }
+}
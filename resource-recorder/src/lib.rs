@@ -16,6 +16,7 @@ use tonic::{Request, Response, Status};
 
 pub mod args;
 mod dal;
+pub mod encryption;
 
 pub use dal::Sqlite;
 use tracing::error;
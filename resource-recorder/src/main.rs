@@ -8,7 +8,7 @@ use shuttle_backends::{
 };
 use shuttle_common::{extract_propagation::ExtractPropagationLayer, log::Backend};
 use shuttle_proto::resource_recorder::resource_recorder_server::ResourceRecorderServer;
-use shuttle_resource_recorder::{args::Args, Service, Sqlite};
+use shuttle_resource_recorder::{args::Args, encryption::Keyring, Service, Sqlite};
 use tonic::transport::Server;
 
 #[tokio::main]
@@ -18,6 +18,8 @@ async fn main() {
         state,
         auth_uri,
         gateway_uri,
+        encryption_key,
+        previous_encryption_keys,
     } = Args::parse();
 
     setup_tracing(tracing_subscriber::registry(), Backend::ResourceRecorder);
@@ -30,8 +32,9 @@ async fn main() {
     let gateway_client = ServicesApiClient::new(gateway_uri);
 
     let db_path = state.join("resource-recorder.sqlite");
+    let keyring = Keyring::new(encryption_key, previous_encryption_keys);
     let svc = Service::new(
-        Sqlite::new(db_path.display().to_string().as_str()).await,
+        Sqlite::new(db_path.display().to_string().as_str(), keyring).await,
         gateway_client,
     );
     let svc = ResourceRecorderServer::new(svc);
@@ -58,11 +58,13 @@ async fn manage_resources() {
                     r#type: "database::shared::postgres".to_string(),
                     config: serde_json::to_vec(&json!({"public": true})).unwrap(),
                     data: serde_json::to_vec(&json!({"username": "test"})).unwrap(),
+                    name: String::new(),
                 },
                 record_request::Resource {
                     r#type: "secrets".to_string(),
                     config: serde_json::to_vec(&json!({})).unwrap(),
                     data: serde_json::to_vec(&json!({"password": "brrrr"})).unwrap(),
+                    name: String::new(),
                 },
             ],
         });
@@ -88,6 +90,7 @@ async fn manage_resources() {
                     r#type: "secrets".to_string(),
                     config: serde_json::to_vec(&json!({"folder": "static"})).unwrap(),
                     data: serde_json::to_vec(&json!({"path": "/tmp/static"})).unwrap(),
+                    name: String::new(),
                 }],
             }))
             .await
@@ -108,6 +111,7 @@ async fn manage_resources() {
                     r#type: "secrets".to_string(),
                     config: serde_json::to_vec(&json!({"folder": "publi"})).unwrap(),
                     data: serde_json::to_vec(&json!({"path": "/tmp/publi"})).unwrap(),
+                    name: String::new(),
                 }],
             }))
             .await
@@ -134,6 +138,7 @@ async fn manage_resources() {
             is_active: true,
             created_at: response.resources[0].created_at.clone(),
             last_updated: response.resources[0].last_updated.clone(),
+            name: String::new(),
         };
         let mut service_secrets = Resource {
             project_id: project_id.clone(),
@@ -144,6 +149,7 @@ async fn manage_resources() {
             is_active: true,
             created_at: response.resources[1].created_at.clone(),
             last_updated: response.resources[1].last_updated.clone(),
+            name: String::new(),
         };
         let service_secrets2 = Resource {
             project_id: project_id.clone(),
@@ -154,6 +160,7 @@ async fn manage_resources() {
             is_active: true,
             created_at: response.resources[2].created_at.clone(),
             last_updated: response.resources[2].last_updated.clone(),
+            name: String::new(),
         };
 
         let expected = ResourcesResponse {
@@ -181,6 +188,7 @@ async fn manage_resources() {
             project_id: project_id.clone(),
             service_id: service_id2.clone(),
             r#type: "secrets".to_string(),
+            name: String::new(),
         };
 
         let expected = ResourcesResponse {
@@ -195,6 +203,7 @@ async fn manage_resources() {
                     is_active: true,
                     created_at: response.resources[2].created_at.clone(),
                     last_updated: response.resources[2].last_updated.clone(),
+                    name: String::new(),
                     project_id: service2_static_folder.project_id.clone(),
                     service_id: service2_static_folder.service_id.clone(),
                     r#type: service2_static_folder.r#type.clone(),
@@ -232,6 +241,7 @@ async fn manage_resources() {
                     r#type: "database::shared::postgres".to_string(),
                     config: serde_json::to_vec(&json!({"public": false})).unwrap(),
                     data: serde_json::to_vec(&json!({"username": "inner"})).unwrap(),
+                    name: String::new(),
                 }],
             }))
             .await
@@ -276,3 +286,120 @@ async fn manage_resources() {
         _ = test_future => {},
     }
 }
+
+#[tokio::test]
+async fn manage_named_resources() {
+    let port = pick_unused_port().unwrap();
+    let addr = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), port);
+
+    let server = get_mocked_gateway_server().await;
+    let client = ServicesApiClient::new(server.uri().parse().unwrap());
+
+    let server_future = async {
+        Server::builder()
+            .layer(JwtScopesLayer::new(vec![
+                Scope::Resources,
+                Scope::ResourcesWrite,
+            ]))
+            .add_service(ResourceRecorderServer::new(Service::new(
+                Sqlite::new_in_memory().await,
+                client,
+            )))
+            .serve(addr)
+            .await
+            .unwrap()
+    };
+
+    let test_future = async {
+        // Make sure the server starts first
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+
+        let mut client = ResourceRecorderClient::connect(format!("http://localhost:{port}"))
+            .await
+            .unwrap();
+
+        let project_id = "00000000000000000000000001".to_string();
+        let service_id = "00000000000000000000000001".to_string();
+
+        // Two instances of the same type
+        let response = client
+            .record_resources(Request::new(RecordRequest {
+                project_id: project_id.clone(),
+                service_id: service_id.clone(),
+                resources: vec![
+                    record_request::Resource {
+                        r#type: "database::shared::postgres".to_string(),
+                        config: serde_json::to_vec(&json!({})).unwrap(),
+                        data: serde_json::to_vec(&json!({"username": "app"})).unwrap(),
+                        name: String::new(),
+                    },
+                    record_request::Resource {
+                        r#type: "database::shared::postgres".to_string(),
+                        config: serde_json::to_vec(&json!({})).unwrap(),
+                        data: serde_json::to_vec(&json!({"username": "analytics"})).unwrap(),
+                        name: "analytics".to_string(),
+                    },
+                ],
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+
+        assert!(response.success);
+
+        let response = client
+            .get_project_resources(Request::new(ProjectResourcesRequest {
+                project_id: project_id.clone(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+
+        assert_eq!(response.resources.len(), 2);
+
+        let analytics_ids = ResourceIds {
+            project_id: project_id.clone(),
+            service_id: service_id.clone(),
+            r#type: "database::shared::postgres".to_string(),
+            name: "analytics".to_string(),
+        };
+
+        let response = client
+            .get_resource(Request::new(analytics_ids.clone()))
+            .await
+            .unwrap()
+            .into_inner();
+
+        let resource = response.resource.unwrap();
+        assert_eq!(resource.name, "analytics");
+        assert_eq!(
+            resource.data,
+            serde_json::to_vec(&json!({"username": "analytics"})).unwrap()
+        );
+
+        // Deleting the named instance leaves the default one alone
+        let response = client
+            .delete_resource(Request::new(analytics_ids))
+            .await
+            .unwrap()
+            .into_inner();
+
+        assert!(response.success);
+
+        let response = client
+            .get_project_resources(Request::new(ProjectResourcesRequest {
+                project_id: project_id.clone(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+
+        assert_eq!(response.resources.len(), 1);
+        assert_eq!(response.resources[0].name, "");
+    };
+
+    select! {
+        _ = server_future => panic!("server finished first"),
+        _ = test_future => {},
+    }
+}
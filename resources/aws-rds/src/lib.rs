@@ -28,7 +28,7 @@ macro_rules! aws_engine {
             #[cfg(feature = $feature)]
             #[derive(Default)]
             #[doc = "Shuttle managed AWS RDS " $struct_ident " instance"]
-            pub struct $struct_ident(DbInput);
+            pub struct $struct_ident(DbInput, Option<String>);
 
             #[cfg(feature = $feature)]
             impl $struct_ident {
@@ -45,6 +45,14 @@ macro_rules! aws_engine {
 
                     self
                 }
+
+                /// Give this instance a name, so that several instances of this engine can be used by the same service.
+                /// Names can have up to 32 lowercase letters and digits.
+                pub fn name(mut self, name: &str) -> Self {
+                    self.1 = Some(name.to_string());
+
+                    self
+                }
             }
 
             #[cfg(feature = $feature)]
@@ -62,7 +70,8 @@ macro_rules! aws_engine {
                         ),
                         serde_json::to_value(&self.0).unwrap(),
                         serde_json::Value::Null,
-                    ))
+                    )
+                    .with_name(self.1))
                 }
             }
         }
@@ -71,7 +71,9 @@ impl ResourceInputBuilder for Qdrant {
                         container_name: "qdrant".to_string(),
                         image: "docker.io/qdrant/qdrant:v1.10.0".to_string(),
                         port: "6334/tcp".to_string(),
+                        extra_ports: vec![],
                         env: vec![],
+                        ready_cmd: vec![],
                     })
                     .unwrap(),
                     serde_json::Value::Null,
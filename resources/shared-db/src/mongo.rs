@@ -8,7 +8,7 @@ use shuttle_service::{
 
 /// Shuttle managed MongoDB in a shared cluster
 #[derive(Default)]
-pub struct MongoDb(DbInput);
+pub struct MongoDb(DbInput, Option<String>);
 
 impl MongoDb {
     /// Use a custom connection string for local runs
@@ -17,6 +17,14 @@ impl MongoDb {
 
         self
     }
+
+    /// Give this database a name, so that several databases of this type can be used by the same service.
+    /// Names can have up to 32 lowercase letters and digits.
+    pub fn name(mut self, name: &str) -> Self {
+        self.1 = Some(name.to_string());
+
+        self
+    }
 }
 
 #[async_trait]
@@ -29,7 +37,8 @@ impl ResourceInputBuilder for MongoDb {
             Type::Database(database::Type::Shared(database::SharedEngine::MongoDb)),
             serde_json::to_value(self.0).unwrap(),
             serde_json::Value::Null,
-        ))
+        )
+        .with_name(self.1))
     }
 }
 
@@ -22,7 +22,7 @@ const MAX_CONNECTIONS: u32 = 5;
 
 /// Shuttle managed Postgres DB in a shared cluster
 #[derive(Default)]
-pub struct Postgres(DbInput);
+pub struct Postgres(DbInput, Option<String>);
 
 impl Postgres {
     /// Use a custom connection string for local runs
@@ -31,6 +31,14 @@ impl Postgres {
 
         self
     }
+
+    /// Give this database a name, so that several databases of this type can be used by the same service.
+    /// Names can have up to 32 lowercase letters and digits.
+    pub fn name(mut self, name: &str) -> Self {
+        self.1 = Some(name.to_string());
+
+        self
+    }
 }
 
 #[async_trait]
@@ -43,7 +51,8 @@ impl ResourceInputBuilder for Postgres {
             Type::Database(database::Type::Shared(database::SharedEngine::Postgres)),
             serde_json::to_value(self.0).unwrap(),
             serde_json::Value::Null,
-        ))
+        )
+        .with_name(self.1))
     }
 }
 
@@ -0,0 +1,83 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use shuttle_service::{
+    database,
+    resource::{ProvisionResourceRequest, ShuttleResourceOutput, Type},
+    DatabaseResource, DbInput, Error, IntoResource, ResourceFactory, ResourceInputBuilder,
+};
+
+/// Shuttle managed Redis in a shared cluster
+///
+/// Keys and pub/sub channels have to be prefixed with `<project name>:` in the shared cluster,
+/// or with `<project name>_<name>:` if the database was given a [`Redis::name`].
+#[derive(Default)]
+pub struct Redis(DbInput, Option<String>);
+
+impl Redis {
+    /// Use a custom connection string for local runs
+    pub fn local_uri(mut self, local_uri: &str) -> Self {
+        self.0.local_uri = Some(local_uri.to_string());
+
+        self
+    }
+
+    /// Give this database a name, so that several databases of this type can be used by the same service.
+    /// Names can have up to 32 lowercase letters and digits.
+    pub fn name(mut self, name: &str) -> Self {
+        self.1 = Some(name.to_string());
+
+        self
+    }
+}
+
+#[async_trait]
+impl ResourceInputBuilder for Redis {
+    type Input = ProvisionResourceRequest;
+    type Output = OutputWrapper;
+
+    async fn build(self, _factory: &ResourceFactory) -> Result<Self::Input, Error> {
+        Ok(ProvisionResourceRequest::new(
+            Type::Database(database::Type::Shared(database::SharedEngine::Redis)),
+            serde_json::to_value(self.0).unwrap(),
+            serde_json::Value::Null,
+        )
+        .with_name(self.1))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct OutputWrapper(ShuttleResourceOutput<DatabaseResource>);
+
+#[async_trait]
+impl IntoResource<String> for OutputWrapper {
+    async fn into_resource(self) -> Result<String, Error> {
+        Ok(match self.0.output {
+            DatabaseResource::ConnectionString(s) => s.clone(),
+            DatabaseResource::Info(info) => info.connection_string_shuttle(),
+        })
+    }
+}
+
+#[async_trait]
+impl IntoResource<redis::Client> for OutputWrapper {
+    async fn into_resource(self) -> Result<redis::Client, Error> {
+        let connection_string: String = self.into_resource().await.unwrap();
+
+        Ok(redis::Client::open(connection_string)
+            .map_err(shuttle_service::error::CustomError::new)?)
+    }
+}
+
+/// A multiplexed connection that reconnects automatically.
+/// It is cheap to clone and can be shared between tasks, so it takes the role of a connection pool.
+#[async_trait]
+impl IntoResource<redis::aio::ConnectionManager> for OutputWrapper {
+    async fn into_resource(self) -> Result<redis::aio::ConnectionManager, Error> {
+        let client: redis::Client = self.into_resource().await?;
+
+        Ok(redis::aio::ConnectionManager::new(client)
+            .await
+            .map_err(shuttle_service::error::CustomError::new)?)
+    }
+}
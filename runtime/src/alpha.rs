@@ -16,7 +16,7 @@ use shuttle_proto::runtime::{
     LoadRequest, LoadResponse, Ping, Pong, StartRequest, StartResponse, StopReason, StopRequest,
     StopResponse, SubscribeStopRequest, SubscribeStopResponse, VersionInfo,
 };
-use shuttle_service::{ResourceFactory, Service};
+use shuttle_service::{CancellationToken, ResourceFactory, Service};
 use tokio::sync::{
     broadcast::{self, Sender},
     mpsc, oneshot,
@@ -26,7 +26,7 @@ use tonic::{transport::Server, Request, Response, Status};
 
 use crate::{
     __internals::{Loader, Runner},
-    version,
+    version, SHUTDOWN_GRACE_PERIOD,
 };
 
 pub async fn start(
@@ -63,7 +63,8 @@ pub enum State {
 pub struct Alpha<L, R> {
     // Mutexes are for interior mutability
     stopped_tx: Sender<(StopReason, String)>,
-    kill_tx: Mutex<Option<oneshot::Sender<String>>>,
+    /// Asks the running service to shut down. The passed sender is notified once it has stopped.
+    kill_tx: Mutex<Option<oneshot::Sender<oneshot::Sender<()>>>>,
     loader: Mutex<Option<L>>,
     runner: Mutex<Option<R>>,
     /// The current state of the runtime, which is used by the ECS task to determine if the runtime
@@ -224,7 +225,9 @@ where
 
         // start service as a background task with a kill receiver
         tokio::spawn(async move {
-            let mut background = handle.spawn(service.bind(service_address));
+            let shutdown = CancellationToken::new();
+            let mut background =
+                handle.spawn(service.bind_with_shutdown(service_address, shutdown.clone()));
 
             tokio::select! {
                 res = &mut background => {
@@ -260,18 +263,37 @@ where
                     }
                 },
                 message = kill_rx => {
-                    match message {
-                        Ok(_) => {
+                    let done_tx = match message {
+                        Ok(done_tx) => {
                             let _ = stopped_tx
                                 .send((StopReason::Request, String::new()))
                                 .map_err(|e| println!("{e}"));
+                            Some(done_tx)
+                        }
+                        Err(_) => {
+                            println!("the kill sender dropped");
+                            None
                         }
-                        Err(_) => println!("the kill sender dropped")
                     };
 
-                    println!("will now abort the service");
-                    background.abort();
-                    background.await.unwrap().expect("to stop service");
+                    println!("will now shut down the service");
+                    shutdown.cancel();
+                    match tokio::time::timeout(SHUTDOWN_GRACE_PERIOD, &mut background).await {
+                        Ok(Ok(Ok(()))) => println!("service shut down gracefully"),
+                        Ok(Ok(Err(error))) => println!("service errored while shutting down: {error}"),
+                        Ok(Err(error)) => println!("service crashed while shutting down: {error}"),
+                        Err(_) => {
+                            println!(
+                                "service did not shut down within {}s, aborting it",
+                                SHUTDOWN_GRACE_PERIOD.as_secs()
+                            );
+                            background.abort();
+                        }
+                    }
+
+                    if let Some(done_tx) = done_tx {
+                        let _ = done_tx.send(());
+                    }
                 }
             }
         });
@@ -290,11 +312,15 @@ where
         let kill_tx = self.kill_tx.lock().unwrap().deref_mut().take();
 
         if let Some(kill_tx) = kill_tx {
-            if kill_tx.send("stopping deployment".to_owned()).is_err() {
+            let (done_tx, done_rx) = oneshot::channel();
+            if kill_tx.send(done_tx).is_err() {
                 println!("the kill receiver dropped");
                 return Err(Status::internal("failed to stop deployment"));
             }
 
+            // Only respond once the service has had a chance to shut down gracefully
+            let _ = done_rx.await;
+
             Ok(Response::new(StopResponse { success: true }))
         } else {
             println!("failed to stop deployment");
@@ -342,3 +368,65 @@ where
         Ok(Response::new(Pong {}))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use shuttle_service::Error;
+
+    use super::*;
+
+    /// Takes a while to finish its in-flight work once it is asked to shut down
+    struct DrainingService {
+        drained: Arc<AtomicBool>,
+    }
+
+    #[async_trait]
+    impl Service for DrainingService {
+        async fn bind(mut self, addr: SocketAddr) -> Result<(), Error> {
+            self.bind_with_shutdown(addr, CancellationToken::new())
+                .await
+        }
+
+        async fn bind_with_shutdown(
+            self,
+            _addr: SocketAddr,
+            shutdown: CancellationToken,
+        ) -> Result<(), Error> {
+            shutdown.cancelled().await;
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            self.drained.store(true, Ordering::SeqCst);
+
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn stop_waits_for_the_service_to_drain() {
+        let drained = Arc::new(AtomicBool::new(false));
+        let service = DrainingService {
+            drained: drained.clone(),
+        };
+        let alpha = Alpha::new(
+            |_: ResourceFactory| async { Ok(Vec::new()) },
+            |_: Vec<Vec<u8>>| async { Ok(service) },
+        );
+
+        let response = alpha
+            .start(Request::new(StartRequest {
+                ip: "127.0.0.1:0".to_string(),
+                resources: Vec::new(),
+            }))
+            .await
+            .unwrap();
+        assert!(response.into_inner().success);
+
+        let response = alpha.stop(Request::new(StopRequest {})).await.unwrap();
+        assert!(response.into_inner().success);
+        assert!(
+            drained.load(Ordering::SeqCst),
+            "stop should only return once the service has shut down"
+        );
+    }
+}
@@ -4,10 +4,12 @@ use std::{
     iter::FromIterator,
     net::{IpAddr, Ipv4Addr, SocketAddr},
     process::exit,
+    sync::{Arc, OnceLock},
 };
 
 use anyhow::Context;
 use hyper::{
+    header::CONTENT_TYPE,
     service::{make_service_fn, service_fn},
     Body, Response, Server,
 };
@@ -16,9 +18,14 @@ use shuttle_common::{
     resource::{ResourceInput, ResourceState, Type},
     secrets::Secret,
 };
-use shuttle_service::{Environment, ResourceFactory, Service, ShuttleResourceOutput};
+use shuttle_service::{
+    CancellationToken, Environment, HealthReporter, ResourceFactory, Service, ShuttleResourceOutput,
+};
 
-use crate::__internals::{Loader, Runner};
+use crate::{
+    __internals::{Loader, Runner},
+    SHUTDOWN_GRACE_PERIOD,
+};
 
 struct BetaEnvArgs {
     /// Are we running in a Shuttle deployment?
@@ -84,13 +91,30 @@ pub async fn start(loader: impl Loader + Send + 'static, runner: impl Runner + S
     let service_addr = SocketAddr::new(ip, port);
     let client = ShuttleApiClient::new(api_url, api_key, None);
 
+    // set once the service is initialized, if it has a status to report
+    let health_reporter: Arc<OnceLock<HealthReporter>> = Default::default();
+
     // start a health check server if requested
     if let Some(healthz_port) = healthz_port {
+        let health_reporter = health_reporter.clone();
         tokio::task::spawn(async move {
-            let make_service = make_service_fn(|_conn| async {
-                Ok::<_, Infallible>(service_fn(|_req| async move {
-                    Result::<Response<Body>, hyper::Error>::Ok(Response::new(Body::empty()))
-                }))
+            let make_service = make_service_fn(move |_conn| {
+                let health_reporter = health_reporter.clone();
+                async move {
+                    Ok::<_, Infallible>(service_fn(move |_req| {
+                        let health_reporter = health_reporter.clone();
+                        async move {
+                            let response = match health_reporter.get() {
+                                Some(reporter) => Response::builder()
+                                    .header(CONTENT_TYPE, "application/json")
+                                    .body(Body::from(reporter().to_string()))
+                                    .expect("to build health check response"),
+                                None => Response::new(Body::empty()),
+                            };
+                            Result::<Response<Body>, hyper::Error>::Ok(response)
+                        }
+                    }))
+                }
             });
             let server = Server::bind(&SocketAddr::new(Ipv4Addr::LOCALHOST.into(), healthz_port))
                 .serve(make_service);
@@ -215,14 +239,52 @@ pub async fn start(loader: impl Loader + Send + 'static, runner: impl Runner + S
             exit(151);
         }
     };
+    if let Some(reporter) = service.health_reporter() {
+        let _ = health_reporter.set(reporter);
+    }
 
     //
     // RUNNING PHASE
     //
     println!("Starting service!");
 
-    if let Err(e) = service.bind(service_addr).await {
+    let shutdown = CancellationToken::new();
+    tokio::spawn({
+        let shutdown = shutdown.clone();
+        async move {
+            wait_for_shutdown_signal().await;
+            println!("Received shutdown signal, stopping service...");
+            shutdown.cancel();
+
+            tokio::time::sleep(SHUTDOWN_GRACE_PERIOD).await;
+            eprintln!(
+                "Service did not shut down within {}s",
+                SHUTDOWN_GRACE_PERIOD.as_secs()
+            );
+            exit(1);
+        }
+    });
+
+    if let Err(e) = service.bind_with_shutdown(service_addr, shutdown).await {
         eprintln!("Service encountered an error in `bind`: {e}");
         exit(1);
     }
 }
+
+/// Resolves on SIGTERM (sent when a deployment is stopped) or Ctrl-C (local runs)
+async fn wait_for_shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut sigterm = signal(SignalKind::terminate()).expect("can not get SIGTERM signal");
+        tokio::select! {
+            _ = sigterm.recv() => {}
+            _ = tokio::signal::ctrl_c() => {}
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
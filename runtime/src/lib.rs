@@ -7,8 +7,9 @@
 // Public API
 pub use shuttle_codegen::main;
 pub use shuttle_service::{
-    CustomError, DbInput, DeploymentMetadata, Environment, Error, IntoResource, ResourceFactory,
-    ResourceInputBuilder, SecretStore, Service,
+    CancellationToken, ContainerResponse, CustomError, DbInput, DeploymentMetadata, Environment,
+    Error, HealthReporter, IntoResource, ResourceFactory, ResourceInputBuilder, SecretStore,
+    Service,
 };
 
 // Useful re-exports
@@ -25,7 +26,10 @@ fn version() -> String {
     format!("{} {}", crate::NAME, crate::VERSION)
 }
 
-pub use plugins::{Metadata, Secrets};
+/// How long a service gets to finish in-flight work after being asked to shut down
+const SHUTDOWN_GRACE_PERIOD: std::time::Duration = std::time::Duration::from_secs(10);
+
+pub use plugins::{Container, Metadata, Secrets};
 /// Built-in plugins
 mod plugins;
 
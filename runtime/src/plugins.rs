@@ -1,7 +1,8 @@
 use crate::async_trait;
 use shuttle_service::{
     resource::{ProvisionResourceRequest, ShuttleResourceOutput, Type},
-    DeploymentMetadata, Error, ResourceFactory, ResourceInputBuilder, SecretStore,
+    ContainerRequest, ContainerResponse, CustomError, DeploymentMetadata, Environment, Error,
+    ResourceFactory, ResourceInputBuilder, SecretStore,
 };
 
 /// ## Shuttle Metadata
@@ -72,3 +73,124 @@ impl ResourceInputBuilder for Secrets {
         ))
     }
 }
+
+/// ## Shuttle Container
+///
+/// Plugin for starting an arbitrary Docker container next to your service during local runs,
+/// such as NATS, MinIO or Elasticsearch.
+///
+/// The container is started by `cargo shuttle run` and kept around between runs.
+/// This resource is **not available in deployments**, requesting it there fails with an error.
+///
+/// ### Options
+///
+/// - `image` (required): the image to run, ex. `"docker.io/library/nats:2.10"`
+/// - `port` (required): the internal port to expose, ex. `"4222/tcp"`. Repeat to expose more ports.
+/// - `name`: used in the container name, defaults to the image name
+/// - `env`: a `"KEY=value"` environment variable. Repeat to set more variables.
+/// - `ready_cmd`: a whitespace separated command that is executed in the container until it succeeds
+///
+/// ### Example
+///
+/// ```rust,ignore
+/// #[shuttle_runtime::main]
+/// async fn main(
+///     #[shuttle_runtime::Container(
+///         image = "docker.io/library/nats:2.10",
+///         port = "4222/tcp",
+///         port = "8222/tcp",
+///     )]
+///     nats: ContainerResponse,
+/// ) -> __ {
+///     let url = format!("nats://localhost:{}", nats.host_port);
+///     let monitoring_port = nats.host_port_for("8222/tcp").unwrap();
+///     ...
+/// }
+/// ```
+#[derive(Default)]
+pub struct Container {
+    name: Option<String>,
+    image: Option<String>,
+    ports: Vec<String>,
+    env: Vec<String>,
+    ready_cmd: Vec<String>,
+}
+
+impl Container {
+    /// Name of the container, which is used in the Docker container name
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = Some(name.to_string());
+        self
+    }
+    /// The image to run
+    pub fn image(mut self, image: &str) -> Self {
+        self.image = Some(image.to_string());
+        self
+    }
+    /// Expose an internal port of the container. The first port added is the main port.
+    pub fn port(mut self, port: &str) -> Self {
+        self.ports.push(port.to_string());
+        self
+    }
+    /// Set an environment variable given as `KEY=value`
+    pub fn env(mut self, env: &str) -> Self {
+        self.env.push(env.to_string());
+        self
+    }
+    /// Command to check if the container is ready. Arguments are separated by whitespace.
+    pub fn ready_cmd(mut self, ready_cmd: &str) -> Self {
+        self.ready_cmd = ready_cmd.split_whitespace().map(str::to_string).collect();
+        self
+    }
+}
+
+#[async_trait]
+impl ResourceInputBuilder for Container {
+    type Input = ProvisionResourceRequest;
+    type Output = ShuttleResourceOutput<ContainerResponse>;
+
+    async fn build(self, factory: &ResourceFactory) -> Result<Self::Input, Error> {
+        let md = factory.get_metadata();
+        if md.env == Environment::Deployment {
+            return Err(Error::Custom(CustomError::msg(
+                "the `Container` resource is only available in local runs",
+            )));
+        }
+
+        let image = self
+            .image
+            .ok_or_else(|| Error::Custom(CustomError::msg("missing `image` parameter")))?;
+        let mut ports = self.ports.into_iter();
+        let port = ports
+            .next()
+            .ok_or_else(|| Error::Custom(CustomError::msg("missing `port` parameter")))?;
+        let container_name = self
+            .name
+            .unwrap_or_else(|| container_name_from_image(&image));
+
+        Ok(ProvisionResourceRequest::new(
+            Type::Container,
+            serde_json::to_value(ContainerRequest {
+                project_name: md.project_name,
+                container_name,
+                image,
+                port,
+                extra_ports: ports.collect(),
+                env: self.env,
+                ready_cmd: self.ready_cmd,
+            })
+            .unwrap(),
+            serde_json::Value::Null,
+        ))
+    }
+}
+
+/// Turn "docker.io/library/nats:2.10" into "nats"
+fn container_name_from_image(image: &str) -> String {
+    let name = image.rsplit('/').next().unwrap_or(image);
+    let name = name.split([':', '@']).next().unwrap_or(name);
+
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
@@ -30,7 +30,14 @@ pub async fn spawn_runtime(project_path: &str) -> Result<TestRuntime> {
     let runtime_address = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), runtime_port);
 
     let (tx, _) = tokio::sync::mpsc::channel::<String>(256);
-    let runtimes = build_workspace(Path::new(project_path), false, tx, false).await?;
+    let runtimes = build_workspace(
+        Path::new(project_path),
+        false,
+        &Default::default(),
+        tx,
+        false,
+    )
+    .await?;
     let service = runtimes[0].clone();
 
     let secrets: HashMap<String, String> = Default::default();
@@ -1,9 +1,11 @@
 use std::fs::read_to_string;
 use std::path::{Path, PathBuf};
 use std::process::Stdio;
+use std::time::Duration;
 
 use anyhow::{anyhow, bail, Context};
 use cargo_metadata::{Metadata, Package};
+use serde::{Deserialize, Serialize};
 use shuttle_common::constants::RUNTIME_NAME;
 use tokio::io::AsyncBufReadExt;
 use tracing::{debug, error, info, trace};
@@ -59,10 +61,296 @@ fn extract_shuttle_toml_name(path: PathBuf) -> anyhow::Result<String> {
     Ok(name)
 }
 
+/// The parts of Shuttle.toml that are used when building and testing
+#[derive(Default, Deserialize)]
+struct ShuttleToml {
+    build: Option<BuildConfig>,
+    deploy: Option<DeployToml>,
+}
+
+#[derive(Deserialize)]
+struct DeployToml {
+    test: Option<TestConfig>,
+    health_check: Option<HealthCheckConfig>,
+    restart: Option<RestartConfig>,
+}
+
+fn read_shuttle_toml(project_path: &Path) -> anyhow::Result<ShuttleToml> {
+    let path = project_path.join("Shuttle.toml");
+    if !path.exists() {
+        return Ok(ShuttleToml::default());
+    }
+    let shuttle_toml = read_to_string(&path).context("failed to read Shuttle.toml")?;
+
+    toml::from_str(&shuttle_toml).context("failed to parse Shuttle.toml")
+}
+
+/// The `[build]` section of Shuttle.toml, which configures how the services are compiled
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct BuildConfig {
+    /// Features to activate instead of the `shuttle` feature
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub features: Vec<String>,
+    pub no_default_features: bool,
+    /// Cargo profile to build with instead of `release` or `dev`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    /// Binary target to build and run, for packages with several binaries
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bin: Option<String>,
+    /// Flags added to `RUSTFLAGS`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rustflags: Option<String>,
+}
+
+impl BuildConfig {
+    /// Read the `[build]` section of the Shuttle.toml in a project directory, if there is one
+    pub fn from_project(project_path: &Path) -> anyhow::Result<Self> {
+        Ok(read_shuttle_toml(project_path)?.build.unwrap_or_default())
+    }
+
+    /// The name of the profile to build with, and the directory in the target directory that it builds to
+    fn profile(&self, release_mode: bool) -> (&str, &str) {
+        match self.profile.as_deref() {
+            Some(profile @ ("dev" | "test")) => (profile, "debug"),
+            Some(profile @ "bench") => (profile, "release"),
+            Some(profile) => (profile, profile),
+            None if release_mode => ("release", "release"),
+            None => ("dev", "debug"),
+        }
+    }
+
+    /// The `RUSTFLAGS` to compile with, if `rustflags` adds to them
+    pub fn rustflags_env(&self) -> Option<String> {
+        let rustflags = self.rustflags.as_ref()?;
+        let mut flags = std::env::var("RUSTFLAGS").unwrap_or_default();
+        if !flags.is_empty() {
+            flags.push(' ');
+        }
+        flags.push_str(rustflags);
+
+        Some(flags)
+    }
+}
+
+/// The `[deploy.test]` section of Shuttle.toml, which configures the tests that run before a deployment
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct TestConfig {
+    /// Only test these packages instead of the whole workspace
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub packages: Vec<String>,
+    /// Features to activate
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub features: Vec<String>,
+    pub all_features: bool,
+    pub no_default_features: bool,
+    /// Only run the tests whose names contain one of these filters
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub filters: Vec<String>,
+    /// Run all tests instead of stopping at the first failing test binary
+    pub no_fail_fast: bool,
+    /// Time limit for building and running the tests, in seconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+}
+
+impl TestConfig {
+    /// Read the `[deploy.test]` section of the Shuttle.toml in a project directory, if there is one
+    pub fn from_project(project_path: &Path) -> anyhow::Result<Self> {
+        let config = read_shuttle_toml(project_path)?
+            .deploy
+            .and_then(|deploy| deploy.test)
+            .unwrap_or_default();
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    /// Reject settings that contradict each other
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.all_features && !self.features.is_empty() {
+            bail!("`features` and `all_features` can not both be set in [deploy.test]");
+        }
+
+        Ok(())
+    }
+
+    /// The arguments to give `cargo test` to run the tests.
+    ///
+    /// The tests are built with the features and profile of the `[build]` section, on top of the ones set here.
+    /// Its `RUSTFLAGS` are set with [`BuildConfig::rustflags_env`].
+    pub fn cargo_test_args(&self, build: &BuildConfig, release_mode: bool) -> Vec<String> {
+        let mut args = Vec::new();
+        if build.profile.is_some() {
+            args.push(format!("--profile={}", build.profile(release_mode).0));
+        } else if release_mode {
+            args.push("--release".to_owned());
+        }
+        for package in &self.packages {
+            args.push(format!("--package={package}"));
+        }
+        let features: Vec<_> = build
+            .features
+            .iter()
+            .chain(&self.features)
+            .cloned()
+            .collect();
+        if self.all_features {
+            args.push("--all-features".to_owned());
+        } else if !features.is_empty() {
+            args.push(format!("--features={}", features.join(",")));
+        }
+        if self.no_default_features || build.no_default_features {
+            args.push("--no-default-features".to_owned());
+        }
+        if self.no_fail_fast {
+            args.push("--no-fail-fast".to_owned());
+        }
+        // cargo only takes one test name filter, so the rest are passed on to the test harness
+        if let Some((first, rest)) = self.filters.split_first() {
+            args.push(first.to_owned());
+            if !rest.is_empty() {
+                args.push("--".to_owned());
+                args.extend(rest.iter().cloned());
+            }
+        }
+
+        args
+    }
+
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout.map(Duration::from_secs)
+    }
+
+    /// Wait for a `cargo test` process to finish, and kill it if it runs into the time limit
+    pub async fn wait_for_tests(
+        &self,
+        tests: &mut tokio::process::Child,
+    ) -> Result<std::process::ExitStatus, TestWaitError> {
+        let Some(limit) = self.timeout() else {
+            return Ok(tests.wait().await?);
+        };
+
+        match tokio::time::timeout(limit, tests.wait()).await {
+            Ok(status) => Ok(status?),
+            Err(_) => {
+                let _ = tests.kill().await;
+                Err(TestWaitError::Timeout(limit))
+            }
+        }
+    }
+}
+
+/// Why the pre-deploy tests did not finish
+#[derive(Debug, thiserror::Error)]
+pub enum TestWaitError {
+    #[error("failed to wait for the tests: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("the tests did not finish within {} seconds", .0.as_secs())]
+    Timeout(Duration),
+}
+
+/// The `[deploy.health_check]` section of Shuttle.toml, which configures how a new deployment is checked before it
+/// takes over the traffic of the running one
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct HealthCheckConfig {
+    /// HTTP path that has to respond without a server error for the deployment to be healthy.
+    /// Without it, a deployment is healthy once it has started.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Time for the deployment to become healthy, in seconds
+    pub timeout: u64,
+}
+
+impl Default for HealthCheckConfig {
+    fn default() -> Self {
+        Self {
+            path: None,
+            timeout: 60,
+        }
+    }
+}
+
+impl HealthCheckConfig {
+    /// Read the `[deploy.health_check]` section of the Shuttle.toml in a project directory, if there is one
+    pub fn from_project(project_path: &Path) -> anyhow::Result<Self> {
+        Ok(read_shuttle_toml(project_path)?
+            .deploy
+            .and_then(|deploy| deploy.health_check)
+            .unwrap_or_default())
+    }
+
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout)
+    }
+}
+
+/// Longest time to wait before restarting a crashed deployment, however many attempts it took
+const MAX_RESTART_BACKOFF: Duration = Duration::from_secs(300);
+
+/// When a crashed deployment is started again
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum RestartPolicy {
+    /// Crashed deployments stay down until the next deployment or project restart
+    #[default]
+    Never,
+    /// Crashed deployments are restarted until they run out of attempts
+    OnFailure,
+}
+
+/// The `[deploy.restart]` section of Shuttle.toml, which configures what happens when a deployment crashes
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct RestartConfig {
+    pub policy: RestartPolicy,
+    /// Number of restarts after which a crashed deployment stays down
+    pub max_attempts: u32,
+    /// Time to wait before the first restart, in seconds. It is doubled for every following attempt.
+    pub backoff: u64,
+}
+
+impl Default for RestartConfig {
+    fn default() -> Self {
+        Self {
+            policy: RestartPolicy::Never,
+            max_attempts: 5,
+            backoff: 1,
+        }
+    }
+}
+
+impl RestartConfig {
+    /// Read the `[deploy.restart]` section of the Shuttle.toml in a project directory, if there is one
+    pub fn from_project(project_path: &Path) -> anyhow::Result<Self> {
+        Ok(read_shuttle_toml(project_path)?
+            .deploy
+            .and_then(|deploy| deploy.restart)
+            .unwrap_or_default())
+    }
+
+    /// How long to wait before restart `attempt`, counting from 1, or `None` if the deployment should stay down
+    pub fn backoff(&self, attempt: u32) -> Option<Duration> {
+        if self.policy == RestartPolicy::Never || attempt == 0 || attempt > self.max_attempts {
+            return None;
+        }
+        // Gives the crashed runtime a moment to be cleaned up
+        let backoff = Duration::from_secs(self.backoff.max(1))
+            .saturating_mul(2u32.saturating_pow(attempt - 1))
+            .min(MAX_RESTART_BACKOFF);
+
+        Some(backoff)
+    }
+}
+
 /// Given a project directory path, builds the crate
 pub async fn build_workspace(
     project_path: &Path,
     release_mode: bool,
+    config: &BuildConfig,
     tx: tokio::sync::mpsc::Sender<String>,
     deployment: bool,
 ) -> anyhow::Result<Vec<BuiltService>> {
@@ -100,17 +388,29 @@ pub async fn build_workspace(
     notification.abort();
 
     let metadata = async_cargo_metadata(manifest_path.as_path()).await?;
-    let packages = find_shuttle_packages(&metadata)?;
+    let mut packages = find_shuttle_packages(&metadata)?;
     if packages.is_empty() {
         bail!(
             "Did not find any packages that Shuttle can run. \
             Make sure your crate has a binary target that uses `#[shuttle_runtime::main]`."
         );
     }
+    if let Some(bin) = &config.bin {
+        packages.retain(|package| {
+            package
+                .targets
+                .iter()
+                .any(|target| target.is_bin() && &target.name == bin)
+        });
+        if packages.is_empty() {
+            bail!("Did not find a Shuttle package with a binary target named `{bin}`.");
+        }
+    }
 
     let services = compile(
         packages,
         release_mode,
+        config,
         project_path.clone(),
         metadata.target_directory.clone(),
         deployment,
@@ -201,6 +501,7 @@ pub async fn clean_crate(project_path: &Path) -> anyhow::Result<()> {
 async fn compile(
     packages: Vec<Package>,
     release_mode: bool,
+    config: &BuildConfig,
     project_path: PathBuf,
     target_path: impl Into<PathBuf>,
     deployment: bool,
@@ -223,20 +524,31 @@ async fn compile(
         cmd.arg("--jobs=4");
     }
 
-    // TODO: Compile only one binary target in the package.
+    // Features from Shuttle.toml replace the `shuttle` feature
+    let custom_features = !config.features.is_empty() || config.no_default_features;
+    // TODO: Compile only one binary target in the package when no bin is configured.
     for package in &packages {
-        if package.features.contains_key("shuttle") {
+        if !custom_features && package.features.contains_key("shuttle") {
             cmd.arg("--no-default-features").arg("--features=shuttle");
         }
         cmd.arg("--package").arg(package.name.as_str());
     }
+    if !config.features.is_empty() {
+        cmd.arg(format!("--features={}", config.features.join(",")));
+    }
+    if config.no_default_features {
+        cmd.arg("--no-default-features");
+    }
+    if let Some(bin) = &config.bin {
+        cmd.arg("--bin").arg(bin);
+    }
 
-    let profile = if release_mode {
-        cmd.arg("--release");
-        "release"
-    } else {
-        "debug"
-    };
+    let (profile, profile_dir) = config.profile(release_mode);
+    cmd.arg(format!("--profile={profile}"));
+
+    if let Some(rustflags) = config.rustflags_env() {
+        cmd.env("RUSTFLAGS", rustflags);
+    }
 
     cmd.stderr(Stdio::piped());
     cmd.stdout(Stdio::null());
@@ -262,8 +574,8 @@ async fn compile(
             let mut path: PathBuf = [
                 project_path.clone(),
                 target_path.clone(),
-                profile.into(),
-                package.name.clone().into(),
+                profile_dir.into(),
+                config.bin.as_ref().unwrap_or(&package.name).into(),
             ]
             .iter()
             .collect();
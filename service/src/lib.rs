@@ -1,6 +1,7 @@
 use std::collections::BTreeMap;
 use std::net::SocketAddr;
 use std::path::PathBuf;
+use std::sync::Arc;
 
 use async_trait::async_trait;
 use serde::{de::DeserializeOwned, Serialize};
@@ -12,6 +13,7 @@ pub use shuttle_common::{
     secrets::Secret,
     ContainerRequest, ContainerResponse, DatabaseInfo, DatabaseResource, DbInput, SecretStore,
 };
+pub use tokio_util::sync::CancellationToken;
 
 pub use crate::error::{CustomError, Error};
 
@@ -123,4 +125,34 @@ pub trait Service: Send {
     /// The passed [`SocketAddr`] receives proxied HTTP traffic from you Shuttle subdomain (or custom domain).
     /// Binding to the address is only relevant if this service is an HTTP server.
     async fn bind(mut self, addr: SocketAddr) -> Result<(), error::Error>;
+
+    /// Same as [`Service::bind`], but also receives a [`CancellationToken`] that is cancelled when
+    /// the runtime is asked to stop (a `Stop` request, SIGTERM, or Ctrl-C on a local run).
+    ///
+    /// Implementors should stop accepting new work once the token is cancelled, finish in-flight
+    /// requests, and then return. The runtime gives the service a grace period to do so before
+    /// forcefully stopping it.
+    ///
+    /// The default implementation ignores the token and calls [`Service::bind`].
+    async fn bind_with_shutdown(
+        self,
+        addr: SocketAddr,
+        shutdown: CancellationToken,
+    ) -> Result<(), error::Error>
+    where
+        Self: Sized,
+    {
+        let _ = shutdown;
+        self.bind(addr).await
+    }
+
+    /// Status to report in the body of the runtime's health check endpoint, such as a job schedule.
+    ///
+    /// This is called once before the service is bound. The returned reporter is then called on every health check.
+    fn health_reporter(&self) -> Option<HealthReporter> {
+        None
+    }
 }
+
+/// Produces the JSON body of a health check response. See [`Service::health_reporter`].
+pub type HealthReporter = Arc<dyn Fn() -> serde_json::Value + Send + Sync>;
@@ -20,15 +20,18 @@ pub async fn start(
         args = %format!("{} {}", runtime_executable.display(), args.join(" ")),
         "Spawning runtime process",
     );
-    let runtime = process::Command::new(
+    let mut command = process::Command::new(
         dunce::canonicalize(runtime_executable).context("canonicalize path of executable")?,
-    )
-    .current_dir(project_path)
-    .args(&args)
-    .stdout(Stdio::piped())
-    .kill_on_drop(true)
-    .spawn()
-    .context("spawning runtime process")?;
+    );
+    command
+        .current_dir(project_path)
+        .args(&args)
+        .stdout(Stdio::piped())
+        .kill_on_drop(true);
+    // Keep terminal signals (Ctrl-C) away from the runtime so that the parent can stop it gracefully
+    #[cfg(unix)]
+    command.process_group(0);
+    let runtime = command.spawn().context("spawning runtime process")?;
 
     // runtime might start on localhost or 0.0.0.0, but we can reach it on localhost:port
     let runtime_client = runtime::get_client(format!("http://localhost:{port}")).await?;
@@ -1,15 +1,21 @@
 use std::path::{Path, PathBuf};
 
-use shuttle_service::builder::{build_workspace, BuiltService};
+use shuttle_service::builder::{build_workspace, BuildConfig, BuiltService};
 
 #[tokio::test]
 #[should_panic(expected = "Build failed. Is the Shuttle runtime missing?")]
 async fn not_shuttle() {
     let (tx, _) = tokio::sync::mpsc::channel::<String>(256);
     let project_path = format!("{}/tests/resources/not-shuttle", env!("CARGO_MANIFEST_DIR"));
-    build_workspace(Path::new(&project_path), false, tx, false)
-        .await
-        .unwrap();
+    build_workspace(
+        Path::new(&project_path),
+        false,
+        &Default::default(),
+        tx,
+        false,
+    )
+    .await
+    .unwrap();
 }
 
 #[tokio::test]
@@ -19,7 +25,15 @@ async fn not_shuttle() {
 async fn not_bin() {
     let (tx, _) = tokio::sync::mpsc::channel::<String>(256);
     let project_path = format!("{}/tests/resources/not-bin", env!("CARGO_MANIFEST_DIR"));
-    match build_workspace(Path::new(&project_path), false, tx, false).await {
+    match build_workspace(
+        Path::new(&project_path),
+        false,
+        &Default::default(),
+        tx,
+        false,
+    )
+    .await
+    {
         Ok(_) => {}
         Err(e) => panic!("{}", e.to_string()),
     }
@@ -31,9 +45,15 @@ async fn is_bin() {
     let project_path = format!("{}/tests/resources/is-bin", env!("CARGO_MANIFEST_DIR"));
 
     assert_eq!(
-        build_workspace(Path::new(&project_path), false, tx, false)
-            .await
-            .unwrap(),
+        build_workspace(
+            Path::new(&project_path),
+            false,
+            &Default::default(),
+            tx,
+            false
+        )
+        .await
+        .unwrap(),
         vec![BuiltService {
             workspace_path: PathBuf::from(&project_path),
             manifest_path: PathBuf::from(&project_path).join("Cargo.toml"),
@@ -43,6 +63,39 @@ async fn is_bin() {
     );
 }
 
+#[tokio::test]
+async fn is_bin_with_build_config() {
+    let (tx, _) = tokio::sync::mpsc::channel::<String>(256);
+    let project_path = format!("{}/tests/resources/is-bin", env!("CARGO_MANIFEST_DIR"));
+    let config = BuildConfig {
+        profile: Some("test".to_owned()),
+        bin: Some("is-bin".to_owned()),
+        ..Default::default()
+    };
+
+    assert_eq!(
+        build_workspace(Path::new(&project_path), true, &config, tx, false)
+            .await
+            .unwrap()[0]
+            .executable_path,
+        PathBuf::from(&project_path).join("target/debug/is-bin"),
+    );
+}
+
+#[tokio::test]
+#[should_panic(expected = "Did not find a Shuttle package with a binary target named `missing`.")]
+async fn missing_bin() {
+    let (tx, _) = tokio::sync::mpsc::channel::<String>(256);
+    let project_path = format!("{}/tests/resources/is-bin", env!("CARGO_MANIFEST_DIR"));
+    let config = BuildConfig {
+        bin: Some("missing".to_owned()),
+        ..Default::default()
+    };
+    build_workspace(Path::new(&project_path), false, &config, tx, false)
+        .await
+        .unwrap();
+}
+
 #[tokio::test]
 #[should_panic(expected = "Cargo manifest file not found")]
 async fn not_found() {
@@ -51,9 +104,15 @@ async fn not_found() {
         "{}/tests/resources/non-existing",
         env!("CARGO_MANIFEST_DIR")
     );
-    build_workspace(Path::new(&project_path), false, tx, false)
-        .await
-        .unwrap();
+    build_workspace(
+        Path::new(&project_path),
+        false,
+        &Default::default(),
+        tx,
+        false,
+    )
+    .await
+    .unwrap();
 }
 
 // Test that alpha projects are compiled correctly. Any shared library crates should not be compiled too
@@ -68,9 +127,15 @@ async fn workspace() {
     let project_path = format!("{}/tests/resources/workspace", env!("CARGO_MANIFEST_DIR"));
 
     assert_eq!(
-        build_workspace(Path::new(&project_path), false, tx, false)
-            .await
-            .unwrap(),
+        build_workspace(
+            Path::new(&project_path),
+            false,
+            &Default::default(),
+            tx,
+            false
+        )
+        .await
+        .unwrap(),
         vec![
             BuiltService {
                 workspace_path: PathBuf::from(&project_path),
@@ -1,2 +1,4 @@
 #[cfg(feature = "builder")]
 mod build_crate;
+#[cfg(feature = "builder")]
+mod shuttle_toml;
@@ -0,0 +1,147 @@
+use std::path::Path;
+use std::time::Duration;
+
+use shuttle_service::builder::{
+    BuildConfig, HealthCheckConfig, RestartConfig, RestartPolicy, TestConfig, TestWaitError,
+};
+
+#[test]
+fn reads_deploy_test_section() {
+    let project_path = format!("{}/tests/resources/test-config", env!("CARGO_MANIFEST_DIR"));
+    let config = TestConfig::from_project(Path::new(&project_path)).unwrap();
+
+    assert_eq!(config.timeout(), Some(Duration::from_secs(600)));
+    assert_eq!(
+        config.cargo_test_args(&BuildConfig::default(), true),
+        vec![
+            "--release",
+            "--package=api",
+            "--package=worker",
+            "--features=integration",
+            "--no-fail-fast",
+            "routes::",
+            "--",
+            "jobs::",
+        ]
+    );
+}
+
+#[test]
+fn defaults_to_testing_everything() {
+    // is-bin has no Shuttle.toml
+    let project_path = format!("{}/tests/resources/is-bin", env!("CARGO_MANIFEST_DIR"));
+    let config = TestConfig::from_project(Path::new(&project_path)).unwrap();
+
+    assert_eq!(config, TestConfig::default());
+    assert_eq!(config.timeout(), None);
+    assert_eq!(
+        config.cargo_test_args(&BuildConfig::default(), false),
+        Vec::<String>::new()
+    );
+}
+
+#[test]
+fn tests_with_the_build_section() {
+    let project_path = format!("{}/tests/resources/test-config", env!("CARGO_MANIFEST_DIR"));
+    let config = TestConfig::from_project(Path::new(&project_path)).unwrap();
+    let build = BuildConfig::from_project(Path::new(&project_path)).unwrap();
+
+    assert_eq!(
+        config.cargo_test_args(&build, true),
+        vec![
+            "--profile=staging",
+            "--package=api",
+            "--package=worker",
+            "--features=postgres,integration",
+            "--no-default-features",
+            "--no-fail-fast",
+            "routes::",
+            "--",
+            "jobs::",
+        ]
+    );
+    assert!(build
+        .rustflags_env()
+        .unwrap()
+        .ends_with("--cfg tokio_unstable"));
+    assert_eq!(BuildConfig::default().rustflags_env(), None);
+}
+
+#[test]
+fn rejects_features_with_all_features() {
+    let config = TestConfig {
+        features: vec!["integration".to_owned()],
+        all_features: true,
+        ..Default::default()
+    };
+
+    assert!(config.validate().is_err());
+    assert!(TestConfig::default().validate().is_ok());
+}
+
+#[tokio::test]
+async fn kills_tests_that_run_into_the_timeout() {
+    let config = TestConfig {
+        timeout: Some(1),
+        ..Default::default()
+    };
+    let mut tests = tokio::process::Command::new("sleep")
+        .arg("30")
+        .spawn()
+        .unwrap();
+
+    assert!(matches!(
+        config.wait_for_tests(&mut tests).await,
+        Err(TestWaitError::Timeout(limit)) if limit == Duration::from_secs(1)
+    ));
+    assert!(tests.try_wait().unwrap().is_some());
+}
+
+#[test]
+fn reads_build_section() {
+    let project_path = format!("{}/tests/resources/test-config", env!("CARGO_MANIFEST_DIR"));
+    let config = BuildConfig::from_project(Path::new(&project_path)).unwrap();
+
+    assert_eq!(
+        config,
+        BuildConfig {
+            features: vec!["postgres".to_owned()],
+            no_default_features: true,
+            profile: Some("staging".to_owned()),
+            bin: Some("server".to_owned()),
+            rustflags: Some("--cfg tokio_unstable".to_owned()),
+        }
+    );
+}
+
+#[test]
+fn reads_health_check_section() {
+    let project_path = format!("{}/tests/resources/test-config", env!("CARGO_MANIFEST_DIR"));
+    let config = HealthCheckConfig::from_project(Path::new(&project_path)).unwrap();
+    assert_eq!(config.path.as_deref(), Some("/health"));
+    assert_eq!(config.timeout(), Duration::from_secs(120));
+
+    // is-bin has no Shuttle.toml
+    let project_path = format!("{}/tests/resources/is-bin", env!("CARGO_MANIFEST_DIR"));
+    let config = HealthCheckConfig::from_project(Path::new(&project_path)).unwrap();
+    assert_eq!(config, HealthCheckConfig::default());
+}
+
+#[test]
+fn reads_restart_section() {
+    let project_path = format!("{}/tests/resources/test-config", env!("CARGO_MANIFEST_DIR"));
+    let config = RestartConfig::from_project(Path::new(&project_path)).unwrap();
+    assert_eq!(config.policy, RestartPolicy::OnFailure);
+    assert_eq!(config.backoff(0), None);
+    assert_eq!(config.backoff(1), Some(Duration::from_secs(2)));
+    assert_eq!(config.backoff(3), Some(Duration::from_secs(8)));
+    // Capped at five minutes
+    assert_eq!(config.backoff(10), Some(Duration::from_secs(300)));
+    assert_eq!(config.backoff(11), None);
+
+    // is-bin has no Shuttle.toml, so it is never restarted
+    let project_path = format!("{}/tests/resources/is-bin", env!("CARGO_MANIFEST_DIR"));
+    let config = RestartConfig::from_project(Path::new(&project_path)).unwrap();
+    assert_eq!(config, RestartConfig::default());
+    assert_eq!(config.backoff(1), None);
+}
@@ -1,4 +1,5 @@
 #![doc = include_str!("../README.md")]
+use shuttle_runtime::CancellationToken;
 use std::net::SocketAddr;
 
 /// A wrapper type for a closure that returns an [actix_web::web::ServiceConfig] so we can implement
@@ -12,6 +13,16 @@ where
     F: FnOnce(&mut actix_web::web::ServiceConfig) + Send + Clone + 'static,
 {
     async fn bind(mut self, addr: SocketAddr) -> Result<(), shuttle_runtime::Error> {
+        self.bind_with_shutdown(addr, CancellationToken::new())
+            .await
+    }
+
+    /// Same as `bind`, but stops the server gracefully once `shutdown` is cancelled.
+    async fn bind_with_shutdown(
+        self,
+        addr: SocketAddr,
+        shutdown: CancellationToken,
+    ) -> Result<(), shuttle_runtime::Error> {
         // Start a worker for each cpu, but no more than 4.
         let worker_count = num_cpus::get().min(4);
 
@@ -21,6 +32,12 @@ where
                 .bind(addr)?
                 .run();
 
+        let handle = server.handle();
+        shuttle_runtime::tokio::spawn(async move {
+            shutdown.cancelled().await;
+            handle.stop(true).await;
+        });
+
         server.await.map_err(shuttle_runtime::CustomError::new)?;
 
         Ok(())
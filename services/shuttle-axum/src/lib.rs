@@ -1,5 +1,5 @@
 #![doc = include_str!("../README.md")]
-use shuttle_runtime::{CustomError, Error};
+use shuttle_runtime::{CancellationToken, CustomError, Error};
 use std::net::SocketAddr;
 
 #[cfg(feature = "axum")]
@@ -15,6 +15,16 @@ impl shuttle_runtime::Service for AxumService {
     /// Takes the router that is returned by the user in their [shuttle_runtime::main] function
     /// and binds to an address passed in by shuttle.
     async fn bind(mut self, addr: SocketAddr) -> Result<(), Error> {
+        self.bind_with_shutdown(addr, CancellationToken::new())
+            .await
+    }
+
+    /// Same as `bind`, but finishes in-flight requests and returns once `shutdown` is cancelled.
+    async fn bind_with_shutdown(
+        self,
+        addr: SocketAddr,
+        shutdown: CancellationToken,
+    ) -> Result<(), Error> {
         #[cfg(feature = "axum")]
         axum::serve(
             shuttle_runtime::tokio::net::TcpListener::bind(addr)
@@ -22,11 +32,13 @@ impl shuttle_runtime::Service for AxumService {
                 .map_err(CustomError::new)?,
             self.0,
         )
+        .with_graceful_shutdown(shutdown.cancelled_owned())
         .await
         .map_err(CustomError::new)?;
         #[cfg(feature = "axum-0-6")]
         axum_0_6::Server::bind(&addr)
             .serve(self.0.into_make_service())
+            .with_graceful_shutdown(shutdown.cancelled_owned())
             .await
             .map_err(CustomError::new)?;
 
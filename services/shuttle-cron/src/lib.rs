@@ -0,0 +1,385 @@
+#![doc = include_str!("../README.md")]
+use std::{
+    fmt::Display,
+    future::Future,
+    net::SocketAddr,
+    pin::Pin,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+use cron::Schedule;
+use rand::Rng;
+use serde::Serialize;
+use shuttle_runtime::{
+    tokio::{self, task::JoinHandle},
+    CancellationToken, CustomError, Error, HealthReporter,
+};
+use tracing::{error, info, warn};
+
+/// Random delay added to each run by default, so that jobs sharing a schedule don't all start at once
+const DEFAULT_MAX_JITTER: Duration = Duration::from_secs(1);
+
+type JobFuture = Pin<Box<dyn Future<Output = Result<(), String>> + Send>>;
+type JobFn = Arc<dyn Fn() -> JobFuture + Send + Sync>;
+
+/// A service that runs async jobs on cron schedules.
+///
+/// A job is skipped if its previous run has not finished yet, so runs of the same job never overlap.
+pub struct CronService {
+    jobs: Vec<CronJob>,
+    max_jitter: Duration,
+}
+
+struct CronJob {
+    name: String,
+    schedule: Schedule,
+    run: JobFn,
+    status: Arc<Mutex<JobStatus>>,
+}
+
+/// The state of a job, as shown in the health check
+#[derive(Clone, Serialize)]
+struct JobStatus {
+    name: String,
+    schedule: String,
+    next_run: Option<DateTime<Utc>>,
+    last_run: Option<DateTime<Utc>>,
+    running: bool,
+}
+
+impl Default for CronService {
+    fn default() -> Self {
+        Self {
+            jobs: Vec::new(),
+            max_jitter: DEFAULT_MAX_JITTER,
+        }
+    }
+}
+
+impl CronService {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a job that runs on the given schedule.
+    ///
+    /// The schedule is a cron expression in UTC with a leading seconds field, for example `0 */5 * * * *` for every
+    /// five minutes. An error returned by the job is logged, and the job keeps running on its schedule.
+    pub fn job<F, Fut, E>(mut self, name: &str, schedule: &str, job: F) -> Result<Self, Error>
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), E>> + Send + 'static,
+        E: Display + 'static,
+    {
+        let parsed = Schedule::from_str(schedule).map_err(|error| {
+            CustomError::msg(format!(
+                "invalid schedule '{schedule}' for job '{name}': {error}"
+            ))
+        })?;
+
+        self.jobs.push(CronJob {
+            name: name.to_string(),
+            schedule: parsed,
+            run: Arc::new(move || {
+                let fut = job();
+                Box::pin(async move { fut.await.map_err(|error| error.to_string()) })
+            }),
+            status: Arc::new(Mutex::new(JobStatus {
+                name: name.to_string(),
+                schedule: schedule.to_string(),
+                next_run: None,
+                last_run: None,
+                running: false,
+            })),
+        });
+
+        Ok(self)
+    }
+
+    /// Delay each run by a random duration of up to `max_jitter`. Defaults to one second.
+    pub fn with_jitter(mut self, max_jitter: Duration) -> Self {
+        self.max_jitter = max_jitter;
+        self
+    }
+}
+
+impl CronJob {
+    /// Run this job on its schedule until `shutdown` is cancelled, then wait for the current run to finish
+    async fn run_scheduled(self, max_jitter: Duration, shutdown: CancellationToken) {
+        let mut current_run: Option<JoinHandle<()>> = None;
+
+        while let Some(next_run) = self.schedule.upcoming(Utc).next() {
+            self.status.lock().unwrap().next_run = Some(next_run);
+
+            let delay = (next_run - Utc::now()).to_std().unwrap_or_default() + jitter(max_jitter);
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = shutdown.cancelled() => break,
+            }
+
+            if current_run
+                .as_ref()
+                .is_some_and(|handle| !handle.is_finished())
+            {
+                warn!(job = %self.name, "Skipping cron job run since the previous run is still in progress");
+                continue;
+            }
+
+            {
+                let mut status = self.status.lock().unwrap();
+                status.running = true;
+                status.last_run = Some(Utc::now());
+            }
+            current_run = Some(tokio::spawn(run_once(
+                self.name.clone(),
+                self.run.clone(),
+                self.status.clone(),
+            )));
+        }
+
+        self.status.lock().unwrap().next_run = None;
+        if let Some(handle) = current_run {
+            let _ = handle.await;
+        }
+    }
+}
+
+async fn run_once(name: String, run: JobFn, status: Arc<Mutex<JobStatus>>) {
+    info!(job = %name, "Running cron job");
+    let start = Instant::now();
+
+    // Run in its own task so that a panicking job is reported like an error
+    match tokio::spawn(run()).await {
+        Ok(Ok(())) => {
+            info!(job = %name, elapsed_ms = start.elapsed().as_millis(), "Cron job finished")
+        }
+        Ok(Err(error)) => error!(job = %name, %error, "Cron job failed"),
+        Err(error) => error!(job = %name, %error, "Cron job panicked"),
+    }
+
+    status.lock().unwrap().running = false;
+}
+
+fn jitter(max_jitter: Duration) -> Duration {
+    if max_jitter.is_zero() {
+        return Duration::ZERO;
+    }
+
+    rand::thread_rng().gen_range(Duration::ZERO..max_jitter)
+}
+
+#[shuttle_runtime::async_trait]
+impl shuttle_runtime::Service for CronService {
+    /// Takes the jobs that are returned by the user in their [shuttle_runtime::main] function
+    /// and runs them on their schedules. The address passed in by shuttle is not used.
+    async fn bind(self, addr: SocketAddr) -> Result<(), Error> {
+        self.bind_with_shutdown(addr, CancellationToken::new())
+            .await
+    }
+
+    /// Same as `bind`, but stops scheduling new runs and waits for running jobs once `shutdown` is cancelled.
+    async fn bind_with_shutdown(
+        self,
+        _addr: SocketAddr,
+        shutdown: CancellationToken,
+    ) -> Result<(), Error> {
+        if self.jobs.is_empty() {
+            return Err(CustomError::msg("no jobs were added to the cron service").into());
+        }
+
+        for job in &self.jobs {
+            info!(job = %job.name, schedule = %job.schedule, "Scheduling cron job");
+        }
+
+        let handles: Vec<_> = self
+            .jobs
+            .into_iter()
+            .map(|job| tokio::spawn(job.run_scheduled(self.max_jitter, shutdown.clone())))
+            .collect();
+
+        for handle in handles {
+            handle.await.map_err(CustomError::new)?;
+        }
+
+        Ok(())
+    }
+
+    fn health_reporter(&self) -> Option<HealthReporter> {
+        let statuses: Vec<_> = self.jobs.iter().map(|job| job.status.clone()).collect();
+
+        Some(Arc::new(move || {
+            let jobs: Vec<JobStatus> = statuses
+                .iter()
+                .map(|status| status.lock().unwrap().clone())
+                .collect();
+
+            serde_json::json!({ "jobs": jobs })
+        }))
+    }
+}
+
+#[doc = include_str!("../README.md")]
+pub type ShuttleCron = Result<CronService, Error>;
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use shuttle_runtime::Service;
+
+    use super::*;
+
+    const EVERY_SECOND: &str = "* * * * * *";
+
+    fn address() -> SocketAddr {
+        "127.0.0.1:0".parse().unwrap()
+    }
+
+    /// Run `service` until `duration` has passed, then shut it down and wait for it to return
+    async fn run_for(service: CronService, duration: Duration) {
+        let shutdown = CancellationToken::new();
+        let handle = tokio::spawn(service.bind_with_shutdown(address(), shutdown.clone()));
+
+        tokio::time::sleep(duration).await;
+        shutdown.cancel();
+
+        handle.await.unwrap().unwrap();
+    }
+
+    #[test]
+    fn rejects_invalid_schedules() {
+        let result =
+            CronService::new().job("broken", "every minute", || async { Ok::<_, String>(()) });
+
+        let error = result.err().unwrap().to_string();
+        assert!(error.contains("invalid schedule 'every minute' for job 'broken'"));
+    }
+
+    #[test]
+    fn jitter_stays_below_the_maximum() {
+        assert_eq!(jitter(Duration::ZERO), Duration::ZERO);
+
+        let max_jitter = Duration::from_millis(10);
+        for _ in 0..1000 {
+            assert!(jitter(max_jitter) < max_jitter);
+        }
+    }
+
+    #[tokio::test(crate = "shuttle_runtime::tokio")]
+    async fn runs_jobs_on_their_schedule() {
+        let runs = Arc::new(AtomicUsize::new(0));
+        let service = CronService::new()
+            .with_jitter(Duration::ZERO)
+            .job("count", EVERY_SECOND, {
+                let runs = runs.clone();
+                move || {
+                    let runs = runs.clone();
+                    async move {
+                        runs.fetch_add(1, Ordering::SeqCst);
+                        Ok::<_, String>(())
+                    }
+                }
+            })
+            .unwrap();
+
+        run_for(service, Duration::from_millis(3500)).await;
+
+        let runs = runs.load(Ordering::SeqCst);
+        assert!((2..=4).contains(&runs), "expected about 3 runs, got {runs}");
+    }
+
+    #[tokio::test(crate = "shuttle_runtime::tokio")]
+    async fn skips_runs_while_the_previous_one_is_running() {
+        let started = Arc::new(AtomicUsize::new(0));
+        let running = Arc::new(AtomicUsize::new(0));
+        let max_running = Arc::new(AtomicUsize::new(0));
+        let service = CronService::new()
+            .with_jitter(Duration::ZERO)
+            .job("slow", EVERY_SECOND, {
+                let (started, running, max_running) =
+                    (started.clone(), running.clone(), max_running.clone());
+                move || {
+                    let (started, running, max_running) =
+                        (started.clone(), running.clone(), max_running.clone());
+                    async move {
+                        started.fetch_add(1, Ordering::SeqCst);
+                        let now_running = running.fetch_add(1, Ordering::SeqCst) + 1;
+                        max_running.fetch_max(now_running, Ordering::SeqCst);
+
+                        tokio::time::sleep(Duration::from_millis(2500)).await;
+
+                        running.fetch_sub(1, Ordering::SeqCst);
+                        Ok::<_, String>(())
+                    }
+                }
+            })
+            .unwrap();
+
+        run_for(service, Duration::from_millis(3500)).await;
+
+        assert_eq!(max_running.load(Ordering::SeqCst), 1);
+        // Ticking every second for 3.5s would start about 3 runs if they were allowed to overlap
+        let started = started.load(Ordering::SeqCst);
+        assert!(
+            started <= 2,
+            "expected overlapping runs to be skipped, got {started} runs"
+        );
+    }
+
+    #[tokio::test(crate = "shuttle_runtime::tokio")]
+    async fn shutdown_waits_for_the_running_job() {
+        let finished = Arc::new(AtomicUsize::new(0));
+        let service = CronService::new()
+            .with_jitter(Duration::ZERO)
+            .job("slow", EVERY_SECOND, {
+                let finished = finished.clone();
+                move || {
+                    let finished = finished.clone();
+                    async move {
+                        tokio::time::sleep(Duration::from_millis(1500)).await;
+                        finished.fetch_add(1, Ordering::SeqCst);
+                        Ok::<_, String>(())
+                    }
+                }
+            })
+            .unwrap();
+
+        // Shut down while the first run is still going
+        run_for(service, Duration::from_millis(1500)).await;
+
+        assert_eq!(finished.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test(crate = "shuttle_runtime::tokio")]
+    async fn reports_job_status_in_the_health_check() {
+        let service = CronService::new()
+            .job("report", "0 0 0 1 1 *", || async { Ok::<_, String>(()) })
+            .unwrap();
+        let reporter = service.health_reporter().unwrap();
+
+        let shutdown = CancellationToken::new();
+        let handle = tokio::spawn(service.bind_with_shutdown(address(), shutdown.clone()));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let jobs = &reporter()["jobs"];
+        assert_eq!(jobs[0]["name"], "report");
+        assert_eq!(jobs[0]["running"], false);
+        assert!(jobs[0]["next_run"].is_string());
+
+        shutdown.cancel();
+        handle.await.unwrap().unwrap();
+        assert!(reporter()["jobs"][0]["next_run"].is_null());
+    }
+
+    #[tokio::test(crate = "shuttle_runtime::tokio")]
+    async fn needs_at_least_one_job() {
+        let result = CronService::new()
+            .bind_with_shutdown(address(), CancellationToken::new())
+            .await;
+
+        assert!(result.is_err());
+    }
+}
@@ -1,4 +1,6 @@
 #![doc = include_str!("../README.md")]
+use shuttle_runtime::CancellationToken;
+
 /// A wrapper type for [poem::Endpoint] so we can implement [shuttle_runtime::Service] for it.
 pub struct PoemService<T>(pub T);
 
@@ -8,8 +10,18 @@ where
     T: poem::Endpoint + Send + 'static,
 {
     async fn bind(mut self, addr: std::net::SocketAddr) -> Result<(), shuttle_runtime::Error> {
+        self.bind_with_shutdown(addr, CancellationToken::new())
+            .await
+    }
+
+    /// Same as `bind`, but finishes in-flight requests and returns once `shutdown` is cancelled.
+    async fn bind_with_shutdown(
+        self,
+        addr: std::net::SocketAddr,
+        shutdown: CancellationToken,
+    ) -> Result<(), shuttle_runtime::Error> {
         poem::Server::new(poem::listener::TcpListener::bind(addr))
-            .run(self.0)
+            .run_with_graceful_shutdown(self.0, shutdown.cancelled_owned(), None)
             .await
             .map_err(shuttle_runtime::CustomError::new)?;
 
@@ -1,4 +1,5 @@
 #![doc = include_str!("../README.md")]
+use shuttle_runtime::CancellationToken;
 use std::net::SocketAddr;
 
 /// A wrapper type for [rocket::Rocket<rocket::Build>] so we can implement [shuttle_runtime::Service] for it.
@@ -9,7 +10,17 @@ impl shuttle_runtime::Service for RocketService {
     /// Takes the router that is returned by the user in their [shuttle_runtime::main] function
     /// and binds to an address passed in by shuttle.
     async fn bind(mut self, addr: SocketAddr) -> Result<(), shuttle_runtime::Error> {
-        let shutdown = rocket::config::Shutdown {
+        self.bind_with_shutdown(addr, CancellationToken::new())
+            .await
+    }
+
+    /// Same as `bind`, but notifies Rocket's graceful shutdown once `shutdown` is cancelled.
+    async fn bind_with_shutdown(
+        self,
+        addr: SocketAddr,
+        shutdown: CancellationToken,
+    ) -> Result<(), shuttle_runtime::Error> {
+        let shutdown_config = rocket::config::Shutdown {
             ctrlc: false,
             ..rocket::config::Shutdown::default()
         };
@@ -21,11 +32,22 @@ impl shuttle_runtime::Service for RocketService {
             .merge((rocket::Config::ADDRESS, addr.ip()))
             .merge((rocket::Config::PORT, addr.port()))
             .merge((rocket::Config::LOG_LEVEL, rocket::config::LogLevel::Off))
-            .merge((rocket::Config::SHUTDOWN, shutdown));
+            .merge((rocket::Config::SHUTDOWN, shutdown_config));
 
-        let _rocket = self
+        let rocket = self
             .0
             .configure(config)
+            .ignite()
+            .await
+            .map_err(shuttle_runtime::CustomError::new)?;
+
+        let handle = rocket.shutdown();
+        shuttle_runtime::tokio::spawn(async move {
+            shutdown.cancelled().await;
+            handle.notify();
+        });
+
+        let _rocket = rocket
             .launch()
             .await
             .map_err(shuttle_runtime::CustomError::new)?;
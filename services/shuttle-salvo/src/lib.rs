@@ -1,6 +1,6 @@
 #![doc = include_str!("../README.md")]
 use salvo::Listener;
-use shuttle_runtime::Error;
+use shuttle_runtime::{CancellationToken, Error};
 use std::net::SocketAddr;
 
 /// A wrapper type for [salvo::Router] so we can implement [shuttle_runtime::Service] for it.
@@ -11,9 +11,26 @@ impl shuttle_runtime::Service for SalvoService {
     /// Takes the router that is returned by the user in their [shuttle_runtime::main] function
     /// and binds to an address passed in by shuttle.
     async fn bind(mut self, addr: SocketAddr) -> Result<(), Error> {
+        self.bind_with_shutdown(addr, CancellationToken::new())
+            .await
+    }
+
+    /// Same as `bind`, but stops the server gracefully once `shutdown` is cancelled.
+    async fn bind_with_shutdown(
+        self,
+        addr: SocketAddr,
+        shutdown: CancellationToken,
+    ) -> Result<(), Error> {
         let listener = salvo::conn::TcpListener::new(addr).bind().await;
 
-        salvo::Server::new(listener).serve(self.0).await;
+        let server = salvo::Server::new(listener);
+        let handle = server.handle();
+        shuttle_runtime::tokio::spawn(async move {
+            shutdown.cancelled().await;
+            handle.stop_graceful(None);
+        });
+
+        server.serve(self.0).await;
 
         Ok(())
     }
@@ -1,5 +1,5 @@
 #![doc = include_str!("../README.md")]
-use shuttle_runtime::{CustomError, Error};
+use shuttle_runtime::{CancellationToken, CustomError, Error};
 use std::net::SocketAddr;
 
 #[cfg(feature = "serenity")]
@@ -14,7 +14,26 @@ pub struct SerenityService(pub Client);
 impl shuttle_runtime::Service for SerenityService {
     /// Takes the client that is returned by the user in their [shuttle_runtime::main] function
     /// and starts it.
-    async fn bind(mut self, _addr: SocketAddr) -> Result<(), Error> {
+    async fn bind(mut self, addr: SocketAddr) -> Result<(), Error> {
+        self.bind_with_shutdown(addr, CancellationToken::new())
+            .await
+    }
+
+    /// Same as `bind`, but shuts down all shards once `shutdown` is cancelled.
+    async fn bind_with_shutdown(
+        mut self,
+        _addr: SocketAddr,
+        shutdown: CancellationToken,
+    ) -> Result<(), Error> {
+        let shard_manager = self.0.shard_manager.clone();
+        shuttle_runtime::tokio::spawn(async move {
+            shutdown.cancelled().await;
+            #[cfg(feature = "serenity")]
+            shard_manager.shutdown_all().await;
+            #[cfg(feature = "serenity-0-11")]
+            shard_manager.lock().await.shutdown_all().await;
+        });
+
         self.0.start_autosharded().await.map_err(CustomError::new)?;
 
         Ok(())
@@ -0,0 +1,220 @@
+#![doc = include_str!("../README.md")]
+use std::{fmt::Display, future::Future, net::SocketAddr, pin::Pin, sync::Arc, time::Duration};
+
+use shuttle_runtime::{
+    tokio::{
+        self,
+        net::{TcpListener, TcpStream},
+        task::JoinSet,
+    },
+    CancellationToken, CustomError, Error,
+};
+use tracing::{debug, info, warn};
+
+const ACCEPT_BACKOFF_START: Duration = Duration::from_millis(5);
+const ACCEPT_BACKOFF_MAX: Duration = Duration::from_secs(1);
+
+type ConnectionFuture = Pin<Box<dyn Future<Output = Result<(), String>> + Send>>;
+type ConnectionFn = Arc<dyn Fn(TcpStream, SocketAddr) -> ConnectionFuture + Send + Sync>;
+
+/// A service that accepts raw TCP connections and runs a handler for each of them.
+///
+/// The address of a connection is the one of the shuttle proxy in front of the service, not the one of the client.
+pub struct TcpService {
+    handler: ConnectionFn,
+}
+
+impl TcpService {
+    /// Handle every connection with `handler`. An error returned by the handler is logged, and only ends its own
+    /// connection.
+    pub fn new<F, Fut, E>(handler: F) -> Self
+    where
+        F: Fn(TcpStream, SocketAddr) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), E>> + Send + 'static,
+        E: Display + 'static,
+    {
+        Self {
+            handler: Arc::new(move |stream, peer| {
+                let fut = handler(stream, peer);
+                Box::pin(async move { fut.await.map_err(|error| error.to_string()) })
+            }),
+        }
+    }
+}
+
+#[shuttle_runtime::async_trait]
+impl shuttle_runtime::Service for TcpService {
+    /// Takes the handler that is returned by the user in their [shuttle_runtime::main] function
+    /// and accepts connections on the address passed in by shuttle.
+    async fn bind(self, addr: SocketAddr) -> Result<(), Error> {
+        self.bind_with_shutdown(addr, CancellationToken::new())
+            .await
+    }
+
+    /// Same as `bind`, but stops accepting connections and waits for the open ones once `shutdown` is cancelled.
+    async fn bind_with_shutdown(
+        self,
+        addr: SocketAddr,
+        shutdown: CancellationToken,
+    ) -> Result<(), Error> {
+        let listener = TcpListener::bind(addr).await.map_err(CustomError::new)?;
+        info!(%addr, "Accepting TCP connections");
+
+        let mut connections = JoinSet::new();
+        let mut backoff = ACCEPT_BACKOFF_START;
+        loop {
+            let (stream, peer) = tokio::select! {
+                accepted = listener.accept() => match accepted {
+                    Ok(accepted) => accepted,
+                    Err(error) => {
+                        // Errors like running out of file descriptors only affect this connection, but retrying
+                        // them right away would be a busy loop
+                        warn!(%error, "Failed to accept TCP connection, retrying in {backoff:?}");
+                        tokio::select! {
+                            _ = tokio::time::sleep(backoff) => {}
+                            _ = shutdown.cancelled() => break,
+                        }
+                        backoff = (backoff * 2).min(ACCEPT_BACKOFF_MAX);
+                        continue;
+                    }
+                },
+                _ = shutdown.cancelled() => break,
+            };
+            backoff = ACCEPT_BACKOFF_START;
+
+            // Forget about the connections that are done, so the set does not grow forever
+            while connections.try_join_next().is_some() {}
+
+            let handler = self.handler.clone();
+            connections.spawn(async move {
+                debug!(%peer, "Handling TCP connection");
+                if let Err(error) = handler(stream, peer).await {
+                    warn!(%peer, %error, "TCP connection failed");
+                }
+            });
+        }
+
+        drop(listener);
+        while connections.join_next().await.is_some() {}
+
+        Ok(())
+    }
+}
+
+#[doc = include_str!("../README.md")]
+pub type ShuttleTcp = Result<TcpService, Error>;
+
+#[cfg(test)]
+mod tests {
+    use shuttle_runtime::{
+        tokio::io::{AsyncReadExt, AsyncWriteExt},
+        Service,
+    };
+
+    use super::*;
+
+    /// An address that nothing listens on yet
+    fn free_address() -> SocketAddr {
+        std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+    }
+
+    async fn connect(addr: SocketAddr) -> TcpStream {
+        for _ in 0..100 {
+            if let Ok(stream) = TcpStream::connect(addr).await {
+                return stream;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("service did not start listening on {addr}");
+    }
+
+    async fn echo(stream: &mut TcpStream, message: &[u8]) -> Vec<u8> {
+        stream.write_all(message).await.unwrap();
+        let mut buf = vec![0; message.len()];
+        stream.read_exact(&mut buf).await.unwrap();
+        buf
+    }
+
+    fn echo_service() -> TcpService {
+        TcpService::new(|mut stream, _peer| async move {
+            let mut buf = [0; 1024];
+            loop {
+                let read = stream.read(&mut buf).await?;
+                if read == 0 {
+                    return Ok::<_, std::io::Error>(());
+                }
+                stream.write_all(&buf[..read]).await?;
+            }
+        })
+    }
+
+    #[tokio::test(crate = "shuttle_runtime::tokio")]
+    async fn handles_connections_until_shutdown() {
+        let addr = free_address();
+        let shutdown = CancellationToken::new();
+        let handle = tokio::spawn(echo_service().bind_with_shutdown(addr, shutdown.clone()));
+
+        let mut first = connect(addr).await;
+        let mut second = connect(addr).await;
+        assert_eq!(echo(&mut first, b"ping").await, b"ping");
+        assert_eq!(echo(&mut second, b"pong").await, b"pong");
+
+        shutdown.cancel();
+
+        // Open connections are still handled, and the service waits for them
+        assert_eq!(echo(&mut first, b"still here").await, b"still here");
+        assert!(!handle.is_finished());
+        drop(first);
+        drop(second);
+
+        tokio::time::timeout(Duration::from_secs(5), handle)
+            .await
+            .expect("the service to stop once its connections are closed")
+            .unwrap()
+            .unwrap();
+        assert!(TcpStream::connect(addr).await.is_err());
+    }
+
+    #[tokio::test(crate = "shuttle_runtime::tokio")]
+    async fn failing_connections_do_not_stop_the_service() {
+        let addr = free_address();
+        let shutdown = CancellationToken::new();
+        let service = TcpService::new(|mut stream, _peer| async move {
+            let mut buf = [0; 4];
+            stream
+                .read_exact(&mut buf)
+                .await
+                .map_err(|e| e.to_string())?;
+            if &buf == b"fail" {
+                return Err("asked to fail".to_string());
+            }
+            stream.write_all(&buf).await.map_err(|e| e.to_string())
+        });
+        let handle = tokio::spawn(service.bind_with_shutdown(addr, shutdown.clone()));
+
+        let mut failing = connect(addr).await;
+        failing.write_all(b"fail").await.unwrap();
+        // The handler returns, which closes the connection
+        assert_eq!(failing.read(&mut [0; 4]).await.unwrap(), 0);
+
+        let mut working = connect(addr).await;
+        assert_eq!(echo(&mut working, b"work").await, b"work");
+
+        shutdown.cancel();
+        handle.await.unwrap().unwrap();
+    }
+
+    #[tokio::test(crate = "shuttle_runtime::tokio")]
+    async fn fails_when_the_address_is_taken() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        assert!(echo_service()
+            .bind_with_shutdown(addr, CancellationToken::new())
+            .await
+            .is_err());
+    }
+}
@@ -1,5 +1,5 @@
 #![doc = include_str!("../README.md")]
-use shuttle_runtime::Error;
+use shuttle_runtime::{CancellationToken, Error};
 use std::net::SocketAddr;
 
 /// A wrapper type for [thruster::ThrusterServer] so we can implement [shuttle_runtime::Service] for it.
@@ -13,7 +13,22 @@ where
     /// Takes the server that is returned by the user in their [shuttle_runtime::main] function
     /// and binds to an address passed in by shuttle.
     async fn bind(mut self, addr: SocketAddr) -> Result<(), Error> {
-        self.0.build(&addr.ip().to_string(), addr.port()).await;
+        self.bind_with_shutdown(addr, CancellationToken::new())
+            .await
+    }
+
+    /// Same as `bind`, but returns once `shutdown` is cancelled.
+    ///
+    /// Thruster has no graceful shutdown of its own, so the server is simply dropped.
+    async fn bind_with_shutdown(
+        self,
+        addr: SocketAddr,
+        shutdown: CancellationToken,
+    ) -> Result<(), Error> {
+        shuttle_runtime::tokio::select! {
+            _ = self.0.build(&addr.ip().to_string(), addr.port()) => {}
+            _ = shutdown.cancelled() => {}
+        }
 
         Ok(())
     }
@@ -1,5 +1,5 @@
 #![doc = include_str!("../README.md")]
-use shuttle_runtime::{CustomError, Error};
+use shuttle_runtime::{CancellationToken, CustomError, Error};
 use std::net::SocketAddr;
 
 /// A wrapper type for [tide::Server<T] so we can implement [shuttle_runtime::Service] for it.
@@ -13,7 +13,22 @@ where
     /// Takes the router that is returned by the user in their [shuttle_runtime::main] function
     /// and binds to an address passed in by shuttle.
     async fn bind(mut self, addr: SocketAddr) -> Result<(), Error> {
-        self.0.listen(addr).await.map_err(CustomError::new)?;
+        self.bind_with_shutdown(addr, CancellationToken::new())
+            .await
+    }
+
+    /// Same as `bind`, but returns once `shutdown` is cancelled.
+    ///
+    /// Tide has no graceful shutdown of its own, so the listener is simply dropped.
+    async fn bind_with_shutdown(
+        self,
+        addr: SocketAddr,
+        shutdown: CancellationToken,
+    ) -> Result<(), Error> {
+        shuttle_runtime::tokio::select! {
+            res = self.0.listen(addr) => res.map_err(CustomError::new)?,
+            _ = shutdown.cancelled() => {}
+        }
 
         Ok(())
     }
@@ -1,5 +1,5 @@
 #![doc = include_str!("../README.md")]
-use shuttle_runtime::{CustomError, Error};
+use shuttle_runtime::{CancellationToken, CustomError, Error};
 use std::net::SocketAddr;
 
 /// A wrapper type for [tower::Service] so we can implement [shuttle_runtime::Service] for it.
@@ -19,9 +19,20 @@ where
     /// Takes the service that is returned by the user in their [shuttle_runtime::main] function
     /// and binds to an address passed in by shuttle.
     async fn bind(mut self, addr: SocketAddr) -> Result<(), Error> {
+        self.bind_with_shutdown(addr, CancellationToken::new())
+            .await
+    }
+
+    /// Same as `bind`, but finishes in-flight requests and returns once `shutdown` is cancelled.
+    async fn bind_with_shutdown(
+        self,
+        addr: SocketAddr,
+        shutdown: CancellationToken,
+    ) -> Result<(), Error> {
         let shared = tower::make::Shared::new(self.0);
         hyper::Server::bind(&addr)
             .serve(shared)
+            .with_graceful_shutdown(shutdown.cancelled_owned())
             .await
             .map_err(CustomError::new)?;
 
@@ -1,5 +1,5 @@
 #![doc = include_str!("../README.md")]
-use shuttle_runtime::Error;
+use shuttle_runtime::{CancellationToken, CustomError, Error};
 use std::net::SocketAddr;
 use std::ops::Deref;
 
@@ -15,7 +15,21 @@ where
     /// Takes the router that is returned by the user in their [shuttle_runtime::main] function
     /// and binds to an address passed in by shuttle.
     async fn bind(mut self, addr: SocketAddr) -> Result<(), Error> {
-        warp::serve((*self).clone()).run(addr).await;
+        self.bind_with_shutdown(addr, CancellationToken::new())
+            .await
+    }
+
+    /// Same as `bind`, but finishes in-flight requests and returns once `shutdown` is cancelled.
+    async fn bind_with_shutdown(
+        self,
+        addr: SocketAddr,
+        shutdown: CancellationToken,
+    ) -> Result<(), Error> {
+        let (_, server) = warp::serve((*self).clone())
+            .try_bind_with_graceful_shutdown(addr, shutdown.cancelled_owned())
+            .map_err(CustomError::new)?;
+        server.await;
+
         Ok(())
     }
 }